@@ -50,7 +50,7 @@ async fn do_main() -> anyhow::Result<()> {
         .await?;
 
     println!("* Downloading file.");
-    let download_request = b2::DownloadFile::with_id(&file.file_id());
+    let download_request = b2::DownloadFile::with_id(file.file_id());
     let (downloaded, _headers) = b2::download_file(&mut auth, download_request)
         .await?;
 
@@ -66,7 +66,10 @@ fn main() -> anyhow::Result<()> { main_runner() }
 #[cfg(any(feature = "with_surf", feature = "with_isahc"))]
 fn main_runner() -> anyhow::Result<()> { async_std::task::block_on(do_main()) }
 
-#[cfg(feature = "with_hyper")]
+#[cfg(feature = "with_ureq")]
+fn main_runner() -> anyhow::Result<()> { b2::blocking::block_on(do_main()) }
+
+#[cfg(any(feature = "with_hyper", feature = "with_reqwest"))]
 fn main_runner() -> anyhow::Result<()> {
     tokio::runtime::Builder::new_current_thread()
         .enable_all()
@@ -84,11 +87,17 @@ pub fn client() -> client::HyperClient { client::HyperClient::default() }
 #[cfg(feature = "with_isahc")]
 pub fn client() -> client::IsahcClient { client::IsahcClient::default() }
 
+#[cfg(feature = "with_reqwest")]
+pub fn client() -> client::ReqwestClient { client::ReqwestClient::default() }
+
+#[cfg(feature = "with_ureq")]
+pub fn client() -> client::UreqClient { client::UreqClient::default() }
+
 
 // Allow us to build when running `cargo test` on the main project without
 // features. You can ignore this.
 #[cfg(not(any(feature="with_hyper", feature="with_surf",
-    feature="with_isahc")))]
+    feature="with_isahc", feature="with_reqwest", feature="with_ureq")))]
 mod empty {
     pub fn main_runner() -> anyhow::Result<()> {
         Err(anyhow::anyhow!(
@@ -111,6 +120,10 @@ mod empty {
         -> Result<&mut Self, b2_client::error::ValidationError> { Ok(self) }
         fn post(&mut self, _url: impl AsRef<str>)
         -> Result<&mut Self, b2_client::error::ValidationError> { Ok(self) }
+        fn put(&mut self, _url: impl AsRef<str>)
+        -> Result<&mut Self, b2_client::error::ValidationError> { Ok(self) }
+        fn delete(&mut self, _url: impl AsRef<str>)
+        -> Result<&mut Self, b2_client::error::ValidationError> { Ok(self) }
 
         fn with_header<S: AsRef<str>>(&mut self, _name: S, _value: S)
         -> Result<&mut Self, b2_client::error::ValidationError> { Ok(self) }
@@ -120,20 +133,25 @@ mod empty {
         -> &mut Self { self }
         fn read_body_from_file(&mut self, _path: impl Into<std::path::PathBuf>)
         -> &mut Self { self }
+        fn with_body_reader<R>(&mut self, _reader: R, _content_length: u64)
+        -> &mut Self
+            where R: futures_io::AsyncRead + Send + Sync + Unpin + 'static
+        { self }
 
         fn user_agent(&mut self, _user_agent_string: impl Into<String>)
         -> Result<&mut Self, b2_client::error::ValidationError> { Ok(self) }
 
-        async fn send(&mut self) -> Result<Vec<u8>, Self::Error> {
+        async fn send(&mut self)
+        -> Result<b2_client::client::Response, Self::Error> {
             Err(b2_client::Error::Client(""))
         }
 
-        async fn send_keep_headers(&mut self)
-        -> Result<(Vec<u8>, b2_client::client::HeaderMap), Self::Error> {
+        async fn send_streaming(&mut self)
+        -> Result<b2_client::client::StreamingResponse, Self::Error> {
             Err(b2_client::Error::Client(""))
         }
     }
 }
 #[cfg(not(any(feature="with_hyper", feature="with_surf",
-    feature="with_isahc")))]
+    feature="with_isahc", feature="with_reqwest", feature="with_ureq")))]
 pub use empty::*;
@@ -5,12 +5,19 @@
 
 //! Account-related B2 API calls.
 
-use std::fmt;
+use std::{
+    fmt,
+    future::Future,
+    sync::{Arc, Mutex},
+    task::{Poll, Waker},
+};
 
 use crate::{
     prelude::*,
-    client::HttpClient,
-    error::{ValidationError, Error},
+    client::{HttpClient, Response},
+    credentials::Credentials,
+    error::{B2Error, ValidationError, Error, Restriction, RotateKeyError},
+    pagination::{sealed::Sealed, Fetch, ListRequest, ListStream},
     types::*,
 };
 
@@ -18,7 +25,59 @@ use chrono::{DateTime, Utc};
 use serde::{Serialize, Deserialize};
 
 
-const B2_AUTH_URL: &str = "https://api.backblazeb2.com/b2api/v2/";
+/// The base URL of the B2 service's authorization endpoint.
+pub const B2_AUTH_URL: &str = "https://api.backblazeb2.com";
+
+/// How long an authorization token remains valid after it is issued.
+const AUTHORIZATION_LIFETIME_HOURS: i64 = 24;
+
+/// The environment variable that overrides [B2_AUTH_URL] in builds with the
+/// `testing` feature.
+#[cfg(feature = "testing")]
+const AUTH_URL_VAR: &str = "B2_CLIENT_AUTH_URL";
+
+/// Get the base URL used by [authorize_account].
+///
+/// This is [B2_AUTH_URL]. With the `testing` feature, the `B2_CLIENT_AUTH_URL`
+/// environment variable overrides it, which allows using a local emulator of
+/// the B2 API. Other builds never send application keys anywhere else.
+pub(crate) fn auth_url() -> String {
+    #[cfg(feature = "testing")]
+    if let Ok(url) = std::env::var(AUTH_URL_VAR) {
+        return url;
+    }
+
+    B2_AUTH_URL.to_owned()
+}
+
+/// The version of the B2 native API used by an [Authorization].
+///
+/// Version 3 allows an application key to be restricted to several buckets
+/// rather than at most one. Both versions are supported by every function in
+/// this crate; select one with [authorize_account_with_version] or
+/// [Credentials::with_api_version].
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    #[default]
+    V2,
+    V3,
+}
+
+impl ApiVersion {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::V2 => "v2",
+            Self::V3 => "v3",
+        }
+    }
+}
+
+impl fmt::Display for ApiVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
 
 /// Authorization token and related information obtained from
 /// [authorize_account].
@@ -41,8 +100,97 @@ pub struct Authorization<C>
     pub(crate) download_url: String,
     recommended_part_size: u64,
     absolute_minimum_part_size: u64,
-    // The base URL to use for all API calls using the AWS S3-compatible API.j
-    _s3_api_url: String,
+    // The base URL to use for all API calls using the AWS S3-compatible API.
+    s3_api_url: String,
+    // The base URL this authorization was obtained from; it is used again to
+    // renew the authorization.
+    pub(crate) auth_url: String,
+    api_version: ApiVersion,
+    // When we received the authorization token.
+    issued_at: DateTime<Utc>,
+    // Set if this authorization was obtained from a
+    // [Session](crate::session::Session), so that it can re-authorize.
+    pub(crate) session: Option<Arc<SharedAuthorization<C>>>,
+}
+
+/// The current authorization of a [Session](crate::session::Session), shared
+/// by every [Authorization] obtained from it.
+#[derive(Debug)]
+pub(crate) struct SharedAuthorization<C>
+    where C: HttpClient,
+{
+    credentials: Credentials,
+    // The `session` field of this Authorization is always `None`; linking it
+    // back to this struct would create a reference cycle.
+    current: Mutex<Authorization<C>>,
+    // Held while renewing `current` so that concurrent renewals authorize the
+    // account only once.
+    renewal: RenewalLock,
+}
+
+impl<C> SharedAuthorization<C>
+    where C: HttpClient,
+{
+    pub(crate) fn new(mut auth: Authorization<C>, credentials: Credentials)
+    -> Self {
+        auth.session = None;
+
+        Self {
+            credentials,
+            current: Mutex::new(auth),
+            renewal: RenewalLock::default(),
+        }
+    }
+
+    /// Get a copy of the current authorization, linked to this shared state.
+    pub(crate) fn current(self: &Arc<Self>) -> Authorization<C> {
+        let mut auth = self.current.lock().unwrap().clone();
+        auth.session = Some(Arc::clone(self));
+        auth
+    }
+}
+
+/// A lock that may be held across an `await` without depending on a
+/// particular async runtime.
+#[derive(Debug, Default)]
+struct RenewalLock {
+    state: Mutex<RenewalState>,
+}
+
+#[derive(Debug, Default)]
+struct RenewalState {
+    locked: bool,
+    waiters: Vec<Waker>,
+}
+
+impl RenewalLock {
+    fn lock(&self) -> impl Future<Output = RenewalGuard<'_>> + Send {
+        std::future::poll_fn(move |cx| {
+            let mut state = self.state.lock().unwrap();
+
+            if state.locked {
+                state.waiters.push(cx.waker().clone());
+                Poll::Pending
+            } else {
+                state.locked = true;
+                Poll::Ready(RenewalGuard(self))
+            }
+        })
+    }
+}
+
+struct RenewalGuard<'a>(&'a RenewalLock);
+
+impl Drop for RenewalGuard<'_> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.locked = false;
+
+        // The first waiter to run takes the lock; the others wait again.
+        for waker in state.waiters.drain(..) {
+            waker.wake();
+        }
+    }
 }
 
 impl<C> Authorization<C>
@@ -61,7 +209,7 @@ impl<C> Authorization<C>
         download_url: String,
         recommended_part_size: u64,
         absolute_minimum_part_size: u64,
-        _s3_api_url: String,
+        s3_api_url: String,
     ) -> Self {
         Self {
             client,
@@ -72,7 +220,57 @@ impl<C> Authorization<C>
             download_url,
             recommended_part_size,
             absolute_minimum_part_size,
-            _s3_api_url,
+            s3_api_url,
+            auth_url: B2_AUTH_URL.into(),
+            api_version: ApiVersion::V2,
+            issued_at: Utc::now(),
+            session: None,
+        }
+    }
+
+    /// Restore an `Authorization` saved with [save](Self::save), sending
+    /// its requests with the given client.
+    ///
+    /// The restored authorization is not checked with the B2 service; if its
+    /// token has expired or been revoked, requests using it will fail.
+    pub fn restore(client: C, saved: SavedAuthorization) -> Self {
+        Self {
+            client,
+            account_id: saved.account_id,
+            authorization_token: saved.authorization_token,
+            allowed: saved.allowed,
+            api_url: saved.api_url,
+            download_url: saved.download_url,
+            recommended_part_size: saved.recommended_part_size,
+            absolute_minimum_part_size: saved.absolute_minimum_part_size,
+            s3_api_url: saved.s3_api_url,
+            auth_url: saved.auth_url,
+            api_version: saved.api_version,
+            issued_at: saved.issued_at,
+            session: None,
+        }
+    }
+
+    /// Save this `Authorization` so that it can be [restored](Self::restore)
+    /// later, possibly by another process.
+    ///
+    /// The HTTP client is not saved, nor is the application key retained by a
+    /// [Session](crate::session::Session). The saved authorization token
+    /// grants access to the account until it expires, so it should be stored
+    /// as carefully as an application key.
+    pub fn save(&self) -> SavedAuthorization {
+        SavedAuthorization {
+            account_id: self.account_id.clone(),
+            authorization_token: self.authorization_token.clone(),
+            allowed: self.allowed.clone(),
+            api_url: self.api_url.clone(),
+            download_url: self.download_url.clone(),
+            recommended_part_size: self.recommended_part_size,
+            absolute_minimum_part_size: self.absolute_minimum_part_size,
+            s3_api_url: self.s3_api_url.clone(),
+            auth_url: self.auth_url.clone(),
+            api_version: self.api_version,
+            issued_at: self.issued_at,
         }
     }
 
@@ -88,6 +286,22 @@ impl<C> Authorization<C>
     /// The smallest possible size in bytes of a part of a large file, except
     /// the final part.
     pub fn minimum_part_size(&self) -> u64 { self.absolute_minimum_part_size }
+    /// The base URL of the account's S3-compatible API.
+    ///
+    /// See the [s3](crate::s3) module to send requests to it.
+    pub fn s3_api_url(&self) -> &str { &self.s3_api_url }
+    /// The version of the B2 API used for requests.
+    pub fn api_version(&self) -> ApiVersion { self.api_version }
+    /// When the authorization token was obtained.
+    pub fn issued_at(&self) -> DateTime<Utc> { self.issued_at }
+
+    /// When the authorization token expires.
+    ///
+    /// The B2 service may reject the token earlier, for example if its
+    /// application key is deleted.
+    pub fn expires_at(&self) -> DateTime<Utc> {
+        expiration(self.issued_at)
+    }
 
     pub fn has_capability(&self, cap: Capability) -> bool {
         self.allowed.has_capability(cap)
@@ -97,7 +311,8 @@ impl<C> Authorization<C>
     ///
     /// This URL is used for all API calls except downloading files.
     pub(crate) fn api_url<S: AsRef<str>>(&self, endpoint: S) -> String {
-        format!("{}/b2api/v2/{}", self.api_url, endpoint.as_ref())
+        format!("{}/b2api/{}/{}",
+            self.api_url, self.api_version, endpoint.as_ref())
     }
 
     /// Return the API url for GET requests to the specified service download
@@ -109,8 +324,138 @@ impl<C> Authorization<C>
     /// Return the API url for POST requests to the specified service download
     /// endpoint.
     pub(crate) fn download_url<S: AsRef<str>>(&self, endpoint: S) -> String {
-        format!("{}/b2api/v2/{}", self.download_url, endpoint.as_ref())
+        format!("{}/b2api/{}/{}",
+            self.download_url, self.api_version, endpoint.as_ref())
+    }
+
+    /// Returns `true` if the response is a rejection of an expired
+    /// authorization token that we retain the key to replace.
+    pub(crate) fn should_reauthorize(&self, res: &Response) -> bool {
+        self.session.is_some()
+            && ! res.is_success()
+            && B2Error::from_response(res).is_expired_authorization()
+    }
+}
+
+impl<C, E> Authorization<C>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    /// Obtain a new authorization token for the session this
+    /// `Authorization` came from, updating both in place.
+    ///
+    /// If another copy of the session's authorization has already been
+    /// renewed since this one was obtained, its token is reused rather than
+    /// authorizing the account again. Only one copy renews at a time; any
+    /// others that need to renew wait for it, then use its token. Every other
+    /// copy of the authorization (such as one held by an
+    /// [UploadAuthorization](crate::file::UploadAuthorization)) picks up the
+    /// new token when it next needs to renew.
+    ///
+    /// Returns [Error::MissingAuthorization] if this `Authorization` did not
+    /// come from a session.
+    pub(crate) async fn reauthorize(&mut self) -> Result<(), Error<E>> {
+        let shared = self.session.clone()
+            .ok_or(Error::MissingAuthorization)?;
+
+        let _renewing = shared.renewal.lock().await;
+
+        let renewed = {
+            let current = shared.current.lock().unwrap();
+
+            if current.authorization_token != self.authorization_token {
+                Some(current.clone())
+            } else {
+                None
+            }
+        };
+
+        let new_auth = match renewed {
+            Some(auth) => auth,
+            None => {
+                let auth = authorize_account_with_version(
+                    self.client.clone(),
+                    &self.auth_url,
+                    self.api_version,
+                    &shared.credentials.key_id,
+                    &shared.credentials.key
+                ).await?;
+
+                *shared.current.lock().unwrap() = auth.clone();
+                auth
+            },
+        };
+
+        *self = Authorization {
+            session: Some(Arc::clone(&shared)),
+            ..new_auth
+        };
+
+        Ok(())
     }
+
+    /// Send `body` to the given API endpoint, renewing an expired
+    /// authorization token and sending the request again if possible.
+    pub(crate) async fn post_renewing(
+        &mut self,
+        endpoint: &str,
+        body: serde_json::Value
+    ) -> Result<Response, Error<E>> {
+        let res = self.client.post(self.api_url(endpoint))
+            .expect("Invalid URL")
+            .with_header("Authorization", &self.authorization_token).unwrap()
+            .with_body_json(body.clone())
+            .send().await?;
+
+        if ! self.should_reauthorize(&res) {
+            return Ok(res);
+        }
+
+        self.reauthorize().await?;
+
+        self.client.post(self.api_url(endpoint))
+            .expect("Invalid URL")
+            .with_header("Authorization", &self.authorization_token).unwrap()
+            .with_body_json(body)
+            .send().await
+    }
+}
+
+fn expiration(issued_at: DateTime<Utc>) -> DateTime<Utc> {
+    issued_at + chrono::Duration::hours(AUTHORIZATION_LIFETIME_HOURS)
+}
+
+/// An [Authorization] without its HTTP client, which can be serialized.
+///
+/// Obtain a `SavedAuthorization` from [Authorization::save] and turn it back
+/// into an `Authorization` with [Authorization::restore].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavedAuthorization {
+    account_id: String,
+    authorization_token: String,
+    allowed: Capabilities,
+    api_url: String,
+    download_url: String,
+    recommended_part_size: u64,
+    absolute_minimum_part_size: u64,
+    s3_api_url: String,
+    auth_url: String,
+    // Authorizations saved before v3 support was added used v2.
+    #[serde(default)]
+    api_version: ApiVersion,
+    issued_at: DateTime<Utc>,
+}
+
+impl SavedAuthorization {
+    /// The ID for the account.
+    pub fn account_id(&self) -> &str { &self.account_id }
+    /// The base URL the authorization was obtained from.
+    pub fn auth_url(&self) -> &str { &self.auth_url }
+    /// When the authorization token was obtained.
+    pub fn issued_at(&self) -> DateTime<Utc> { self.issued_at }
+    /// When the authorization token expires.
+    pub fn expires_at(&self) -> DateTime<Utc> { expiration(self.issued_at) }
 }
 
 /// The authorization information received from B2
@@ -127,11 +472,63 @@ struct ProtoAuthorization {
     download_url: String,
     recommended_part_size: u64,
     absolute_minimum_part_size: u64,
-    _s3_api_url: String,
+    s3_api_url: String,
+}
+
+/// The authorization information received from version 3 of the B2 API.
+///
+/// This nests everything but the account ID and token in
+/// `apiInfo.storageApi`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProtoAuthorizationV3 {
+    account_id: String,
+    authorization_token: String,
+    api_info: ApiInfo,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiInfo {
+    storage_api: StorageApi,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StorageApi {
+    allowed: Capabilities,
+    api_url: String,
+    download_url: String,
+    recommended_part_size: u64,
+    absolute_minimum_part_size: u64,
+    s3_api_url: String,
+}
+
+impl From<ProtoAuthorizationV3> for ProtoAuthorization {
+    fn from(auth: ProtoAuthorizationV3) -> Self {
+        let api = auth.api_info.storage_api;
+
+        Self {
+            account_id: auth.account_id,
+            authorization_token: auth.authorization_token,
+            allowed: api.allowed,
+            api_url: api.api_url,
+            download_url: api.download_url,
+            recommended_part_size: api.recommended_part_size,
+            absolute_minimum_part_size: api.absolute_minimum_part_size,
+            s3_api_url: api.s3_api_url,
+        }
+    }
 }
 
 impl ProtoAuthorization {
-    fn create_authorization<C: HttpClient>(self, c: C) -> Authorization<C> {
+    fn create_authorization<C: HttpClient>(
+        self,
+        c: C,
+        auth_url: String,
+        api_version: ApiVersion,
+        issued_at: DateTime<Utc>,
+    ) -> Authorization<C> {
         Authorization {
             client: c,
             account_id: self.account_id,
@@ -141,22 +538,76 @@ impl ProtoAuthorization {
             download_url: self.download_url,
             recommended_part_size: self.recommended_part_size,
             absolute_minimum_part_size: self.absolute_minimum_part_size,
-            _s3_api_url: self._s3_api_url,
+            s3_api_url: self.s3_api_url,
+            auth_url,
+            api_version,
+            issued_at,
+            session: None,
         }
     }
 }
 
 /// The set of capabilities and associated information granted by an
 /// authorization token.
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", from = "CapabilitiesRepr")]
 pub struct Capabilities {
     capabilities: Vec<Capability>,
+    // Empty if access is not restricted to particular buckets.
+    buckets: Vec<AllowedBucket>,
+    name_prefix: Option<String>,
+}
+
+/// The bucket restriction as sent by either version of the API.
+///
+/// Version 2 restricts a key to at most one bucket, given by `bucketId` and
+/// `bucketName`; version 3 lists the allowed buckets in `buckets`.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CapabilitiesRepr {
+    capabilities: Vec<Capability>,
+    #[serde(default)]
+    buckets: Option<Vec<AllowedBucket>>,
+    #[serde(default)]
     bucket_id: Option<String>,
+    #[serde(default)]
     bucket_name: Option<String>,
     name_prefix: Option<String>,
 }
 
+impl From<CapabilitiesRepr> for Capabilities {
+    fn from(repr: CapabilitiesRepr) -> Self {
+        let buckets = match (repr.buckets, repr.bucket_id) {
+            (Some(buckets), _) => buckets,
+            (None, Some(id)) => vec![AllowedBucket {
+                id,
+                name: repr.bucket_name,
+            }],
+            (None, None) => vec![],
+        };
+
+        Self {
+            capabilities: repr.capabilities,
+            buckets,
+            name_prefix: repr.name_prefix,
+        }
+    }
+}
+
+/// A bucket that an authorization token is restricted to.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AllowedBucket {
+    id: String,
+    name: Option<String>,
+}
+
+impl AllowedBucket {
+    /// The bucket's ID.
+    pub fn id(&self) -> &str { &self.id }
+    /// The bucket's name, or `None` if the bucket has been deleted.
+    pub fn name(&self) -> Option<&str> { self.name.as_deref() }
+}
+
 impl Capabilities {
     // Allow tests to create Capabilities.
     #[cfg(test)]
@@ -167,30 +618,157 @@ impl Capabilities {
         bucket_name: Option<String>,
         name_prefix: Option<String>,
     ) -> Self {
+        let buckets = bucket_id.into_iter()
+            .map(|id| AllowedBucket { id, name: bucket_name.clone() })
+            .collect();
+
         Self {
             capabilities,
-            bucket_id,
-            bucket_name,
+            buckets,
             name_prefix,
         }
     }
 
     /// The list of capabilities granted.
     pub fn capabilities(&self) -> &[Capability] { &self.capabilities }
+    /// The buckets the capabilities are limited to.
+    ///
+    /// If this is empty, every bucket in the account can be accessed. Only
+    /// version 3 of the API can restrict a key to more than one bucket.
+    pub fn buckets(&self) -> &[AllowedBucket] { &self.buckets }
     /// If the capabilities are limited to a single bucket, this is the bucket's
     /// ID.
-    pub fn bucket_id(&self) -> Option<&String> { self.bucket_id.as_ref() }
+    ///
+    /// If they are limited to several buckets, this is the first of them; see
+    /// [buckets](Self::buckets).
+    pub fn bucket_id(&self) -> Option<&String> {
+        self.buckets.first().map(|b| &b.id)
+    }
     /// If the bucket is valid and hasn't been deleted, the name of the bucket
     /// corresponding to `bucket_id`. If the bucket referred to by `bucket_id`
     /// no longer exists, this will be `None`.
-    pub fn bucket_name(&self) -> Option<&String> { self.bucket_name.as_ref() }
+    pub fn bucket_name(&self) -> Option<&String> {
+        self.buckets.first().and_then(|b| b.name.as_ref())
+    }
     /// If set, access is limited to files whose names begin with this prefix.
     pub fn name_prefix(&self) -> Option<&String> { self.name_prefix.as_ref() }
 
     /// Check if the provided capability is granted to the object containing
     /// this [Capabilities] object.
     pub fn has_capability(&self, cap: Capability) -> bool {
-        self.capabilities.iter().any(|&c| c == cap)
+        self.capabilities.contains(&cap)
+    }
+
+    /// Check whether these capabilities are sufficient to perform each of the
+    /// given operations.
+    ///
+    /// Returns each operation that cannot be performed along with the
+    /// capabilities it lacks. If every operation can be performed, the list
+    /// is empty.
+    ///
+    /// Bucket and file name restrictions are not considered; see
+    /// [check_bucket](Self::check_bucket) and
+    /// [check_file_name](Self::check_file_name).
+    pub fn missing_capabilities(&self, operations: &[Operation])
+    -> Vec<(Operation, Vec<Capability>)> {
+        operations.iter()
+            .filter_map(|op| {
+                let missing: Vec<_> = op.required_capabilities().into_iter()
+                    .filter(|cap| ! self.has_capability(*cap))
+                    .collect();
+
+                if missing.is_empty() {
+                    None
+                } else {
+                    Some((*op, missing))
+                }
+            })
+            .collect()
+    }
+
+    /// Check that the bucket with the given ID is within the bucket
+    /// restriction, if there is one.
+    pub fn check_bucket(&self, bucket_id: &str) -> Result<(), Restriction> {
+        if self.buckets.is_empty()
+            || self.buckets.iter().any(|b| b.id == bucket_id)
+        {
+            Ok(())
+        } else {
+            Err(Restriction::Bucket {
+                allowed: self.allowed_bucket_ids(),
+                requested: Some(bucket_id.to_owned()),
+            })
+        }
+    }
+
+    /// Check that the bucket with the given name is within the bucket
+    /// restriction, if there is one.
+    ///
+    /// If a restricted bucket has been deleted its name is unknown, so any
+    /// name is accepted.
+    pub fn check_bucket_name(&self, bucket_name: &str)
+    -> Result<(), Restriction> {
+        let allowed = self.buckets.iter()
+            .map(AllowedBucket::name)
+            .collect::<Option<Vec<_>>>();
+
+        match allowed {
+            Some(names) if ! names.is_empty()
+                && ! names.contains(&bucket_name) =>
+                Err(Restriction::Bucket {
+                    allowed: names.into_iter().map(String::from).collect(),
+                    requested: Some(bucket_name.to_owned()),
+                }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that there is no bucket restriction, so that a request may
+    /// involve any bucket in the account.
+    pub fn check_all_buckets(&self) -> Result<(), Restriction> {
+        if self.buckets.is_empty() {
+            Ok(())
+        } else {
+            Err(Restriction::Bucket {
+                allowed: self.allowed_bucket_ids(),
+                requested: None,
+            })
+        }
+    }
+
+    fn allowed_bucket_ids(&self) -> Vec<String> {
+        self.buckets.iter().map(|b| b.id.clone()).collect()
+    }
+
+    /// Check that the given file name is within the file name prefix
+    /// restriction, if there is one.
+    pub fn check_file_name(&self, file_name: &str) -> Result<(), Restriction> {
+        self.check_prefix(Some(file_name))
+    }
+
+    /// Check that a listing with the given prefix is within the file name
+    /// prefix restriction, if there is one.
+    ///
+    /// A key restricted to a prefix must specify a prefix that begins with it.
+    pub fn check_prefix(&self, prefix: Option<&str>)
+    -> Result<(), Restriction> {
+        match &self.name_prefix {
+            Some(allowed) if ! allowed.is_empty()
+                && ! prefix.is_some_and(|p| p.starts_with(allowed.as_str())) =>
+                Err(Restriction::NamePrefix {
+                    allowed: allowed.to_owned(),
+                    requested: prefix.map(String::from),
+                }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check that a file in the given bucket is within both the bucket and
+    /// file name prefix restrictions.
+    pub(crate) fn check_file(&self, bucket_id: &str, file_name: &str)
+    -> Result<(), Restriction> {
+        self.check_bucket(bucket_id)?;
+        self.check_file_name(file_name)
     }
 }
 
@@ -222,6 +800,198 @@ pub enum Capability {
     BypassGovernance,
     ReadBucketReplications,
     WriteBucketReplications,
+    ReadBucketNotifications,
+    WriteBucketNotifications,
+}
+
+/// An operation performed via the B2 API, used to determine the
+/// [Capability]s needed to perform it.
+///
+/// The API functions check these same requirements before sending a request,
+/// so a key created with the capabilities returned by
+/// [required_capabilities] can perform each of the given operations.
+///
+/// Operations that take options, such as setting a file's retention policy
+/// while uploading it, require additional capabilities when those options are
+/// used.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Operation {
+    /// [list_keys]
+    ListKeys,
+    /// [create_key]
+    CreateKey,
+    /// [delete_key] and [delete_key_by_id]
+    DeleteKey,
+    /// [create_bucket](crate::bucket::create_bucket)
+    CreateBucket { file_lock: bool, encryption: bool, replication: bool },
+    /// [delete_bucket](crate::bucket::delete_bucket)
+    DeleteBucket,
+    /// [list_buckets](crate::bucket::list_buckets)
+    ListBuckets,
+    /// [update_bucket](crate::bucket::update_bucket)
+    ///
+    /// `retention`, `encryption`, and `replication` are set if the update
+    /// changes the bucket's default retention, encryption, or replication
+    /// settings.
+    UpdateBucket { retention: bool, encryption: bool, replication: bool },
+    /// [get_bucket_notification_rules][get-rules]
+    ///
+    /// [get-rules]: crate::bucket::get_bucket_notification_rules
+    GetBucketNotificationRules,
+    /// [set_bucket_notification_rules][set-rules]
+    ///
+    /// [set-rules]: crate::bucket::set_bucket_notification_rules
+    SetBucketNotificationRules,
+    /// [list_file_names](crate::file::list_file_names)
+    ListFileNames,
+    /// [list_file_versions](crate::file::list_file_versions)
+    ListFileVersions,
+    /// [list_unfinished_large_files](crate::file::list_unfinished_large_files)
+    ListUnfinishedLargeFiles,
+    /// [list_file_parts](crate::file::list_file_parts)
+    ListFileParts,
+    /// [get_file_info](crate::file::get_file_info)
+    GetFileInfo,
+    /// Downloading a file or its headers, such as with
+    /// [download_file](crate::file::download_file).
+    DownloadFile,
+    /// [get_download_authorization](crate::file::get_download_authorization)
+    GetDownloadAuthorization,
+    /// Obtaining an upload authorization and uploading a file with
+    /// [upload_file](crate::file::upload_file).
+    UploadFile { retention: bool, legal_hold: bool },
+    /// [start_large_file](crate::file::start_large_file)
+    StartLargeFile { retention: bool, legal_hold: bool, encryption: bool },
+    /// Obtaining an upload authorization and uploading a part of a large file
+    /// with [upload_file_part](crate::file::upload_file_part).
+    UploadFilePart,
+    /// [copy_file_part](crate::file::copy_file_part)
+    CopyFilePart,
+    /// [finish_large_file_upload](crate::file::finish_large_file_upload)
+    FinishLargeFile,
+    /// [cancel_large_file](crate::file::cancel_large_file)
+    CancelLargeFile,
+    /// [copy_file](crate::file::copy_file)
+    CopyFile { retention: bool, legal_hold: bool, encryption: bool },
+    /// [hide_file](crate::file::hide_file)
+    HideFile,
+    /// [delete_file_version](crate::file::delete_file_version)
+    DeleteFileVersion { bypass_governance: bool },
+    /// [update_file_legal_hold](crate::file::update_file_legal_hold)
+    UpdateFileLegalHold,
+    /// [update_file_retention](crate::file::update_file_retention)
+    UpdateFileRetention { bypass_governance: bool },
+}
+
+impl Operation {
+    /// The capabilities needed to perform this operation.
+    pub fn required_capabilities(&self) -> Vec<Capability> {
+        use Capability as Cap;
+
+        // Add the capability if the option is set.
+        fn with(caps: &mut Vec<Capability>, opt: bool, cap: Capability) {
+            if opt {
+                caps.push(cap);
+            }
+        }
+
+        let mut caps = Vec::with_capacity(4);
+
+        match *self {
+            Self::ListKeys => caps.push(Cap::ListKeys),
+            Self::CreateKey => caps.push(Cap::WriteKeys),
+            Self::DeleteKey => caps.push(Cap::DeleteKeys),
+            Self::CreateBucket { file_lock, encryption, replication } => {
+                caps.push(Cap::WriteBuckets);
+                with(&mut caps, file_lock, Cap::WriteBucketRetentions);
+                with(&mut caps, encryption, Cap::WriteBucketEncryption);
+                with(&mut caps, replication, Cap::WriteBucketReplications);
+            },
+            Self::DeleteBucket => caps.push(Cap::DeleteBuckets),
+            Self::ListBuckets => caps.push(Cap::ListBuckets),
+            Self::UpdateBucket { retention, encryption, replication } => {
+                caps.push(Cap::WriteBuckets);
+                with(&mut caps, retention, Cap::WriteBucketRetentions);
+                with(&mut caps, encryption, Cap::WriteBucketEncryption);
+                with(&mut caps, replication, Cap::WriteBucketReplications);
+            },
+            Self::GetBucketNotificationRules =>
+                caps.push(Cap::ReadBucketNotifications),
+            Self::SetBucketNotificationRules =>
+                caps.push(Cap::WriteBucketNotifications),
+            Self::ListFileNames
+            | Self::ListFileVersions
+            | Self::ListUnfinishedLargeFiles => caps.push(Cap::ListFiles),
+            Self::GetFileInfo | Self::DownloadFile =>
+                caps.push(Cap::ReadFiles),
+            Self::GetDownloadAuthorization => caps.push(Cap::ShareFiles),
+            Self::UploadFile { retention, legal_hold } => {
+                caps.push(Cap::WriteFiles);
+                with(&mut caps, retention, Cap::WriteFileRetentions);
+                with(&mut caps, legal_hold, Cap::WriteFileLegalHolds);
+            },
+            Self::StartLargeFile { retention, legal_hold, encryption }
+            | Self::CopyFile { retention, legal_hold, encryption } => {
+                caps.push(Cap::WriteFiles);
+                with(&mut caps, retention, Cap::WriteFileRetentions);
+                with(&mut caps, legal_hold, Cap::WriteFileLegalHolds);
+                with(&mut caps, encryption, Cap::WriteBucketEncryption);
+            },
+            Self::ListFileParts
+            | Self::UploadFilePart
+            | Self::CopyFilePart
+            | Self::FinishLargeFile
+            | Self::CancelLargeFile
+            | Self::HideFile => caps.push(Cap::WriteFiles),
+            Self::DeleteFileVersion { bypass_governance } => {
+                caps.push(Cap::DeleteFiles);
+                with(&mut caps, bypass_governance, Cap::BypassGovernance);
+            },
+            Self::UpdateFileLegalHold => caps.push(Cap::WriteFileLegalHolds),
+            Self::UpdateFileRetention { bypass_governance } => {
+                caps.push(Cap::WriteFileRetentions);
+                with(&mut caps, bypass_governance, Cap::BypassGovernance);
+            },
+        }
+
+        caps
+    }
+}
+
+/// Get the smallest set of capabilities needed to perform all of the given
+/// operations.
+///
+/// The result can be passed to [CreateKeyBuilder::capabilities] to create a
+/// key that can perform exactly these operations.
+///
+/// # Examples
+///
+/// ```
+/// use b2_client::account::{required_capabilities, Capability, Operation};
+///
+/// let caps = required_capabilities(&[
+///     Operation::UploadFile { retention: true, legal_hold: false },
+///     Operation::DownloadFile,
+///     Operation::ListFileVersions,
+/// ]);
+///
+/// assert_eq!(caps, [
+///     Capability::WriteFiles,
+///     Capability::WriteFileRetentions,
+///     Capability::ReadFiles,
+///     Capability::ListFiles,
+/// ]);
+/// ```
+pub fn required_capabilities(operations: &[Operation]) -> Vec<Capability> {
+    let mut caps = Vec::new();
+
+    for cap in operations.iter().flat_map(Operation::required_capabilities) {
+        if ! caps.contains(&cap) {
+            caps.push(cap);
+        }
+    }
+
+    caps
 }
 
 /// Log onto the B2 API.
@@ -231,6 +1001,11 @@ pub enum Capability {
 /// You can obtain the `key_id` and `key` from the B2 administration pages or
 /// from [create_key].
 ///
+/// To sign in to a service other than [B2_AUTH_URL], such as the
+/// `b2-emulator` program, use [authorize_account_with_url]. When the `testing`
+/// feature is enabled, the `B2_CLIENT_AUTH_URL` environment variable (e.g.,
+/// `http://127.0.0.1:8000`) is used instead of [B2_AUTH_URL] if it is set.
+///
 /// See <https://www.backblaze.com/b2/docs/b2_authorize_account.html> for
 /// further information.
 ///
@@ -253,33 +1028,143 @@ pub enum Capability {
 /// let removed_key = delete_key_by_id(&mut auth, "OTHER KEY ID").await?;
 /// # Ok(()) }
 /// ```
-pub async fn authorize_account<C, E>(mut client: C, key_id: &str, key: &str)
+pub async fn authorize_account<C, E>(client: C, key_id: &str, key: &str)
 -> Result<Authorization<C>, Error<E>>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
+    authorize_account_with_url(client, &auth_url(), key_id, key).await
+}
+
+/// Log onto the B2 API at the given base URL.
+///
+/// This is [authorize_account] for a service other than the default
+/// [B2_AUTH_URL], such as a local emulator or a proxy. The authorization
+/// request is sent to `{auth_url}/b2api/v2/b2_authorize_account`; the URLs
+/// used for all later requests are taken from the response, as usual.
+///
+/// The `B2_CLIENT_AUTH_URL` environment variable has no effect on this
+/// function.
+///
+/// # Errors
+///
+/// Returns [ValidationError::BadUrl] if `auth_url` is not an HTTP or HTTPS URL.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "with_surf")]
+/// # use b2_client::{
+/// #     client::{HttpClient, SurfClient},
+/// #     account::authorize_account_with_url,
+/// # };
+/// # #[cfg(feature = "with_surf")]
+/// # async fn f() -> anyhow::Result<()> {
+/// let mut auth = authorize_account_with_url(
+///     SurfClient::default(),
+///     "http://127.0.0.1:8000",
+///     "MY KEY ID",
+///     "MY KEY"
+/// ).await?;
+/// # Ok(()) }
+/// ```
+pub async fn authorize_account_with_url<C, E>(
+    client: C,
+    auth_url: &str,
+    key_id: &str,
+    key: &str,
+) -> Result<Authorization<C>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    authorize_account_with_version(client, auth_url, ApiVersion::V2, key_id,
+        key).await
+}
+
+/// Log onto the given version of the B2 API at the given base URL.
+///
+/// This is [authorize_account_with_url] for an API version other than
+/// [ApiVersion::V2]; pass [B2_AUTH_URL] to use the B2 service itself. The
+/// returned [Authorization] sends every later request to the same version,
+/// including when a [Session](crate::session::Session) renews it.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "with_surf")]
+/// # use b2_client::{
+/// #     client::{HttpClient, SurfClient},
+/// #     account::{authorize_account_with_version, ApiVersion, B2_AUTH_URL},
+/// # };
+/// # #[cfg(feature = "with_surf")]
+/// # async fn f() -> anyhow::Result<()> {
+/// let auth = authorize_account_with_version(
+///     SurfClient::default(),
+///     B2_AUTH_URL,
+///     ApiVersion::V3,
+///     "MY KEY ID",
+///     "MY KEY"
+/// ).await?;
+///
+/// for bucket in auth.capabilities().buckets() {
+///     println!("The key may access {}", bucket.id());
+/// }
+/// # Ok(()) }
+/// ```
+pub async fn authorize_account_with_version<C, E>(
+    mut client: C,
+    auth_url: &str,
+    api_version: ApiVersion,
+    key_id: &str,
+    key: &str,
+) -> Result<Authorization<C>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    let auth_url = auth_url.trim_end_matches('/').to_owned();
+
     let id_and_key = format!("{}:{}", key_id, key);
     let id_and_key = base64::encode(id_and_key.as_bytes());
 
     let mut auth = String::from("Basic ");
     auth.push_str(&id_and_key);
 
-    let req = client.get(
-        format!("{}b2_authorize_account", B2_AUTH_URL)
-    ).expect("Invalid URL")
+    let url = format!("{}/b2api/{}/b2_authorize_account", auth_url,
+        api_version);
+
+    match url::Url::parse(&url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host()
+            => {},
+        _ => return Err(ValidationError::BadUrl(auth_url).into()),
+    }
+
+    let req = client.get(url)?
         .with_header("Authorization", &auth).unwrap();
 
+    // The token cannot have been issued before we asked for it, so this errs
+    // on the side of expiring early.
+    let issued_at = Utc::now();
     let res = req.send().await?;
 
-    let auth: B2Result<ProtoAuthorization> = serde_json::from_slice(&res)?;
-    auth.map(|v| v.create_authorization(client)).into()
+    let auth: B2Result<ProtoAuthorization> = match api_version {
+        ApiVersion::V2 => B2Result::from_response(&res)?,
+        ApiVersion::V3 => {
+            let auth: B2Result<ProtoAuthorizationV3> =
+                B2Result::from_response(&res)?;
+            auth.map(ProtoAuthorization::from)
+        },
+    };
+
+    auth.map(|v|
+        v.create_authorization(client, auth_url, api_version, issued_at)
+    ).into()
 }
 
 /// A request to create a B2 API key with certain capabilities.
 ///
 /// Use [CreateKeyBuilder] to create a `CreateKey` object, then pass it to
 /// [create_key] to create a new application [Key] from the request.
-#[derive(Serialize)]
+#[derive(Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateKey<'a> {
     // account_id is provided by the Authorization object.
@@ -288,8 +1173,9 @@ pub struct CreateKey<'a> {
     key_name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     valid_duration_in_seconds: Option<Duration>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    bucket_id: Option<String>,
+    // Sent as `bucketId` or `bucketIds` depending on the API version.
+    #[serde(skip)]
+    bucket_ids: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     name_prefix: Option<String>,
 }
@@ -312,7 +1198,7 @@ pub struct CreateKeyBuilder {
     capabilities: Option<Vec<Capability>>,
     name: Option<String>,
     valid_duration: Option<Duration>,
-    bucket_id: Option<String>,
+    bucket_ids: Vec<String>,
     name_prefix: Option<String>,
 }
 
@@ -387,7 +1273,29 @@ impl CreateKeyBuilder {
     /// Limit the key's access to the specified bucket.
     pub fn limit_to_bucket<S: Into<String>>(mut self, id: S)
     -> Result<Self, ValidationError> {
-        self.bucket_id = Some(id.into());
+        self.bucket_ids = vec![id.into()];
+        Ok(self)
+    }
+
+    /// Limit the key's access to the specified buckets.
+    ///
+    /// A key can only be restricted to more than one bucket with version 3 of
+    /// the API; [create_key] fails with a validation error if the
+    /// [Authorization] uses version 2.
+    pub fn limit_to_buckets<I, S>(mut self, ids: I)
+    -> Result<Self, ValidationError>
+        where I: IntoIterator<Item = S>,
+              S: Into<String>,
+    {
+        let ids: Vec<String> = ids.into_iter().map(Into::into).collect();
+
+        if ids.is_empty() {
+            return Err(ValidationError::MissingData(
+                "At least one bucket ID must be provided".into()
+            ));
+        }
+
+        self.bucket_ids = ids;
         Ok(self)
     }
 
@@ -415,7 +1323,7 @@ impl CreateKeyBuilder {
             )
         )?;
 
-        if self.bucket_id.is_some() {
+        if ! self.bucket_ids.is_empty() {
             for cap in &capabilities {
                 match cap {
                     Capability::ListAllBucketNames
@@ -436,7 +1344,9 @@ impl CreateKeyBuilder {
                     | Capability::WriteFileRetentions
                     | Capability::BypassGovernance
                     | Capability::ReadBucketReplications
-                    | Capability::WriteBucketReplications => {},
+                    | Capability::WriteBucketReplications
+                    | Capability::ReadBucketNotifications
+                    | Capability::WriteBucketNotifications => {},
                     cap => return Err(ValidationError::Incompatible(format!(
                         "Invalid capability when bucket_id is set: {:?}",
                         cap
@@ -454,7 +1364,7 @@ impl CreateKeyBuilder {
             capabilities,
             key_name: name,
             valid_duration_in_seconds: self.valid_duration,
-            bucket_id: self.bucket_id,
+            bucket_ids: self.bucket_ids,
             name_prefix: self.name_prefix,
         })
     }
@@ -469,7 +1379,9 @@ pub struct Key {
     capabilities: Vec<Capability>,
     account_id: String,
     expiration_timestamp: Option<DateTime<Utc>>,
+    // Version 2 of the API sends `bucketId`; version 3 sends `bucketIds`.
     bucket_id: Option<String>,
+    bucket_ids: Option<Vec<String>>,
     name_prefix: Option<String>,
     // options: Option<Vec<String>>, // Currently unused by B2.
 }
@@ -487,7 +1399,20 @@ impl Key {
     pub fn account_id(&self) -> &str { &self.account_id }
     /// If present, this key's capabilities are restricted to the returned
     /// bucket.
-    pub fn bucket_id(&self) -> Option<&String> { self.bucket_id.as_ref() }
+    ///
+    /// If the key is restricted to several buckets, this is the first of them;
+    /// see [bucket_ids](Self::bucket_ids).
+    pub fn bucket_id(&self) -> Option<&String> { self.bucket_ids().first() }
+    /// The buckets this key's capabilities are restricted to.
+    ///
+    /// If this is empty, the key can access every bucket in the account.
+    pub fn bucket_ids(&self) -> &[String] {
+        match (&self.bucket_id, &self.bucket_ids) {
+            (Some(id), _) => std::slice::from_ref(id),
+            (None, Some(ids)) => ids,
+            (None, None) => &[],
+        }
+    }
     /// If set, access is limited to files whose names begin with this prefix.
     pub fn name_prefix(&self) -> Option<&String> { self.name_prefix.as_ref() }
 
@@ -498,7 +1423,7 @@ impl Key {
 
     /// Check if the provided capability is granted by this key.
     pub fn has_capability(&self, cap: Capability) -> bool {
-        self.capabilities.iter().any(|&c| c == cap)
+        self.capabilities.contains(&cap)
     }
 }
 
@@ -516,6 +1441,7 @@ struct NewlyCreatedKey {
     account_id: String,
     expiration_timestamp: Option<DateTime<Utc>>,
     bucket_id: Option<String>,
+    bucket_ids: Option<Vec<String>>,
     name_prefix: Option<String>,
     // options: Option<Vec<String>>, Currently unused by B2.
 }
@@ -531,6 +1457,7 @@ impl NewlyCreatedKey {
             account_id: self.account_id,
             expiration_timestamp: self.expiration_timestamp,
             bucket_id: self.bucket_id,
+            bucket_ids: self.bucket_ids,
             name_prefix: self.name_prefix,
         };
 
@@ -578,18 +1505,29 @@ pub async fn create_key<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::WriteKeys);
+    require_capabilities!(auth, Operation::CreateKey);
 
     let mut new_key_info = new_key_info;
     new_key_info.account_id = Some(&auth.account_id);
 
+    let mut body = serde_json::to_value(&new_key_info)?;
+
+    match (auth.api_version, new_key_info.bucket_ids.as_slice()) {
+        (_, []) => {},
+        (ApiVersion::V2, [id]) => body["bucketId"] = serde_json::json!(id),
+        (ApiVersion::V2, _) => return Err(ValidationError::Incompatible(
+            "Restricting a key to several buckets requires API v3".into()
+        ).into()),
+        (ApiVersion::V3, ids) => body["bucketIds"] = serde_json::json!(ids),
+    }
+
     let res = auth.client.post(auth.api_url("b2_create_key"))
         .expect("Invalid URL")
         .with_header("Authorization", &auth.authorization_token).unwrap()
-        .with_body_json(serde_json::to_value(new_key_info)?)
+        .with_body_json(body)
         .send().await?;
 
-    let new_key: B2Result<NewlyCreatedKey> = serde_json::from_slice(&res)?;
+    let new_key: B2Result<NewlyCreatedKey> = B2Result::from_response(&res)?;
     new_key.map(|key| key.create_public_key()).into()
 }
 
@@ -667,7 +1605,7 @@ pub async fn delete_key_by_id<C, E, S: AsRef<str>>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::DeleteKeys);
+    require_capabilities!(auth, Operation::DeleteKey);
 
     let res = auth.client.post(auth.api_url("b2_delete_key"))
         .expect("Invalid URL")
@@ -677,10 +1615,113 @@ pub async fn delete_key_by_id<C, E, S: AsRef<str>>(
         ))
         .send().await?;
 
-    let key: B2Result<Key> = serde_json::from_slice(&res)?;
+    let key: B2Result<Key> = B2Result::from_response(&res)?;
     key.into()
 }
 
+/// Replace an application key with a new one that grants the same access.
+///
+/// The steps are performed in order, stopping at the first failure:
+///
+/// 1. Create a key with the same name, capabilities, bucket restriction, and
+///    file name prefix as `old_key`. If `expires_after` is `None` the new key
+///    does not expire, regardless of whether the old key did.
+/// 2. Verify the new key by authorizing the account with it.
+/// 3. Call `store` with the new key's secret and description so that it can
+///    be saved wherever the old key was kept.
+/// 4. Delete `old_key`.
+///
+/// Returns the new key's secret and description. On failure, the
+/// [RotateKeyError] describes which keys remain; the old key is never deleted
+/// unless `store` succeeded.
+///
+/// The [Authorization] must have [Capability::WriteKeys] and
+/// [Capability::DeleteKeys]. If it was obtained with `old_key`, it becomes
+/// invalid once the old key is deleted.
+///
+/// # Examples
+///
+/// ```no_run
+/// # #[cfg(feature = "with_surf")]
+/// # use b2_client::{
+/// #     client::{HttpClient, SurfClient},
+/// #     account::{authorize_account, list_keys, rotate_key, ListKeys},
+/// # };
+/// # #[cfg(feature = "with_surf")]
+/// # async fn f() -> anyhow::Result<()> {
+/// let mut auth = authorize_account(
+///     SurfClient::default(),
+///     "MY KEY ID",
+///     "MY KEY"
+/// ).await?;
+///
+/// let (keys, _) = list_keys(&mut auth, ListKeys::default()).await?;
+/// let old_key = keys.into_iter().find(|k| k.key_name() == "backups").unwrap();
+///
+/// let (_secret, new_key) = rotate_key(&mut auth, old_key, None,
+///     |secret, key| async move {
+///         let line = format!("{}:{}", key.key_id(), secret);
+///         std::fs::write("backups.key", line)
+///     }
+/// ).await?;
+/// # Ok(()) }
+/// ```
+pub async fn rotate_key<C, E, F, Fut, S>(
+    auth: &mut Authorization<C>,
+    old_key: Key,
+    expires_after: Option<chrono::Duration>,
+    store: F,
+) -> Result<(String, Key), RotateKeyError<E, S>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+          F: FnOnce(String, Key) -> Fut,
+          Fut: std::future::Future<Output=Result<(), S>>,
+{
+    let req = || -> Result<_, ValidationError> {
+        let mut req = CreateKey::builder()
+            .name(old_key.key_name())?
+            .capabilities(old_key.capabilities())?;
+
+        if let Some(dur) = expires_after {
+            req = req.expires_after(dur)?;
+        }
+        if ! old_key.bucket_ids().is_empty() {
+            req = req.limit_to_buckets(old_key.bucket_ids())?;
+        }
+        if let Some(prefix) = &old_key.name_prefix {
+            req = req.name_prefix(prefix)?;
+        }
+
+        req.build()
+    };
+
+    let req = req().map_err(|e| RotateKeyError::Create(e.into()))?;
+
+    let (secret, new_key) = create_key(auth, req).await
+        .map_err(RotateKeyError::Create)?;
+
+    let verified = authorize_account_with_url(
+        auth.client.clone(),
+        &auth.auth_url,
+        new_key.key_id(),
+        &secret
+    ).await;
+
+    if let Err(error) = verified {
+        let cleanup = delete_key_by_id(auth, new_key.key_id()).await.err();
+        return Err(RotateKeyError::Verify { new_key, error, cleanup });
+    }
+
+    if let Err(error) = store(secret.clone(), new_key.clone()).await {
+        return Err(RotateKeyError::Store { secret, new_key, error });
+    }
+
+    match delete_key(auth, old_key).await {
+        Ok(_) => Ok((secret, new_key)),
+        Err(error) => Err(RotateKeyError::DeleteOld { new_key, error }),
+    }
+}
+
 /// A request to obtain a list of keys associated with an account.
 ///
 /// Use [ListKeysBuilder] to create a `ListKeys`, then pass it to
@@ -699,6 +1740,15 @@ impl<'a> ListKeys<'a> {
     pub fn builder() -> ListKeysBuilder {
         ListKeysBuilder::default()
     }
+
+    /// Detach the request from the [Authorization] that returned it.
+    pub(crate) fn into_owned(self) -> ListKeys<'static> {
+        ListKeys {
+            account_id: None,
+            max_key_count: self.max_key_count,
+            start_application_key_id: self.start_application_key_id,
+        }
+    }
 }
 
 impl<'a> Default for ListKeys<'a> {
@@ -821,7 +1871,7 @@ pub async fn list_keys<'a, C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::ListKeys);
+    require_capabilities!(auth, Operation::ListKeys);
 
     let mut list_req = list_req;
     list_req.account_id = Some(&auth.account_id);
@@ -832,7 +1882,7 @@ pub async fn list_keys<'a, C, E>(
         .with_body_json(serde_json::to_value(list_req.clone())?)
         .send().await?;
 
-    let keys: B2Result<KeyList> = serde_json::from_slice(&res)?;
+    let keys: B2Result<KeyList> = B2Result::from_response(&res)?;
 
     match keys {
         B2Result::Ok(keys) => {
@@ -853,6 +1903,56 @@ pub async fn list_keys<'a, C, E>(
     }
 }
 
+/// Stream the application keys associated with the account of the given
+/// [Authorization], requesting each page of the listing as it is needed.
+///
+/// The request's key limit is the size of each page. The stream's
+/// [cursor](ListStream::cursor) is a key ID that can be passed to
+/// [ListKeysBuilder::start_at_key] to continue the listing.
+///
+/// See [list_keys] and the [pagination](crate::pagination) module.
+pub fn list_keys_stream<'a, C, E>(
+    auth: &'a mut Authorization<C>,
+    list_req: ListKeys<'a>,
+) -> ListStream<'a, C, E, ListKeys<'a>>
+    where C: HttpClient<Error=Error<E>> + Send + 'a,
+          E: fmt::Debug + fmt::Display + 'a,
+{
+    ListStream::new(auth, list_req)
+}
+
+impl<'a> Sealed for ListKeys<'a> {}
+
+impl<'a> ListRequest<'a> for ListKeys<'a> {
+    type Item = Key;
+    type Cursor = String;
+
+    fn start(&self) -> Option<String> {
+        self.start_application_key_id.clone()
+    }
+
+    fn cursor_of(key: &Key) -> String { key.application_key_id.clone() }
+
+    fn fetch<C, E>(self, auth: &'a mut Authorization<C>)
+    -> Fetch<'a, C, E, Self>
+        where C: HttpClient<Error=Error<E>> + Send + 'a,
+              E: fmt::Debug + fmt::Display + 'a,
+    {
+        Box::pin(async move {
+            // The next request borrows the account ID from `auth`, so we
+            // rebuild it without the ID; list_keys will set it again.
+            let res = list_keys(&mut *auth, self).await
+                .map(|(keys, next)| (keys, next.map(|next| ListKeys {
+                    account_id: None,
+                    max_key_count: next.max_key_count,
+                    start_application_key_id: next.start_application_key_id,
+                })));
+
+            (auth, res)
+        })
+    }
+}
+
 
 // TODO: Find a good way to mock responses for any/all backends.
 #[cfg(feature = "with_surf")]
@@ -897,6 +1997,25 @@ mod tests {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn authorize_account_bad_url() {
+        use crate::client::SurfClient;
+
+        for url in ["", "127.0.0.1:8000", "localhost:8000"] {
+            let res = authorize_account_with_url(
+                SurfClient::default(),
+                url,
+                "B2_KEY_ID",
+                "B2_AUTH_KEY"
+            ).await;
+
+            assert!(matches!(
+                res,
+                Err(Error::Validation(ValidationError::BadUrl(_)))
+            ), "{}", url);
+        }
+    }
+
     #[async_std::test]
     async fn authorize_account_bad_key() -> Result<(), anyhow::Error> {
         let client = create_test_client(
@@ -987,6 +2106,122 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn capabilities_check_restrictions() {
+        let caps = Capabilities::new(
+            vec![Capability::ListFiles],
+            Some("bucket-a".into()),
+            Some("my-bucket".into()),
+            Some("logs/".into()),
+        );
+
+        assert!(caps.check_bucket("bucket-a").is_ok());
+        assert_eq!(caps.check_bucket("bucket-b"), Err(Restriction::Bucket {
+            allowed: vec!["bucket-a".into()],
+            requested: Some("bucket-b".into()),
+        }));
+        assert!(caps.check_bucket_name("my-bucket").is_ok());
+        assert!(caps.check_bucket_name("your-bucket").is_err());
+        assert!(caps.check_all_buckets().is_err());
+
+        assert!(caps.check_file_name("logs/a.txt").is_ok());
+        assert!(caps.check_file_name("a.txt").is_err());
+        assert!(caps.check_prefix(Some("logs/2022/")).is_ok());
+        assert!(caps.check_prefix(Some("log")).is_err());
+        assert_eq!(caps.check_prefix(None), Err(Restriction::NamePrefix {
+            allowed: "logs/".into(),
+            requested: None,
+        }));
+
+        let caps = Capabilities::new(vec![], None, None, None);
+
+        assert!(caps.check_all_buckets().is_ok());
+        assert!(caps.check_bucket_name("any-bucket").is_ok());
+        assert!(caps.check_prefix(None).is_ok());
+    }
+
+    #[test]
+    fn capabilities_accept_v2_and_v3_bucket_restrictions() {
+        let v2: Capabilities = serde_json::from_value(serde_json::json!({
+            "capabilities": ["listFiles"],
+            "bucketId": "bucket-a",
+            "bucketName": "my-bucket",
+            "namePrefix": null,
+        })).unwrap();
+
+        assert_eq!(v2.buckets(), [AllowedBucket {
+            id: "bucket-a".into(),
+            name: Some("my-bucket".into()),
+        }]);
+
+        let v3: Capabilities = serde_json::from_value(serde_json::json!({
+            "capabilities": ["listFiles"],
+            "buckets": [
+                { "id": "bucket-a", "name": "my-bucket" },
+                { "id": "bucket-b", "name": null },
+            ],
+            "namePrefix": null,
+        })).unwrap();
+
+        assert_eq!(v3.bucket_id().map(String::as_str), Some("bucket-a"));
+        assert!(v3.check_bucket("bucket-b").is_ok());
+        assert_eq!(v3.check_bucket("bucket-c"), Err(Restriction::Bucket {
+            allowed: vec!["bucket-a".into(), "bucket-b".into()],
+            requested: Some("bucket-c".into()),
+        }));
+        // The name of bucket-b is unknown, so any name may refer to it.
+        assert!(v3.check_bucket_name("your-bucket").is_ok());
+        assert!(v3.check_all_buckets().is_err());
+
+        // Saved authorizations store the version 3 form.
+        let saved = serde_json::to_value(&v3).unwrap();
+        let restored: Capabilities = serde_json::from_value(saved).unwrap();
+        assert_eq!(restored.buckets(), v3.buckets());
+
+        let unrestricted: Capabilities = serde_json::from_value(
+            serde_json::json!({ "capabilities": [], "buckets": null })
+        ).unwrap();
+        assert!(unrestricted.check_all_buckets().is_ok());
+    }
+
+    #[test]
+    fn capabilities_report_missing_operations() {
+        let caps = Capabilities::new(
+            vec![Capability::ListFiles, Capability::WriteFiles],
+            None, None, None
+        );
+
+        let upload = Operation::UploadFile {
+            retention: false,
+            legal_hold: false,
+        };
+        let retain = Operation::UploadFile {
+            retention: true,
+            legal_hold: true,
+        };
+
+        assert!(caps.missing_capabilities(&[upload, Operation::ListFileNames])
+            .is_empty());
+
+        assert_eq!(
+            caps.missing_capabilities(&[
+                retain,
+                Operation::ListFileVersions,
+                Operation::DeleteFileVersion { bypass_governance: true },
+            ]),
+            [
+                (retain, vec![
+                    Capability::WriteFileRetentions,
+                    Capability::WriteFileLegalHolds,
+                ]),
+                (
+                    Operation::DeleteFileVersion { bypass_governance: true },
+                    vec![Capability::DeleteFiles, Capability::BypassGovernance]
+                ),
+            ]
+        );
+    }
+
     #[async_std::test]
     async fn test_list_keys() -> Result<(), anyhow::Error> {
         let client = create_test_client(
@@ -0,0 +1,363 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+   License, v. 2.0. If a copy of the MPL was not distributed with this
+   file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Review an account's keys and buckets for risky configuration.
+//!
+//! [audit_account] lists every application key and bucket in the account and
+//! returns an [AuditReport] describing each potential problem it found. The
+//! report can be serialized (e.g., to JSON) for further processing:
+//!
+//! ```no_run
+//! # #[cfg(feature = "with_surf")]
+//! # async fn f() -> anyhow::Result<()> {
+//! use b2_client::{
+//!     client::SurfClient,
+//!     account::authorize_account,
+//!     audit::{audit_account, Severity},
+//! };
+//!
+//! let mut auth = authorize_account(SurfClient::default(), "KEY ID", "KEY")
+//!     .await?;
+//!
+//! let report = audit_account(&mut auth).await?;
+//! println!("{}", serde_json::to_string_pretty(&report)?);
+//!
+//! if report.findings().iter().any(|f| f.severity() == Severity::High) {
+//!     // ...
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The audit needs the [ListKeys](crate::account::Capability::ListKeys) and
+//! [ListBuckets](crate::account::Capability::ListBuckets) capabilities on a key
+//! that is not restricted to a bucket. Settings that the key is not allowed to
+//! read (such as a bucket's encryption without
+//! [ReadBucketEncryption](crate::account::Capability::ReadBucketEncryption))
+//! are reported as unverifiable rather than silently skipped.
+
+use std::fmt;
+
+use crate::{
+    account::{list_keys, Authorization, Capability, Key, ListKeys},
+    bucket::{
+        list_buckets, Bucket, BucketType, CorsOperation, ListBuckets,
+        ServerSideEncryption,
+    },
+    client::HttpClient,
+    error::Error,
+    pagination::ListRequest as _,
+};
+
+use serde::{Serialize, Deserialize};
+
+
+/// Capabilities that allow a key to escalate its own access or to remove
+/// protection from locked files.
+const DANGEROUS_CAPABILITIES: [Capability; 3] = [
+    Capability::WriteKeys,
+    Capability::DeleteKeys,
+    Capability::BypassGovernance,
+];
+
+/// How serious a [Finding] is.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+/// The key or bucket to which a [Finding] applies.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum Subject {
+    #[serde(rename_all = "camelCase")]
+    Key {
+        key_id: String,
+        key_name: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    Bucket {
+        bucket_id: String,
+        bucket_name: String,
+    },
+}
+
+impl From<&Key> for Subject {
+    fn from(key: &Key) -> Self {
+        Self::Key {
+            key_id: key.key_id().to_owned(),
+            key_name: key.key_name().to_owned(),
+        }
+    }
+}
+
+impl From<&Bucket> for Subject {
+    fn from(bucket: &Bucket) -> Self {
+        Self::Bucket {
+            bucket_id: bucket.bucket_id().to_owned(),
+            bucket_name: bucket.name().to_owned(),
+        }
+    }
+}
+
+/// A potentially risky configuration.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "issue", rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum Issue {
+    /// The key has no expiration.
+    KeyNeverExpires,
+    /// The key can manage other keys or bypass governance-mode retention.
+    KeyHasDangerousCapabilities { capabilities: Vec<Capability> },
+    /// The key can access every bucket in the account.
+    KeyNotRestrictedToBucket,
+    /// Anyone can download the bucket's files.
+    PublicBucket,
+    /// A CORS rule allows any origin to upload or delete files.
+    #[serde(rename_all = "camelCase")]
+    CorsAllowsAnyOriginWrite {
+        rule_name: String,
+        operations: Vec<CorsOperation>,
+    },
+    /// The bucket does not encrypt new files by default.
+    NoDefaultEncryption,
+    /// The bucket's default encryption could not be read with the auditing
+    /// key.
+    EncryptionNotReadable,
+    /// File lock is not enabled on the bucket.
+    NoFileLock,
+    /// The bucket's file lock configuration could not be read with the
+    /// auditing key.
+    FileLockNotReadable,
+}
+
+impl Issue {
+    /// How serious the issue is.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::KeyHasDangerousCapabilities { .. }
+                | Self::PublicBucket
+                | Self::CorsAllowsAnyOriginWrite { .. } => Severity::High,
+            Self::KeyNeverExpires
+                | Self::KeyNotRestrictedToBucket
+                | Self::NoDefaultEncryption => Severity::Medium,
+            Self::EncryptionNotReadable
+                | Self::NoFileLock
+                | Self::FileLockNotReadable => Severity::Low,
+        }
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeyNeverExpires => write!(f, "Key never expires"),
+            Self::KeyHasDangerousCapabilities { capabilities } => write!(f,
+                "Key has dangerous capabilities: {:?}", capabilities),
+            Self::KeyNotRestrictedToBucket =>
+                write!(f, "Key is not restricted to a bucket"),
+            Self::PublicBucket => write!(f, "Bucket is public"),
+            Self::CorsAllowsAnyOriginWrite { rule_name, operations } =>
+                write!(f,
+                    "CORS rule {} allows any origin to perform {:?}",
+                    rule_name, operations
+                ),
+            Self::NoDefaultEncryption =>
+                write!(f, "Bucket has no default encryption"),
+            Self::EncryptionNotReadable =>
+                write!(f, "Bucket encryption settings could not be read"),
+            Self::NoFileLock => write!(f, "Bucket does not have file lock"),
+            Self::FileLockNotReadable =>
+                write!(f, "Bucket file lock settings could not be read"),
+        }
+    }
+}
+
+/// A single problem found by an audit.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Finding {
+    subject: Subject,
+    severity: Severity,
+    #[serde(flatten)]
+    issue: Issue,
+}
+
+impl Finding {
+    fn new(subject: Subject, issue: Issue) -> Self {
+        Self { subject, severity: issue.severity(), issue }
+    }
+
+    pub fn subject(&self) -> &Subject { &self.subject }
+    pub fn severity(&self) -> Severity { self.severity }
+    pub fn issue(&self) -> &Issue { &self.issue }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.subject {
+            Subject::Key { key_id, .. } => write!(f, "key {}", key_id)?,
+            Subject::Bucket { bucket_name, .. } =>
+                write!(f, "bucket {}", bucket_name)?,
+        }
+
+        write!(f, ": {}", self.issue)
+    }
+}
+
+/// The results of an audit.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditReport {
+    keys_checked: usize,
+    buckets_checked: usize,
+    findings: Vec<Finding>,
+}
+
+impl AuditReport {
+    /// Audit the provided keys and buckets.
+    ///
+    /// This does not contact the B2 service; use [audit_account] to audit
+    /// everything in an account.
+    pub fn from_parts(keys: &[Key], buckets: &[Bucket]) -> Self {
+        let mut findings = vec![];
+
+        for key in keys {
+            audit_key(key, &mut findings);
+        }
+
+        for bucket in buckets {
+            audit_bucket(bucket, &mut findings);
+        }
+
+        Self {
+            keys_checked: keys.len(),
+            buckets_checked: buckets.len(),
+            findings,
+        }
+    }
+
+    pub fn keys_checked(&self) -> usize { self.keys_checked }
+    pub fn buckets_checked(&self) -> usize { self.buckets_checked }
+    pub fn findings(&self) -> &[Finding] { &self.findings }
+
+    /// Returns `true` if nothing was found.
+    pub fn is_clean(&self) -> bool { self.findings.is_empty() }
+
+    /// Get the findings at or above the given severity.
+    pub fn at_least(&self, severity: Severity)
+    -> impl Iterator<Item = &Finding> {
+        self.findings.iter().filter(move |f| f.severity >= severity)
+    }
+}
+
+fn audit_key(key: &Key, findings: &mut Vec<Finding>) {
+    let mut report = |issue| findings.push(Finding::new(key.into(), issue));
+
+    if key.expiration().is_none() {
+        report(Issue::KeyNeverExpires);
+    }
+
+    let capabilities: Vec<_> = DANGEROUS_CAPABILITIES.iter()
+        .filter(|c| key.has_capability(**c))
+        .copied()
+        .collect();
+
+    if ! capabilities.is_empty() {
+        report(Issue::KeyHasDangerousCapabilities { capabilities });
+    }
+
+    if key.bucket_id().is_none() {
+        report(Issue::KeyNotRestrictedToBucket);
+    }
+}
+
+fn audit_bucket(bucket: &Bucket, findings: &mut Vec<Finding>) {
+    let mut report = |issue| findings.push(Finding::new(bucket.into(), issue));
+
+    if let BucketType::Public = bucket.bucket_type() {
+        report(Issue::PublicBucket);
+    }
+
+    for rule in bucket.cors_rules() {
+        if ! rule.allowed_origins().iter().any(|o| o == "*") {
+            continue;
+        }
+
+        let operations: Vec<_> = rule.allowed_operations().iter()
+            .filter(|op| is_write(op))
+            .copied()
+            .collect();
+
+        if ! operations.is_empty() {
+            report(Issue::CorsAllowsAnyOriginWrite {
+                rule_name: rule.name().to_owned(),
+                operations,
+            });
+        }
+    }
+
+    let encryption = bucket.encryption_info();
+    if ! encryption.can_read() {
+        report(Issue::EncryptionNotReadable);
+    } else if matches!(
+        encryption.settings(),
+        None | Some(ServerSideEncryption::NoEncryption)
+    ) {
+        report(Issue::NoDefaultEncryption);
+    }
+
+    match bucket.file_lock_configuration().lock_is_enabled() {
+        Some(true) => {},
+        Some(false) => report(Issue::NoFileLock),
+        None => report(Issue::FileLockNotReadable),
+    }
+}
+
+fn is_write(op: &CorsOperation) -> bool {
+    matches!(op,
+        CorsOperation::UploadFile
+        | CorsOperation::UploadPart
+        | CorsOperation::S3Put
+        | CorsOperation::S3Post
+        | CorsOperation::S3Delete
+    )
+}
+
+/// Audit every application key and bucket in the account.
+///
+/// The authorization must not be restricted to a bucket. See the
+/// [module documentation](self) for the required capabilities.
+pub async fn audit_account<C, E>(auth: &mut Authorization<C>)
+-> Result<AuditReport, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    let mut keys = vec![];
+    let mut start = None;
+
+    loop {
+        let mut req = ListKeys::builder();
+        if let Some(id) = start.take() {
+            req = req.start_at_key(id)?;
+        }
+
+        let (page, next) = list_keys(auth, req.build()).await?;
+        keys.extend(page);
+
+        // The next request borrows the authorization, so we rebuild it.
+        match next.and_then(|req| req.start()) {
+            Some(id) => start = Some(id),
+            None => break,
+        }
+    }
+
+    let buckets = list_buckets(auth, ListBuckets::builder().build()).await?;
+
+    Ok(AuditReport::from_parts(&keys, &buckets))
+}
@@ -0,0 +1,80 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+   License, v. 2.0. If a copy of the MPL was not distributed with this
+   file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Serve an emulation of the B2 native API on a local port.
+//!
+//! Buckets, files, and keys are stored in a directory so that they survive
+//! restarting the emulator. Point the library at it by passing the URL printed
+//! at startup to `authorize_account_with_url` or, in programs built with the
+//! `testing` feature, by setting the `B2_CLIENT_AUTH_URL` environment variable
+//! to it.
+
+use std::{net::TcpListener, process};
+
+use b2_client::testing::FakeB2;
+
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:8000";
+const DEFAULT_DIRECTORY: &str = "b2-emulator-data";
+
+fn usage() -> String {
+    format!(
+        "Usage: b2-emulator [--listen ADDRESS] [--data DIRECTORY]\n\
+        \n\
+        Options:\n    \
+            --listen ADDRESS    Address to listen on [default: {}]\n    \
+            --data DIRECTORY    Directory to store data in [default: {}]\n    \
+            --help              Print this message",
+        DEFAULT_ADDRESS, DEFAULT_DIRECTORY
+    )
+}
+
+fn fail(msg: impl std::fmt::Display) -> ! {
+    eprintln!("b2-emulator: {}", msg);
+    process::exit(1);
+}
+
+fn main() {
+    let mut address = DEFAULT_ADDRESS.to_owned();
+    let mut directory = DEFAULT_DIRECTORY.to_owned();
+
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--listen" => address = args.next()
+                .unwrap_or_else(|| fail("--listen requires an address")),
+            "--data" => directory = args.next()
+                .unwrap_or_else(|| fail("--data requires a directory")),
+            "-h" | "--help" => {
+                println!("{}", usage());
+                return;
+            },
+            _ => fail(format!("Unknown argument: {}\n\n{}", arg, usage())),
+        }
+    }
+
+    let b2 = FakeB2::open(&directory)
+        .unwrap_or_else(|e| fail(format!("Cannot open {}: {}", directory, e)));
+
+    let listener = TcpListener::bind(&address).unwrap_or_else(|e|
+        fail(format!("Cannot listen on {}: {}", address, e))
+    );
+
+    let addr = listener.local_addr().unwrap_or_else(|e| fail(e));
+    let (key_id, key) = b2.master_key();
+
+    println!("Serving the B2 API at http://{}", addr);
+    println!("Data is stored in {}", directory);
+    println!();
+    println!("    export B2_CLIENT_AUTH_URL=http://{}", addr);
+    println!();
+    println!("Master key ID: {}", key_id);
+    println!("Master key:    {}", key);
+
+    if let Err(e) = b2.serve(listener) {
+        fail(e);
+    }
+}
@@ -0,0 +1,900 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+   License, v. 2.0. If a copy of the MPL was not distributed with this
+   file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! A synchronous API for programs that do not use an async runtime.
+//!
+//! Each function in this module calls the async function of the same name and
+//! blocks the current thread until it completes. No runtime is started; the
+//! thread is parked until the request makes progress.
+//!
+//! This only works with an [HttpClient] that does not need a runtime of its
+//! own. The `with_ureq` feature provides `client::UreqClient`, which performs
+//! its I/O synchronously and is intended for use with this module. Clients
+//! built on tokio, such as `HyperClient`, will panic.
+//!
+//! ```no_run
+//! # #[cfg(feature = "with_ureq")]
+//! # fn f() -> anyhow::Result<()> {
+//! use b2_client::{self as b2, blocking, client::UreqClient};
+//!
+//! let mut auth = blocking::authorize_account(
+//!     UreqClient::default(),
+//!     "key-id",
+//!     "key"
+//! )?;
+//!
+//! let mut upload_auth = blocking::get_upload_authorization_by_id(
+//!     &mut auth,
+//!     "my-bucket-id"
+//! )?;
+//!
+//! let file = b2::UploadFile::builder()
+//!     .file_name("my-file.txt")?
+//!     .sha1_checksum("61b8d6600ac94d912874f569a9341120f680c9f8")
+//!     .build()?;
+//!
+//! let file_info = blocking::upload_file(
+//!     &mut upload_auth,
+//!     file,
+//!     b"very important information"
+//! )?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [block_on] can be used to run any other async function in this library,
+//! such as [Session::call](crate::session::Session::call).
+
+use std::{
+    fmt,
+    future::Future,
+    io::Read,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, Wake},
+    thread::{self, Thread},
+};
+
+use crate::{
+    account::{self, Authorization, CreateKey, Key, ListKeys},
+    bucket::{
+        self, Bucket, CreateBucket, ListBuckets, NotificationRule,
+        ServerSideEncryption, UpdateBucket,
+    },
+    client::{self, HeaderMap, HttpClient},
+    error::{Error, RotateKeyError},
+    file::{
+        self, BypassGovernance, CancelledFileUpload, CopyFile, CopyFilePart,
+        DeletedFile, DownloadAuth, DownloadAuthorization,
+        DownloadAuthorizationRequest, DownloadFile, File, FilePart,
+        ListFileNames, ListFileParts, ListFileVersions,
+        ListUnfinishedLargeFiles, StartLargeFile,
+        UpdateFileLegalHold, UpdateFileRetention, UploadAuthorization,
+        UploadFile, UploadFilePart, UploadPartAuthorization,
+    },
+};
+
+use futures_io::AsyncRead;
+
+
+/// Wakes a thread parked in [block_on].
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) { self.0.unpark(); }
+    fn wake_by_ref(self: &Arc<Self>) { self.0.unpark(); }
+}
+
+/// Run a future to completion on the current thread.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = std::pin::pin!(future);
+
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            // A spurious wakeup just polls the future again.
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// A [Read] over an [AsyncRead] source.
+///
+/// Every read blocks the current thread until the source has data.
+pub struct BlockingReader<R> {
+    inner: R,
+}
+
+impl<R> BlockingReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    /// Return the underlying reader.
+    pub fn into_inner(self) -> R { self.inner }
+}
+
+impl<R> Read for BlockingReader<R>
+    where R: AsyncRead + Unpin,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let inner = &mut self.inner;
+
+        block_on(std::future::poll_fn(|cx| {
+            Pin::new(&mut *inner).poll_read(cx, buf)
+        }))
+    }
+}
+
+impl<R> fmt::Debug for BlockingReader<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BlockingReader").finish_non_exhaustive()
+    }
+}
+
+/// An [AsyncRead] over a blocking [Read] source.
+///
+/// Reads complete immediately, blocking the thread that polls them.
+pub(crate) struct AsyncReader<R> {
+    inner: R,
+}
+
+impl<R> AsyncReader<R> {
+    pub(crate) fn new(inner: R) -> Self {
+        Self { inner }
+    }
+}
+
+impl<R> AsyncRead for AsyncReader<R>
+    where R: Read + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8]
+    ) -> Poll<std::io::Result<usize>> {
+        Poll::Ready(self.inner.read(buf))
+    }
+}
+
+/// See [account::authorize_account].
+pub fn authorize_account<C, E>(client: C, key_id: &str, key: &str)
+-> Result<Authorization<C>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(account::authorize_account(client, key_id, key))
+}
+
+/// See [account::authorize_account_with_url].
+pub fn authorize_account_with_url<C, E>(
+    client: C,
+    auth_url: &str,
+    key_id: &str,
+    key: &str,
+) -> Result<Authorization<C>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(account::authorize_account_with_url(client, auth_url, key_id, key))
+}
+
+/// See [account::create_key].
+pub fn create_key<C, E>(
+    auth: &mut Authorization<C>,
+    new_key_info: CreateKey<'_>
+) -> Result<(String, Key), Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(account::create_key(auth, new_key_info))
+}
+
+/// See [account::delete_key].
+pub fn delete_key<C, E>(auth: &mut Authorization<C>, key: Key)
+-> Result<Key, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(account::delete_key(auth, key))
+}
+
+/// See [account::delete_key_by_id].
+pub fn delete_key_by_id<C, E, S: AsRef<str>>(
+    auth: &mut Authorization<C>,
+    key_id: S
+) -> Result<Key, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(account::delete_key_by_id(auth, key_id))
+}
+
+/// See [account::rotate_key].
+///
+/// `store` is called with the new key's secret and description and must save
+/// them before the old key is deleted.
+#[allow(clippy::result_large_err)]
+pub fn rotate_key<C, E, F, S>(
+    auth: &mut Authorization<C>,
+    old_key: Key,
+    expires_after: Option<chrono::Duration>,
+    store: F,
+) -> Result<(String, Key), RotateKeyError<E, S>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+          F: FnOnce(String, Key) -> Result<(), S>,
+{
+    block_on(account::rotate_key(auth, old_key, expires_after,
+        |secret, key| std::future::ready(store(secret, key))
+    ))
+}
+
+/// See [account::list_keys].
+pub fn list_keys<'a, C, E>(
+    auth: &'a mut Authorization<C>,
+    list_req: ListKeys<'a>
+) -> Result<(Vec<Key>, Option<ListKeys<'a>>), Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(account::list_keys(auth, list_req))
+}
+
+/// See [bucket::create_bucket].
+pub fn create_bucket<C, E>(
+    auth: &mut Authorization<C>,
+    new_bucket_info: CreateBucket<'_>
+) -> Result<Bucket, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(bucket::create_bucket(auth, new_bucket_info))
+}
+
+/// See [bucket::delete_bucket].
+pub fn delete_bucket<C, E>(
+    auth: &mut Authorization<C>,
+    bucket_id: impl AsRef<str>
+) -> Result<Bucket, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(bucket::delete_bucket(auth, bucket_id))
+}
+
+/// See [bucket::get_bucket_notification_rules].
+pub fn get_bucket_notification_rules<C, E>(
+    auth: &mut Authorization<C>,
+    bucket_id: impl AsRef<str>
+) -> Result<Vec<NotificationRule>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(bucket::get_bucket_notification_rules(auth, bucket_id))
+}
+
+/// See [bucket::list_buckets].
+pub fn list_buckets<C, E>(
+    auth: &mut Authorization<C>,
+    list_info: ListBuckets<'_>
+) -> Result<Vec<Bucket>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(bucket::list_buckets(auth, list_info))
+}
+
+/// See [bucket::set_bucket_notification_rules].
+pub fn set_bucket_notification_rules<C, E>(
+    auth: &mut Authorization<C>,
+    bucket_id: impl AsRef<str>,
+    rules: impl Into<Vec<NotificationRule>>
+) -> Result<Vec<NotificationRule>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(bucket::set_bucket_notification_rules(auth, bucket_id, rules))
+}
+
+/// See [bucket::update_bucket].
+pub fn update_bucket<C, E>(
+    auth: &mut Authorization<C>,
+    bucket_info: UpdateBucket<'_>
+) -> Result<Bucket, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(bucket::update_bucket(auth, bucket_info))
+}
+
+/// See [file::cancel_large_file].
+pub fn cancel_large_file<C, E>(auth: &mut Authorization<C>, file: File)
+-> Result<CancelledFileUpload, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::cancel_large_file(auth, file))
+}
+
+/// See [file::cancel_large_file_by_id].
+pub fn cancel_large_file_by_id<C, E>(
+    auth: &mut Authorization<C>,
+    id: impl AsRef<str>
+) -> Result<CancelledFileUpload, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::cancel_large_file_by_id(auth, id))
+}
+
+/// See [file::copy_file].
+pub fn copy_file<C, E>(
+    auth: &mut Authorization<C>,
+    file: CopyFile<'_>
+) -> Result<File, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::copy_file(auth, file))
+}
+
+/// See [file::copy_file_part].
+pub fn copy_file_part<C, E>(
+    auth: &mut Authorization<C>,
+    file_part: CopyFilePart<'_>
+) -> Result<FilePart, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::copy_file_part(auth, file_part))
+}
+
+/// See [file::delete_file_version].
+pub fn delete_file_version<C, E>(
+    auth: &mut Authorization<C>,
+    file: File,
+    bypass_governance: BypassGovernance,
+) -> Result<DeletedFile, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::delete_file_version(auth, file, bypass_governance))
+}
+
+/// See [file::delete_file_version_by_name_id].
+pub fn delete_file_version_by_name_id<C, E>(
+    auth: &mut Authorization<C>,
+    file_name: impl AsRef<str>,
+    file_id: impl AsRef<str>,
+    bypass_governance: BypassGovernance,
+) -> Result<DeletedFile, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::delete_file_version_by_name_id(
+        auth, file_name, file_id, bypass_governance
+    ))
+}
+
+/// See [file::download_file_headers].
+pub fn download_file_headers<C, E>(
+    auth: &mut Authorization<C>,
+    file: &File
+) -> Result<HeaderMap, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::download_file_headers(auth, file))
+}
+
+/// See [file::download_file_headers_by_id].
+pub fn download_file_headers_by_id<C, E>(
+    auth: &mut Authorization<C>,
+    file_id: impl AsRef<str>
+) -> Result<HeaderMap, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::download_file_headers_by_id(auth, file_id))
+}
+
+/// See [file::download_file].
+pub fn download_file<'a, C, E>(
+    auth: impl Into<DownloadAuth<'a, C>>,
+    file: DownloadFile<'_>
+) -> Result<(Vec<u8>, HeaderMap), Error<E>>
+    where C: HttpClient<Error=Error<E>> + 'a,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::download_file(auth, file))
+}
+
+/// See [file::download_file_stream].
+///
+/// The returned reader blocks on each read.
+pub fn download_file_stream<'a, C, E>(
+    auth: impl Into<DownloadAuth<'a, C>>,
+    file: DownloadFile<'_>
+) -> Result<(BlockingReader<client::ResponseReader>, HeaderMap), Error<E>>
+    where C: HttpClient<Error=Error<E>> + 'a,
+          E: fmt::Debug + fmt::Display,
+{
+    let (body, headers) = block_on(file::download_file_stream(auth, file))?;
+    Ok((BlockingReader::new(body), headers))
+}
+
+/// See [file::finish_large_file_upload].
+pub fn finish_large_file_upload<C, E>(
+    auth: &mut Authorization<C>,
+    file: &File,
+    sha1_checksums: &[String],
+) -> Result<File, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::finish_large_file_upload(auth, file, sha1_checksums))
+}
+
+/// See [file::finish_large_file_upload_by_id].
+pub fn finish_large_file_upload_by_id<C, E>(
+    auth: &mut Authorization<C>,
+    file_id: impl AsRef<str>,
+    sha1_checksums: &[String],
+) -> Result<File, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::finish_large_file_upload_by_id(
+        auth, file_id, sha1_checksums
+    ))
+}
+
+/// See [file::get_file_info].
+pub fn get_file_info<C, E>(
+    auth: &mut Authorization<C>,
+    file_id: impl AsRef<str>
+) -> Result<File, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::get_file_info(auth, file_id))
+}
+
+/// See [file::get_download_authorization].
+pub fn get_download_authorization<C, E>(
+    auth: &mut Authorization<C>,
+    download_req: DownloadAuthorizationRequest<'_>
+) -> Result<DownloadAuthorization<C>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::get_download_authorization(auth, download_req))
+}
+
+/// See [file::get_upload_part_authorization].
+pub fn get_upload_part_authorization<C, E>(
+    auth: &mut Authorization<C>,
+    file: &File,
+) -> Result<UploadPartAuthorization<C, E>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::get_upload_part_authorization(auth, file))
+}
+
+/// See [file::get_upload_part_authorization_by_id].
+pub fn get_upload_part_authorization_by_id<C, E>(
+    auth: &mut Authorization<C>,
+    file_id: impl AsRef<str>,
+    encryption: Option<&ServerSideEncryption>,
+) -> Result<UploadPartAuthorization<C, E>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::get_upload_part_authorization_by_id(
+        auth, file_id, encryption
+    ))
+}
+
+/// See [file::get_upload_authorization].
+pub fn get_upload_authorization<C, E>(
+    auth: &mut Authorization<C>,
+    bucket: &Bucket,
+) -> Result<UploadAuthorization<C, E>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::get_upload_authorization(auth, bucket))
+}
+
+/// See [file::get_upload_authorization_by_id].
+pub fn get_upload_authorization_by_id<C, E>(
+    auth: &mut Authorization<C>,
+    bucket_id: impl AsRef<str>,
+) -> Result<UploadAuthorization<C, E>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::get_upload_authorization_by_id(auth, bucket_id))
+}
+
+/// See [file::hide_file].
+pub fn hide_file<C, E>(auth: &mut Authorization<C>, file: &File)
+-> Result<File, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::hide_file(auth, file))
+}
+
+/// See [file::hide_file_by_name].
+pub fn hide_file_by_name<C, E>(
+    auth: &mut Authorization<C>,
+    bucket_id: impl AsRef<str>,
+    file_name: impl AsRef<str>,
+) -> Result<File, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::hide_file_by_name(auth, bucket_id, file_name))
+}
+
+/// See [file::list_file_names].
+pub fn list_file_names<'a, C, E>(
+    auth: &mut Authorization<C>,
+    request: ListFileNames<'a>,
+) -> Result<(Vec<File>, Option<ListFileNames<'a>>), Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::list_file_names(auth, request))
+}
+
+/// See [file::list_file_versions].
+pub fn list_file_versions<'a, C, E>(
+    auth: &mut Authorization<C>,
+    request: ListFileVersions<'a>,
+) -> Result<(Vec<File>, Option<ListFileVersions<'a>>), Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::list_file_versions(auth, request))
+}
+
+/// See [file::list_file_parts].
+pub fn list_file_parts<'a, C, E>(
+    auth: &mut Authorization<C>,
+    request: ListFileParts<'a>,
+) -> Result<(Vec<FilePart>, Option<ListFileParts<'a>>), Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::list_file_parts(auth, request))
+}
+
+/// See [file::list_unfinished_large_files].
+pub fn list_unfinished_large_files<'a, C, E>(
+    auth: &mut Authorization<C>,
+    request: ListUnfinishedLargeFiles<'a>
+) -> Result<(Vec<File>, Option<ListUnfinishedLargeFiles<'a>>), Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::list_unfinished_large_files(auth, request))
+}
+
+/// See [file::start_large_file].
+pub fn start_large_file<C, E>(
+    auth: &mut Authorization<C>,
+    file: StartLargeFile<'_>
+) -> Result<File, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::start_large_file(auth, file))
+}
+
+/// See [file::update_file_legal_hold].
+pub fn update_file_legal_hold<C, E>(
+    auth: &mut Authorization<C>,
+    file_update: UpdateFileLegalHold<'_>
+) -> Result<(), Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::update_file_legal_hold(auth, file_update))
+}
+
+/// See [file::update_file_retention].
+pub fn update_file_retention<C, E>(
+    auth: &mut Authorization<C>,
+    retention_update: UpdateFileRetention<'_>,
+) -> Result<(), Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::update_file_retention(auth, retention_update))
+}
+
+/// See [file::upload_file].
+pub fn upload_file<C, E>(
+    auth: &mut UploadAuthorization<C, E>,
+    upload: UploadFile<'_>,
+    data: &[u8],
+) -> Result<File, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::upload_file(auth, upload, data))
+}
+
+/// See [file::upload_file_stream].
+///
+/// `data` is read on the calling thread as the request is sent.
+pub fn upload_file_stream<C, E, R>(
+    auth: &mut UploadAuthorization<C, E>,
+    upload: UploadFile<'_>,
+    data: R,
+    content_length: u64,
+) -> Result<File, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+          R: Read + Send + Sync + Unpin + 'static,
+{
+    block_on(file::upload_file_stream(
+        auth, upload, AsyncReader::new(data), content_length
+    ))
+}
+
+/// See [file::upload_file_part].
+pub fn upload_file_part<C, E>(
+    auth: &mut UploadPartAuthorization<C, E>,
+    upload: &UploadFilePart<'_>,
+    data: &[u8],
+) -> Result<FilePart, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    block_on(file::upload_file_part(auth, upload, data))
+}
+
+/// See [file::upload_file_part_stream].
+///
+/// `data` is read on the calling thread as the request is sent.
+pub fn upload_file_part_stream<C, E, R>(
+    auth: &mut UploadPartAuthorization<C, E>,
+    upload: &UploadFilePart<'_>,
+    data: R,
+    content_length: u64,
+) -> Result<FilePart, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+          R: Read + Send + Sync + Unpin + 'static,
+{
+    block_on(file::upload_file_part_stream(
+        auth, upload, AsyncReader::new(data), content_length
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn block_on_waits_for_wakeup() {
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let waker = Arc::new(std::sync::Mutex::new(None));
+
+        let future_waker = waker.clone();
+        let future = std::future::poll_fn(move |cx| {
+            match rx.try_recv() {
+                Ok(()) => Poll::Ready(42),
+                Err(_) => {
+                    *future_waker.lock().unwrap() = Some(cx.waker().clone());
+                    Poll::Pending
+                },
+            }
+        });
+
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            tx.send(()).unwrap();
+
+            if let Some(waker) = waker.lock().unwrap().take() {
+                waker.wake();
+            }
+        });
+
+        assert_eq!(block_on(future), 42);
+    }
+
+    #[test]
+    fn readers_convert_between_sync_and_async() {
+        let data = b"some data".to_vec();
+
+        let mut reader = BlockingReader::new(
+            AsyncReader::new(std::io::Cursor::new(data.clone()))
+        );
+
+        let mut out = vec![];
+        reader.read_to_end(&mut out).unwrap();
+
+        assert_eq!(out, data);
+    }
+}
+
+#[cfg(all(test, feature = "with_ureq"))]
+mod tests_ureq {
+    //! Run the recorded sessions used for the surf tests via the blocking API
+    //! and a UreqClient.
+
+    use super::*;
+    use crate::{
+        account::Capability,
+        bucket::{BucketType, LifecycleRule},
+        client::UreqClient,
+        error::ErrorCode,
+        file::ByteRange,
+        replay_server::ReplayServer,
+    };
+
+
+    #[test]
+    fn list_buckets_success() -> anyhow::Result<()> {
+        let server = ReplayServer::start("test_sessions/buckets.yaml");
+        let mut auth = server.create_test_auth(
+            UreqClient::default(),
+            vec![Capability::ListBuckets]
+        );
+
+        let req = ListBuckets::builder()
+            .bucket_name("testing-b2-client")?
+            .build();
+
+        let buckets = list_buckets(&mut auth, req)?;
+        assert_eq!(buckets.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn create_bucket_already_exists() -> anyhow::Result<()> {
+        let server = ReplayServer::start("test_sessions/buckets.yaml");
+        let mut auth = server.create_test_auth(
+            UreqClient::default(),
+            vec![Capability::WriteBuckets]
+        );
+
+        let req = CreateBucket::builder()
+            .name("testing-b2-client")?
+            .bucket_type(BucketType::Private)?
+            .lifecycle_rules(vec![
+                LifecycleRule::builder()
+                    .filename_prefix("my-files/")?
+                    .delete_after_hide(chrono::Duration::days(5))?
+                    .build()?
+            ])?
+            .build()?;
+
+        match create_bucket(&mut auth, req).unwrap_err() {
+            Error::B2(e) =>
+                assert_eq!(e.code(), ErrorCode::DuplicateBucketName),
+            e => panic!("Unexpected error: {:?}", e),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn upload_file_success() -> anyhow::Result<()> {
+        let server = ReplayServer::start("test_sessions/file.yaml");
+        let mut auth = server.create_test_auth(
+            UreqClient::default(),
+            vec![Capability::WriteFiles]
+        );
+
+        let mut upload_auth = get_upload_authorization_by_id(
+            &mut auth,
+            "8d625eb63be2775577c70e1a"
+        )?;
+
+        let file = UploadFile::builder()
+            .file_name("test-file-upload.txt")?
+            .sha1_checksum("81fe8bfe87576c3ecb22426f8e57847382917acf")
+            .build()?;
+
+        let file = upload_file(&mut upload_auth, file, b"abcd")?;
+        assert_eq!(file.file_name(), "test-file-upload.txt");
+
+        Ok(())
+    }
+
+    #[test]
+    fn upload_file_stream_success() -> anyhow::Result<()> {
+        let server = ReplayServer::start("test_sessions/file.yaml");
+        let mut auth = server.create_test_auth(
+            UreqClient::default(),
+            vec![Capability::WriteFiles]
+        );
+
+        let mut upload_auth = get_upload_authorization_by_id(
+            &mut auth,
+            "8d625eb63be2775577c70e1a"
+        )?;
+
+        let file = UploadFile::builder()
+            .file_name("test-file-upload.txt")?
+            .sha1_checksum("81fe8bfe87576c3ecb22426f8e57847382917acf")
+            .build()?;
+
+        let data = std::io::Cursor::new(b"abcd");
+        let file = upload_file_stream(&mut upload_auth, file, data, 4)?;
+        assert_eq!(file.file_name(), "test-file-upload.txt");
+
+        Ok(())
+    }
+
+    #[test]
+    fn download_file_by_id_success() -> anyhow::Result<()> {
+        let server = ReplayServer::start("test_sessions/file.yaml");
+        let mut auth = server.create_test_auth(
+            UreqClient::default(),
+            vec![Capability::ReadFiles]
+        );
+
+        let req = DownloadFile::with_id(concat!("4_z8d625eb63be2775577c70e1a_f",
+            "111954e3108ff3f6_d20211118_m151810_c002_v0001168_t0010"));
+
+        let (file, _headers) = download_file(&mut auth, req)?;
+        assert_eq!(file, b"Some text\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn download_file_range_success() -> anyhow::Result<()> {
+        let server = ReplayServer::start("test_sessions/file.yaml");
+        let mut auth = server.create_test_auth(
+            UreqClient::default(),
+            vec![Capability::ReadFiles]
+        );
+
+        let req = DownloadFile::builder()
+            .file_name("test-file.txt", "testing-b2-client")
+            .range(ByteRange::new(5, 8)?)
+            .build()?;
+
+        let (file, _headers) = download_file(&mut auth, req)?;
+        assert_eq!(file, b"text");
+
+        Ok(())
+    }
+
+    #[test]
+    fn download_file_stream_by_name_success() -> anyhow::Result<()> {
+        let server = ReplayServer::start("test_sessions/file.yaml");
+        let mut auth = server.create_test_auth(
+            UreqClient::default(),
+            vec![Capability::ReadFiles]
+        );
+
+        let req = DownloadFile::with_name("test-file.txt", "testing-b2-client");
+
+        let (mut body, headers) = download_file_stream(&mut auth, req)?;
+
+        let mut file = vec![];
+        body.read_to_end(&mut file)?;
+
+        assert_eq!(file, b"Some text\n");
+        assert!(headers.contains_key("x-bz-file-name"));
+
+        Ok(())
+    }
+}
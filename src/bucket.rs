@@ -11,7 +11,7 @@
 //! A B2 account has a limit of 100 buckets. All bucket names must be globally
 //! unique (unique across all accounts).
 
-use std::{borrow::Cow, fmt};
+use std::{borrow::Cow, collections::BTreeMap, fmt};
 
 use crate::{
     prelude::*,
@@ -52,7 +52,7 @@ impl fmt::Display for BucketType {
 }
 
 /// A valid CORS operation for B2 buckets.
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
 #[non_exhaustive]
 pub enum CorsOperation {
     #[serde(rename = "b2_download_file_by_name")]
@@ -98,6 +98,13 @@ impl CorsRule {
     pub fn builder() -> CorsRuleBuilder {
         CorsRuleBuilder::default()
     }
+
+    pub fn name(&self) -> &str { &self.cors_rule_name }
+    pub fn allowed_origins(&self) -> &[String] { &self.allowed_origins }
+
+    pub fn allowed_operations(&self) -> &[CorsOperation] {
+        &self.allowed_operations
+    }
 }
 
 /// Create a [CorsRule].
@@ -377,7 +384,7 @@ pub struct LifecycleRule {
 
 impl std::cmp::PartialOrd for LifecycleRule {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        self.file_name_prefix.partial_cmp(&other.file_name_prefix)
+        Some(self.cmp(other))
     }
 }
 
@@ -479,18 +486,20 @@ impl<'a> LifecycleRuleBuilder<'a> {
     /// at least one of a [hide_after_upload](Self::hide_after_upload) or
     /// [delete_after_hide](Self::delete_after_hide) rule set.
     pub fn build(self) -> Result<LifecycleRule, ValidationError> {
-        if self.prefix.is_none() {
-            Err(ValidationError::MissingData(
+        let prefix = self.prefix.ok_or_else(||
+            ValidationError::MissingData(
                 "Rule must have a filename prefix".into()
-            ))
-        } else if self.hide_after.is_none() && self.delete_after.is_none() {
+            )
+        )?;
+
+        if self.hide_after.is_none() && self.delete_after.is_none() {
             Err(ValidationError::Incompatible(
                 "The rule must have at least one of a hide or deletion rule"
                     .into()
             ))
         } else {
             Ok(LifecycleRule {
-                file_name_prefix: self.prefix.unwrap().to_owned(),
+                file_name_prefix: prefix.to_owned(),
                 delete_after: self.delete_after,
                 hide_after: self.hide_after,
             })
@@ -498,6 +507,794 @@ impl<'a> LifecycleRuleBuilder<'a> {
     }
 }
 
+/// A rule to replicate new files from a source bucket to a destination bucket.
+///
+/// See <https://www.backblaze.com/docs/cloud-storage-cloud-replication> for
+/// further information.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationRule {
+    #[serde(rename = "replicationRuleName")]
+    name: String,
+    destination_bucket_id: String,
+    file_name_prefix: String,
+    include_existing_files: bool,
+    is_enabled: bool,
+    priority: u32,
+}
+
+impl ReplicationRule {
+    /// Get a builder for a `ReplicationRule`.
+    pub fn builder() -> ReplicationRuleBuilder {
+        ReplicationRuleBuilder::default()
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+    pub fn destination_bucket_id(&self) -> &str { &self.destination_bucket_id }
+    pub fn file_name_prefix(&self) -> &str { &self.file_name_prefix }
+
+    /// Whether files that existed before the rule was created are also
+    /// replicated.
+    pub fn includes_existing_files(&self) -> bool {
+        self.include_existing_files
+    }
+
+    pub fn is_enabled(&self) -> bool { self.is_enabled }
+    pub fn priority(&self) -> u32 { self.priority }
+}
+
+/// A builder for a [ReplicationRule].
+pub struct ReplicationRuleBuilder {
+    name: Option<String>,
+    destination_bucket_id: Option<String>,
+    prefix: String,
+    priority: u32,
+    include_existing_files: bool,
+    enabled: bool,
+}
+
+impl Default for ReplicationRuleBuilder {
+    fn default() -> Self {
+        Self {
+            name: None,
+            destination_bucket_id: None,
+            prefix: String::new(),
+            priority: 1,
+            include_existing_files: false,
+            enabled: true,
+        }
+    }
+}
+
+impl ReplicationRuleBuilder {
+    /// Set the rule's name, which must be unique within the bucket.
+    ///
+    /// Names can contain ASCII letters, numbers, and '-', and must be between 2
+    /// and 64 characters, inclusive.
+    pub fn name(mut self, name: impl Into<String>)
+    -> Result<Self, ValidationError> {
+        self.name = Some(validated_replication_rule_name(name)?);
+        Ok(self)
+    }
+
+    /// Replicate files to the bucket with the given ID.
+    ///
+    /// The destination may be in another account.
+    pub fn destination_bucket_id(mut self, id: impl Into<String>) -> Self {
+        self.destination_bucket_id = Some(id.into());
+        self
+    }
+
+    /// Only replicate files whose names begin with the given prefix.
+    ///
+    /// The default prefix of `""` replicates every file.
+    pub fn file_name_prefix(mut self, prefix: &str)
+    -> Result<Self, FileNameValidationError> {
+        self.prefix = validated_file_name(prefix)?.to_owned();
+        Ok(self)
+    }
+
+    /// Set the rule's priority, used to choose between rules that would
+    /// replicate the same file to the same destination.
+    ///
+    /// The priority must be between 1 and 2,147,483,647; the default is 1.
+    pub fn priority(mut self, priority: u32) -> Result<Self, ValidationError> {
+        if priority < 1 || priority > i32::MAX as u32 {
+            return Err(ValidationError::OutOfBounds(format!(
+                "Priority must be between 1 and {}", i32::MAX
+            )));
+        }
+
+        self.priority = priority;
+        Ok(self)
+    }
+
+    /// Also replicate the files that are in the bucket when the rule is
+    /// created.
+    ///
+    /// By default only new files are replicated.
+    pub fn include_existing_files(mut self, include: bool) -> Self {
+        self.include_existing_files = include;
+        self
+    }
+
+    /// Create the rule in the given state; rules are enabled by default.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Create a [ReplicationRule].
+    ///
+    /// # Errors
+    ///
+    /// Returns [ValidationError::MissingData] if the name or destination
+    /// bucket was not provided.
+    pub fn build(self) -> Result<ReplicationRule, ValidationError> {
+        let name = self.name.ok_or_else(||
+            ValidationError::MissingData(
+                "The replication rule must have a name".into()
+            )
+        )?;
+
+        let destination_bucket_id = self.destination_bucket_id.ok_or_else(||
+            ValidationError::MissingData(
+                "The replication rule must have a destination bucket".into()
+            )
+        )?;
+
+        Ok(ReplicationRule {
+            name,
+            destination_bucket_id,
+            file_name_prefix: self.prefix,
+            include_existing_files: self.include_existing_files,
+            is_enabled: self.enabled,
+            priority: self.priority,
+        })
+    }
+}
+
+/// A bucket's replication settings.
+///
+/// A bucket can be the source of replication rules, the destination of
+/// another bucket's rules, or both. Setting a configuration with neither (the
+/// [Default]) on a bucket removes its replication.
+///
+/// See <https://www.backblaze.com/docs/cloud-storage-cloud-replication> for
+/// further information.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplicationConfiguration {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    as_replication_source: Option<ReplicationSource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    as_replication_destination: Option<ReplicationDestination>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplicationSource {
+    replication_rules: Vec<ReplicationRule>,
+    source_application_key_id: String,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ReplicationDestination {
+    source_to_destination_key_mapping: BTreeMap<String, String>,
+}
+
+impl ReplicationConfiguration {
+    /// Get a builder for a `ReplicationConfiguration`.
+    pub fn builder() -> ReplicationConfigurationBuilder {
+        ReplicationConfigurationBuilder::default()
+    }
+
+    /// The rules replicating this bucket's files to other buckets.
+    pub fn rules(&self) -> &[ReplicationRule] {
+        self.as_replication_source.as_ref()
+            .map(|s| s.replication_rules.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// The ID of the application key used to read the files to replicate, if
+    /// this bucket is a replication source.
+    pub fn source_key_id(&self) -> Option<&str> {
+        self.as_replication_source.as_ref()
+            .map(|s| s.source_application_key_id.as_str())
+    }
+
+    /// If this bucket is a replication destination, the application key in
+    /// this account used to write the files replicated with each source
+    /// bucket's key.
+    pub fn key_mapping(&self) -> Option<&BTreeMap<String, String>> {
+        self.as_replication_destination.as_ref()
+            .map(|d| &d.source_to_destination_key_mapping)
+    }
+}
+
+/// A builder for a [ReplicationConfiguration].
+#[derive(Default)]
+pub struct ReplicationConfigurationBuilder {
+    source_key_id: Option<String>,
+    rules: Vec<ReplicationRule>,
+    key_mapping: BTreeMap<String, String>,
+}
+
+impl ReplicationConfigurationBuilder {
+    /// Replicate this bucket's files using the application key with the given
+    /// ID.
+    ///
+    /// The key must be in this bucket's account and be able to read its files.
+    pub fn source_key_id(mut self, key_id: impl Into<String>) -> Self {
+        self.source_key_id = Some(key_id.into());
+        self
+    }
+
+    /// Replicate this bucket's files according to the given rules.
+    ///
+    /// Every rule must have a different name.
+    pub fn rules(mut self, rules: impl Into<Vec<ReplicationRule>>)
+    -> Result<Self, ValidationError> {
+        let rules = rules.into();
+
+        for (i, rule) in rules.iter().enumerate() {
+            if rules[..i].iter().any(|r| r.name == rule.name) {
+                return Err(ValidationError::Incompatible(format!(
+                    "Duplicate replication rule name: {}", rule.name
+                )));
+            }
+        }
+
+        self.rules = rules;
+        Ok(self)
+    }
+
+    /// Accept files replicated from another bucket, writing them with the
+    /// key `destination_key_id` when the source bucket replicates them with
+    /// `source_key_id`.
+    ///
+    /// This may be called once for each source key.
+    pub fn map_source_key(
+        mut self,
+        source_key_id: impl Into<String>,
+        destination_key_id: impl Into<String>,
+    ) -> Self {
+        self.key_mapping
+            .insert(source_key_id.into(), destination_key_id.into());
+        self
+    }
+
+    /// Create a [ReplicationConfiguration].
+    ///
+    /// # Errors
+    ///
+    /// Returns [ValidationError::MissingData] if replication rules are given
+    /// without a [source key](Self::source_key_id), or a source key without
+    /// rules.
+    pub fn build(self) -> Result<ReplicationConfiguration, ValidationError> {
+        let as_replication_source = match (self.source_key_id, self.rules) {
+            (None, rules) if rules.is_empty() => None,
+            (Some(_), rules) if rules.is_empty() =>
+                return Err(ValidationError::MissingData(
+                    "A replication source must have at least one rule".into()
+                )),
+            (None, _) => return Err(ValidationError::MissingData(
+                "Replication rules require a source application key".into()
+            )),
+            (Some(key_id), rules) => Some(ReplicationSource {
+                replication_rules: rules,
+                source_application_key_id: key_id,
+            }),
+        };
+
+        let as_replication_destination = if self.key_mapping.is_empty() {
+            None
+        } else {
+            Some(ReplicationDestination {
+                source_to_destination_key_mapping: self.key_mapping,
+            })
+        };
+
+        Ok(ReplicationConfiguration {
+            as_replication_source,
+            as_replication_destination,
+        })
+    }
+}
+
+/// A kind of event that can trigger a [NotificationRule].
+///
+/// The `*All` types match every event in their category. A rule cannot list
+/// both a category's `*All` type and another type in the same category.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[non_exhaustive]
+pub enum EventType {
+    ObjectCreatedAll,
+    ObjectCreatedUpload,
+    ObjectCreatedMultipartUpload,
+    ObjectCreatedCopy,
+    ObjectCreatedReplica,
+    ObjectCreatedMultipartReplica,
+    ObjectDeletedAll,
+    ObjectDeletedDelete,
+    ObjectDeletedLifecycleRule,
+    HideMarkerCreatedAll,
+    HideMarkerCreatedHide,
+    HideMarkerCreatedLifecycleRule,
+    /// Sent when testing a rule's target; it cannot be used in a rule.
+    TestEvent,
+    /// An event type that this library does not recognize, by its B2 name.
+    ///
+    /// It cannot be used in a newly-built rule, but a rule read from B2 that
+    /// contains it is sent back unchanged.
+    Unknown(String),
+}
+
+impl EventType {
+    const KNOWN: [EventType; 13] = [
+        Self::ObjectCreatedAll,
+        Self::ObjectCreatedUpload,
+        Self::ObjectCreatedMultipartUpload,
+        Self::ObjectCreatedCopy,
+        Self::ObjectCreatedReplica,
+        Self::ObjectCreatedMultipartReplica,
+        Self::ObjectDeletedAll,
+        Self::ObjectDeletedDelete,
+        Self::ObjectDeletedLifecycleRule,
+        Self::HideMarkerCreatedAll,
+        Self::HideMarkerCreatedHide,
+        Self::HideMarkerCreatedLifecycleRule,
+        Self::TestEvent,
+    ];
+
+    /// The event type's name in the B2 API.
+    fn name(&self) -> &str {
+        match self {
+            Self::ObjectCreatedAll => "b2:ObjectCreated:*",
+            Self::ObjectCreatedUpload => "b2:ObjectCreated:Upload",
+            Self::ObjectCreatedMultipartUpload =>
+                "b2:ObjectCreated:MultipartUpload",
+            Self::ObjectCreatedCopy => "b2:ObjectCreated:Copy",
+            Self::ObjectCreatedReplica => "b2:ObjectCreated:Replica",
+            Self::ObjectCreatedMultipartReplica =>
+                "b2:ObjectCreated:MultipartReplica",
+            Self::ObjectDeletedAll => "b2:ObjectDeleted:*",
+            Self::ObjectDeletedDelete => "b2:ObjectDeleted:Delete",
+            Self::ObjectDeletedLifecycleRule =>
+                "b2:ObjectDeleted:LifecycleRule",
+            Self::HideMarkerCreatedAll => "b2:HideMarkerCreated:*",
+            Self::HideMarkerCreatedHide => "b2:HideMarkerCreated:Hide",
+            Self::HideMarkerCreatedLifecycleRule =>
+                "b2:HideMarkerCreated:LifecycleRule",
+            Self::TestEvent => "b2:TestEvent",
+            Self::Unknown(name) => name,
+        }
+    }
+
+    fn category(&self) -> &str {
+        match self {
+            Self::ObjectCreatedAll
+            | Self::ObjectCreatedUpload
+            | Self::ObjectCreatedMultipartUpload
+            | Self::ObjectCreatedCopy
+            | Self::ObjectCreatedReplica
+            | Self::ObjectCreatedMultipartReplica => "ObjectCreated",
+            Self::ObjectDeletedAll
+            | Self::ObjectDeletedDelete
+            | Self::ObjectDeletedLifecycleRule => "ObjectDeleted",
+            Self::HideMarkerCreatedAll
+            | Self::HideMarkerCreatedHide
+            | Self::HideMarkerCreatedLifecycleRule => "HideMarkerCreated",
+            Self::TestEvent => "TestEvent",
+            // "b2:Category:Event"
+            Self::Unknown(name) => name.split(':').nth(1).unwrap_or(name),
+        }
+    }
+
+    fn is_wildcard(&self) -> bool {
+        matches!(self,
+            Self::ObjectCreatedAll
+            | Self::ObjectDeletedAll
+            | Self::HideMarkerCreatedAll
+        )
+    }
+
+    /// Returns `true` if an event of type `other` would also match this type,
+    /// or the reverse.
+    fn overlaps(&self, other: &Self) -> bool {
+        self == other
+            || (self.category() == other.category()
+                && (self.is_wildcard() || other.is_wildcard()))
+    }
+}
+
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl Serialize for EventType {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: serde::Serializer,
+    {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for EventType {
+    fn deserialize<D>(deserializer: D) -> Result<EventType, D::Error>
+        where D: serde::Deserializer<'de>,
+    {
+        let name = String::deserialize(deserializer)?;
+
+        Ok(Self::KNOWN.iter()
+            .find(|typ| typ.name() == name)
+            .cloned()
+            .unwrap_or(Self::Unknown(name)))
+    }
+}
+
+/// A rule that sends a bucket's events to a webhook.
+///
+/// See <https://www.backblaze.com/docs/cloud-storage-event-notifications> for
+/// further information.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationRule {
+    name: String,
+    event_types: Vec<EventType>,
+    object_name_prefix: String,
+    is_enabled: bool,
+    target_configuration: WebhookTarget,
+    // Set by B2 if it stops sending events to a failing target.
+    #[serde(default, skip_serializing)]
+    is_suspended: bool,
+    #[serde(default, skip_serializing)]
+    suspension_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookTarget {
+    // Always "webhook".
+    target_type: String,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hmac_sha256_signing_secret: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    custom_headers: Option<Vec<CustomHeader>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+struct CustomHeader {
+    name: String,
+    value: String,
+}
+
+impl NotificationRule {
+    /// Get a builder for a `NotificationRule`.
+    pub fn builder() -> NotificationRuleBuilder {
+        NotificationRuleBuilder::default()
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+    pub fn event_types(&self) -> &[EventType] { &self.event_types }
+    pub fn object_name_prefix(&self) -> &str { &self.object_name_prefix }
+    pub fn is_enabled(&self) -> bool { self.is_enabled }
+
+    /// The URL to which events are sent.
+    pub fn url(&self) -> &str { &self.target_configuration.url }
+
+    /// The secret used to sign each request to the [url](Self::url).
+    pub fn signing_secret(&self) -> Option<&str> {
+        self.target_configuration.hmac_sha256_signing_secret.as_deref()
+    }
+
+    /// The additional headers sent with each request to the
+    /// [url](Self::url).
+    pub fn custom_headers(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.target_configuration.custom_headers.iter().flatten()
+            .map(|h| (h.name.as_str(), h.value.as_str()))
+    }
+
+    /// Returns `true` if B2 has stopped sending events for this rule, such as
+    /// after repeated failures to reach its target.
+    pub fn is_suspended(&self) -> bool { self.is_suspended }
+
+    /// Why the rule was suspended, if it was.
+    pub fn suspension_reason(&self) -> Option<&str> {
+        self.suspension_reason.as_deref().filter(|r| ! r.is_empty())
+    }
+}
+
+/// A builder for a [NotificationRule].
+pub struct NotificationRuleBuilder {
+    name: Option<String>,
+    event_types: Vec<EventType>,
+    prefix: String,
+    url: Option<String>,
+    secret: Option<String>,
+    headers: Vec<CustomHeader>,
+    enabled: bool,
+}
+
+impl Default for NotificationRuleBuilder {
+    fn default() -> Self {
+        Self {
+            name: None,
+            event_types: vec![],
+            prefix: String::new(),
+            url: None,
+            secret: None,
+            headers: vec![],
+            enabled: true,
+        }
+    }
+}
+
+impl NotificationRuleBuilder {
+    /// Set the rule's name, which must be unique within the bucket.
+    ///
+    /// Names can contain ASCII letters, numbers, and '-', and must be no more
+    /// than 63 characters.
+    pub fn name(mut self, name: impl Into<String>)
+    -> Result<Self, ValidationError> {
+        self.name = Some(validated_notification_rule_name(name)?);
+        Ok(self)
+    }
+
+    /// Send events of the given types.
+    ///
+    /// At least one type is required. No two types may match the same event,
+    /// and neither [EventType::TestEvent] nor [EventType::Unknown] is
+    /// allowed.
+    pub fn event_types(mut self, types: impl Into<Vec<EventType>>)
+    -> Result<Self, ValidationError> {
+        let types = types.into();
+
+        if types.is_empty() {
+            return Err(ValidationError::MissingData(
+                "A notification rule must have at least one event type".into()
+            ));
+        } else if types.contains(&EventType::TestEvent) {
+            return Err(ValidationError::BadFormat(
+                "Test events cannot be used in a notification rule".into()
+            ));
+        } else if types.iter().any(|t| matches!(t, EventType::Unknown(_))) {
+            return Err(ValidationError::BadFormat(
+                "Unknown events cannot be used in a notification rule".into()
+            ));
+        }
+
+        for (i, typ) in types.iter().enumerate() {
+            if let Some(other) = types[..i].iter().find(|t| t.overlaps(typ)) {
+                return Err(ValidationError::Incompatible(format!(
+                    "Event types {} and {} overlap", other, typ
+                )));
+            }
+        }
+
+        self.event_types = types;
+        Ok(self)
+    }
+
+    /// Only send events for files whose names begin with the given prefix.
+    ///
+    /// The default prefix of `""` matches every file.
+    pub fn object_name_prefix(mut self, prefix: &str)
+    -> Result<Self, FileNameValidationError> {
+        self.prefix = validated_file_name(prefix)?.to_owned();
+        Ok(self)
+    }
+
+    /// Send events to the given URL, which must use HTTPS.
+    pub fn url(mut self, url: impl AsRef<str>)
+    -> Result<Self, ValidationError> {
+        let url = url::Url::parse(url.as_ref())?;
+
+        if url.scheme() != "https" {
+            return Err(ValidationError::BadUrl(
+                "The webhook URL must use HTTPS".into()
+            ));
+        }
+
+        self.url = Some(url.into());
+        Ok(self)
+    }
+
+    /// Sign each request with the given secret, which must be 32 ASCII letters
+    /// and numbers.
+    ///
+    /// Use [webhook::verify_signature](crate::webhook::verify_signature) to
+    /// check the signatures of received requests.
+    pub fn signing_secret(mut self, secret: impl Into<String>)
+    -> Result<Self, ValidationError> {
+        let secret = secret.into();
+
+        if secret.len() != 32
+            || ! secret.chars().all(|c| c.is_ascii_alphanumeric())
+        {
+            return Err(ValidationError::BadFormat(
+                "The signing secret must be 32 ASCII alphanumeric characters"
+                    .into()
+            ));
+        }
+
+        self.secret = Some(secret);
+        Ok(self)
+    }
+
+    /// Send an additional header with each request.
+    ///
+    /// No more than 10 headers may be added, and their names cannot begin
+    /// with `X-Bz-`.
+    pub fn custom_header(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>
+    ) -> Result<Self, ValidationError> {
+        let name = name.into();
+
+        validated_http_header(&name)
+            .map_err(|e| ValidationError::BadFormat(e.to_string()))?;
+
+        if name.to_ascii_lowercase().starts_with("x-bz-") {
+            return Err(ValidationError::BadFormat(
+                "Custom header names cannot begin with X-Bz-".into()
+            ));
+        } else if self.headers.len() == 10 {
+            return Err(ValidationError::OutOfBounds(
+                "A notification rule can have no more than 10 headers".into()
+            ));
+        }
+
+        self.headers.push(CustomHeader { name, value: value.into() });
+        Ok(self)
+    }
+
+    /// Create the rule in the given state; rules are enabled by default.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Create a [NotificationRule].
+    ///
+    /// # Errors
+    ///
+    /// Returns [ValidationError::MissingData] if the name, event types, or URL
+    /// was not provided.
+    pub fn build(self) -> Result<NotificationRule, ValidationError> {
+        let name = self.name.ok_or_else(||
+            ValidationError::MissingData(
+                "The notification rule must have a name".into()
+            )
+        )?;
+
+        if self.event_types.is_empty() {
+            return Err(ValidationError::MissingData(
+                "A notification rule must have at least one event type".into()
+            ));
+        }
+
+        let url = self.url.ok_or_else(||
+            ValidationError::MissingData(
+                "The notification rule must have a URL".into()
+            )
+        )?;
+
+        Ok(NotificationRule {
+            name,
+            event_types: self.event_types,
+            object_name_prefix: self.prefix,
+            is_enabled: self.enabled,
+            target_configuration: WebhookTarget {
+                target_type: "webhook".into(),
+                url,
+                hmac_sha256_signing_secret: self.secret,
+                custom_headers: Some(self.headers)
+                    .filter(|h| ! h.is_empty()),
+            },
+            is_suspended: false,
+            suspension_reason: None,
+        })
+    }
+}
+
+/// The body of a request B2 sends to a [NotificationRule]'s URL.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EventNotification {
+    events: Vec<NotificationEvent>,
+}
+
+impl EventNotification {
+    pub fn events(&self) -> &[NotificationEvent] { &self.events }
+    pub fn into_events(self) -> Vec<NotificationEvent> { self.events }
+}
+
+/// An event that matched a [NotificationRule].
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationEvent {
+    account_id: String,
+    bucket_id: String,
+    bucket_name: String,
+    event_id: String,
+    // Milliseconds since midnight, 1970-1-1
+    event_timestamp: i64,
+    event_type: EventType,
+    event_version: u32,
+    matched_rule_name: String,
+    // Test events do not refer to a file.
+    #[serde(default)]
+    object_name: Option<String>,
+    #[serde(default)]
+    object_size: Option<u64>,
+    #[serde(default)]
+    object_version_id: Option<String>,
+}
+
+impl NotificationEvent {
+    pub fn account_id(&self) -> &str { &self.account_id }
+    pub fn bucket_id(&self) -> &str { &self.bucket_id }
+    pub fn bucket_name(&self) -> &str { &self.bucket_name }
+
+    /// A unique ID for the event; an event may be sent more than once.
+    pub fn event_id(&self) -> &str { &self.event_id }
+    pub fn event_type(&self) -> &EventType { &self.event_type }
+    pub fn event_version(&self) -> u32 { self.event_version }
+    pub fn matched_rule_name(&self) -> &str { &self.matched_rule_name }
+
+    /// When the event occurred.
+    pub fn timestamp(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        use chrono::{TimeZone as _, Utc};
+
+        Utc.timestamp_millis_opt(self.event_timestamp).single()
+    }
+
+    /// The name of the file the event concerns.
+    pub fn file_name(&self) -> Option<&str> { self.object_name.as_deref() }
+
+    /// The ID of the file version the event concerns.
+    pub fn file_id(&self) -> Option<&str> {
+        self.object_version_id.as_deref()
+    }
+
+    /// The number of bytes stored in the file, if the event created it.
+    pub fn content_length(&self) -> Option<u64> { self.object_size }
+
+    /// The [FileAction](crate::file::FileAction) of the file version the
+    /// event created.
+    ///
+    /// Returns `None` for deletions and test events, which do not create a
+    /// file version.
+    pub fn action(&self) -> Option<crate::file::FileAction> {
+        use crate::file::FileAction;
+
+        match self.event_type {
+            EventType::ObjectCreatedUpload
+                | EventType::ObjectCreatedMultipartUpload
+                | EventType::ObjectCreatedReplica
+                | EventType::ObjectCreatedMultipartReplica =>
+                    Some(FileAction::Upload),
+            EventType::ObjectCreatedCopy => Some(FileAction::Copy),
+            EventType::HideMarkerCreatedHide
+                | EventType::HideMarkerCreatedLifecycleRule =>
+                    Some(FileAction::Hide),
+            // Wildcards are only used in rules, not in events.
+            EventType::ObjectCreatedAll
+                | EventType::ObjectDeletedAll
+                | EventType::ObjectDeletedDelete
+                | EventType::ObjectDeletedLifecycleRule
+                | EventType::HideMarkerCreatedAll
+                | EventType::TestEvent
+                | EventType::Unknown(_) => None,
+        }
+    }
+}
+
 /// Valid encryption algorithms for server-side encryption.
 ///
 /// AES256 is the only supported algorithm.
@@ -541,52 +1338,221 @@ impl SelfManagedEncryption {
     }
 }
 
-/// Configuration for server-side encryption.
-#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
-#[serde(try_from = "serialization::InnerEncryptionConfig")]
-#[serde(into = "serialization::InnerEncryptionConfig")]
-pub enum ServerSideEncryption {
-    /// Let the B2 service manage encryption settings.
-    B2Managed(EncryptionAlgorithm),
-    /// Provide the encryption configuration for the B2 service to use.
-    SelfManaged(SelfManagedEncryption),
-    /// Do not encrypt the bucket or file.
-    NoEncryption,
+/// Configuration for server-side encryption.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "serialization::InnerEncryptionConfig")]
+#[serde(into = "serialization::InnerEncryptionConfig")]
+pub enum ServerSideEncryption {
+    /// Let the B2 service manage encryption settings.
+    B2Managed(EncryptionAlgorithm),
+    /// Provide the encryption configuration for the B2 service to use.
+    SelfManaged(SelfManagedEncryption),
+    /// Do not encrypt the bucket or file.
+    #[default]
+    NoEncryption,
+}
+
+impl ServerSideEncryption {
+    /// Generate the headers required when uploading files.
+    pub(crate) fn to_headers(&self)
+    -> Option<Vec<(&'static str, Cow<'_, str>)>> {
+        match self {
+            Self::B2Managed(enc) => {
+                Some(vec![
+                    ("X-Bz-Server-Side-Encryption", Cow::from(enc.to_string()))
+                ])
+            },
+            Self::SelfManaged(enc) => {
+                Some(vec![
+                    (
+                        "X-Bz-Server-Side-Encryption",
+                        Cow::from(enc.algorithm.to_string())
+                    ),
+                    (
+                        "X-Bz-Server-Side-Encryption-Customer-Key",
+                        Cow::from(&enc.key)
+                    ),
+                    (
+                        "X-Bz-Server-Side-Encryption-Customer-Key-Md5",
+                        Cow::from(&enc.digest)
+                    )
+                ])
+            },
+            Self::NoEncryption => None,
+        }
+    }
+}
+
+/// The most entries B2 allows in a bucket's [BucketInfo].
+const MAX_BUCKET_INFO_ENTRIES: usize = 10;
+
+/// The largest total size in bytes of a [BucketInfo]'s keys and values; this
+/// is the limit B2 places on file info.
+const MAX_BUCKET_INFO_BYTES: usize = 7000;
+
+const CACHE_CONTROL: &str = "Cache-Control";
+
+/// User-defined information stored with a [Bucket].
+///
+/// Entries with a meaning to B2, such as the default Cache-Control header of
+/// the bucket's files, have their own accessors; any other entries are
+/// [custom](Self::custom) strings for your own use.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(from = "BTreeMap<String, String>")]
+pub struct BucketInfo {
+    #[serde(rename = "Cache-Control")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cache_control: Option<String>,
+    #[serde(flatten)]
+    custom: BTreeMap<String, String>,
+}
+
+impl BucketInfo {
+    /// Get a builder for a `BucketInfo`.
+    pub fn builder() -> BucketInfoBuilder {
+        BucketInfoBuilder::default()
+    }
+
+    /// The default Cache-Control header value for files downloaded from the
+    /// bucket.
+    pub fn cache_control(&self) -> Option<&str> {
+        self.cache_control.as_deref()
+    }
+
+    /// The custom entries, excluding those with their own accessors.
+    pub fn custom(&self) -> &BTreeMap<String, String> { &self.custom }
+
+    /// Get the value of the entry with the given key.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        if key.eq_ignore_ascii_case(CACHE_CONTROL) {
+            self.cache_control()
+        } else {
+            self.custom.get(key).map(String::as_str)
+        }
+    }
+
+    /// The number of entries.
+    pub fn len(&self) -> usize {
+        self.custom.len() + usize::from(self.cache_control.is_some())
+    }
+
+    pub fn is_empty(&self) -> bool { self.len() == 0 }
+
+    fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.cache_control.iter()
+            .map(|v| (CACHE_CONTROL, v.as_str()))
+            .chain(self.custom.iter().map(|(k, v)| (k.as_str(), v.as_str())))
+    }
+
+    fn validated(self) -> Result<Self, ValidationError> {
+        if self.len() > MAX_BUCKET_INFO_ENTRIES {
+            return Err(ValidationError::OutOfBounds(format!(
+                "Bucket info cannot contain more than {} entries",
+                MAX_BUCKET_INFO_ENTRIES
+            )));
+        }
+
+        let mut size = 0;
+
+        for (key, value) in self.entries() {
+            if let Some(ch) = value.chars().find(char::is_ascii_control) {
+                return Err(ValidationError::BadFormat(format!(
+                    "Invalid character in bucket info value for {}: {:?}",
+                    key, ch
+                )));
+            }
+
+            size += key.len() + value.len();
+        }
+
+        if size > MAX_BUCKET_INFO_BYTES {
+            Err(ValidationError::OutOfBounds(format!(
+                "Bucket info cannot be larger than {} bytes, but is {}",
+                MAX_BUCKET_INFO_BYTES, size
+            )))
+        } else {
+            Ok(self)
+        }
+    }
+}
+
+impl From<BTreeMap<String, String>> for BucketInfo {
+    /// Create a `BucketInfo` from B2's map of entries.
+    ///
+    /// B2 does not preserve the case of every key, so the Cache-Control entry
+    /// is found regardless of case.
+    fn from(mut custom: BTreeMap<String, String>) -> Self {
+        let key = custom.keys()
+            .find(|k| k.eq_ignore_ascii_case(CACHE_CONTROL))
+            .cloned();
+
+        Self {
+            cache_control: key.and_then(|k| custom.remove(&k)),
+            custom,
+        }
+    }
+}
+
+/// A builder for a [BucketInfo].
+#[derive(Default)]
+pub struct BucketInfoBuilder {
+    info: BucketInfo,
 }
 
-impl Default for ServerSideEncryption {
-    fn default() -> Self {
-        Self::NoEncryption
+impl BucketInfoBuilder {
+    /// Set the default Cache-Control header value for files downloaded from the
+    /// bucket.
+    pub fn cache_control(mut self, cache_control: CacheControl) -> Self {
+        self.info.cache_control = Some(cache_control.value().to_string());
+        self
     }
-}
 
-impl ServerSideEncryption {
-    /// Generate the headers required when uploading files.
-    pub(crate) fn to_headers(&self) -> Option<Vec<(&'static str, Cow<str>)>> {
-        match self {
-            Self::B2Managed(enc) => {
-                Some(vec![
-                    ("X-Bz-Server-Side-Encryption", Cow::from(enc.to_string()))
-                ])
-            },
-            Self::SelfManaged(enc) => {
-                Some(vec![
-                    (
-                        "X-Bz-Server-Side-Encryption",
-                        Cow::from(enc.algorithm.to_string())
-                    ),
-                    (
-                        "X-Bz-Server-Side-Encryption-Customer-Key",
-                        Cow::from(&enc.key)
-                    ),
-                    (
-                        "X-Bz-Server-Side-Encryption-Customer-Key-Md5",
-                        Cow::from(&enc.digest)
-                    )
-                ])
-            },
-            Self::NoEncryption => None,
+    /// Add a custom entry, replacing any previous value for the key.
+    ///
+    /// Keys must be no longer than 50 bytes, and may contain letters, numbers,
+    /// and the characters ``-_.`~!#$%^&*'|+``. Values are checked by
+    /// [build](Self::build). Use the dedicated methods
+    /// (e.g., [cache_control](Self::cache_control)) to set entries with a
+    /// meaning to B2.
+    pub fn entry(mut self, key: impl Into<String>, value: impl Into<String>)
+    -> Result<Self, ValidationError> {
+        let key = validated_bucket_info_key(key)?;
+
+        if key.eq_ignore_ascii_case(CACHE_CONTROL) {
+            return Err(ValidationError::BadFormat(
+                "Use cache_control to set the Cache-Control entry".into()
+            ));
         }
+
+        self.info.custom.insert(key, value.into());
+        Ok(self)
+    }
+
+    /// Create a [BucketInfo].
+    ///
+    /// # Errors
+    ///
+    /// * Returns [ValidationError::OutOfBounds] if there are more than 10
+    ///   entries, or if the keys and values total more than 7,000 bytes.
+    /// * Returns [ValidationError::BadFormat] if a value contains an ASCII
+    ///   control character.
+    pub fn build(self) -> Result<BucketInfo, ValidationError> {
+        self.info.validated()
+    }
+}
+
+/// Add a Cache-Control value set on a request builder to the request's bucket
+/// info.
+fn merged_bucket_info(info: Option<BucketInfo>, cache_control: Option<String>)
+-> Result<Option<BucketInfo>, ValidationError> {
+    match cache_control {
+        Some(cache_control) => {
+            let mut info = info.unwrap_or_default();
+            info.cache_control = Some(cache_control);
+
+            info.validated().map(Some)
+        },
+        None => Ok(info),
     }
 }
 
@@ -602,7 +1568,7 @@ pub struct CreateBucket<'a> {
     bucket_name: String,
     bucket_type: BucketType,
     #[serde(skip_serializing_if = "Option::is_none")]
-    bucket_info: Option<serde_json::Value>,
+    bucket_info: Option<BucketInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     cors_rules: Option<Vec<CorsRule>>,
     file_lock_enabled: bool,
@@ -610,6 +1576,8 @@ pub struct CreateBucket<'a> {
     lifecycle_rules: Option<Vec<LifecycleRule>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     default_server_side_encryption: Option<ServerSideEncryption>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    replication_configuration: Option<ReplicationConfiguration>,
 }
 
 impl<'a> CreateBucket<'a> {
@@ -628,12 +1596,13 @@ impl<'a> CreateBucket<'a> {
 pub struct CreateBucketBuilder {
     bucket_name: Option<String>,
     bucket_type: Option<BucketType>,
-    bucket_info: Option<serde_json::Value>,
+    bucket_info: Option<BucketInfo>,
     cache_control: Option<String>,
     cors_rules: Option<Vec<CorsRule>>,
     file_lock_enabled: bool,
     lifecycle_rules: Option<Vec<LifecycleRule>>,
     default_server_side_encryption: Option<ServerSideEncryption>,
+    replication_configuration: Option<ReplicationConfiguration>,
 }
 
 impl CreateBucketBuilder {
@@ -667,21 +1636,12 @@ impl CreateBucketBuilder {
 
     /// Use the provided information with the bucket.
     ///
-    /// This can contain arbitrary metadata for your own use. You can also set
-    /// cache-control settings from here (but see
-    /// [cache_control](Self::cache_control)). If Cache-Control is set here and
-    /// via the `cache-control` method, the latter will override this value.
-    // TODO: Validate CORS rules if provided.
-    pub fn bucket_info(mut self, info: serde_json::Value)
-    -> Result<Self, ValidationError> {
-        if info.is_object() {
-            self.bucket_info = Some(info);
-            Ok(self)
-        } else {
-            Err(ValidationError::BadFormat(
-                "Bucket info must be a JSON object".into()
-            ))
-        }
+    /// If Cache-Control is set here and via the
+    /// [cache_control](Self::cache_control) method, the latter will override
+    /// this value.
+    pub fn bucket_info(mut self, info: BucketInfo) -> Self {
+        self.bucket_info = Some(info);
+        self
     }
 
     /// Set the default Cache-Control header value for files downloaded from the
@@ -801,6 +1761,18 @@ impl CreateBucketBuilder {
         self
     }
 
+    /// Use the provided replication settings on the bucket.
+    ///
+    /// The [Authorization] must have
+    /// [Capability::WriteBucketReplications].
+    pub fn replication_configuration(
+        mut self,
+        config: ReplicationConfiguration
+    ) -> Self {
+        self.replication_configuration = Some(config);
+        self
+    }
+
     /// Create a [CreateBucket].
     pub fn build<'a>(self) -> Result<CreateBucket<'a>, ValidationError> {
         let bucket_name = self.bucket_name.ok_or_else(||
@@ -815,21 +1787,8 @@ impl CreateBucketBuilder {
             )
         )?;
 
-        let bucket_info = if let Some(cache_control) = self.cache_control {
-            let mut info = self.bucket_info.unwrap_or_else(||
-                serde_json::Value::Object(serde_json::Map::new())
-            );
-
-            info.as_object_mut()
-                .map(|map| map.insert(
-                    String::from("Cache-Control"),
-                    serde_json::Value::String(cache_control)
-                ));
-
-            Some(info)
-        } else {
-            self.bucket_info
-        };
+        let bucket_info =
+            merged_bucket_info(self.bucket_info, self.cache_control)?;
 
         Ok(CreateBucket {
             account_id: None,
@@ -840,19 +1799,25 @@ impl CreateBucketBuilder {
             file_lock_enabled: self.file_lock_enabled,
             lifecycle_rules: self.lifecycle_rules,
             default_server_side_encryption: self.default_server_side_encryption,
+            replication_configuration: self.replication_configuration,
         })
     }
 }
 
-/// Information from B2 concerning a file's retention settings.
-#[derive(Debug, Deserialize)]
+/// Information from B2 concerning a bucket's file lock settings.
+#[derive(Debug, Clone, Copy, Deserialize)]
 pub struct FileLockConfiguration {
     #[serde(rename = "isClientAuthorizedToRead")]
     can_read: bool,
-    #[serde(rename = "isFileLockEnabled")]
-    file_lock_enabled: bool,
-    #[serde(rename = "value")]
-    retention: FileRetentionPolicy,
+    value: Option<FileLockSettings>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FileLockSettings {
+    #[serde(default)]
+    default_retention: FileRetentionPolicy,
+    is_file_lock_enabled: bool,
 }
 
 impl FileLockConfiguration {
@@ -861,7 +1826,7 @@ impl FileLockConfiguration {
     /// If not authorized to read the file lock configuration, returns `None`.
     pub fn lock_is_enabled(&self) -> Option<bool> {
         if self.can_read {
-            Some(self.file_lock_enabled)
+            self.value.map(|v| v.is_file_lock_enabled)
         } else {
             None
         }
@@ -872,7 +1837,7 @@ impl FileLockConfiguration {
     /// If not authorized to read the file lock configuration, returns `None`.
     pub fn retention_policy(&self) -> Option<FileRetentionPolicy> {
         if self.can_read {
-            Some(self.retention)
+            self.value.map(|v| v.default_retention)
         } else {
             None
         }
@@ -965,6 +1930,27 @@ impl BucketEncryptionInfo {
     }
 }
 
+/// Response from B2 with the configured bucket replication settings.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketReplicationInfo {
+    is_client_authorized_to_read: bool,
+    value: Option<ReplicationConfiguration>,
+}
+
+impl BucketReplicationInfo {
+    /// True if the authorization token allows access to the replication
+    /// settings.
+    ///
+    /// If this is `false`, then `configuration` will return `None`.
+    pub fn can_read(&self) -> bool { self.is_client_authorized_to_read }
+
+    /// The [ReplicationConfiguration] of the bucket.
+    pub fn configuration(&self) -> Option<&ReplicationConfiguration> {
+        self.value.as_ref()
+    }
+}
+
 /// A B2 bucket
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -973,11 +1959,13 @@ pub struct Bucket {
     pub(crate) bucket_id: String,
     bucket_name: String,
     bucket_type: BucketType,
-    bucket_info: serde_json::Value,
+    bucket_info: BucketInfo,
     cors_rules: Vec<CorsRule>,
-    file_lock_configuration: FileRetentionPolicy,
+    file_lock_configuration: FileLockConfiguration,
     default_server_side_encryption: BucketEncryptionInfo,
     lifecycle_rules: Vec<LifecycleRule>,
+    #[serde(default)]
+    replication_configuration: BucketReplicationInfo,
     revision: u16,
     options: Option<Vec<String>>,
 }
@@ -987,11 +1975,19 @@ impl Bucket {
     pub fn bucket_id(&self) -> &str { &self.bucket_id }
     pub fn name(&self) -> &str { &self.bucket_name }
     pub fn bucket_type(&self) -> BucketType { self.bucket_type }
-    pub fn info(&self) -> &serde_json::Value { &self.bucket_info }
+    pub fn info(&self) -> &BucketInfo { &self.bucket_info }
     pub fn cors_rules(&self) -> &[CorsRule] { &self.cors_rules }
 
+    /// The bucket's default retention policy.
+    ///
+    /// If not authorized to read the file lock configuration, this is an
+    /// empty policy.
     pub fn retention_policy(&self) -> FileRetentionPolicy {
-        self.file_lock_configuration
+        self.file_lock_configuration.retention_policy().unwrap_or_default()
+    }
+
+    pub fn file_lock_configuration(&self) -> &FileLockConfiguration {
+        &self.file_lock_configuration
     }
 
     pub fn encryption_info(&self) -> &BucketEncryptionInfo {
@@ -999,6 +1995,11 @@ impl Bucket {
     }
 
     pub fn lifecycle_rules(&self) -> &[LifecycleRule] { &self.lifecycle_rules }
+
+    pub fn replication_info(&self) -> &BucketReplicationInfo {
+        &self.replication_configuration
+    }
+
     pub fn revision(&self) -> u16 { self.revision }
     pub fn options(&self) -> Option<&Vec<String>> { self.options.as_ref() }
 }
@@ -1011,13 +2012,14 @@ pub async fn create_bucket<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::WriteBuckets);
-    if new_bucket_info.file_lock_enabled {
-        require_capability!(auth, Capability::WriteBucketRetentions);
-    }
-    if new_bucket_info.default_server_side_encryption.is_some() {
-        require_capability!(auth, Capability::WriteBucketEncryption);
-    }
+    require_capabilities!(auth, Operation::CreateBucket {
+        file_lock: new_bucket_info.file_lock_enabled,
+        encryption: new_bucket_info.default_server_side_encryption.is_some(),
+        replication: new_bucket_info.replication_configuration.is_some(),
+    });
+
+    // A key restricted to a bucket cannot create others.
+    auth.capabilities().check_all_buckets()?;
 
     let mut new_bucket_info = new_bucket_info;
     new_bucket_info.account_id = Some(&auth.account_id);
@@ -1028,7 +2030,7 @@ pub async fn create_bucket<C, E>(
         .with_body_json(serde_json::to_value(new_bucket_info)?)
         .send().await?;
 
-    let new_bucket: B2Result<Bucket> = serde_json::from_slice(&res)?;
+    let new_bucket: B2Result<Bucket> = B2Result::from_response(&res)?;
     new_bucket.into()
 }
 
@@ -1045,7 +2047,8 @@ pub async fn delete_bucket<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::DeleteBuckets);
+    require_capabilities!(auth, Operation::DeleteBucket);
+    auth.capabilities().check_bucket(bucket_id.as_ref())?;
 
     let res = auth.client.post(auth.api_url("b2_delete_bucket"))
         .expect("Invalid URL")
@@ -1056,7 +2059,7 @@ pub async fn delete_bucket<C, E>(
         }))
         .send().await?;
 
-    let new_bucket: B2Result<Bucket> = serde_json::from_slice(&res)?;
+    let new_bucket: B2Result<Bucket> = B2Result::from_response(&res)?;
     new_bucket.into()
 }
 
@@ -1181,7 +2184,15 @@ pub async fn list_buckets<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::ListBuckets);
+    require_capabilities!(auth, Operation::ListBuckets);
+
+    // A key restricted to a bucket must name that bucket.
+    let allowed = auth.capabilities();
+    match &list_info.bucket {
+        Some(BucketRef::Id(id)) => allowed.check_bucket(id)?,
+        Some(BucketRef::Name(name)) => allowed.check_bucket_name(name)?,
+        None => allowed.check_all_buckets()?,
+    }
 
     let mut list_info = list_info;
     list_info.account_id = Some(&auth.account_id);
@@ -1192,12 +2203,12 @@ pub async fn list_buckets<C, E>(
         .with_body_json(serde_json::to_value(list_info)?)
         .send().await?;
 
-    let buckets: B2Result<BucketList> = serde_json::from_slice(&res)?;
+    let buckets: B2Result<BucketList> = B2Result::from_response(&res)?;
     buckets.map(|b| b.buckets).into()
 }
 
 /// A request to update one or more settings on a [Bucket].
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateBucket<'a> {
     account_id: Option<&'a str>,
@@ -1205,7 +2216,7 @@ pub struct UpdateBucket<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     bucket_type: Option<BucketType>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    bucket_info: Option<serde_json::Value>,
+    bucket_info: Option<BucketInfo>,
     #[serde(skip_serializing_if = "Option::is_none")]
     cors_rules: Option<Vec<CorsRule>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -1215,6 +2226,8 @@ pub struct UpdateBucket<'a> {
     #[serde(skip_serializing_if = "Option::is_none")]
     lifecycle_rules: Option<Vec<LifecycleRule>>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    replication_configuration: Option<ReplicationConfiguration>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     if_revision_is: Option<u16>,
 }
 
@@ -1229,12 +2242,13 @@ impl<'a> UpdateBucket<'a> {
 pub struct UpdateBucketBuilder {
     bucket_id: Option<String>,
     bucket_type: Option<BucketType>,
-    bucket_info: Option<serde_json::Value>,
+    bucket_info: Option<BucketInfo>,
     cache_control: Option<String>,
     cors_rules: Option<Vec<CorsRule>>,
     default_retention: Option<FileRetentionPolicy>,
     default_server_side_encryption: Option<ServerSideEncryption>,
     lifecycle_rules: Option<Vec<LifecycleRule>>,
+    replication_configuration: Option<ReplicationConfiguration>,
     if_revision_is: Option<u16>,
 }
 
@@ -1262,12 +2276,10 @@ impl UpdateBucketBuilder {
 
     /// Replace the current bucket information with the specified information.
     ///
-    /// This can contain arbitrary metadata for your own use. You can also set
-    /// cache-control settings from here (but see
-    /// [cache_control](Self::cache_control)). If Cache-Control is set here and
-    /// via the `cache-control` method, the latter will override this value.
-    pub fn bucket_info(mut self, info: serde_json::Value)
-    -> Self {
+    /// If Cache-Control is set here and via the
+    /// [cache_control](Self::cache_control) method, the latter will override
+    /// this value.
+    pub fn bucket_info(mut self, info: BucketInfo) -> Self {
         self.bucket_info = Some(info);
         self
     }
@@ -1282,6 +2294,8 @@ impl UpdateBucketBuilder {
     /// Replace the bucket's current provided CORS rules with the provided
     /// rules.
     ///
+    /// Pass an empty list to remove every rule.
+    ///
     /// See <https://www.backblaze.com/b2/docs/cors_rules.html> for further
     /// information.
     pub fn cors_rules(mut self, rules: impl Into<Vec<CorsRule>>)
@@ -1292,10 +2306,9 @@ impl UpdateBucketBuilder {
             return Err(ValidationError::OutOfBounds(
                 "A bucket can have no more than 100 CORS rules".into()
             ));
-        } else if ! rules.is_empty() {
-            self.cors_rules = Some(rules);
         }
 
+        self.cors_rules = Some(rules);
         Ok(self)
     }
 
@@ -1330,6 +2343,19 @@ impl UpdateBucketBuilder {
         Ok(self)
     }
 
+    /// Replace the bucket's replication settings.
+    ///
+    /// Pass [ReplicationConfiguration::default] to stop replicating the bucket.
+    /// The [Authorization] must have
+    /// [Capability::WriteBucketReplications].
+    pub fn replication_configuration(
+        mut self,
+        config: ReplicationConfiguration
+    ) -> Self {
+        self.replication_configuration = Some(config);
+        self
+    }
+
     /// Only perform the update if the bucket's current revision is the provided
     /// version.
     pub fn if_revision_is(mut self, revision: u16) -> Self {
@@ -1344,21 +2370,8 @@ impl UpdateBucketBuilder {
             )
         )?;
 
-        let bucket_info = if let Some(cache_control) = self.cache_control {
-            let mut info = self.bucket_info.unwrap_or_else(||
-                serde_json::Value::Object(serde_json::Map::new())
-            );
-
-            info.as_object_mut()
-                .map(|map| map.insert(
-                    String::from("Cache-Control"),
-                    serde_json::Value::String(cache_control)
-                ));
-
-            Some(info)
-        } else {
-            self.bucket_info
-        };
+        let bucket_info =
+            merged_bucket_info(self.bucket_info, self.cache_control)?;
 
         Ok(UpdateBucket {
             account_id: None,
@@ -1369,6 +2382,7 @@ impl UpdateBucketBuilder {
             default_retention: self.default_retention,
             default_server_side_encryption: self.default_server_side_encryption,
             lifecycle_rules: self.lifecycle_rules,
+            replication_configuration: self.replication_configuration,
             if_revision_is: self.if_revision_is,
         })
     }
@@ -1385,13 +2399,13 @@ pub async fn update_bucket<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::WriteBuckets);
-    if bucket_info.default_retention.is_some() {
-        require_capability!(auth, Capability::WriteBucketRetentions);
-    }
-    if bucket_info.default_server_side_encryption.is_some() {
-        require_capability!(auth, Capability::WriteBucketEncryption);
-    }
+    require_capabilities!(auth, Operation::UpdateBucket {
+        retention: bucket_info.default_retention.is_some(),
+        encryption: bucket_info.default_server_side_encryption.is_some(),
+        replication: bucket_info.replication_configuration.is_some(),
+    });
+
+    auth.capabilities().check_bucket(&bucket_info.bucket_id)?;
 
     let mut bucket_info = bucket_info;
     bucket_info.account_id = Some(&auth.account_id);
@@ -1402,10 +2416,83 @@ pub async fn update_bucket<C, E>(
         .with_body_json(serde_json::to_value(bucket_info)?)
         .send().await?;
 
-    let bucket: B2Result<Bucket> = serde_json::from_slice(&res)?;
+    let bucket: B2Result<Bucket> = B2Result::from_response(&res)?;
     bucket.into()
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct NotificationRules {
+    event_notification_rules: Vec<NotificationRule>,
+}
+
+/// Get the event notification rules of the bucket with the given ID.
+///
+/// See <https://www.backblaze.com/apidocs/b2-get-bucket-notification-rules>
+/// for further information.
+pub async fn get_bucket_notification_rules<C, E>(
+    auth: &mut Authorization<C>,
+    bucket_id: impl AsRef<str>
+) -> Result<Vec<NotificationRule>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    require_capabilities!(auth, Operation::GetBucketNotificationRules);
+    auth.capabilities().check_bucket(bucket_id.as_ref())?;
+
+    let res = auth.client.post(auth.api_url("b2_get_bucket_notification_rules"))
+        .expect("Invalid URL")
+        .with_header("Authorization", &auth.authorization_token).unwrap()
+        .with_body_json(serde_json::json!({
+            "bucketId": bucket_id.as_ref(),
+        }))
+        .send().await?;
+
+    let rules: B2Result<NotificationRules> = B2Result::from_response(&res)?;
+    rules.map(|r| r.event_notification_rules).into()
+}
+
+/// Replace the event notification rules of the bucket with the given ID.
+///
+/// Any existing rule not in `rules` is removed; pass an empty list to remove
+/// every rule. Returns the bucket's new rules.
+///
+/// See <https://www.backblaze.com/apidocs/b2-set-bucket-notification-rules>
+/// for further information.
+pub async fn set_bucket_notification_rules<C, E>(
+    auth: &mut Authorization<C>,
+    bucket_id: impl AsRef<str>,
+    rules: impl Into<Vec<NotificationRule>>
+) -> Result<Vec<NotificationRule>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    require_capabilities!(auth, Operation::SetBucketNotificationRules);
+    auth.capabilities().check_bucket(bucket_id.as_ref())?;
+
+    let rules = rules.into();
+
+    for (i, rule) in rules.iter().enumerate() {
+        if rules[..i].iter().any(|r| r.name == rule.name) {
+            return Err(ValidationError::Incompatible(format!(
+                "Duplicate notification rule name: {}", rule.name
+            )).into());
+        }
+    }
+
+    let res = auth.client.post(auth.api_url("b2_set_bucket_notification_rules"))
+        .expect("Invalid URL")
+        .with_header("Authorization", &auth.authorization_token).unwrap()
+        .with_body_json(serde_json::json!({
+            "bucketId": bucket_id.as_ref(),
+            "eventNotificationRules": rules,
+        }))
+        .send().await?;
+
+    let rules: B2Result<NotificationRules> = B2Result::from_response(&res)?;
+    rules.map(|r| r.event_notification_rules).into()
+}
+
 mod serialization {
     //! Our public encryption configuration type is sufficiently different from
     //! the JSON that we cannot simply deserialize it. We use the types here as
@@ -1898,7 +2985,10 @@ mod tests {
             "revision": 2,
         });
 
-        let _: Bucket = from_value(info).unwrap();
+        let bucket: Bucket = from_value(info).unwrap();
+        let lock = bucket.file_lock_configuration();
+        assert_eq!(lock.lock_is_enabled(), Some(false));
+        assert!(lock.retention_policy().unwrap().mode().is_none());
     }
 
     #[test]
@@ -1945,5 +3035,326 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn replication_configuration_to_json() -> anyhow::Result<()> {
+        let config = ReplicationConfiguration::builder()
+            .source_key_id("source-key")
+            .rules([
+                ReplicationRule::builder()
+                    .name("to-backup")?
+                    .destination_bucket_id("backup-bucket")
+                    .file_name_prefix("docs/")?
+                    .priority(2)?
+                    .include_existing_files(true)
+                    .build()?,
+            ])?
+            .map_source_key("remote-key", "local-key")
+            .build()?;
+
+        let expected = json!({
+            "asReplicationSource": {
+                "replicationRules": [{
+                    "replicationRuleName": "to-backup",
+                    "destinationBucketId": "backup-bucket",
+                    "fileNamePrefix": "docs/",
+                    "includeExistingFiles": true,
+                    "isEnabled": true,
+                    "priority": 2,
+                }],
+                "sourceApplicationKeyId": "source-key",
+            },
+            "asReplicationDestination": {
+                "sourceToDestinationKeyMapping": { "remote-key": "local-key" },
+            },
+        });
+
+        assert_eq!(to_value(&config)?, expected);
+
+        let parsed: ReplicationConfiguration = from_value(expected)?;
+        assert_eq!(parsed, config);
+        assert_eq!(parsed.rules()[0].destination_bucket_id(), "backup-bucket");
+        assert_eq!(parsed.source_key_id(), Some("source-key"));
+
+        assert_eq!(to_value(ReplicationConfiguration::default())?, json!({}));
+
+        Ok(())
+    }
+
+    #[test]
+    fn replication_configuration_is_validated() -> anyhow::Result<()> {
+        assert!(ReplicationRule::builder().name("x").is_err());
+        assert!(ReplicationRule::builder().name("to_backup").is_err());
+        assert!(ReplicationRule::builder().priority(0).is_err());
+        assert!(ReplicationRule::builder().name("to-backup")?.build().is_err());
+
+        let rule = ReplicationRule::builder()
+            .name("to-backup")?
+            .destination_bucket_id("backup-bucket")
+            .build()?;
+
+        assert!(ReplicationConfiguration::builder()
+            .rules([rule.clone(), rule.clone()])
+            .is_err());
+        assert!(ReplicationConfiguration::builder()
+            .rules([rule])?
+            .build()
+            .is_err());
+        assert!(ReplicationConfiguration::builder()
+            .source_key_id("source-key")
+            .build()
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn bucket_info_to_json() -> anyhow::Result<()> {
+        use http_types::cache::CacheDirective;
+
+        let mut cache_control = CacheControl::new();
+        cache_control.push(CacheDirective::MaxAge(
+            std::time::Duration::from_secs(3600)
+        ));
+
+        let info = BucketInfo::builder()
+            .cache_control(cache_control)
+            .entry("owner", "storage-team")?
+            .build()?;
+
+        let expected = json!({
+            "Cache-Control": "max-age=3600",
+            "owner": "storage-team",
+        });
+
+        assert_eq!(to_value(&info)?, expected);
+        assert_eq!(info.len(), 2);
+
+        let parsed: BucketInfo = from_value(expected)?;
+        assert_eq!(parsed, info);
+        assert_eq!(parsed.cache_control(), Some("max-age=3600"));
+        assert_eq!(parsed.get("cache-control"), Some("max-age=3600"));
+        assert_eq!(parsed.get("owner"), Some("storage-team"));
+        assert_eq!(parsed.custom().len(), 1);
+
+        let lowercase: BucketInfo = from_value(json!({
+            "cache-control": "max-age=3600",
+            "owner": "storage-team",
+        }))?;
+        assert_eq!(lowercase, info);
+        assert_eq!(lowercase.get("Cache-Control"), Some("max-age=3600"));
+
+        assert_eq!(to_value(BucketInfo::default())?, json!({}));
+
+        Ok(())
+    }
+
+    #[test]
+    fn bucket_info_is_validated() -> anyhow::Result<()> {
+        assert!(BucketInfo::builder().entry("", "value").is_err());
+        assert!(BucketInfo::builder().entry("a".repeat(51), "value").is_err());
+        assert!(BucketInfo::builder().entry("bad key", "value").is_err());
+        assert!(BucketInfo::builder().entry("cache-control", "value").is_err());
+
+        let with_entries = |n| (0..n).try_fold(BucketInfo::builder(),
+            |b, i| b.entry(format!("key-{}", i), "value")
+        );
+
+        assert!(with_entries(11)?.build().is_err());
+        let full = with_entries(10)?.build()?;
+
+        assert!(BucketInfo::builder().entry("key", "a\nb")?.build().is_err());
+        assert!(BucketInfo::builder().entry("key", "a\tb")?.build().is_err());
+        assert!(BucketInfo::builder().entry("key", "é ✓")?.build().is_ok());
+
+        let large = "x".repeat(MAX_BUCKET_INFO_BYTES - 3);
+        assert!(BucketInfo::builder().entry("key", &large)?.build().is_ok());
+        assert!(BucketInfo::builder().entry("key1", large)?.build().is_err());
+
+        // The Cache-Control set on a request counts against the limit.
+        let req = CreateBucket::builder()
+            .name("my-test-bucket")?
+            .bucket_type(BucketType::Private)?
+            .bucket_info(full)
+            .cache_control(CacheControl::new());
+        assert!(req.build().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn notification_rule_to_json() -> anyhow::Result<()> {
+        let rule = NotificationRule::builder()
+            .name("new-uploads")?
+            .event_types([
+                EventType::ObjectCreatedUpload,
+                EventType::ObjectCreatedMultipartUpload,
+                EventType::HideMarkerCreatedAll,
+            ])?
+            .object_name_prefix("incoming/")?
+            .url("https://example.com/hooks/b2")?
+            .signing_secret("0123456789abcdefghijABCDEFGHIJ01")?
+            .build()?;
+
+        let expected = json!({
+            "name": "new-uploads",
+            "eventTypes": [
+                "b2:ObjectCreated:Upload",
+                "b2:ObjectCreated:MultipartUpload",
+                "b2:HideMarkerCreated:*",
+            ],
+            "objectNamePrefix": "incoming/",
+            "isEnabled": true,
+            "targetConfiguration": {
+                "targetType": "webhook",
+                "url": "https://example.com/hooks/b2",
+                "hmacSha256SigningSecret": "0123456789abcdefghijABCDEFGHIJ01",
+            },
+        });
+
+        assert_eq!(to_value(&rule)?, expected);
+
+        for typ in rule.event_types() {
+            assert_eq!(to_value(typ)?, json!(typ.to_string()));
+        }
+
+        // Event types we don't know are sent back as they were received.
+        let mut unknown = expected.clone();
+        unknown["eventTypes"] = json!(["b2:ObjectRestored:Completed"]);
+        let parsed: NotificationRule = from_value(unknown.clone())?;
+        assert_eq!(parsed.event_types(), [
+            EventType::Unknown("b2:ObjectRestored:Completed".into())
+        ]);
+        assert_eq!(to_value(&parsed)?, unknown);
+
+        let mut response = expected;
+        response["isSuspended"] = json!(true);
+        response["suspensionReason"] = json!("Too many failures");
+
+        let parsed: NotificationRule = from_value(response)?;
+        assert!(parsed.is_suspended());
+        assert_eq!(parsed.suspension_reason(), Some("Too many failures"));
+        assert_eq!(parsed.custom_headers().count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn notification_rule_is_validated() -> anyhow::Result<()> {
+        let builder = NotificationRule::builder;
+
+        assert!(builder().name("").is_err());
+        assert!(builder().name("new_uploads").is_err());
+        assert!(builder().name("a".repeat(64)).is_err());
+
+        assert!(builder().event_types([]).is_err());
+        assert!(builder().event_types([EventType::TestEvent]).is_err());
+        assert!(builder().event_types([
+            EventType::Unknown("b2:ObjectRestored:Completed".into())
+        ]).is_err());
+        assert!(builder().event_types([
+            EventType::ObjectCreatedAll,
+            EventType::ObjectCreatedCopy,
+        ]).is_err());
+        assert!(builder().event_types([
+            EventType::ObjectDeletedDelete,
+            EventType::ObjectDeletedDelete,
+        ]).is_err());
+        assert!(builder().event_types([
+            EventType::ObjectCreatedAll,
+            EventType::ObjectDeletedAll,
+        ]).is_ok());
+
+        assert!(builder().url("http://example.com/hook").is_err());
+        assert!(builder().url("not a url").is_err());
+
+        assert!(builder().signing_secret("too-short").is_err());
+        assert!(builder().signing_secret("0123456789abcdefghij-BCDEFGHIJ01")
+            .is_err());
+
+        assert!(builder().custom_header("X-Bz-Thing", "value").is_err());
+        assert!(builder().custom_header("Bad Header", "value").is_err());
+
+        let mut many_headers = builder();
+        for i in 0..10 {
+            many_headers = many_headers
+                .custom_header(format!("X-Header-{}", i), "value")?;
+        }
+        assert!(many_headers.custom_header("X-Header-10", "value").is_err());
+
+        assert!(builder().name("no-url")?
+            .event_types([EventType::ObjectCreatedAll])?
+            .build()
+            .is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn notification_events_from_json() -> anyhow::Result<()> {
+        let body = json!({
+            "events": [
+                {
+                    "accountId": "e85c6a500333",
+                    "bucketId": "aea8c5bc362ef5e58183001c",
+                    "bucketName": "my-bucket",
+                    "eventId": "ce8f4b4b2b6a4f7d9b8f",
+                    "eventTimestamp": 1684793309123_i64,
+                    "eventType": "b2:ObjectCreated:Upload",
+                    "eventVersion": 1,
+                    "matchedRuleName": "new-uploads",
+                    "objectName": "incoming/report.pdf",
+                    "objectSize": 10495842,
+                    "objectVersionId": "4_zaea8c5bc362ef5e58183001c_f1",
+                },
+                {
+                    "accountId": "e85c6a500333",
+                    "bucketId": "aea8c5bc362ef5e58183001c",
+                    "bucketName": "my-bucket",
+                    "eventId": "b3e27ac11c6f4d4a8e2c",
+                    "eventTimestamp": 1684793309456_i64,
+                    "eventType": "b2:TestEvent",
+                    "eventVersion": 1,
+                    "matchedRuleName": "new-uploads",
+                },
+                {
+                    "accountId": "e85c6a500333",
+                    "bucketId": "aea8c5bc362ef5e58183001c",
+                    "bucketName": "my-bucket",
+                    "eventId": "0d6f3c1a9e2b4c7d8a5f",
+                    "eventTimestamp": 1684793309789_i64,
+                    "eventType": "b2:ObjectRestored:Completed",
+                    "eventVersion": 1,
+                    "matchedRuleName": "new-uploads",
+                },
+            ],
+        });
+
+        let events = from_value::<EventNotification>(body)?.into_events();
+        assert_eq!(events.len(), 3);
+
+        let upload = &events[0];
+        assert_eq!(upload.event_type(), &EventType::ObjectCreatedUpload);
+        assert_eq!(upload.file_name(), Some("incoming/report.pdf"));
+        assert_eq!(upload.file_id(), Some("4_zaea8c5bc362ef5e58183001c_f1"));
+        assert_eq!(upload.content_length(), Some(10495842));
+        assert_eq!(
+            upload.timestamp().map(|t| t.timestamp_millis()),
+            Some(1684793309123)
+        );
+
+        let test = &events[1];
+        assert_eq!(test.event_type(), &EventType::TestEvent);
+        assert_eq!(test.file_name(), None);
+        assert_eq!(test.content_length(), None);
+
+        assert_eq!(
+            events[2].event_type(),
+            &EventType::Unknown("b2:ObjectRestored:Completed".into())
+        );
+        assert_eq!(events[2].action(), None);
+
+        Ok(())
+    }
+
     // TODO: Test CorsRuleBuilder with allowed headers, etc.
 }
@@ -0,0 +1,280 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+   License, v. 2.0. If a copy of the MPL was not distributed with this
+   file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! An on-disk cache of authorization tokens.
+//!
+//! Every call to [authorize_account](crate::account::authorize_account) is a
+//! billable (Class C) transaction. Short-lived programs that run often can use
+//! a [TokenCache] to reuse an authorization token obtained by an earlier run
+//! for as long as it remains valid:
+//!
+//! ```no_run
+//! # #[cfg(feature = "with_surf")]
+//! # async fn f() -> anyhow::Result<()> {
+//! use b2_client::{client::SurfClient, cache::TokenCache};
+//!
+//! let cache = TokenCache::new("/home/me/.cache/my-program/b2-tokens.json");
+//! let mut auth = cache.authorize(SurfClient::default(), "key-id", "key")
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The cache file holds authorization tokens, which grant the same access as
+//! the application keys they were obtained with until they expire. On Unix
+//! systems the file is created readable only by its owner.
+
+use std::{
+    fmt,
+    fs,
+    io,
+    path::PathBuf,
+};
+
+use crate::{
+    account::{
+        auth_url, authorize_account_with_url, Authorization,
+        SavedAuthorization,
+    },
+    client::HttpClient,
+    error::Error,
+};
+
+use chrono::Utc;
+use serde::{Serialize, Deserialize};
+
+
+/// An authorization saved in the cache file.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    key_id: String,
+    authorization: SavedAuthorization,
+}
+
+/// A file of saved [Authorization]s, indexed by application key ID and
+/// authorization URL.
+#[derive(Debug, Clone)]
+pub struct TokenCache {
+    path: PathBuf,
+    min_validity: chrono::Duration,
+}
+
+impl TokenCache {
+    /// Use the cache file at the given path.
+    ///
+    /// The file is created when an authorization is first saved to it.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            min_validity: chrono::Duration::hours(1),
+        }
+    }
+
+    /// Only reuse a cached token that will remain valid for at least this
+    /// long.
+    ///
+    /// The default is one hour.
+    pub fn min_validity(mut self, dur: chrono::Duration) -> Self {
+        self.min_validity = dur;
+        self
+    }
+
+    /// Obtain an [Authorization], reusing a cached token if possible.
+    ///
+    /// If the cache has no token for `key_id` that remains valid for the
+    /// [minimum duration](Self::min_validity), the account is authorized with
+    /// [authorize_account](crate::account::authorize_account) and the new
+    /// token is saved to the cache.
+    ///
+    /// A cache file that cannot be parsed is treated as empty and replaced.
+    pub async fn authorize<C, E>(&self, client: C, key_id: &str, key: &str)
+    -> Result<Authorization<C>, Error<E>>
+        where C: HttpClient<Error=Error<E>>,
+              E: fmt::Debug + fmt::Display,
+    {
+        self.authorize_with_url(client, &auth_url(), key_id, key).await
+    }
+
+    /// Obtain an [Authorization] from the given base URL, reusing a cached
+    /// token if possible.
+    ///
+    /// See [authorize](Self::authorize) and [authorize_account_with_url].
+    pub async fn authorize_with_url<C, E>(
+        &self,
+        client: C,
+        auth_url: &str,
+        key_id: &str,
+        key: &str,
+    ) -> Result<Authorization<C>, Error<E>>
+        where C: HttpClient<Error=Error<E>>,
+              E: fmt::Debug + fmt::Display,
+    {
+        if let Some(saved) = self.get(auth_url, key_id)? {
+            return Ok(Authorization::restore(client, saved));
+        }
+
+        let auth = authorize_account_with_url(client, auth_url, key_id, key)
+            .await?;
+
+        self.insert(key_id, &auth)?;
+        Ok(auth)
+    }
+
+    /// Get the cached authorization for `key_id` from the given base URL if
+    /// it remains valid for the [minimum duration](Self::min_validity).
+    pub fn get(&self, auth_url: &str, key_id: &str)
+    -> io::Result<Option<SavedAuthorization>> {
+        let auth_url = auth_url.trim_end_matches('/');
+        let valid_until = Utc::now() + self.min_validity;
+
+        Ok(self.read()?.into_iter()
+            .find(|e| e.key_id == key_id
+                && e.authorization.auth_url() == auth_url)
+            .map(|e| e.authorization)
+            .filter(|auth| auth.expires_at() > valid_until))
+    }
+
+    /// Save an authorization obtained with the application key `key_id` to
+    /// the cache, replacing any previous authorization for that key.
+    ///
+    /// Expired authorizations are removed from the cache.
+    pub fn insert<C>(&self, key_id: &str, auth: &Authorization<C>)
+    -> io::Result<()>
+        where C: HttpClient,
+    {
+        let auth = auth.save();
+        let now = Utc::now();
+
+        let mut entries = self.read()?;
+        entries.retain(|e| e.authorization.expires_at() > now
+            && ! (e.key_id == key_id
+                && e.authorization.auth_url() == auth.auth_url()));
+
+        entries.push(Entry { key_id: key_id.to_owned(), authorization: auth });
+        self.write(&entries)
+    }
+
+    /// Remove any cached authorization for `key_id`, such as after the key
+    /// has been deleted.
+    pub fn remove(&self, key_id: &str) -> io::Result<()> {
+        let mut entries = self.read()?;
+        let len = entries.len();
+
+        entries.retain(|e| e.key_id != key_id);
+
+        if entries.len() == len {
+            Ok(())
+        } else {
+            self.write(&entries)
+        }
+    }
+
+    fn read(&self) -> io::Result<Vec<Entry>> {
+        match fs::read(&self.path) {
+            Ok(data) => Ok(serde_json::from_slice(&data).unwrap_or_default()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(vec![]),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replace the cache file, so that other processes never see a partially
+    /// written file.
+    fn write(&self, entries: &[Entry]) -> io::Result<()> {
+        use io::Write as _;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(format!(".{}.tmp", std::process::id()));
+
+        let mut opts = fs::OpenOptions::new();
+        opts.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt as _;
+            opts.mode(0o600);
+        }
+
+        let mut file = opts.open(&tmp)?;
+        file.write_all(&serde_json::to_vec(entries)?)?;
+        file.sync_all()?;
+
+        fs::rename(tmp, &self.path)
+    }
+}
+
+#[cfg(all(test, feature = "testing"))]
+mod tests {
+    use super::*;
+    use crate::testing::FakeB2;
+
+    fn temp_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("b2-client-{}-{}.json", name, std::process::id()));
+
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    #[async_std::test]
+    async fn cached_token_is_reused() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (key_id, key) = b2.master_key();
+        let cache = TokenCache::new(temp_file("cache-reuse"));
+
+        let first = cache.authorize(b2.client(), &key_id, &key).await?;
+        let second = cache.authorize(b2.client(), &key_id, &key).await?;
+
+        assert_eq!(first.authorization_token(), second.authorization_token());
+        assert_eq!(first.issued_at(), second.issued_at());
+        assert_eq!(second.api_url("x"), first.api_url("x"));
+
+        cache.remove(&key_id)?;
+        let third = cache.authorize(b2.client(), &key_id, &key).await?;
+        assert_ne!(first.authorization_token(), third.authorization_token());
+
+        fs::remove_file(&cache.path)?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn tokens_near_expiry_are_replaced() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (key_id, key) = b2.master_key();
+        let cache = TokenCache::new(temp_file("cache-expiry"))
+            .min_validity(chrono::Duration::hours(25));
+
+        let first = cache.authorize(b2.client(), &key_id, &key).await?;
+        let second = cache.authorize(b2.client(), &key_id, &key).await?;
+
+        assert_ne!(first.authorization_token(), second.authorization_token());
+        assert!(cache.get(&first.auth_url, &key_id)?.is_none());
+
+        fs::remove_file(&cache.path)?;
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn corrupt_cache_is_replaced() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (key_id, key) = b2.master_key();
+        let path = temp_file("cache-corrupt");
+
+        fs::write(&path, b"not json")?;
+
+        let cache = TokenCache::new(&path);
+        let auth = cache.authorize(b2.client(), &key_id, &key).await?;
+
+        let saved = cache.get(&auth.auth_url, &key_id)?.unwrap();
+        assert_eq!(saved.account_id(), auth.account_id());
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
@@ -19,6 +19,8 @@ use std::{
 
 use crate::error::ValidationError;
 
+use futures_io::AsyncRead;
+
 #[cfg(feature = "with_surf")]
 pub use surf_client::SurfClient;
 
@@ -28,6 +30,12 @@ pub use hyper_client::HyperClient;
 #[cfg(feature = "with_isahc")]
 pub use isahc_client::IsahcClient;
 
+#[cfg(feature = "with_reqwest")]
+pub use reqwest_client::ReqwestClient;
+
+#[cfg(feature = "with_ureq")]
+pub use ureq_client::UreqClient;
+
 /// A trait that wraps an HTTP client to send HTTP requests.
 #[async_trait::async_trait]
 pub trait HttpClient
@@ -45,6 +53,12 @@ pub trait HttpClient
     /// Create an HTTP `POST` request to the specified URL.
     fn post(&mut self, url: impl AsRef<str>)
     -> Result<&mut Self, ValidationError>;
+    /// Create an HTTP `PUT` request to the specified URL.
+    fn put(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError>;
+    /// Create an HTTP `DELETE` request to the specified URL.
+    fn delete(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError>;
 
     /// Add a header to the request.
     ///
@@ -57,23 +71,220 @@ pub trait HttpClient
     fn with_body_json(&mut self, body: serde_json::Value) -> &mut Self;
     /// Read the provided path as the request's body.
     fn read_body_from_file(&mut self, path: impl Into<PathBuf>) -> &mut Self;
+    /// Stream the request's body from the provided reader.
+    ///
+    /// `content_length` must be the exact number of bytes that `reader` will
+    /// produce; it is sent as the `Content-Length` of the request.
+    /// Implementations should send the data as it is read rather than
+    /// buffering the full body in memory.
+    fn with_body_reader<R>(&mut self, reader: R, content_length: u64)
+    -> &mut Self
+        where R: AsyncRead + Send + Sync + Unpin + 'static;
 
     /// Set the User-Agent header value to send with requests.
     fn user_agent(&mut self, user_agent_string: impl Into<String>)
     -> Result<&mut Self, ValidationError>;
 
     /// Send the previously-constructed request and return a response.
-    async fn send(&mut self) -> Result<Vec<u8>, Self::Error>;
+    async fn send(&mut self) -> Result<Response, Self::Error>;
+
+    /// Send the previously-constructed request and return the response's
+    /// status and headers without reading its body.
+    ///
+    /// The body is read from the network as the caller reads from the
+    /// [StreamingResponse].
+    async fn send_streaming(&mut self)
+    -> Result<StreamingResponse, Self::Error>;
 
-    /// Send the previously-constructed request and return a response with the
-    /// returned HTTP headers.
-    async fn send_keep_headers(&mut self)
-    -> Result<(Vec<u8>, HeaderMap), Self::Error>;
+    /// Returns `true` if `e` means the request may not have reached the
+    /// server, such as a failure to connect or a timeout.
+    ///
+    /// [RetryClient](crate::retry::RetryClient) uses this to decide whether
+    /// to send an idempotent request again. The default implementation
+    /// returns `false`.
+    fn is_connection_error(_e: &Self::Error) -> bool { false }
+}
+
+/// Returns `true` if the I/O error indicates a failed or broken connection.
+pub(crate) fn is_connection_io_error(e: &std::io::Error) -> bool {
+    use std::io::ErrorKind;
+
+    matches!(e.kind(),
+        ErrorKind::ConnectionRefused
+        | ErrorKind::ConnectionReset
+        | ErrorKind::ConnectionAborted
+        | ErrorKind::NotConnected
+        | ErrorKind::BrokenPipe
+        | ErrorKind::TimedOut
+        | ErrorKind::UnexpectedEof
+        | ErrorKind::Interrupted
+    )
 }
 
 // TODO: Use http_types::{HeaderName, HeaderValue} instead of Strings?
 pub type HeaderMap = HashMap<String, String>;
 
+/// An HTTP response.
+#[derive(Debug, Clone)]
+pub struct Response {
+    status: u16,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl Response {
+    /// Create a `Response` from a response's status code, headers, and body.
+    pub fn new(status: u16, headers: HeaderMap, body: Vec<u8>) -> Self {
+        Self {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    /// The HTTP status code of the response.
+    pub fn status(&self) -> u16 { self.status }
+
+    /// Returns `true` if the status code is in the 2xx range.
+    pub fn is_success(&self) -> bool { (200..300).contains(&self.status) }
+
+    /// The HTTP headers of the response.
+    pub fn headers(&self) -> &HeaderMap { &self.headers }
+
+    /// The body of the response.
+    pub fn body(&self) -> &[u8] { &self.body }
+
+    /// Split the response into its status code, headers, and body.
+    pub fn into_parts(self) -> (u16, HeaderMap, Vec<u8>) {
+        (self.status, self.headers, self.body)
+    }
+}
+
+/// A reader over an HTTP response body.
+pub type ResponseReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// An HTTP response whose body has not yet been read.
+pub struct StreamingResponse {
+    status: u16,
+    headers: HeaderMap,
+    body: ResponseReader,
+}
+
+impl StreamingResponse {
+    /// Create a `StreamingResponse` from a response's status code, headers,
+    /// and a reader over its body.
+    pub fn new(
+        status: u16,
+        headers: HeaderMap,
+        body: impl AsyncRead + Send + Unpin + 'static
+    ) -> Self {
+        Self {
+            status,
+            headers,
+            body: Box::new(body),
+        }
+    }
+
+    /// The HTTP status code of the response.
+    pub fn status(&self) -> u16 { self.status }
+
+    /// Returns `true` if the status code is in the 2xx range.
+    pub fn is_success(&self) -> bool { (200..300).contains(&self.status) }
+
+    /// The HTTP headers of the response.
+    pub fn headers(&self) -> &HeaderMap { &self.headers }
+
+    /// Split the response into its status code, headers, and body.
+    pub fn into_parts(self) -> (u16, HeaderMap, ResponseReader) {
+        (self.status, self.headers, self.body)
+    }
+
+    /// Read the remainder of the body into memory.
+    pub(crate) async fn into_response(mut self) -> std::io::Result<Response> {
+        use std::pin::Pin;
+
+        let mut body = Vec::new();
+        let mut buf = [0; 8 * 1024];
+
+        loop {
+            let len = std::future::poll_fn(|cx|
+                Pin::new(&mut self.body).poll_read(cx, &mut buf)
+            ).await?;
+
+            if len == 0 { break; }
+            body.extend_from_slice(&buf[..len]);
+        }
+
+        Ok(Response::new(self.status, self.headers, body))
+    }
+}
+
+impl std::fmt::Debug for StreamingResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StreamingResponse")
+            .field("status", &self.status)
+            .field("headers", &self.headers)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A request body that is streamed from an [AsyncRead] source.
+#[cfg(any(feature = "with_surf", feature = "with_hyper",
+    feature = "with_isahc", feature = "with_reqwest", feature = "with_ureq"))]
+struct BodyReader {
+    reader: Box<dyn AsyncRead + Send + Sync + Unpin>,
+    len: u64,
+}
+
+#[cfg(any(feature = "with_surf", feature = "with_hyper",
+    feature = "with_isahc", feature = "with_reqwest", feature = "with_ureq"))]
+impl std::fmt::Debug for BodyReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BodyReader")
+            .field("len", &self.len)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Adapts a [BodyReader]'s source to the byte stream used for hyper and reqwest
+/// request bodies.
+#[cfg(any(feature = "with_hyper", feature = "with_reqwest"))]
+struct ReaderStream {
+    reader: Box<dyn AsyncRead + Send + Sync + Unpin>,
+    buf: Vec<u8>,
+}
+
+#[cfg(any(feature = "with_hyper", feature = "with_reqwest"))]
+impl ReaderStream {
+    fn new(reader: Box<dyn AsyncRead + Send + Sync + Unpin>) -> Self {
+        Self {
+            reader,
+            buf: vec![0; 64 * 1024],
+        }
+    }
+}
+
+#[cfg(any(feature = "with_hyper", feature = "with_reqwest"))]
+impl futures_core::Stream for ReaderStream {
+    type Item = std::io::Result<Vec<u8>>;
+
+    fn poll_next(
+        mut self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>
+    ) -> std::task::Poll<Option<Self::Item>> {
+        use std::{pin::Pin, task::Poll};
+
+        let this = &mut *self;
+
+        match Pin::new(&mut this.reader).poll_read(cx, &mut this.buf) {
+            Poll::Ready(Ok(0)) => Poll::Ready(None),
+            Poll::Ready(Ok(n)) => Poll::Ready(Some(Ok(this.buf[..n].to_vec()))),
+            Poll::Ready(Err(e)) => Poll::Ready(Some(Err(e))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
 /// Generate a standard User-Agent string for HTTP client backends.
 ///
 /// This is only useful if you either:
@@ -129,7 +340,7 @@ mod surf_client {
         Url,
     };
 
-    #[derive(Debug, Clone)]
+    #[derive(Debug)]
     pub struct SurfClient {
         client: surf::Client,
         req: Option<Request>,
@@ -149,8 +360,23 @@ mod surf_client {
         }
     }
 
+    impl Clone for SurfClient {
+        /// Clone a `SurfClient` object.
+        ///
+        /// The client itself and the user-agent string are cloned. The current
+        /// request is not.
+        fn clone(&self) -> Self {
+            Self {
+                client: self.client.clone(),
+                req: None,
+                body: None,
+                user_agent: self.user_agent.clone(),
+            }
+        }
+    }
+
     // Body type for sending; TODO rename to avoid ambiguity?
-    #[derive(Debug, Clone)]
+    #[derive(Debug)]
     enum Body {
         Json(serde_json::Value),
         // TODO: I'd rather store a reference, but doing so spams lifetimes all
@@ -160,6 +386,7 @@ mod surf_client {
         // The best solution is likely going to be to refcount it.
         Bytes(Vec<u8>),
         File(PathBuf),
+        Reader(BodyReader),
     }
 
     impl SurfClient {
@@ -169,45 +396,54 @@ mod surf_client {
             self
         }
 
-        async fn send_impl(&mut self, keep_headers: bool)
-        -> Result<(Vec<u8>, Option<HeaderMap>), <Self as HttpClient>::Error> {
-            if let Some(mut req) = self.req.to_owned() {
-                if let Some(body) = &self.body {
+        /// Send the current request and return the response without reading
+        /// its body.
+        async fn send_request(&mut self)
+        -> Result<surf::Response, <Self as HttpClient>::Error> {
+            if let Some(mut req) = self.req.take() {
+                if let Some(body) = self.body.take() {
                     match body {
-                        Body::Json(val) => req.body_json(val)?,
+                        Body::Json(val) => req.body_json(&val)?,
                         Body::Bytes(data) => req.body_bytes(data),
                         Body::File(path) =>
                             req.set_body(surf::Body::from_file(path).await?),
-                    }
-                }
-
-                req.insert_header("User-Agent", &self.user_agent);
-
-                let mut res = self.client.send(req).await?;
-                let body = res.body_bytes().await?;
+                        Body::Reader(body) => {
+                            use async_std::io::BufReader;
 
-                let headers = if keep_headers {
-                    let headers: &surf::http::Headers = res.as_ref();
-                    let mut ret = HeaderMap::new();
+                            // Surf only uses the length as a hint, so it's OK
+                            // if it doesn't fit on 32-bit targets.
+                            let len = usize::try_from(body.len).ok();
 
-                    for (k, v) in headers.iter() {
-                        ret.insert(k.to_string(), v.to_string());
+                            req.set_body(surf::Body::from_reader(
+                                BufReader::new(body.reader),
+                                len
+                            ));
+                        },
                     }
+                }
 
-                    Some(ret)
-                } else {
-                    None
-                };
-
-                self.req = None;
+                req.insert_header("User-Agent", &self.user_agent);
 
-                Ok((body, headers))
+                Ok(self.client.send(req).await?)
             } else {
                 Err(Error::NoRequest)
             }
         }
     }
 
+    fn header_map(res: &surf::Response) -> HeaderMap {
+        let headers: &surf::http::Headers = res.as_ref();
+        let mut ret = HeaderMap::new();
+
+        // B2 does not repeat headers, so we keep the last value of each
+        // rather than the list-formatted Display of HeaderValues.
+        for (k, v) in headers.iter() {
+            ret.insert(k.to_string(), v.last().to_string());
+        }
+
+        ret
+    }
+
     macro_rules! gen_method_func {
         ($func:ident, $method:ident) => {
             fn $func(&mut self, url: impl AsRef<str>)
@@ -228,6 +464,8 @@ mod surf_client {
         gen_method_func!(get, Get);
         gen_method_func!(head, Head);
         gen_method_func!(post, Post);
+        gen_method_func!(put, Put);
+        gen_method_func!(delete, Delete);
 
         fn with_header<S: AsRef<str>>(&mut self, name: S, value: S)
         -> Result<&mut Self, ValidationError> {
@@ -263,6 +501,18 @@ mod surf_client {
             self
         }
 
+        fn with_body_reader<R>(&mut self, reader: R, content_length: u64)
+        -> &mut Self
+            where R: AsyncRead + Send + Sync + Unpin + 'static,
+        {
+            self.body = Some(Body::Reader(BodyReader {
+                reader: Box::new(reader),
+                len: content_length,
+            }));
+
+            self
+        }
+
         /// Set the User-Agent header value to send with requests.
         ///
         /// The default User-Agent string is "rust-b2-client/<version>; surf".
@@ -290,20 +540,51 @@ mod surf_client {
         ///
         /// * If a request has not been created, returns [Error::NoRequest].
         /// * Returns any underlying HTTP client errors in [Error::Client].
-        async fn send(&mut self) -> Result<Vec<u8>, Self::Error> {
-            self.send_impl(false).await.map(|v| v.0)
+        async fn send(&mut self) -> Result<Response, Self::Error> {
+            let mut res = self.send_request().await?;
+            let body = res.body_bytes().await?;
+
+            Ok(Response::new(res.status().into(), header_map(&res), body))
         }
 
-        /// Send the previously-constructed request and return a response with
-        /// the returned HTTP headers.
+        /// Send the previously-constructed request and return the response
+        /// without reading its body.
         ///
         /// # Errors
         ///
         /// * If a request has not been created, returns [Error::NoRequest].
         /// * Returns any underlying HTTP client errors in [Error::Client].
-        async fn send_keep_headers(&mut self)
-        -> Result<(Vec<u8>, HeaderMap), Self::Error> {
-            self.send_impl(true).await.map(|(r, m)| (r, m.unwrap()))
+        async fn send_streaming(&mut self)
+        -> Result<StreamingResponse, Self::Error> {
+            let mut res = self.send_request().await?;
+            let headers = header_map(&res);
+
+            Ok(StreamingResponse::new(
+                res.status().into(),
+                headers,
+                res.take_body().into_reader()
+            ))
+        }
+
+        /// Returns `true` if the request failed to connect or timed out.
+        fn is_connection_error(e: &Self::Error) -> bool {
+            use surf_isahc::Error as CurlError;
+
+            let e = match e {
+                Error::Client(e) => e,
+                _ => return false,
+            };
+
+            match e.downcast_ref::<CurlError>() {
+                Some(CurlError::Io(e)) => is_connection_io_error(e),
+                Some(e) => matches!(e,
+                    CurlError::ConnectFailed
+                    | CurlError::CouldntResolveHost
+                    | CurlError::Timeout
+                ),
+                None => e.downcast_ref::<std::io::Error>()
+                    .is_some_and(is_connection_io_error),
+            }
         }
     }
 }
@@ -322,7 +603,7 @@ mod hyper_client {
     use url::Url;
 
 
-    #[derive(Debug, Clone)]
+    #[derive(Debug)]
     pub struct HyperClient {
         client: hyper::Client<HttpsConnector<HttpConnector>>,
         method: Option<Method>,
@@ -350,11 +631,29 @@ mod hyper_client {
         }
     }
 
-    #[derive(Debug, Clone)]
+    impl Clone for HyperClient {
+        /// Clone a `HyperClient` object.
+        ///
+        /// The client itself and the user-agent string are cloned. The current
+        /// request is not.
+        fn clone(&self) -> Self {
+            Self {
+                client: self.client.clone(),
+                method: None,
+                url: String::default(),
+                headers: vec![],
+                body: None,
+                user_agent: self.user_agent.clone(),
+            }
+        }
+    }
+
+    #[derive(Debug)]
     enum Body {
         Json(serde_json::Value),
         Bytes(hyper::body::Bytes),
         File(PathBuf),
+        Reader(BodyReader),
     }
 
     macro_rules! gen_method_func {
@@ -390,8 +689,10 @@ mod hyper_client {
             self
         }
 
-        async fn send_impl(&mut self, keep_headers: bool)
-        -> Result<(Vec<u8>, Option<HeaderMap>), <Self as HttpClient>::Error> {
+        /// Send the current request and return the response without reading
+        /// its body.
+        async fn send_request(&mut self)
+        -> Result<hyper::Response<hyper::Body>, <Self as HttpClient>::Error> {
             if self.method.is_none() {
                 return Err(Error::NoRequest);
             }
@@ -406,24 +707,22 @@ mod hyper_client {
 
             req = req.header("User-Agent", &self.user_agent);
 
-            let body = match &self.body {
+            let body = match self.body.take() {
                 Some(body) => match body {
                     Body::Json(val) => hyper::Body::from(val.to_string()),
-                    Body::Bytes(data) => hyper::Body::from(data.clone()),
+                    Body::Bytes(data) => hyper::Body::from(data),
                     Body::File(path) => {
-                        use tokio::{
-                            fs::File,
-                            io::AsyncReadExt as _,
-                        };
+                        let file = tokio::fs::File::open(path).await?;
+                        let len = file.metadata().await?.len();
 
-                        // TODO: We might do better to create a stream over the
-                        // file and pass it to the hyper::Body.
-                        let mut file = File::open(path).await?;
-                        let mut buf = vec![];
-                        file.read_to_end(&mut buf).await?;
+                        let body = BodyReader {
+                            reader: Box::new(FileReader(file)),
+                            len,
+                        };
 
-                        hyper::Body::from(buf)
+                        self.stream_body(&mut req, body)
                     },
+                    Body::Reader(body) => self.stream_body(&mut req, body),
                 },
                 None => hyper::Body::empty(),
             };
@@ -433,39 +732,105 @@ mod hyper_client {
                 "improper validation"
             ));
 
-            let (mut parts, body) = self.client.request(req).await?
-                .into_parts();
-
-            let body = hyper::body::to_bytes(body).await?.to_vec();
-
-            let headers = if keep_headers {
-                let mut headers = HeaderMap::new();
-
-                headers.extend(
-                    parts.headers.drain()
-                        .filter(|(k, _)| k.is_some())
-                        .map(|(k, v)|
-                            // TODO: Ensure that all possible header values from
-                            // B2 are required to be valid strings on their
-                            // side.
-                            (
-                                k.unwrap().to_string(),
-                                v.to_str().unwrap().to_owned()
-                            )
-                        )
-                );
-
-                Some(headers)
-            } else {
-                None
-            };
+            let res = self.client.request(req).await?;
 
             self.method = None;
             self.url = String::default();
             self.headers.clear();
             self.body = None;
 
-            Ok((body, headers))
+            Ok(res)
+        }
+
+        /// Create a streamed body from `body`, setting the request's
+        /// Content-Length if it has not been set already.
+        fn stream_body(
+            &self,
+            req: &mut hyper::http::request::Builder,
+            body: BodyReader
+        ) -> hyper::Body {
+            use hyper::header::CONTENT_LENGTH;
+
+            // Without a Content-Length, hyper would use chunked encoding for a
+            // streamed body.
+            let has_len = self.headers.iter()
+                .any(|(name, _)| name == CONTENT_LENGTH);
+
+            if ! has_len {
+                *req = std::mem::take(req).header(CONTENT_LENGTH, body.len);
+            }
+
+            hyper::Body::wrap_stream(ReaderStream::new(body.reader))
+        }
+    }
+
+    fn header_map(headers: &mut hyper::HeaderMap) -> HeaderMap {
+        let mut ret = HeaderMap::new();
+
+        ret.extend(
+            headers.drain()
+                .filter(|(k, _)| k.is_some())
+                .map(|(k, v)|
+                    // TODO: Ensure that all possible header values from B2 are
+                    // required to be valid strings on their side.
+                    (
+                        k.unwrap().to_string(),
+                        v.to_str().unwrap().to_owned()
+                    )
+                )
+        );
+
+        ret
+    }
+
+    /// Adapts a [tokio::fs::File] to the [AsyncRead] used for request bodies.
+    struct FileReader(tokio::fs::File);
+
+    impl AsyncRead for FileReader {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut [u8]
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            use std::pin::Pin;
+            use tokio::io::{AsyncRead as _, ReadBuf};
+
+            let mut buf = ReadBuf::new(buf);
+
+            Pin::new(&mut self.0).poll_read(cx, &mut buf)
+                .map_ok(|()| buf.filled().len())
+        }
+    }
+
+    /// Adapts a response's [hyper::Body] to an [AsyncRead].
+    struct BodyStreamReader {
+        body: hyper::Body,
+        chunk: hyper::body::Bytes,
+    }
+
+    impl AsyncRead for BodyStreamReader {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut [u8]
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            use std::{pin::Pin, task::Poll};
+            use hyper::body::HttpBody as _;
+
+            while self.chunk.is_empty() {
+                match Pin::new(&mut self.body).poll_data(cx) {
+                    Poll::Ready(Some(Ok(chunk))) => self.chunk = chunk,
+                    Poll::Ready(Some(Err(e))) =>
+                        return Poll::Ready(Err(std::io::Error::other(e))),
+                    Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            let len = buf.len().min(self.chunk.len());
+            buf[..len].copy_from_slice(&self.chunk.split_to(len));
+
+            Poll::Ready(Ok(len))
         }
     }
 
@@ -476,6 +841,8 @@ mod hyper_client {
         gen_method_func!(get, GET);
         gen_method_func!(head, HEAD);
         gen_method_func!(post, POST);
+        gen_method_func!(put, PUT);
+        gen_method_func!(delete, DELETE);
 
         /// Add a header to the request.
         fn with_header<S: AsRef<str>>(&mut self, name: S, value: S)
@@ -509,6 +876,18 @@ mod hyper_client {
             self
         }
 
+        fn with_body_reader<R>(&mut self, reader: R, content_length: u64)
+        -> &mut Self
+            where R: AsyncRead + Send + Sync + Unpin + 'static,
+        {
+            self.body = Some(Body::Reader(BodyReader {
+                reader: Box::new(reader),
+                len: content_length,
+            }));
+
+            self
+        }
+
         fn user_agent(&mut self, user_agent_string: impl Into<String>)
         -> Result<&mut Self, ValidationError> {
             let user_agent = user_agent_string.into();
@@ -529,13 +908,38 @@ mod hyper_client {
         ///
         /// * If a request has not been created, returns [Error::NoRequest].
         /// * Returns any underlying HTTP client errors in [Error::Client].
-        async fn send(&mut self) -> Result<Vec<u8>, Self::Error> {
-            self.send_impl(false).await.map(|v| v.0)
+        async fn send(&mut self) -> Result<Response, Self::Error> {
+            let (mut parts, body) = self.send_request().await?.into_parts();
+
+            let body = hyper::body::to_bytes(body).await?.to_vec();
+
+            Ok(Response::new(
+                parts.status.as_u16(),
+                header_map(&mut parts.headers),
+                body
+            ))
+        }
+
+        async fn send_streaming(&mut self)
+        -> Result<StreamingResponse, Self::Error> {
+            let (mut parts, body) = self.send_request().await?.into_parts();
+
+            Ok(StreamingResponse::new(
+                parts.status.as_u16(),
+                header_map(&mut parts.headers),
+                BodyStreamReader {
+                    body,
+                    chunk: hyper::body::Bytes::new(),
+                }
+            ))
         }
 
-        async fn send_keep_headers(&mut self)
-        -> Result<(Vec<u8>, HeaderMap), Self::Error> {
-            self.send_impl(true).await.map(|(r, m)| (r, m.unwrap()))
+        /// Returns `true` if the request failed to connect or timed out.
+        fn is_connection_error(e: &Self::Error) -> bool {
+            match e {
+                Error::Client(e) => e.is_connect() || e.is_timeout(),
+                _ => false,
+            }
         }
     }
 }
@@ -555,6 +959,7 @@ mod isahc_client {
         Bytes(Vec<u8>),
         Json(serde_json::Value),
         File(PathBuf),
+        Reader(BodyReader),
     }
 
     #[derive(Debug)]
@@ -596,13 +1001,12 @@ mod isahc_client {
     }
 
     impl IsahcClient {
-        async fn send_impl(&mut self, keep_headers: bool)
-        -> Result<(
-            Vec<u8>, Option<HeaderMap>),
+        /// Send the current request and return the response without reading
+        /// its body.
+        async fn send_request(&mut self) -> Result<
+            isahc::Response<isahc::AsyncBody>,
             <Self as super::HttpClient>::Error
         > {
-            use futures_lite::AsyncReadExt as _;
-
             if let Some(mut req) = self.req.take() {
                 for (name, value) in &self.headers {
                     req = req.header(name, value);
@@ -612,68 +1016,66 @@ mod isahc_client {
 
                 let body = if let Some(body) = self.body.take() {
                     match body {
-                        Body::Bytes(bytes) => Some(bytes),
-                        Body::Json(json) => Some(serde_json::to_vec(&json)?),
+                        Body::Bytes(bytes) => isahc::AsyncBody::from(bytes),
+                        Body::Json(json) =>
+                            isahc::AsyncBody::from(serde_json::to_vec(&json)?),
                         Body::File(path) => {
-                            // TODO: Use async_std?
-                            use std::{fs::File, io::Read as _};
+                            use futures_lite::io::AssertAsync;
 
-                            let mut file = File::open(path)?;
-                            let mut buf: Vec<u8> = vec![];
-                            file.read_to_end(&mut buf)?;
+                            let file = std::fs::File::open(path)?;
+                            let len = file.metadata()?.len();
 
-                            Some(buf)
+                            // isahc polls request bodies from its agent
+                            // thread, so reading the file there does not
+                            // block the caller's executor.
+                            isahc::AsyncBody::from_reader_sized(
+                                AssertAsync::new(file),
+                                len
+                            )
                         },
+                        Body::Reader(body) =>
+                            isahc::AsyncBody::from_reader_sized(
+                                body.reader,
+                                body.len
+                            ),
                     }
                 } else {
-                    None
-                };
-
-                let (mut parts, body) = match body {
-                    Some(body) => self.client.send_async(req.body(body)?)
-                        .await?.into_parts(),
-                    None => self.client.send_async(
-                        req.body(isahc::AsyncBody::empty())?
-                    ).await?.into_parts(),
-                };
-
-                let headers = if keep_headers {
-                    let mut headers = HeaderMap::new();
-
-                    headers.extend(
-                        parts.headers.drain()
-                        .filter(|(k, _)| k.is_some())
-                        .map(|(k, v)|
-                            // TODO: Ensure that all possible header values from
-                            // B2 are required to be valid strings on their
-                            // side.
-                            (
-                                k.unwrap().to_string(),
-                                v.to_str().unwrap().to_owned()
-                            )
-                        )
-                    );
-
-                    Some(headers)
-                } else {
-                    None
+                    isahc::AsyncBody::empty()
                 };
 
-                let mut buf = Vec::new();
-                body.bytes().read_to_end(&mut buf).await?;
+                let res = self.client.send_async(req.body(body)?).await?;
 
                 // self.req and self.body had their values reset already; we
                 // only need to clear the list of headers and we're ready for
                 // the next request.
                 self.headers.clear();
 
-                Ok((buf, headers))
+                Ok(res)
             } else {
                 Err(Error::NoRequest)
             }
         }
     }
 
+    fn header_map(headers: &mut isahc::http::HeaderMap) -> HeaderMap {
+        let mut ret = HeaderMap::new();
+
+        ret.extend(
+            headers.drain()
+                .filter(|(k, _)| k.is_some())
+                .map(|(k, v)|
+                    // TODO: Ensure that all possible header values from B2 are
+                    // required to be valid strings on their side.
+                    (
+                        k.unwrap().to_string(),
+                        v.to_str().unwrap().to_owned()
+                    )
+                )
+        );
+
+        ret
+    }
+
     macro_rules! gen_method_func {
         ($func:ident, $method:ident) => {
             fn $func(&mut self, url: impl AsRef<str>)
@@ -698,6 +1100,8 @@ mod isahc_client {
         gen_method_func!(get, GET);
         gen_method_func!(head, HEAD);
         gen_method_func!(post, POST);
+        gen_method_func!(put, PUT);
+        gen_method_func!(delete, DELETE);
 
         fn with_header<S: AsRef<str>>(&mut self, name: S, value: S)
         -> Result<&mut Self, ValidationError> {
@@ -726,6 +1130,18 @@ mod isahc_client {
             self
         }
 
+        fn with_body_reader<R>(&mut self, reader: R, content_length: u64)
+        -> &mut Self
+            where R: AsyncRead + Send + Sync + Unpin + 'static,
+        {
+            self.body = Some(Body::Reader(BodyReader {
+                reader: Box::new(reader),
+                len: content_length,
+            }));
+
+            self
+        }
+
         fn user_agent(&mut self, user_agent_string: impl Into<String>)
         -> Result<&mut Self, ValidationError> {
             let user_agent = user_agent_string.into();
@@ -740,13 +1156,813 @@ mod isahc_client {
             }
         }
 
-        async fn send(&mut self) -> Result<Vec<u8>, Self::Error> {
-            self.send_impl(false).await.map(|v| v.0)
+        async fn send(&mut self) -> Result<Response, Self::Error> {
+            use futures_lite::AsyncReadExt as _;
+
+            let (mut parts, body) = self.send_request().await?.into_parts();
+
+            let mut buf = Vec::new();
+            body.bytes().read_to_end(&mut buf).await?;
+
+            Ok(Response::new(
+                parts.status.as_u16(),
+                header_map(&mut parts.headers),
+                buf
+            ))
+        }
+
+        async fn send_streaming(&mut self)
+        -> Result<StreamingResponse, Self::Error> {
+            let (mut parts, body) = self.send_request().await?.into_parts();
+
+            Ok(StreamingResponse::new(
+                parts.status.as_u16(),
+                header_map(&mut parts.headers),
+                body
+            ))
+        }
+
+        /// Returns `true` if the request failed to connect or timed out.
+        fn is_connection_error(e: &Self::Error) -> bool {
+            use isahc::error::ErrorKind;
+
+            match e {
+                Error::Client(e) => match e.kind() {
+                    ErrorKind::ConnectionFailed
+                    | ErrorKind::NameResolution
+                    | ErrorKind::Timeout => true,
+                    ErrorKind::Io => std::error::Error::source(e)
+                        .and_then(|e| e.downcast_ref::<std::io::Error>())
+                        .is_some_and(is_connection_io_error),
+                    _ => false,
+                },
+                _ => false,
+            }
+        }
+    }
+}
+
+#[cfg(feature = "with_reqwest")]
+mod reqwest_client {
+    use super::*;
+    use crate::error::Error;
+    use reqwest::{
+        header::{
+            HeaderName, HeaderValue,
+            CONTENT_LENGTH, CONTENT_TYPE, USER_AGENT,
+        },
+        Method,
+    };
+    use url::Url;
+
+
+    #[derive(Debug)]
+    pub struct ReqwestClient {
+        client: reqwest::Client,
+        method: Option<Method>,
+        url: String,
+        headers: Vec<(HeaderName, HeaderValue)>,
+        body: Option<Body>,
+        user_agent: String,
+    }
+
+    impl Default for ReqwestClient {
+        /// Create a new `ReqwestClient`.
+        fn default() -> Self {
+            Self {
+                client: reqwest::Client::new(),
+                method: None,
+                url: String::default(),
+                headers: vec![],
+                body: None,
+                user_agent: default_user_agent!("reqwest"),
+            }
+        }
+    }
+
+    impl Clone for ReqwestClient {
+        /// Clone a `ReqwestClient` object.
+        ///
+        /// The client itself and the user-agent string are cloned. The current
+        /// request is not.
+        fn clone(&self) -> Self {
+            Self {
+                client: self.client.clone(),
+                method: None,
+                url: String::default(),
+                headers: vec![],
+                body: None,
+                user_agent: self.user_agent.clone(),
+            }
         }
+    }
+
+    #[derive(Debug)]
+    enum Body {
+        Json(serde_json::Value),
+        Bytes(Vec<u8>),
+        File(PathBuf),
+        Reader(BodyReader),
+    }
+
+    macro_rules! gen_method_func {
+        ($func:ident, $method: ident) => {
+            fn $func(&mut self, url: impl AsRef<str>)
+            -> Result<&mut Self, ValidationError> {
+                let _url = Url::parse(url.as_ref())?;
 
-        async fn send_keep_headers(&mut self)
-        -> Result<(Vec<u8>, HeaderMap), Self::Error> {
-            self.send_impl(true).await.map(|v| (v.0, v.1.unwrap()))
+                self.method = Some(Method::$method);
+                self.url = String::from(url.as_ref());
+                Ok(self)
+            }
+        }
+    }
+
+    impl ReqwestClient {
+        /// Use the provided [reqwest::Client] instead of a new one.
+        ///
+        /// This allows sharing a connection pool, TLS configuration, proxy
+        /// settings, etc. with the rest of your application.
+        pub fn with_client(mut self, client: reqwest::Client) -> Self {
+            self.client = client;
+            self
+        }
+
+        /// Send the current request and return the response without reading
+        /// its body.
+        async fn send_request(&mut self)
+        -> Result<reqwest::Response, <Self as HttpClient>::Error> {
+            let method = self.method.take().ok_or(Error::NoRequest)?;
+
+            let mut req = self.client.request(method, &self.url);
+
+            let has_len = self.headers.iter()
+                .any(|(name, _)| name == CONTENT_LENGTH);
+
+            for (name, value) in self.headers.drain(..) {
+                req = req.header(name, value);
+            }
+
+            req = req.header(USER_AGENT, &self.user_agent);
+
+            if let Some(body) = self.body.take() {
+                req = match body {
+                    Body::Json(val) => req
+                        .header(CONTENT_TYPE, "application/json")
+                        .body(val.to_string()),
+                    Body::Bytes(data) => req.body(data),
+                    Body::File(path) => req.body(tokio::fs::read(path).await?),
+                    Body::Reader(body) => {
+                        // Without a Content-Length, reqwest would use chunked
+                        // encoding for a streamed body.
+                        if ! has_len {
+                            req = req.header(CONTENT_LENGTH, body.len);
+                        }
+
+                        req.body(reqwest::Body::wrap_stream(
+                            ReaderStream::new(body.reader)
+                        ))
+                    },
+                };
+            }
+
+            self.url = String::default();
+
+            Ok(req.send().await?)
+        }
+    }
+
+    fn header_map(headers: &reqwest::header::HeaderMap) -> HeaderMap {
+        headers.iter()
+            // TODO: Ensure that all possible header values from B2 are
+            // required to be valid strings on their side.
+            .map(|(k, v)| (k.to_string(), v.to_str().unwrap().to_owned()))
+            .collect()
+    }
+
+    /// Adapts a response's body stream to an [AsyncRead].
+    struct BodyStreamReader<S, B> {
+        stream: S,
+        chunk: Option<B>,
+        pos: usize,
+    }
+
+    impl<S, B> AsyncRead for BodyStreamReader<S, B>
+        where S: futures_core::Stream<Item = reqwest::Result<B>> + Unpin,
+              B: AsRef<[u8]> + Unpin,
+    {
+        fn poll_read(
+            mut self: std::pin::Pin<&mut Self>,
+            cx: &mut std::task::Context<'_>,
+            buf: &mut [u8]
+        ) -> std::task::Poll<std::io::Result<usize>> {
+            use std::{pin::Pin, task::Poll};
+
+            let this = &mut *self;
+
+            loop {
+                if let Some(chunk) = &this.chunk {
+                    let chunk = &chunk.as_ref()[this.pos..];
+
+                    if ! chunk.is_empty() {
+                        let len = buf.len().min(chunk.len());
+                        buf[..len].copy_from_slice(&chunk[..len]);
+                        this.pos += len;
+
+                        return Poll::Ready(Ok(len));
+                    }
+                }
+
+                match Pin::new(&mut this.stream).poll_next(cx) {
+                    Poll::Ready(Some(Ok(chunk))) => {
+                        this.chunk = Some(chunk);
+                        this.pos = 0;
+                    },
+                    Poll::Ready(Some(Err(e))) =>
+                        return Poll::Ready(Err(std::io::Error::other(e))),
+                    Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpClient for ReqwestClient {
+        type Error = Error<reqwest::Error>;
+
+        gen_method_func!(get, GET);
+        gen_method_func!(head, HEAD);
+        gen_method_func!(post, POST);
+        gen_method_func!(put, PUT);
+        gen_method_func!(delete, DELETE);
+
+        fn with_header<S: AsRef<str>>(&mut self, name: S, value: S)
+        -> Result<&mut Self, ValidationError> {
+            use std::str::FromStr as _;
+
+            let name = HeaderName::from_str(name.as_ref())?;
+            let value = HeaderValue::from_str(value.as_ref())?;
+
+            self.headers.push((name, value));
+            Ok(self)
+        }
+
+        fn with_body(&mut self, data: impl Into<Vec<u8>>) -> &mut Self {
+            self.body = Some(Body::Bytes(data.into()));
+            self
+        }
+
+        fn with_body_json(&mut self, body: serde_json::Value) -> &mut Self {
+            self.body = Some(Body::Json(body));
+            self
+        }
+
+        fn read_body_from_file(&mut self, path: impl Into<PathBuf>)
+        -> &mut Self {
+            self.body = Some(Body::File(path.into()));
+            self
+        }
+
+        fn with_body_reader<R>(&mut self, reader: R, content_length: u64)
+        -> &mut Self
+            where R: AsyncRead + Send + Sync + Unpin + 'static,
+        {
+            self.body = Some(Body::Reader(BodyReader {
+                reader: Box::new(reader),
+                len: content_length,
+            }));
+
+            self
+        }
+
+        fn user_agent(&mut self, user_agent_string: impl Into<String>)
+        -> Result<&mut Self, ValidationError> {
+            let user_agent = user_agent_string.into();
+
+            if user_agent.is_empty() {
+                Err(ValidationError::MissingData(
+                    "User-Agent is required".into()
+                ))
+            } else {
+                self.user_agent = user_agent;
+                Ok(self)
+            }
+        }
+
+        /// Send the previously-constructed request and return a response.
+        ///
+        /// # Errors
+        ///
+        /// * If a request has not been created, returns [Error::NoRequest].
+        /// * Returns any underlying HTTP client errors in [Error::Client].
+        async fn send(&mut self) -> Result<Response, Self::Error> {
+            let res = self.send_request().await?;
+
+            let status = res.status().as_u16();
+            let headers = header_map(res.headers());
+            let body = res.bytes().await?.to_vec();
+
+            Ok(Response::new(status, headers, body))
+        }
+
+        async fn send_streaming(&mut self)
+        -> Result<StreamingResponse, Self::Error> {
+            let res = self.send_request().await?;
+
+            Ok(StreamingResponse::new(
+                res.status().as_u16(),
+                header_map(res.headers()),
+                BodyStreamReader {
+                    stream: Box::pin(res.bytes_stream()),
+                    chunk: None,
+                    pos: 0,
+                }
+            ))
+        }
+
+        /// Returns `true` if the request failed to connect or timed out.
+        fn is_connection_error(e: &Self::Error) -> bool {
+            match e {
+                Error::Client(e) => e.is_connect() || e.is_timeout(),
+                _ => false,
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        //! Run the recorded sessions used for the surf tests against a
+        //! ReqwestClient.
+
+        use super::*;
+        use crate::{
+            account::Capability,
+            bucket::*,
+            error::ErrorCode,
+            file::*,
+            replay_server::ReplayServer,
+        };
+
+
+        fn block_on<F: std::future::Future>(f: F) -> F::Output {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .unwrap()
+                .block_on(f)
+        }
+
+        fn client() -> ReqwestClient {
+            ReqwestClient::default().with_client(reqwest::Client::new())
+        }
+
+        #[test]
+        fn list_buckets_success() -> anyhow::Result<()> {
+            let server = ReplayServer::start("test_sessions/buckets.yaml");
+            let mut auth = server.create_test_auth(
+                client(),
+                vec![Capability::ListBuckets]
+            );
+
+            let req = ListBuckets::builder()
+                .bucket_name("testing-b2-client")?
+                .build();
+
+            let buckets = block_on(list_buckets(&mut auth, req))?;
+            assert_eq!(buckets.len(), 1);
+
+            Ok(())
+        }
+
+        #[test]
+        fn create_bucket_already_exists() -> anyhow::Result<()> {
+            let server = ReplayServer::start("test_sessions/buckets.yaml");
+            let mut auth = server.create_test_auth(
+                client(),
+                vec![Capability::WriteBuckets]
+            );
+
+            let req = CreateBucket::builder()
+                .name("testing-b2-client")?
+                .bucket_type(BucketType::Private)?
+                .lifecycle_rules(vec![
+                    LifecycleRule::builder()
+                        .filename_prefix("my-files/")?
+                        .delete_after_hide(chrono::Duration::days(5))?
+                        .build()?
+                ])?
+                .build()?;
+
+            match block_on(create_bucket(&mut auth, req)).unwrap_err() {
+                Error::B2(e) =>
+                    assert_eq!(e.code(), ErrorCode::DuplicateBucketName),
+                e => panic!("Unexpected error: {:?}", e),
+            }
+
+            Ok(())
+        }
+
+        #[test]
+        fn upload_file_success() -> anyhow::Result<()> {
+            let server = ReplayServer::start("test_sessions/file.yaml");
+            let mut auth = server.create_test_auth(
+                client(),
+                vec![Capability::WriteFiles]
+            );
+
+            let file = block_on(async {
+                let mut upload_auth = get_upload_authorization_by_id(
+                    &mut auth,
+                    "8d625eb63be2775577c70e1a"
+                ).await?;
+
+                let file = UploadFile::builder()
+                    .file_name("test-file-upload.txt")?
+                    .sha1_checksum("81fe8bfe87576c3ecb22426f8e57847382917acf")
+                    .build()?;
+
+                anyhow::Ok(upload_file(&mut upload_auth, file, b"abcd").await?)
+            })?;
+
+            assert_eq!(file.file_name(), "test-file-upload.txt");
+
+            Ok(())
+        }
+
+        #[test]
+        fn upload_file_stream_success() -> anyhow::Result<()> {
+            let server = ReplayServer::start("test_sessions/file.yaml");
+            let mut auth = server.create_test_auth(
+                client(),
+                vec![Capability::WriteFiles]
+            );
+
+            let file = block_on(async {
+                let mut upload_auth = get_upload_authorization_by_id(
+                    &mut auth,
+                    "8d625eb63be2775577c70e1a"
+                ).await?;
+
+                let file = UploadFile::builder()
+                    .file_name("test-file-upload.txt")?
+                    .sha1_checksum("81fe8bfe87576c3ecb22426f8e57847382917acf")
+                    .build()?;
+
+                let data = async_std::io::Cursor::new(b"abcd");
+                anyhow::Ok(
+                    upload_file_stream(&mut upload_auth, file, data, 4).await?
+                )
+            })?;
+
+            assert_eq!(file.file_name(), "test-file-upload.txt");
+
+            Ok(())
+        }
+
+        #[test]
+        fn download_file_by_id_success() -> anyhow::Result<()> {
+            let server = ReplayServer::start("test_sessions/file.yaml");
+            let mut auth = server.create_test_auth(
+                client(),
+                vec![Capability::ReadFiles]
+            );
+
+            let req = DownloadFile::with_id(concat!(
+                "4_z8d625eb63be2775577c70e1a_f",
+                "111954e3108ff3f6_d20211118_m151810_c002_v0001168_t0010"
+            ));
+
+            let (file, _headers) = block_on(download_file(&mut auth, req))?;
+            assert_eq!(file, b"Some text\n");
+
+            Ok(())
+        }
+
+        #[test]
+        fn download_file_range_success() -> anyhow::Result<()> {
+            let server = ReplayServer::start("test_sessions/file.yaml");
+            let mut auth = server.create_test_auth(
+                client(),
+                vec![Capability::ReadFiles]
+            );
+
+            let req = DownloadFile::builder()
+                .file_name("test-file.txt", "testing-b2-client")
+                .range(ByteRange::new(5, 8)?)
+                .build()?;
+
+            let (file, _headers) = block_on(download_file(&mut auth, req))?;
+            assert_eq!(file, b"text");
+
+            Ok(())
+        }
+
+        #[test]
+        fn download_file_stream_by_name_success() -> anyhow::Result<()> {
+            use async_std::io::ReadExt as _;
+
+            let server = ReplayServer::start("test_sessions/file.yaml");
+            let mut auth = server.create_test_auth(
+                client(),
+                vec![Capability::ReadFiles]
+            );
+
+            let req = DownloadFile::with_name(
+                "test-file.txt",
+                "testing-b2-client"
+            );
+
+            let (file, headers) = block_on(async {
+                let (mut body, headers) =
+                    download_file_stream(&mut auth, req).await?;
+
+                let mut file = vec![];
+                body.read_to_end(&mut file).await?;
+
+                anyhow::Ok((file, headers))
+            })?;
+
+            assert_eq!(file, b"Some text\n");
+            assert!(headers.contains_key("x-bz-file-name"));
+
+            Ok(())
+        }
+
+    }
+}
+
+#[cfg(feature = "with_ureq")]
+mod ureq_client {
+    use super::*;
+    use crate::{
+        blocking::{AsyncReader, BlockingReader},
+        error::Error,
+    };
+    use std::io::Read as _;
+    use url::Url;
+
+
+    /// A synchronous HTTP client.
+    ///
+    /// Requests are sent on the thread that polls them, so `UreqClient` does
+    /// not need an async runtime. It is intended for use with the
+    /// [blocking](crate::blocking) module.
+    #[derive(Debug)]
+    pub struct UreqClient {
+        agent: ureq::Agent,
+        method: Option<&'static str>,
+        url: String,
+        headers: Vec<(String, String)>,
+        body: Option<Body>,
+        user_agent: String,
+    }
+
+    impl Default for UreqClient {
+        /// Create a new `UreqClient`.
+        fn default() -> Self {
+            Self {
+                agent: ureq::Agent::new(),
+                method: None,
+                url: String::default(),
+                headers: vec![],
+                body: None,
+                user_agent: default_user_agent!("ureq"),
+            }
+        }
+    }
+
+    impl Clone for UreqClient {
+        /// Clone a `UreqClient` object.
+        ///
+        /// The agent and the user-agent string are cloned. The current request
+        /// is not.
+        fn clone(&self) -> Self {
+            Self {
+                agent: self.agent.clone(),
+                method: None,
+                url: String::default(),
+                headers: vec![],
+                body: None,
+                user_agent: self.user_agent.clone(),
+            }
+        }
+    }
+
+    #[derive(Debug)]
+    enum Body {
+        Json(serde_json::Value),
+        Bytes(Vec<u8>),
+        File(PathBuf),
+        Reader(BodyReader),
+    }
+
+    macro_rules! gen_method_func {
+        ($func:ident, $method: literal) => {
+            fn $func(&mut self, url: impl AsRef<str>)
+            -> Result<&mut Self, ValidationError> {
+                let _url = Url::parse(url.as_ref())?;
+
+                self.method = Some($method);
+                self.url = String::from(url.as_ref());
+                Ok(self)
+            }
+        }
+    }
+
+    impl UreqClient {
+        /// Use the provided [ureq::Agent] instead of a new one.
+        ///
+        /// This allows sharing the agent's connection pool, TLS configuration,
+        /// proxy, and timeouts with the rest of your application.
+        pub fn with_agent(mut self, agent: ureq::Agent) -> Self {
+            self.agent = agent;
+            self
+        }
+
+        /// Send the current request and return the response without reading
+        /// its body.
+        async fn send_request(&mut self)
+        -> Result<ureq::Response, <Self as HttpClient>::Error> {
+            let method = self.method.take().ok_or(Error::NoRequest)?;
+
+            let mut req = self.agent.request(method, &self.url);
+
+            for (name, value) in self.headers.drain(..) {
+                req = req.set(&name, &value);
+            }
+
+            req = req.set("User-Agent", &self.user_agent);
+
+            let res = match self.body.take() {
+                Some(Body::Json(val)) => req
+                    .set("Content-Type", "application/json")
+                    .send_string(&val.to_string()),
+                Some(Body::Bytes(data)) => req.send_bytes(&data),
+                Some(Body::File(path)) => req.send_bytes(&std::fs::read(path)?),
+                Some(Body::Reader(body)) => req
+                    // Without a Content-Length, ureq would use chunked encoding
+                    // for the body.
+                    .set("Content-Length", &body.len.to_string())
+                    .send(BlockingReader::new(body.reader)),
+                None => req.call(),
+            };
+
+            self.url = String::default();
+
+            match res {
+                Ok(res) => Ok(res),
+                // B2 errors are returned in the body of the response.
+                Err(ureq::Error::Status(_, res)) => Ok(res),
+                Err(e) => Err(Error::Client(e)),
+            }
+        }
+    }
+
+    fn header_map(res: &ureq::Response) -> HeaderMap {
+        res.headers_names().into_iter()
+            .filter_map(|name| {
+                let value = res.header(&name)?.to_owned();
+                Some((name.to_lowercase(), value))
+            })
+            .collect()
+    }
+
+    /// Check that a header name is a valid HTTP token.
+    fn validate_header_name(name: &str) -> Result<(), ValidationError> {
+        let is_token_char = |c: char| c.is_ascii_alphanumeric()
+            || "!#$%&'*+-.^_`|~".contains(c);
+
+        if ! name.is_empty() && name.chars().all(is_token_char) {
+            Ok(())
+        } else {
+            Err(ValidationError::BadFormat(
+                format!("Invalid header name: {}", name)
+            ))
+        }
+    }
+
+    /// Check that a header value contains no control characters other than
+    /// tabs.
+    fn validate_header_value(value: &str) -> Result<(), ValidationError> {
+        if value.chars().all(|c| c == '\t' || ! c.is_ascii_control()) {
+            Ok(())
+        } else {
+            Err(ValidationError::BadFormat(
+                format!("Invalid header value: {}", value)
+            ))
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpClient for UreqClient {
+        type Error = Error<ureq::Error>;
+
+        gen_method_func!(get, "GET");
+        gen_method_func!(head, "HEAD");
+        gen_method_func!(post, "POST");
+        gen_method_func!(put, "PUT");
+        gen_method_func!(delete, "DELETE");
+
+        fn with_header<S: AsRef<str>>(&mut self, name: S, value: S)
+        -> Result<&mut Self, ValidationError> {
+            validate_header_name(name.as_ref())?;
+            validate_header_value(value.as_ref())?;
+
+            self.headers.push(
+                (name.as_ref().to_owned(), value.as_ref().to_owned())
+            );
+            Ok(self)
+        }
+
+        fn with_body(&mut self, data: impl Into<Vec<u8>>) -> &mut Self {
+            self.body = Some(Body::Bytes(data.into()));
+            self
+        }
+
+        fn with_body_json(&mut self, body: serde_json::Value) -> &mut Self {
+            self.body = Some(Body::Json(body));
+            self
+        }
+
+        fn read_body_from_file(&mut self, path: impl Into<PathBuf>)
+        -> &mut Self {
+            self.body = Some(Body::File(path.into()));
+            self
+        }
+
+        fn with_body_reader<R>(&mut self, reader: R, content_length: u64)
+        -> &mut Self
+            where R: AsyncRead + Send + Sync + Unpin + 'static,
+        {
+            self.body = Some(Body::Reader(BodyReader {
+                reader: Box::new(reader),
+                len: content_length,
+            }));
+
+            self
+        }
+
+        fn user_agent(&mut self, user_agent_string: impl Into<String>)
+        -> Result<&mut Self, ValidationError> {
+            let user_agent = user_agent_string.into();
+
+            if user_agent.is_empty() {
+                Err(ValidationError::MissingData(
+                    "User-Agent is required".into()
+                ))
+            } else {
+                self.user_agent = user_agent;
+                Ok(self)
+            }
+        }
+
+        /// Send the previously-constructed request and return a response.
+        ///
+        /// The request is sent synchronously; the returned future is ready
+        /// once the response body has been read.
+        ///
+        /// # Errors
+        ///
+        /// * If a request has not been created, returns [Error::NoRequest].
+        /// * Returns any underlying HTTP client errors in [Error::Client].
+        async fn send(&mut self) -> Result<Response, Self::Error> {
+            let res = self.send_request().await?;
+
+            let status = res.status();
+            let headers = header_map(&res);
+
+            let mut body = vec![];
+            res.into_reader().read_to_end(&mut body)?;
+
+            Ok(Response::new(status, headers, body))
+        }
+
+        async fn send_streaming(&mut self)
+        -> Result<StreamingResponse, Self::Error> {
+            let res = self.send_request().await?;
+
+            Ok(StreamingResponse::new(
+                res.status(),
+                header_map(&res),
+                AsyncReader::new(res.into_reader())
+            ))
+        }
+
+        /// Returns `true` if the request failed to connect or timed out.
+        fn is_connection_error(e: &Self::Error) -> bool {
+            use ureq::ErrorKind;
+
+            match e {
+                Error::Client(ureq::Error::Transport(t)) => match t.kind() {
+                    ErrorKind::ConnectionFailed | ErrorKind::Dns => true,
+                    ErrorKind::Io => std::error::Error::source(t)
+                        .and_then(|e| e.downcast_ref::<std::io::Error>())
+                        .is_some_and(is_connection_io_error),
+                    _ => false,
+                },
+                _ => false,
+            }
         }
     }
 }
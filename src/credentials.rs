@@ -0,0 +1,681 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+   License, v. 2.0. If a copy of the MPL was not distributed with this
+   file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Locating application keys.
+//!
+//! A [CredentialsProvider] looks for an application key ID and key in one
+//! place:
+//!
+//! * [Environment] reads the `B2_APPLICATION_KEY_ID` and `B2_APPLICATION_KEY`
+//!   environment variables.
+//! * [ProfileFile] reads a named profile from an INI-style file.
+//! * `AccountInfo` reads the database saved by the official `b2` command-line
+//!   tool; it requires the `b2_account_info` feature.
+//!
+//! A [ProviderChain] tries a list of providers in order and authorizes the
+//! account with the first key found:
+//!
+//! ```no_run
+//! # #[cfg(feature = "with_surf")]
+//! # async fn f() -> anyhow::Result<()> {
+//! use b2_client::{
+//!     client::SurfClient,
+//!     credentials::{Environment, ProfileFile, ProviderChain},
+//! };
+//!
+//! let chain = ProviderChain::new()
+//!     .with(ProfileFile::default().profile("backups"))
+//!     .with(Environment::default());
+//!
+//! let mut auth = chain.authorize(SurfClient::default()).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{
+    fmt,
+    fs,
+    io,
+    path::PathBuf,
+};
+
+use crate::{
+    account::{
+        auth_url,
+        authorize_account_with_version,
+        ApiVersion,
+        Authorization,
+    },
+    client::HttpClient,
+    error::Error,
+    session::Session,
+};
+
+
+/// An application key ID and key, and optionally the URL and API version to
+/// authorize with.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Credentials {
+    pub(crate) key_id: String,
+    pub(crate) key: String,
+    auth_url: Option<String>,
+    api_version: ApiVersion,
+}
+
+impl Credentials {
+    /// Create credentials from an application key ID and key.
+    pub fn new(key_id: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            key_id: key_id.into(),
+            key: key.into(),
+            auth_url: None,
+            api_version: ApiVersion::default(),
+        }
+    }
+
+    /// Authorize with the service at the given base URL rather than the
+    /// default; see [authorize_account_with_url].
+    ///
+    /// [authorize_account_with_url]: crate::account::authorize_account_with_url
+    pub fn with_auth_url(mut self, auth_url: impl Into<String>) -> Self {
+        self.auth_url = Some(auth_url.into());
+        self
+    }
+
+    /// Use the given version of the B2 API rather than version 2.
+    ///
+    /// A [Session] created from these credentials uses this version for every
+    /// request, including when renewing its authorization.
+    pub fn with_api_version(mut self, api_version: ApiVersion) -> Self {
+        self.api_version = api_version;
+        self
+    }
+
+    /// The application key ID.
+    pub fn key_id(&self) -> &str { &self.key_id }
+    /// The application key.
+    pub fn key(&self) -> &str { &self.key }
+    /// The base URL to authorize with, if not the default.
+    pub fn auth_url(&self) -> Option<&str> { self.auth_url.as_deref() }
+    /// The version of the B2 API to use.
+    pub fn api_version(&self) -> ApiVersion { self.api_version }
+
+    /// Log onto the B2 API with these credentials.
+    pub async fn authorize<C, E>(&self, client: C)
+    -> Result<Authorization<C>, Error<E>>
+        where C: HttpClient<Error=Error<E>>,
+              E: fmt::Debug + fmt::Display,
+    {
+        let url = match &self.auth_url {
+            Some(url) => url.to_owned(),
+            None => auth_url(),
+        };
+
+        authorize_account_with_version(
+            client, &url, self.api_version, &self.key_id, &self.key
+        ).await
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("key_id", &self.key_id)
+            .field("key", &"<hidden>")
+            .field("auth_url", &self.auth_url)
+            .field("api_version", &self.api_version)
+            .finish()
+    }
+}
+
+/// A source of application keys.
+pub trait CredentialsProvider: fmt::Debug + Send + Sync {
+    /// Look for credentials.
+    ///
+    /// Returns `Ok(None)` if this provider has no credentials, such as when
+    /// its file does not exist. Errors reading or parsing a source that does
+    /// exist are returned.
+    fn credentials(&self) -> io::Result<Option<Credentials>>;
+}
+
+/// Read credentials from environment variables.
+///
+/// By default the key ID is read from `B2_APPLICATION_KEY_ID` and the key from
+/// `B2_APPLICATION_KEY`, the variables used by the official `b2` command-line
+/// tool. Both must be set.
+#[derive(Debug, Clone)]
+pub struct Environment {
+    key_id_var: String,
+    key_var: String,
+}
+
+impl Default for Environment {
+    fn default() -> Self {
+        Self::with_names("B2_APPLICATION_KEY_ID", "B2_APPLICATION_KEY")
+    }
+}
+
+impl Environment {
+    /// Read the key ID and key from the given environment variables.
+    pub fn with_names(key_id_var: impl Into<String>, key_var: impl Into<String>)
+    -> Self {
+        Self {
+            key_id_var: key_id_var.into(),
+            key_var: key_var.into(),
+        }
+    }
+}
+
+impl CredentialsProvider for Environment {
+    fn credentials(&self) -> io::Result<Option<Credentials>> {
+        let key_id = std::env::var(&self.key_id_var).ok();
+        let key = std::env::var(&self.key_var).ok();
+
+        Ok(key_id.zip(key).map(|(id, key)| Credentials::new(id, key)))
+    }
+}
+
+/// Read credentials from a named profile in an INI-style file.
+///
+/// Each profile is a section containing a `key_id` and `key`, and optionally
+/// an `auth_url`; the longer names `application_key_id` and `application_key`
+/// are also accepted. Values may be quoted, so a file in this form is also
+/// valid TOML:
+///
+/// ```text
+/// [default]
+/// key_id = "0012345abcdef0000000001"
+/// key = "K001abcdefghijklmnopqrstuvwxyz0"
+///
+/// [local]
+/// key_id = "0f0000000000"
+/// key = "K000mastermastermastermastermas"
+/// auth_url = "http://127.0.0.1:8000"
+/// ```
+///
+/// Lines beginning with `#` or `;` are comments.
+#[derive(Debug, Clone)]
+pub struct ProfileFile {
+    path: Option<PathBuf>,
+    profile: String,
+}
+
+impl Default for ProfileFile {
+    /// Read the file named by the `B2_CREDENTIALS_FILE` environment variable,
+    /// or `~/.config/b2-client/credentials` if it is not set.
+    ///
+    /// The profile is named by the `B2_PROFILE` environment variable, or
+    /// `default` if it is not set.
+    fn default() -> Self {
+        let path = std::env::var_os("B2_CREDENTIALS_FILE")
+            .map(PathBuf::from)
+            .or_else(|| home_dir().map(|p|
+                p.join(".config").join("b2-client").join("credentials")
+            ));
+
+        Self {
+            path,
+            profile: std::env::var("B2_PROFILE")
+                .unwrap_or_else(|_| "default".into()),
+        }
+    }
+}
+
+impl ProfileFile {
+    /// Read the `default` profile from the file at the given path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            profile: "default".into(),
+        }
+    }
+
+    /// Read the named profile rather than `default`.
+    pub fn profile(mut self, name: impl Into<String>) -> Self {
+        self.profile = name.into();
+        self
+    }
+
+    /// Find our profile in the file's contents.
+    fn parse(&self, text: &str) -> io::Result<Option<Credentials>> {
+        let invalid = |line_num: usize, msg: &str| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Line {}: {}", line_num + 1, msg)
+        );
+
+        let mut in_profile = false;
+        let mut found = false;
+        let (mut key_id, mut key, mut auth_url) = (None, None, None);
+
+        for (line_num, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') || line.starts_with(';')
+            {
+                continue;
+            }
+
+            if let Some(section) = line.strip_prefix('[') {
+                let section = section.strip_suffix(']')
+                    .ok_or_else(|| invalid(line_num, "Unclosed section name"))?;
+
+                in_profile = unquote(section.trim()) == self.profile;
+                found |= in_profile;
+                continue;
+            }
+
+            let (name, value) = line.split_once('=')
+                .ok_or_else(|| invalid(line_num, "Expected `name = value`"))?;
+
+            if ! in_profile { continue; }
+
+            let value = Some(unquote(value.trim()).to_owned());
+
+            match name.trim() {
+                "key_id" | "application_key_id" => key_id = value,
+                "key" | "application_key" => key = value,
+                "auth_url" => auth_url = value,
+                _ => {},
+            }
+        }
+
+        if ! found {
+            return Ok(None);
+        }
+
+        match (key_id, key) {
+            (Some(key_id), Some(key)) => {
+                let creds = Credentials::new(key_id, key);
+
+                Ok(Some(match auth_url {
+                    Some(url) => creds.with_auth_url(url),
+                    None => creds,
+                }))
+            },
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Profile {} needs both a key_id and key", self.profile)
+            )),
+        }
+    }
+}
+
+impl CredentialsProvider for ProfileFile {
+    fn credentials(&self) -> io::Result<Option<Credentials>> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(None),
+        };
+
+        match fs::read_to_string(path) {
+            Ok(text) => self.parse(&text),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Remove a single pair of matching quotes from a value.
+fn unquote(s: &str) -> &str {
+    for q in ['"', '\''] {
+        if s.len() >= 2 && s.starts_with(q) && s.ends_with(q) {
+            return &s[1..s.len() - 1];
+        }
+    }
+
+    s
+}
+
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME")
+        .or_else(|| std::env::var_os("USERPROFILE"))
+        .map(PathBuf::from)
+}
+
+/// Read the application key saved by the official `b2` command-line tool.
+///
+/// `b2 account authorize` saves the key in an SQLite database. As the tool
+/// does, we read the file named by the `B2_ACCOUNT_INFO` environment variable
+/// or, if it is not set, `~/.b2_account_info` if it exists and
+/// `$XDG_CONFIG_HOME/b2/account_info` otherwise.
+///
+/// The key's realm is honored, so a key saved for a non-production realm is
+/// authorized there.
+#[cfg(feature = "b2_account_info")]
+#[derive(Debug, Clone)]
+pub struct AccountInfo {
+    path: Option<PathBuf>,
+}
+
+#[cfg(feature = "b2_account_info")]
+impl Default for AccountInfo {
+    fn default() -> Self {
+        let path = std::env::var_os("B2_ACCOUNT_INFO")
+            .map(PathBuf::from)
+            .or_else(|| {
+                let legacy = home_dir()?.join(".b2_account_info");
+
+                if legacy.exists() {
+                    return Some(legacy);
+                }
+
+                std::env::var_os("XDG_CONFIG_HOME")
+                    .map(PathBuf::from)
+                    .or_else(|| home_dir().map(|p| p.join(".config")))
+                    .map(|p| p.join("b2").join("account_info"))
+            });
+
+        Self { path }
+    }
+}
+
+#[cfg(feature = "b2_account_info")]
+impl AccountInfo {
+    /// Read the database at the given path.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: Some(path.into()) }
+    }
+}
+
+#[cfg(feature = "b2_account_info")]
+impl CredentialsProvider for AccountInfo {
+    fn credentials(&self) -> io::Result<Option<Credentials>> {
+        use rusqlite::{Connection, OpenFlags, OptionalExtension as _};
+
+        let path = match &self.path {
+            Some(path) if path.exists() => path,
+            _ => return Ok(None),
+        };
+
+        let to_io = |e: rusqlite::Error| io::Error::other(e);
+
+        let db = Connection::open_with_flags(
+            path,
+            OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX
+        ).map_err(to_io)?;
+
+        let read = |key_id_column: &str| db.query_row(
+            &format!(
+                "SELECT {}, application_key, realm FROM account",
+                key_id_column
+            ),
+            [],
+            |row| Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        ).optional();
+
+        // Versions of the tool that predate application keys only saved the
+        // account ID, which is also the ID of the master key.
+        let row = read("COALESCE(account_id_or_app_key_id, account_id)")
+            .or_else(|_| read("account_id"))
+            .map_err(to_io)?;
+
+        Ok(row.map(|(key_id, key, realm)| {
+            let creds = Credentials::new(key_id, key);
+
+            match realm_url(&realm) {
+                Some(url) => creds.with_auth_url(url),
+                None => creds,
+            }
+        }))
+    }
+}
+
+/// Get the authorization URL of a realm named by the `b2` tool, or `None` for
+/// the production realm.
+#[cfg(feature = "b2_account_info")]
+fn realm_url(realm: &str) -> Option<String> {
+    match realm {
+        "production" => None,
+        "staging" => Some("https://api.backblaze.net".into()),
+        "dev" => Some("http://api.backblazeb2.xyz:8180".into()),
+        url => Some(url.into()),
+    }
+}
+
+/// A list of [CredentialsProvider]s to try in order.
+#[derive(Debug, Default)]
+pub struct ProviderChain {
+    providers: Vec<Box<dyn CredentialsProvider>>,
+}
+
+impl ProviderChain {
+    /// Create an empty chain.
+    ///
+    /// To use the usual providers, see [ProviderChain::standard].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a chain that tries the [Environment], then the default
+    /// [ProfileFile], then, if the `b2_account_info` feature is enabled, the
+    /// `b2` command-line tool's `AccountInfo`.
+    pub fn standard() -> Self {
+        let chain = Self::new()
+            .with(Environment::default())
+            .with(ProfileFile::default());
+
+        #[cfg(feature = "b2_account_info")]
+        let chain = chain.with(AccountInfo::default());
+
+        chain
+    }
+
+    /// Add a provider to the end of the chain.
+    pub fn with(mut self, provider: impl CredentialsProvider + 'static)
+    -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    /// Get the credentials from the first provider that has them.
+    ///
+    /// An error from any provider stops the search.
+    pub fn credentials(&self) -> io::Result<Option<Credentials>> {
+        for provider in &self.providers {
+            if let Some(creds) = provider.credentials()? {
+                return Ok(Some(creds));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Log onto the B2 API with the first credentials found.
+    ///
+    /// Returns [Error::MissingCredentials] if no provider has credentials.
+    pub async fn authorize<C, E>(&self, client: C)
+    -> Result<Authorization<C>, Error<E>>
+        where C: HttpClient<Error=Error<E>>,
+              E: fmt::Debug + fmt::Display,
+    {
+        self.credentials()?
+            .ok_or(Error::MissingCredentials)?
+            .authorize(client).await
+    }
+
+    /// Begin a [Session] with the first credentials found.
+    ///
+    /// Returns [Error::MissingCredentials] if no provider has credentials.
+    pub async fn session<C, E>(&self, client: C) -> Result<Session<C>, Error<E>>
+        where C: HttpClient<Error=Error<E>>,
+              E: fmt::Debug + fmt::Display,
+    {
+        let creds = self.credentials()?.ok_or(Error::MissingCredentials)?;
+        Session::with_credentials(client, creds).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("b2-client-{}-{}", name, std::process::id()));
+
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    const PROFILES: &str = r#"
+# Comments and unrelated sections are skipped.
+[other]
+key_id = wrong
+
+[default]
+key_id = "0012345abcdef0000000001"
+key = 'K001secret'
+
+[ "local" ]
+; INI-style unquoted values and long names work too.
+application_key_id = 0f0000000000
+application_key = K000master
+auth_url = "http://127.0.0.1:8000"
+"#;
+
+    #[test]
+    fn profile_file_reads_named_profiles() -> anyhow::Result<()> {
+        let path = temp_file("profiles");
+        fs::write(&path, PROFILES)?;
+
+        let default = ProfileFile::new(&path).credentials()?.unwrap();
+        assert_eq!(default, Credentials::new("0012345abcdef0000000001",
+            "K001secret"));
+
+        let local = ProfileFile::new(&path).profile("local")
+            .credentials()?.unwrap();
+        assert_eq!(local.key_id(), "0f0000000000");
+        assert_eq!(local.key(), "K000master");
+        assert_eq!(local.auth_url(), Some("http://127.0.0.1:8000"));
+
+        assert!(ProfileFile::new(&path).profile("missing")
+            .credentials()?.is_none());
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn incomplete_profile_is_an_error() -> anyhow::Result<()> {
+        let path = temp_file("bad-profile");
+        fs::write(&path, "[default]\nkey_id = abc\n")?;
+
+        let res = ProfileFile::new(&path).credentials();
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        fs::write(&path, "[default\n")?;
+        assert!(ProfileFile::new(&path).credentials().is_err());
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn chain_uses_first_provider_with_credentials() -> anyhow::Result<()> {
+        let path = temp_file("chain");
+        fs::write(&path, PROFILES)?;
+
+        // These variables are never set.
+        let unset = Environment::with_names(
+            "B2_CLIENT_TEST_UNSET_KEY_ID",
+            "B2_CLIENT_TEST_UNSET_KEY"
+        );
+
+        let chain = ProviderChain::new()
+            .with(unset.clone())
+            .with(ProfileFile::new(temp_file("chain-missing")))
+            .with(ProfileFile::new(&path).profile("local"))
+            .with(ProfileFile::new(&path));
+
+        let creds = chain.credentials()?.unwrap();
+        assert_eq!(creds.key_id(), "0f0000000000");
+
+        assert!(ProviderChain::new().with(unset).credentials()?.is_none());
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[test]
+    fn environment_reads_both_variables() -> anyhow::Result<()> {
+        let env = Environment::with_names(
+            "B2_CLIENT_TEST_ENV_KEY_ID",
+            "B2_CLIENT_TEST_ENV_KEY"
+        );
+
+        std::env::set_var("B2_CLIENT_TEST_ENV_KEY_ID", "id");
+        assert!(env.credentials()?.is_none());
+
+        std::env::set_var("B2_CLIENT_TEST_ENV_KEY", "key");
+        assert_eq!(env.credentials()?, Some(Credentials::new("id", "key")));
+
+        Ok(())
+    }
+
+    #[cfg(feature = "b2_account_info")]
+    #[test]
+    fn account_info_reads_b2_cli_database() -> anyhow::Result<()> {
+        let path = temp_file("account-info");
+
+        {
+            let db = rusqlite::Connection::open(&path)?;
+            db.execute_batch("
+                CREATE TABLE account (
+                    account_id TEXT NOT NULL,
+                    application_key TEXT NOT NULL,
+                    account_auth_token TEXT NOT NULL,
+                    api_url TEXT NOT NULL,
+                    download_url TEXT NOT NULL,
+                    minimum_part_size INT NOT NULL,
+                    realm TEXT NOT NULL,
+                    account_id_or_app_key_id TEXT
+                );
+                INSERT INTO account VALUES ('acct', 'K001secret', 'token',
+                    'https://api001.backblazeb2.com',
+                    'https://f001.backblazeb2.com', 100000000, 'production',
+                    '001keyid');
+            ")?;
+        }
+
+        let creds = AccountInfo::new(&path).credentials()?.unwrap();
+        assert_eq!(creds, Credentials::new("001keyid", "K001secret"));
+
+        assert!(AccountInfo::new(temp_file("no-account-info"))
+            .credentials()?.is_none());
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "testing")]
+    #[async_std::test]
+    async fn chain_authorizes_account() -> anyhow::Result<()> {
+        use crate::testing::FakeB2;
+
+        let b2 = FakeB2::new();
+        let (key_id, key) = b2.master_key();
+
+        let path = temp_file("chain-auth");
+        fs::write(&path,
+            format!("[default]\nkey_id={}\nkey={}\n", key_id, key))?;
+
+        let chain = ProviderChain::new().with(ProfileFile::new(&path));
+        let auth = chain.authorize(b2.client()).await?;
+        assert_eq!(auth.account_id(), key_id);
+
+        let session = chain.session(b2.client()).await?;
+        assert_eq!(session.authorization().account_id(), key_id);
+
+        let res = ProviderChain::new().authorize(b2.client()).await;
+        assert!(matches!(res, Err(Error::MissingCredentials)));
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
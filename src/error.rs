@@ -111,6 +111,20 @@ impl From<isahc::http::header::InvalidHeaderValue> for ValidationError {
     }
 }
 
+#[cfg(feature = "with_reqwest")]
+impl From<reqwest::header::InvalidHeaderName> for ValidationError {
+    fn from(e: reqwest::header::InvalidHeaderName) -> Self {
+        Self::BadFormat(format!("{}", e))
+    }
+}
+
+#[cfg(feature = "with_reqwest")]
+impl From<reqwest::header::InvalidHeaderValue> for ValidationError {
+    fn from(e: reqwest::header::InvalidHeaderValue) -> Self {
+        Self::BadFormat(format!("{}", e))
+    }
+}
+
 /// Generic invalid data error.
 #[derive(Debug)]
 pub struct BadData<T>
@@ -232,9 +246,9 @@ impl fmt::Display for LifecycleRuleValidationError {
                 "A bucket can have no more than 100 rules;",
                 "you have provided {}"), i
             ),
-            Self::ConflictingRules(_) => (write!(f,
+            Self::ConflictingRules(_) => write!(f,
                 "Only one lifecycle rule can apply to any given set of files"
-            )),
+            ),
         }
     }
 }
@@ -270,6 +284,74 @@ impl fmt::Display for MissingData {
     }
 }
 
+/// A request that is outside of the bucket or file name prefix to which an
+/// application key is restricted.
+///
+/// These are detected before a request is sent; B2 would reject the request
+/// as unauthorized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Restriction {
+    /// The key can only access the buckets in `allowed`.
+    ///
+    /// `allowed` holds bucket IDs, or bucket names if `requested` is a name.
+    /// `requested` is the bucket the request would access, or `None` if the
+    /// request is not limited to a single bucket (such as listing every
+    /// bucket or creating a new bucket).
+    Bucket {
+        allowed: Vec<String>,
+        requested: Option<String>,
+    },
+    /// The key can only access files whose names begin with `allowed`.
+    ///
+    /// `requested` is the file name or listing prefix of the request, or
+    /// `None` if the request did not specify a prefix.
+    NamePrefix {
+        allowed: String,
+        requested: Option<String>,
+    },
+}
+
+impl std::error::Error for Restriction {}
+
+/// Formats the buckets of a [Restriction::Bucket].
+struct BucketList<'a>(&'a [String]);
+
+impl fmt::Display for BucketList<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            [bucket] => write!(f, "bucket {}", bucket),
+            buckets => write!(f, "buckets {}", buckets.join(", ")),
+        }
+    }
+}
+
+impl fmt::Display for Restriction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bucket { allowed, requested: Some(requested) } => write!(f,
+                "The key is restricted to {}; cannot access bucket {}",
+                BucketList(allowed), requested
+            ),
+            Self::Bucket { allowed, requested: None } => write!(f, concat!(
+                "The key is restricted to {}; ",
+                "the request must be limited to a single bucket"),
+                BucketList(allowed)
+            ),
+            Self::NamePrefix { allowed, requested: Some(requested) } =>
+                write!(f, concat!(
+                    "The key is restricted to file names beginning with ",
+                    "\"{}\"; cannot access \"{}\""), allowed, requested
+                ),
+            Self::NamePrefix { allowed, requested: None } => write!(f,
+                concat!(
+                    "The key is restricted to file names beginning with ",
+                    "\"{}\"; the request must specify a prefix within it"
+                ), allowed
+            ),
+        }
+    }
+}
+
 /// Errors related to making B2 API calls.
 #[derive(Debug)]
 pub enum Error<E>
@@ -282,8 +364,12 @@ pub enum Error<E>
     IO(std::io::Error),
     /// An error from the Backblaze B2 API.
     B2(B2Error),
+    /// An error from B2's S3-compatible API.
+    S3(S3Error),
     /// An error deserializing the HTTP client's response.
     Format(serde_json::Error),
+    /// An error deserializing an XML response from the S3-compatible API.
+    Xml(quick_xml::DeError),
     /// The [Authorization](crate::account::Authorization) lacks a required
     /// capability to perform a task. The provided capability is required.
     ///
@@ -291,6 +377,10 @@ pub enum Error<E>
     /// returning an error or to return what we know will be an authorization
     /// error prior to sending a request to the API.
     Unauthorized(crate::account::Capability),
+    /// The [Authorization](crate::account::Authorization)'s key is restricted
+    /// to a bucket or file name prefix that does not include the target of
+    /// the request.
+    Restricted(Restriction),
     /// An error validating data prior to making a Backblaze B2 API call.
     Validation(ValidationError),
     /// Attempted to send a request without a valid
@@ -298,6 +388,19 @@ pub enum Error<E>
     MissingAuthorization,
     /// Attempted to send a non-existent request.
     NoRequest,
+    /// No [CredentialsProvider](crate::credentials::CredentialsProvider)
+    /// found an application key.
+    MissingCredentials,
+}
+
+impl<E> Error<E>
+    where E: fmt::Debug + fmt::Display,
+{
+    /// Returns `true` if the request was rejected because its authorization
+    /// token has expired or is otherwise no longer valid.
+    pub(crate) fn is_expired_authorization(&self) -> bool {
+        matches!(self, Self::B2(e) if e.is_expired_authorization())
+    }
 }
 
 impl<E> std::error::Error for Error<E>
@@ -314,12 +417,17 @@ impl<E> fmt::Display for Error<E>
             Self::Client(e) => Display::fmt(&e, f),
             Self::IO(e) => e.fmt(f),
             Self::B2(e) => Display::fmt(&e, f),
+            Self::S3(e) => Display::fmt(&e, f),
             Self::Format(e) => e.fmt(f),
+            Self::Xml(e) => e.fmt(f),
             Self::Unauthorized(c) => write!(f, "Missing capability: {:?}", c),
+            Self::Restricted(e) => e.fmt(f),
             Self::Validation(e) => e.fmt(f),
             Self::MissingAuthorization =>
                 write!(f, "An Authorization is required for that operation"),
             Self::NoRequest => write!(f, "No request was created"),
+            Self::MissingCredentials =>
+                write!(f, "No application key could be found"),
         }
     }
 }
@@ -348,6 +456,22 @@ impl<E> From<serde_json::Error> for Error<E>
     }
 }
 
+impl<E> From<S3Error> for Error<E>
+    where E: fmt::Debug + fmt::Display,
+{
+    fn from(e: S3Error) -> Self {
+        Self::S3(e)
+    }
+}
+
+impl<E> From<quick_xml::DeError> for Error<E>
+    where E: fmt::Debug + fmt::Display,
+{
+    fn from(e: quick_xml::DeError) -> Self {
+        Self::Xml(e)
+    }
+}
+
 #[cfg(feature = "with_surf")]
 impl From<surf::Error> for Error<surf::Error> {
     fn from(e: surf::Error) -> Self {
@@ -376,6 +500,28 @@ impl From<isahc::http::Error> for Error<isahc::Error> {
     }
 }
 
+#[cfg(feature = "with_reqwest")]
+impl From<reqwest::Error> for Error<reqwest::Error> {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Client(e)
+    }
+}
+
+#[cfg(feature = "with_ureq")]
+impl From<ureq::Error> for Error<ureq::Error> {
+    fn from(e: ureq::Error) -> Self {
+        Self::Client(e)
+    }
+}
+
+impl<E> From<Restriction> for Error<E>
+    where E: fmt::Debug + fmt::Display,
+{
+    fn from(e: Restriction) -> Self {
+        Self::Restricted(e)
+    }
+}
+
 impl<E> From<ValidationError> for Error<E>
     where E: fmt::Debug + fmt::Display,
 {
@@ -384,12 +530,73 @@ impl<E> From<ValidationError> for Error<E>
     }
 }
 
+/// A failure partway through [rotate_key](crate::account::rotate_key).
+///
+/// Each variant describes which keys exist after the failure. The old key is
+/// only deleted after every other step succeeds, so at least one working key
+/// always remains.
+#[derive(Debug)]
+pub enum RotateKeyError<E, S>
+    where E: fmt::Debug + fmt::Display,
+{
+    /// The replacement key could not be created. The old key is unchanged.
+    Create(Error<E>),
+    /// The replacement key was created but could not be used to authorize
+    /// the account. The old key is unchanged.
+    ///
+    /// We attempt to delete the replacement key; if that also fails,
+    /// `cleanup` is its error and the returned `new_key` still exists.
+    Verify {
+        new_key: crate::account::Key,
+        error: Error<E>,
+        cleanup: Option<Error<E>>,
+    },
+    /// The callback could not store the replacement key. Both keys exist.
+    ///
+    /// The replacement's secret is included so that the caller can try to
+    /// store it again or delete the key.
+    Store {
+        secret: String,
+        new_key: crate::account::Key,
+        error: S,
+    },
+    /// The replacement key was stored, but the old key could not be deleted.
+    /// Both keys exist; the old key should be deleted later.
+    DeleteOld {
+        new_key: crate::account::Key,
+        error: Error<E>,
+    },
+}
+
+impl<E, S> std::error::Error for RotateKeyError<E, S>
+    where E: fmt::Debug + fmt::Display,
+          S: fmt::Debug + fmt::Display,
+{}
+
+impl<E, S> fmt::Display for RotateKeyError<E, S>
+    where E: fmt::Debug + fmt::Display,
+          S: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Create(e) =>
+                write!(f, "Failed to create the replacement key: {}", e),
+            Self::Verify { error, .. } =>
+                write!(f, "Failed to verify the replacement key: {}", error),
+            Self::Store { error, .. } =>
+                write!(f, "Failed to store the replacement key: {}", error),
+            Self::DeleteOld { error, .. } =>
+                write!(f, "Failed to delete the old key: {}", error),
+        }
+    }
+}
+
 /// An error code from the B2 API.
 ///
 /// The HTTP status code is not necessarily constant for any given error code.
 /// See the B2 documentation for the relevant API call to match HTTP status
 /// codes and B2 error codes.
-#[derive(Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum ErrorCode {
     // 400
     BadBucketId,
@@ -432,6 +639,9 @@ pub enum ErrorCode {
     // 416
     RangeNotSatisfiable,
 
+    // 429
+    TooManyRequests,
+
     // 500
     InternalError,
 
@@ -480,6 +690,8 @@ impl ErrorCode {
 
             "conflict" => Self::Conflict,
 
+            "too_many_requests" => Self::TooManyRequests,
+
             "internal_error" => Self::InternalError,
 
             "service_unavailable" => Self::ServiceUnavailable,
@@ -505,12 +717,50 @@ pub struct B2Error {
 }
 
 impl B2Error {
+    /// Create a `B2Error` from an unsuccessful HTTP response.
+    ///
+    /// Responses without a B2 error in the body (such as responses to `HEAD`
+    /// requests) are given the error code B2 uses for their HTTP status.
+    pub(crate) fn from_response(res: &crate::client::Response) -> Self {
+        if let Ok(err) = serde_json::from_slice(res.body()) {
+            return err;
+        }
+
+        let code = match res.status() {
+            400 => "bad_request",
+            401 => "unauthorized",
+            403 => "access_denied",
+            404 => "not_found",
+            405 => "method_not_allowed",
+            408 => "request_timeout",
+            409 => "conflict",
+            416 => "range_not_satisfiable",
+            429 => "too_many_requests",
+            500 => "internal_error",
+            503 => "service_unavailable",
+            _ => "unknown",
+        };
+
+        Self {
+            status: res.status(),
+            code_str: code.into(),
+            message: String::from_utf8_lossy(res.body()).into_owned(),
+        }
+    }
+
     /// Get the HTTP status code for the error.
     pub fn http_status(&self) -> u16 { self.status }
 
     pub fn code(&self) -> ErrorCode {
         ErrorCode::from_api_code(&self.code_str)
     }
+
+    /// Returns `true` if the request was rejected because its authorization
+    /// token has expired or is otherwise no longer valid.
+    pub(crate) fn is_expired_authorization(&self) -> bool {
+        matches!(self.code(),
+            ErrorCode::ExpiredAuthToken | ErrorCode::BadAuthToken)
+    }
 }
 
 impl std::error::Error for B2Error {}
@@ -520,3 +770,96 @@ impl fmt::Display for B2Error {
         write!(f, "{}: {}", self.code_str, self.message)
     }
 }
+
+/// An error response from B2's S3-compatible API.
+///
+/// See <https://docs.aws.amazon.com/AmazonS3/latest/API/ErrorResponses.html>
+/// for the error codes.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct S3Error {
+    #[serde(skip)]
+    status: u16,
+    code: String,
+    #[serde(default)]
+    message: String,
+}
+
+impl S3Error {
+    /// Create an `S3Error` from an unsuccessful HTTP response.
+    ///
+    /// Responses without an error document in the body (such as responses to
+    /// `HEAD` requests) are given a code describing their HTTP status.
+    pub(crate) fn from_response(res: &crate::client::Response) -> Self {
+        if let Ok(err) = quick_xml::de::from_reader::<_, Self>(res.body()) {
+            return Self { status: res.status(), ..err };
+        }
+
+        let code = match res.status() {
+            304 => "NotModified",
+            400 => "BadRequest",
+            403 => "AccessDenied",
+            404 => "NotFound",
+            412 => "PreconditionFailed",
+            _ => "Unknown",
+        };
+
+        Self {
+            status: res.status(),
+            code: code.into(),
+            message: String::from_utf8_lossy(res.body()).into_owned(),
+        }
+    }
+
+    /// Get the HTTP status code for the error.
+    pub fn http_status(&self) -> u16 { self.status }
+
+    /// The S3 error code, such as `NoSuchKey`.
+    pub fn code(&self) -> &str { &self.code }
+
+    /// A description of what went wrong.
+    pub fn message(&self) -> &str { &self.message }
+}
+
+impl std::error::Error for S3Error {}
+
+impl fmt::Display for S3Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code, self.message)
+    }
+}
+
+/// An event notification request that could not be verified or parsed.
+///
+/// See [webhook](crate::webhook).
+#[derive(Debug)]
+pub enum WebhookError {
+    /// The signature header is not of the form `v1=<hex digest>`.
+    MalformedSignature,
+    /// The signature does not match the request body; the request may not
+    /// have come from B2, or the wrong signing secret was used.
+    SignatureMismatch,
+    /// The request body is not a valid event notification.
+    BadPayload(serde_json::Error),
+}
+
+impl std::error::Error for WebhookError {}
+
+impl fmt::Display for WebhookError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MalformedSignature =>
+                write!(f, "The notification signature is malformed"),
+            Self::SignatureMismatch =>
+                write!(f, "The notification signature does not match"),
+            Self::BadPayload(e) =>
+                write!(f, "Invalid event notification: {}", e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for WebhookError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::BadPayload(e)
+    }
+}
@@ -22,11 +22,14 @@
 //! 3. Call [get_upload_authorization] to get an [UploadAuthorization] for a
 //!    bucket.
 //! 4. Call [upload_file] with your `UploadAuthorization`, `UploadFile` request,
-//!    and file data.
+//!    and file data. To stream the data from an `AsyncRead` source instead of
+//!    holding it in memory, call [upload_file_stream].
 //!
 //! You can upload multiple files with a single `UploadAuthorization`, but only
 //! one at a time. To upload multiple files in parallel, each thread or task
-//! needs to obtain its own `UploadAuthorization`.
+//! needs to obtain its own `UploadAuthorization`; any number of them can be
+//! obtained from one [Authorization] or
+//! [Session](crate::session::Session).
 //!
 //!
 //! # Uploading Large Files
@@ -41,10 +44,13 @@
 //!    [UploadPartAuthorization].
 //!     * You can upload parts in separate threads for better performance; each
 //!       thread must call [get_upload_part_authorization] and use its
-//!       respective authorization when uploading data.
+//!       respective authorization when uploading data. The upload
+//!       authorizations do not borrow the `Authorization`, so they can be
+//!       moved into spawned tasks.
 //! 4. Create an [UploadFilePart] object via the [UploadFilePartBuilder].
 //! 5. Use the `UploadPartAuthorization` and `UploadFilePart` to call
-//!    [upload_file_part] with the file data to upload.
+//!    [upload_file_part] with the file data to upload, or
+//!    [upload_file_part_stream] to stream the data from an `AsyncRead` source.
 //! 6. Call [UploadFilePart::create_next_part] to create a new upload part
 //!    request.
 //! 7. Repeat steps 5 and 6 until all parts have been uploaded.
@@ -148,15 +154,16 @@ use std::fmt;
 
 use crate::{
     prelude::*,
-    account::Capability,
+    account::{ApiVersion, Capability},
     bucket::{
         Bucket,
         FileRetentionMode,
         FileRetentionPolicy,
         ServerSideEncryption,
     },
-    client::{HeaderMap, HttpClient},
+    client::{HeaderMap, HttpClient, Response, ResponseReader},
     error::*,
+    pagination::{sealed::Sealed, Fetch, ListRequest, ListStream},
     types::ContentDisposition,
     validate::{
         validate_content_disposition,
@@ -193,7 +200,7 @@ macro_rules! percent_encode {
     };
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 enum LegalHoldValue {
     On,
@@ -210,7 +217,7 @@ impl fmt::Display for LegalHoldValue {
 }
 
 /// Determines whether there is a legal hold on a file.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct FileLegalHold {
     #[serde(rename = "isClientAuthorizedToRead")]
     can_read: bool,
@@ -233,6 +240,22 @@ pub enum FileAction {
     Folder,
 }
 
+/// The progress of replicating a file to another bucket.
+///
+/// See [ReplicationRule](crate::bucket::ReplicationRule).
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ReplicationStatus {
+    /// The file is waiting to be replicated.
+    Pending,
+    /// The file has been replicated.
+    Completed,
+    /// The file could not be replicated.
+    Failed,
+    /// The file is itself a replica of a file in a source bucket.
+    Replica,
+}
+
 // TODO: I may want to rename this to FileRetention since it's one of
 // update_file_retention's parameters.
 // This is different than but very similar to bucket::FileRetentionPolicy.
@@ -267,7 +290,7 @@ impl FileRetentionSetting {
 
 // This is different than but very similar to bucket::FileLockConfiguration.
 /// The retention settings for a file.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct FileRetention {
     #[serde(rename = "isClientAuthorizedToRead")]
     can_read: bool,
@@ -289,7 +312,7 @@ impl FileRetention {
 
 // TODO: Rename to FileMetadata?
 /// Metadata of a file stored in B2.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct File {
@@ -307,6 +330,7 @@ pub struct File {
     file_name: String,
     file_retention: Option<FileRetention>,
     legal_hold: Option<FileLegalHold>,
+    replication_status: Option<ReplicationStatus>,
     server_side_encryption: Option<ServerSideEncryption>,
     // Milliseconds since midnight, 1970-1-1
     // If action is `Folder`, this will be 0.
@@ -403,6 +427,12 @@ impl File {
         self.server_side_encryption.as_ref()
     }
 
+    /// The file's replication status, if its bucket is a replication source or
+    /// destination.
+    pub fn replication_status(&self) -> Option<ReplicationStatus> {
+        self.replication_status
+    }
+
     /// The date and time at which the file was uploaded.
     ///
     /// If the [action](Self::action) is `Folder`, returns `None`.
@@ -411,7 +441,7 @@ impl File {
 
         match self.action {
             FileAction::Folder => None,
-            _ => Some(Utc.timestamp_millis(self.upload_timestamp)),
+            _ => Utc.timestamp_millis_opt(self.upload_timestamp).single(),
         }
     }
 }
@@ -443,7 +473,7 @@ impl FilePart {
     pub fn upload_timestamp(&self) -> chrono::DateTime<chrono::Utc> {
         use chrono::{TimeZone as _, Utc};
 
-        Utc.timestamp_millis(self.upload_timestamp)
+        Utc.timestamp_millis_opt(self.upload_timestamp).unwrap()
     }
 }
 
@@ -474,6 +504,7 @@ pub async fn cancel_large_file<C, E>(auth: &mut Authorization<C>, file: File)
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
+    auth.capabilities().check_file(&file.bucket_id, &file.file_name)?;
     cancel_large_file_by_id(auth, file.file_id).await
 }
 
@@ -487,7 +518,7 @@ pub async fn cancel_large_file_by_id<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::WriteFiles);
+    require_capabilities!(auth, Operation::CancelLargeFile);
 
     let res = auth.client.post(auth.api_url("b2_cancel_large_file"))
         .expect("Invalid URL")
@@ -495,7 +526,7 @@ pub async fn cancel_large_file_by_id<C, E>(
         .with_body_json(serde_json::json!({ "fileId": id.as_ref() }))
         .send().await?;
 
-    let info: B2Result<CancelledFileUpload> = serde_json::from_slice(&res)?;
+    let info: B2Result<CancelledFileUpload> = B2Result::from_response(&res)?;
     info.into()
 }
 
@@ -539,7 +570,7 @@ impl ByteRange {
 }
 
 /// Describe the action to take with file metadata when copying a file.
-#[derive(Debug, Eq, PartialEq, Serialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
 #[serde(rename_all = "UPPERCASE")]
 pub enum MetadataDirective {
     Copy,
@@ -549,7 +580,7 @@ pub enum MetadataDirective {
 /// A request to copy a file from a bucket, potentially to a different bucket.
 ///
 /// Use [CopyFileBuilder] to create a `CopyFile`, then pass it to [copy_file].
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CopyFile<'a> {
     source_file_id: String,
@@ -895,23 +926,23 @@ impl<'a> CopyFileBuilder<'a> {
 ///
 /// If copying from one bucket to another, both buckets must belong to the same
 /// account.
-pub async fn copy_file<'a, C, E>(
+pub async fn copy_file<C, E>(
     auth: &mut Authorization<C>,
     file: CopyFile<'_>
 ) -> Result<File, Error<E>>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::WriteFiles);
-    if file.file_retention.is_some() {
-        require_capability!(auth, Capability::WriteFileRetentions);
-    }
-    if file.legal_hold.is_some() {
-        require_capability!(auth, Capability::WriteFileLegalHolds);
-    }
-    if file.dest_encryption.is_some() {
-        require_capability!(auth, Capability::WriteBucketEncryption);
+    require_capabilities!(auth, Operation::CopyFile {
+        retention: file.file_retention.is_some(),
+        legal_hold: file.legal_hold.is_some(),
+        encryption: file.dest_encryption.is_some(),
+    });
+
+    if let Some(bucket_id) = &file.destination_bucket_id {
+        auth.capabilities().check_bucket(bucket_id)?;
     }
+    auth.capabilities().check_file_name(file.file_name)?;
 
     let res = auth.client.post(auth.api_url("b2_copy_file"))
         .expect("Invalid URL")
@@ -919,12 +950,12 @@ pub async fn copy_file<'a, C, E>(
         .with_body_json(serde_json::to_value(file)?)
         .send().await?;
 
-    let file: B2Result<File> = serde_json::from_slice(&res)?;
+    let file: B2Result<File> = B2Result::from_response(&res)?;
     file.into()
 }
 
 /// A request to copy from an existing file to a part of a large file.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CopyFilePart<'a> {
     source_file_id: &'a str,
@@ -1071,7 +1102,7 @@ pub async fn copy_file_part<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::WriteFiles);
+    require_capabilities!(auth, Operation::CopyFilePart);
 
     let res = auth.client.post(auth.api_url("b2_copy_part"))
         .expect("Invalid URL")
@@ -1079,7 +1110,7 @@ pub async fn copy_file_part<C, E>(
         .with_body_json(serde_json::to_value(file_part)?)
         .send().await?;
 
-    let part: B2Result<FilePart> = serde_json::from_slice(&res)?;
+    let part: B2Result<FilePart> = B2Result::from_response(&res)?;
     part.into()
 }
 
@@ -1106,6 +1137,8 @@ pub async fn delete_file_version<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
+    auth.capabilities().check_bucket(&file.bucket_id)?;
+
     delete_file_version_by_name_id(
         auth,
         &file.file_name,
@@ -1126,6 +1159,7 @@ pub async fn download_file_headers<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
+    auth.capabilities().check_file(&file.bucket_id, &file.file_name)?;
     download_file_headers_by_id(auth, &file.file_id).await
 }
 
@@ -1145,7 +1179,7 @@ pub async fn download_file_headers_by_id<C, E>(
     // don't require an authorization token, but the docs read as if this is
     // necessary if provided. Need to test, and if necessary allow downloading
     // the file without passing the authorization token.
-    require_capability!(auth, Capability::ReadFiles);
+    require_capabilities!(auth, Operation::DownloadFile);
 
     let res = auth.client.head(
             format!("{}?fileId={}",
@@ -1155,12 +1189,17 @@ pub async fn download_file_headers_by_id<C, E>(
         )
         .expect("Invalid URL")
         .with_header("Authorization", &auth.authorization_token).unwrap()
-        .send_keep_headers().await?;
+        .send().await?;
 
-    Ok(res.1)
+    if res.is_success() {
+        let (_, headers, _) = res.into_parts();
+        Ok(headers)
+    } else {
+        Err(B2Error::from_response(&res).into())
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum FileHandle<'a> {
     Id(&'a str),
     Name((String, &'a str)), // (Percent-encoded file name, bucket name)
@@ -1178,7 +1217,7 @@ enum FileHandle<'a> {
 /// See <https://www.backblaze.com/b2/docs/b2_download_file_by_id.html> for
 /// information on downloading files, including the list of headers that may be
 /// returned.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DownloadFile<'a> {
     #[serde(skip_serializing)]
@@ -1428,7 +1467,8 @@ impl<'a, C> DownloadAuth<'a, C>
         match self {
             Self::Auth(auth) => auth.download_url(endpoint),
             Self::Download(auth) =>
-                format!("{}/b2api/v2/{}", auth.download_url, endpoint.as_ref())
+                format!("{}/b2api/{}/{}",
+                    auth.download_url, auth.api_version, endpoint.as_ref())
         }
     }
 
@@ -1445,6 +1485,28 @@ impl<'a, C> DownloadAuth<'a, C>
             _ => true,
         }
     }
+
+    /// Check that the file is within the bucket and file name prefix to
+    /// which the authorization is restricted.
+    fn check_file(&self, bucket_name: &str, file_name: &str)
+    -> Result<(), Restriction> {
+        match self {
+            Self::Auth(auth) => {
+                auth.capabilities().check_bucket_name(bucket_name)?;
+                auth.capabilities().check_file_name(file_name)
+            },
+            Self::Download(auth) => {
+                if file_name.starts_with(&auth.file_name_prefix) {
+                    Ok(())
+                } else {
+                    Err(Restriction::NamePrefix {
+                        allowed: auth.file_name_prefix.clone(),
+                        requested: Some(file_name.to_owned()),
+                    })
+                }
+            },
+        }
+    }
 }
 
 impl<'a, C> From<&'a mut Authorization<C>> for DownloadAuth<'a, C>
@@ -1476,23 +1538,72 @@ pub async fn download_file<'a, C, E>(
 ) -> Result<(Vec<u8>, HeaderMap), Error<E>>
     where C: HttpClient<Error=Error<E>> + 'a,
           E: fmt::Debug + fmt::Display,
+{
+    let req = prepare_download_file(auth, file)?;
+    let res = req.send().await?;
+
+    if res.is_success() {
+        let (_, headers, body) = res.into_parts();
+        Ok((body, headers))
+    } else {
+        Err(B2Error::from_response(&res).into())
+    }
+}
+
+/// Download a file from the B2 service, streaming its contents.
+///
+/// This behaves like [download_file], but rather than reading the file into
+/// memory, returns a reader over the response body along with the response
+/// headers. The file's contents are received as you read from the returned
+/// reader.
+///
+/// If B2 returns an error, it is read and returned as [Error::B2] before
+/// returning.
+pub async fn download_file_stream<'a, C, E>(
+    auth: impl Into<DownloadAuth<'a, C>>,
+    file: DownloadFile<'_>
+) -> Result<(ResponseReader, HeaderMap), Error<E>>
+    where C: HttpClient<Error=Error<E>> + 'a,
+          E: fmt::Debug + fmt::Display,
+{
+    let req = prepare_download_file(auth, file)?;
+    let res = req.send_streaming().await?;
+
+    if res.is_success() {
+        let (_, headers, body) = res.into_parts();
+        Ok((body, headers))
+    } else {
+        let res = res.into_response().await?;
+        Err(B2Error::from_response(&res).into())
+    }
+}
+
+/// Create a request to download a file by either its ID or name; the caller
+/// sends it.
+fn prepare_download_file<'a, C, E>(
+    auth: impl Into<DownloadAuth<'a, C>>,
+    file: DownloadFile<'_>
+) -> Result<&'a mut C, Error<E>>
+    where C: HttpClient<Error=Error<E>> + 'a,
+          E: fmt::Debug + fmt::Display,
 {
     match file.file {
         FileHandle::Id(_) => {
             match auth.into() {
-                DownloadAuth::Auth(auth) => download_file_by_id(auth, file)
-                    .await,
+                DownloadAuth::Auth(auth) =>
+                    prepare_download_file_by_id(auth, file),
                 _ => Err(Error::MissingAuthorization),
             }
         },
-        FileHandle::Name(_) => download_file_by_name(auth, file).await
+        FileHandle::Name(_) => prepare_download_file_by_name(auth, file),
     }
 }
 
-async fn download_file_by_id<C, E>(
-    auth: &mut Authorization<C>,
+/// Create a request to download a file by its ID; the caller sends it.
+fn prepare_download_file_by_id<'a, C, E>(
+    auth: &'a mut Authorization<C>,
     file: DownloadFile<'_>
-) -> Result<(Vec<u8>, HeaderMap), Error<E>>
+) -> Result<&'a mut C, Error<E>>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
@@ -1500,11 +1611,12 @@ async fn download_file_by_id<C, E>(
     // don't require an authorization token, but the docs read as if this is
     // necessary if provided. Need to test, and if necessary allow downloading
     // the file without passing the authorization token.
-    require_capability!(auth, Capability::ReadFiles);
+    require_capabilities!(auth, Operation::DownloadFile);
 
     let file_id = match file.file {
         FileHandle::Id(id) => id,
-        FileHandle::Name(_) => panic!("Call download_file_by_name() instead"),
+        FileHandle::Name(_) =>
+            panic!("Call prepare_download_file_by_name() instead"),
     };
 
     let mut file_req = serde_json::to_value(&file)?;
@@ -1535,33 +1647,33 @@ async fn download_file_by_id<C, E>(
             )?;
     }
 
-    let (body, headers) = req.send_keep_headers().await?;
-
-    // An error from Backblaze would successfully deserialize as Vec<u8>, so we
-    // need to check for it specifically.
-    let res: Result<B2Error, _> = serde_json::from_slice(&body);
-    match res {
-        Ok(e) => Err(e.into()),
-        Err(_) => Ok((body, headers)),
-    }
+    Ok(req)
 }
 
-async fn download_file_by_name<'a, C, E>(
+/// Create a request to download a file by its name; the caller sends it.
+fn prepare_download_file_by_name<'a, C, E>(
     auth: impl Into<DownloadAuth<'a, C>>,
     file: DownloadFile<'_>
-) -> Result<(Vec<u8>, HeaderMap), Error<E>>
+) -> Result<&'a mut C, Error<E>>
     where C: HttpClient<Error=Error<E>> + 'a,
           E: fmt::Debug + fmt::Display,
 {
-    let mut auth = auth.into();
+    let auth = auth.into();
 
     // TODO: This is probably only required for private buckets; public buckets
     // don't require an authorization token, but the docs read as if this is
     // necessary if provided. Need to test, and if necessary allow downloading
     // the file without passing the authorization token.
-    require_capability!(auth, Capability::ReadFiles);
+    require_capabilities!(auth, Operation::DownloadFile);
     assert!(matches!(file.file, FileHandle::Name(_)));
 
+    if let FileHandle::Name((name, bucket)) = &file.file {
+        let name = percent_encoding::percent_decode_str(name)
+            .decode_utf8_lossy();
+
+        auth.check_file(bucket, &name)?;
+    }
+
     let mut url = file.public_url(&auth).to_owned();
 
     macro_rules! add_param {
@@ -1606,8 +1718,8 @@ async fn download_file_by_name<'a, C, E>(
     let auth_token = auth.authorization_token().to_owned();
 
     let client = match auth {
-        DownloadAuth::Auth(ref mut auth) => &mut auth.client,
-        DownloadAuth::Download(ref mut auth) => &mut auth.client,
+        DownloadAuth::Auth(auth) => &mut auth.client,
+        DownloadAuth::Download(auth) => &mut auth.client,
     };
 
     let mut req = client.get(url)
@@ -1618,15 +1730,7 @@ async fn download_file_by_name<'a, C, E>(
         req = req.with_header("Range", &range.to_string())?
     }
 
-    let (body, headers) = req.send_keep_headers().await?;
-
-    // An error from Backblaze would successfully deserialize as Vec<u8>, so we
-    // need to check for it specifically.
-    let res: Result<B2Error, _> = serde_json::from_slice(&body);
-    match res {
-        Ok(e) => Err(e.into()),
-        Err(_) => Ok((body, headers)),
-    }
+    Ok(req)
 }
 
 /// Delete a version of a file.
@@ -1645,7 +1749,10 @@ pub async fn delete_file_version_by_name_id<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::DeleteFiles);
+    require_capabilities!(auth, Operation::DeleteFileVersion {
+        bypass_governance: matches!(bypass_governance, BypassGovernance::Yes),
+    });
+    auth.capabilities().check_file_name(file_name.as_ref())?;
 
     let mut body = serde_json::json!({
         "fileName": &file_name.as_ref(),
@@ -1653,7 +1760,6 @@ pub async fn delete_file_version_by_name_id<C, E>(
     });
 
     if matches!(bypass_governance, BypassGovernance::Yes) {
-        require_capability!(auth, Capability::BypassGovernance);
         body["bypassGovernance"] = serde_json::Value::Bool(true);
     }
 
@@ -1663,7 +1769,7 @@ pub async fn delete_file_version_by_name_id<C, E>(
         .with_body_json(body)
         .send().await?;
 
-    let file: B2Result<DeletedFile> = serde_json::from_slice(&res)?;
+    let file: B2Result<DeletedFile> = B2Result::from_response(&res)?;
     file.into()
 }
 
@@ -1684,6 +1790,7 @@ pub async fn finish_large_file_upload<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
+    auth.capabilities().check_file(&file.bucket_id, &file.file_name)?;
     finish_large_file_upload_by_id(auth, &file.file_id, sha1_checksums).await
 }
 
@@ -1700,7 +1807,7 @@ pub async fn finish_large_file_upload_by_id<C, E>(
 {
     use serde_json::json;
 
-    require_capability!(auth, Capability::WriteFiles);
+    require_capabilities!(auth, Operation::FinishLargeFile);
 
     let res = auth.client.post(auth.api_url("b2_finish_large_file"))
         .expect("Invalid URL")
@@ -1711,7 +1818,7 @@ pub async fn finish_large_file_upload_by_id<C, E>(
         }))
         .send().await?;
 
-    let file: B2Result<File> = serde_json::from_slice(&res)?;
+    let file: B2Result<File> = B2Result::from_response(&res)?;
     file.into()
 }
 
@@ -1733,7 +1840,7 @@ pub async fn get_file_info<C, E>(
 {
     use serde_json::json;
 
-    require_capability!(auth, Capability::ReadFiles);
+    require_capabilities!(auth, Operation::GetFileInfo);
 
     let res = auth.client.post(auth.api_url("b2_get_file_info"))
         .expect("Invalid URL")
@@ -1743,7 +1850,7 @@ pub async fn get_file_info<C, E>(
         }))
         .send().await?;
 
-    let file_info: B2Result<File> = serde_json::from_slice(&res)?;
+    let file_info: B2Result<File> = B2Result::from_response(&res)?;
     match file_info {
         B2Result::Ok(mut info) => {
             if let Some(sha1) = &info.content_sha1 {
@@ -1763,7 +1870,7 @@ pub async fn get_file_info<C, E>(
 /// Use [DownloadAuthorizationRequestBuilder] to create a
 /// `DownloadAuthorizationRequest`, then pass it to [get_download_authorization]
 /// to obtain a [DownloadAuthorization].
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DownloadAuthorizationRequest<'a> {
     bucket_id: &'a str,
@@ -1928,6 +2035,7 @@ pub struct DownloadAuthorization<C>
     client: C,
     api_url: String,
     download_url: String,
+    api_version: ApiVersion,
 
     bucket_id: String,
     file_name_prefix: String,
@@ -1951,6 +2059,7 @@ impl<C> DownloadAuthorization<C>
             client: auth.client.clone(),
             api_url: auth.api_url.clone(),
             download_url: auth.download_url.clone(),
+            api_version: auth.api_version(),
             bucket_id: proto.bucket_id,
             file_name_prefix: proto.file_name_prefix,
             authorization_token: proto.authorization_token,
@@ -2005,14 +2114,16 @@ struct ProtoDownloadAuthorization {
 ///     .await?;
 /// # Ok(()) }
 /// ```
-pub async fn get_download_authorization<'a, C, E>(
+pub async fn get_download_authorization<C, E>(
     auth: &mut Authorization<C>,
     download_req: DownloadAuthorizationRequest<'_>
 ) -> Result<DownloadAuthorization<C>, Error<E>>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::ShareFiles);
+    require_capabilities!(auth, Operation::GetDownloadAuthorization);
+    auth.capabilities().check_bucket(download_req.bucket_id)?;
+    auth.capabilities().check_prefix(Some(download_req.file_name_prefix))?;
 
     let res = auth.client.post(auth.api_url("b2_get_download_authorization"))
         .expect("Invalid URL")
@@ -2021,30 +2132,60 @@ pub async fn get_download_authorization<'a, C, E>(
         .send().await?;
 
     let proto_auth: B2Result<ProtoDownloadAuthorization> =
-        serde_json::from_slice(&res)?;
+        B2Result::from_response(&res)?;
 
     proto_auth.map(|a| DownloadAuthorization::from_proto(a, auth)).into()
 }
 
 /// An authorization to upload file contents to a B2 file.
+///
+/// An `UploadPartAuthorization` holds its own copy of the [Authorization] it
+/// was obtained with, so it can be moved to another thread or task.
 #[derive(Deserialize)]
 #[allow(dead_code)]
 #[serde(rename_all = "camelCase")]
-pub struct UploadPartAuthorization<'a, 'b, C, E>
+pub struct UploadPartAuthorization<C, E>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
     #[serde(skip_deserializing)]
     #[serde(default = "make_none")]
-    auth: Option<&'a mut Authorization<C>>,
+    auth: Option<Authorization<C>>,
     #[serde(skip_deserializing)]
     #[serde(default = "make_none")]
-    encryption: Option<&'b ServerSideEncryption>,
+    encryption: Option<ServerSideEncryption>,
     file_id: String,
     upload_url: String,
     authorization_token: String,
 }
 
+impl<C, E> UploadPartAuthorization<C, E>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    /// Obtain a new upload URL and authorization token for the same file.
+    pub async fn refresh(&mut self) -> Result<(), Error<E>> {
+        // Unwrap safety: see prepare_upload_file_part.
+        let auth = self.auth.as_mut().unwrap();
+        let new = get_upload_part_authorization_by_id(
+            auth, &self.file_id, self.encryption.as_ref()
+        ).await?;
+
+        let (upload_url, authorization_token) =
+            (new.upload_url, new.authorization_token);
+
+        self.upload_url = upload_url;
+        self.authorization_token = authorization_token;
+        Ok(())
+    }
+
+    /// Returns `true` if the upload was rejected because of an expired
+    /// authorization that we are able to renew.
+    fn should_refresh(&self, res: &Response) -> bool {
+        self.auth.as_ref().is_some_and(|a| a.should_reauthorize(res))
+    }
+}
+
 fn make_none<T>() -> Option<T> { None }
 
 /// Get an [UploadPartAuthorization] to upload data to a new B2 file.
@@ -2055,7 +2196,8 @@ fn make_none<T>() -> Option<T> { None }
 /// rejects an upload.
 ///
 /// If uploading multiple parts concurrently, each thread or task needs its own
-/// authorization.
+/// `UploadPartAuthorization`; they can all be obtained with the same
+/// [Authorization].
 ///
 /// The [Authorization] must have [Capability::WriteFiles].
 ///
@@ -2063,13 +2205,15 @@ fn make_none<T>() -> Option<T> { None }
 ///
 /// The equivalent B2 endpoint is called
 /// [`b2_get_upload_url`](https://www.backblaze.com/b2/docs/b2_get_upload_part_url.html).
-pub async fn get_upload_part_authorization<'a, 'b, C, E>(
-    auth: &'a mut Authorization<C>,
-    file: &'b File,
-) -> Result<UploadPartAuthorization<'a, 'b, C, E>, Error<E>>
+pub async fn get_upload_part_authorization<C, E>(
+    auth: &mut Authorization<C>,
+    file: &File,
+) -> Result<UploadPartAuthorization<C, E>, Error<E>>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
+    auth.capabilities().check_file(&file.bucket_id, &file.file_name)?;
+
     get_upload_part_authorization_by_id(
         auth,
         &file.file_id,
@@ -2081,55 +2225,79 @@ pub async fn get_upload_part_authorization<'a, 'b, C, E>(
 ///
 /// See [get_upload_part_authorization] for documentation on retrieving the
 /// authorization.
-pub async fn get_upload_part_authorization_by_id<'a, 'b, C, E>(
-    auth: &'a mut Authorization<C>,
+pub async fn get_upload_part_authorization_by_id<C, E>(
+    auth: &mut Authorization<C>,
     file_id: impl AsRef<str>,
-    encryption: Option<&'b ServerSideEncryption>,
-) -> Result<UploadPartAuthorization<'a, 'b, C, E>, Error<E>>
+    encryption: Option<&ServerSideEncryption>,
+) -> Result<UploadPartAuthorization<C, E>, Error<E>>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
     use serde_json::json;
 
-    require_capability!(auth, Capability::WriteFiles);
+    require_capabilities!(auth, Operation::UploadFilePart);
 
-    let res = auth.client.post(auth.api_url("b2_get_upload_part_url"))
-        .expect("Invalid URL")
-        .with_header("Authorization", &auth.authorization_token).unwrap()
-        .with_body_json(json!({ "fileId": file_id.as_ref() }))
-        .send().await?;
+    // Uploaders tend to be long-lived, so we renew a Session's expired
+    // authorization here as well as in upload_file_part.
+    let res = auth.post_renewing(
+        "b2_get_upload_part_url",
+        json!({ "fileId": file_id.as_ref() })
+    ).await?;
 
-    let upload_auth: B2Result<UploadPartAuthorization<'_, '_, _, _>> =
-        serde_json::from_slice(&res)?;
+    let upload_auth: B2Result<UploadPartAuthorization<_, _>> =
+        B2Result::from_response(&res)?;
 
     upload_auth.map(move |mut a| {
-        a.auth = Some(auth);
-        a.encryption = encryption;
+        a.auth = Some(auth.clone());
+        a.encryption = encryption.cloned();
         a
     }).into()
 }
 
 /// An authorization to upload a file to a B2 bucket.
+///
+/// An `UploadAuthorization` holds its own copy of the [Authorization] it was
+/// obtained with, so it can be moved to another thread or task.
 #[derive(Deserialize)]
 #[allow(dead_code)]
 #[serde(rename_all = "camelCase")]
-pub struct UploadAuthorization<'a, C, E>
+pub struct UploadAuthorization<C, E>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
     #[serde(skip_deserializing)]
     #[serde(default = "make_none")]
-    auth: Option<&'a mut Authorization<C>>,
+    auth: Option<Authorization<C>>,
     bucket_id: String,
     upload_url: String,
     authorization_token: String,
 }
 
-impl<'a, C, E> UploadAuthorization<'a, C, E>
+impl<C, E> UploadAuthorization<C, E>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
     pub fn bucket_id(&self) -> &str { &self.bucket_id }
+
+    /// Obtain a new upload URL and authorization token for the same bucket.
+    pub async fn refresh(&mut self) -> Result<(), Error<E>> {
+        // Unwrap safety: see prepare_upload_file.
+        let auth = self.auth.as_mut().unwrap();
+        let new = get_upload_authorization_by_id(auth, &self.bucket_id).await?;
+
+        let (upload_url, authorization_token) =
+            (new.upload_url, new.authorization_token);
+
+        self.upload_url = upload_url;
+        self.authorization_token = authorization_token;
+        Ok(())
+    }
+
+    /// Returns `true` if the upload was rejected because of an expired
+    /// authorization that we are able to renew.
+    fn should_refresh(&self, res: &Response) -> bool {
+        self.auth.as_ref().is_some_and(|a| a.should_reauthorize(res))
+    }
 }
 
 /// Obtain an authorization to upload files to a bucket.
@@ -2148,10 +2316,10 @@ impl<'a, C, E> UploadAuthorization<'a, C, E>
 ///
 /// The equivalent B2 endpoint is called
 /// [`b2_get_upload_url`](https://www.backblaze.com/b2/docs/b2_get_upload_url.html).
-pub async fn get_upload_authorization<'a, 'b, C, E>(
-    auth: &'a mut Authorization<C>,
-    bucket: &'b Bucket,
-) -> Result<UploadAuthorization<'a, C, E>, Error<E>>
+pub async fn get_upload_authorization<C, E>(
+    auth: &mut Authorization<C>,
+    bucket: &Bucket,
+) -> Result<UploadAuthorization<C, E>, Error<E>>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
@@ -2162,27 +2330,32 @@ pub async fn get_upload_authorization<'a, 'b, C, E>(
 ///
 /// See [get_upload_authorization] for documentation on retrieving the
 /// authorization.
-pub async fn get_upload_authorization_by_id<'a, 'b, C, E>(
-    auth: &'a mut Authorization<C>,
+pub async fn get_upload_authorization_by_id<C, E>(
+    auth: &mut Authorization<C>,
     bucket_id: impl AsRef<str>,
-) -> Result<UploadAuthorization<'a, C, E>, Error<E>>
+) -> Result<UploadAuthorization<C, E>, Error<E>>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
     use serde_json::json;
 
-    require_capability!(auth, Capability::WriteFiles);
+    require_capabilities!(auth, Operation::UploadFile {
+        retention: false,
+        legal_hold: false,
+    });
+    auth.capabilities().check_bucket(bucket_id.as_ref())?;
 
-    let res = auth.client.post(auth.api_url("b2_get_upload_url"))
-        .expect("Invalid URL")
-        .with_header("Authorization", &auth.authorization_token).unwrap()
-        .with_body_json(json!({ "bucketId": bucket_id.as_ref() }))
-        .send().await?;
+    // Uploaders tend to be long-lived, so we renew a Session's expired
+    // authorization here as well as in upload_file.
+    let res = auth.post_renewing(
+        "b2_get_upload_url",
+        json!({ "bucketId": bucket_id.as_ref() })
+    ).await?;
 
-    let upload_auth: B2Result<UploadAuthorization<'_, _, _>> =
-        serde_json::from_slice(&res)?;
+    let upload_auth: B2Result<UploadAuthorization<_, _>> =
+        B2Result::from_response(&res)?;
 
-    upload_auth.map(move |mut a| { a.auth = Some(auth); a }).into()
+    upload_auth.map(move |mut a| { a.auth = Some(auth.clone()); a }).into()
 }
 
 /// Hide a file so that it cannot be downloaded by name.
@@ -2231,7 +2404,8 @@ pub async fn hide_file_by_name<C, E>(
 {
     use serde_json::json;
 
-    require_capability!(auth, Capability::WriteFiles);
+    require_capabilities!(auth, Operation::HideFile);
+    auth.capabilities().check_file(bucket_id.as_ref(), file_name.as_ref())?;
 
     let res = auth.client.post(auth.api_url("b2_hide_file"))
         .expect("Invalid URL")
@@ -2242,12 +2416,12 @@ pub async fn hide_file_by_name<C, E>(
         }))
         .send().await?;
 
-    let file: B2Result<File> = serde_json::from_slice(&res)?;
+    let file: B2Result<File> = B2Result::from_response(&res)?;
     file.into()
 }
 
 /// A request to list the names of files stored in a bucket.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct ListFileNames<'a> {
@@ -2372,7 +2546,9 @@ pub async fn list_file_names<'a, C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::ListFiles);
+    require_capabilities!(auth, Operation::ListFileNames);
+    auth.capabilities().check_bucket(request.bucket_id)?;
+    auth.capabilities().check_prefix(request.prefix)?;
 
     let res = auth.client.post(auth.api_url("b2_list_file_names"))
         .expect("Invalid URL")
@@ -2380,7 +2556,7 @@ pub async fn list_file_names<'a, C, E>(
         .with_body_json(serde_json::to_value(&request)?)
         .send().await?;
 
-    let files: B2Result<FileNameList> = serde_json::from_slice(&res)?;
+    let files: B2Result<FileNameList> = B2Result::from_response(&res)?;
     match files {
         B2Result::Ok(files) => {
             if let Some(next_file) = files.next_file_name {
@@ -2396,8 +2572,48 @@ pub async fn list_file_names<'a, C, E>(
     }
 }
 
+/// Stream the files in a bucket, requesting each page of the listing as it is
+/// needed.
+///
+/// The request's `max_file_count` is the size of each page. The stream's
+/// [cursor](ListStream::cursor) is a file name that can be passed to
+/// [ListFileNamesBuilder::start_file_name] to continue the listing.
+///
+/// See [list_file_names] and the [pagination](crate::pagination) module.
+pub fn list_file_names_stream<'a, C, E>(
+    auth: &'a mut Authorization<C>,
+    request: ListFileNames<'a>,
+) -> ListStream<'a, C, E, ListFileNames<'a>>
+    where C: HttpClient<Error=Error<E>> + Send + 'a,
+          E: fmt::Debug + fmt::Display + 'a,
+{
+    ListStream::new(auth, request)
+}
+
+impl<'a> Sealed for ListFileNames<'a> {}
+
+impl<'a> ListRequest<'a> for ListFileNames<'a> {
+    type Item = File;
+    type Cursor = String;
+
+    fn start(&self) -> Option<String> { self.start_file_name.clone() }
+
+    fn cursor_of(file: &File) -> String { file.file_name.clone() }
+
+    fn fetch<C, E>(self, auth: &'a mut Authorization<C>)
+    -> Fetch<'a, C, E, Self>
+        where C: HttpClient<Error=Error<E>> + Send + 'a,
+              E: fmt::Debug + fmt::Display + 'a,
+    {
+        Box::pin(async move {
+            let res = list_file_names(auth, self).await;
+            (auth, res)
+        })
+    }
+}
+
 /// A request to list the names of files stored in a bucket.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct ListFileVersions<'a> {
@@ -2547,7 +2763,9 @@ pub async fn list_file_versions<'a, C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::ListFiles);
+    require_capabilities!(auth, Operation::ListFileVersions);
+    auth.capabilities().check_bucket(request.bucket_id)?;
+    auth.capabilities().check_prefix(request.prefix)?;
 
     let res = auth.client.post(auth.api_url("b2_list_file_versions"))
         .expect("Invalid URL")
@@ -2555,7 +2773,7 @@ pub async fn list_file_versions<'a, C, E>(
         .with_body_json(serde_json::to_value(&request)?)
         .send().await?;
 
-    let files: B2Result<FileVersionList> = serde_json::from_slice(&res)?;
+    let files: B2Result<FileVersionList> = B2Result::from_response(&res)?;
     match files {
         B2Result::Ok(files) => {
             let mut request = request;
@@ -2573,7 +2791,53 @@ pub async fn list_file_versions<'a, C, E>(
     }
 }
 
-#[derive(Debug, Serialize)]
+/// Stream all versions of the files in a bucket, requesting each page of the
+/// listing as it is needed.
+///
+/// The request's `max_file_count` is the size of each page. The stream's
+/// [cursor](ListStream::cursor) is a file name and ID that can be passed to
+/// [ListFileVersionsBuilder::start_file_name] and
+/// [ListFileVersionsBuilder::start_file_id] to continue the listing.
+///
+/// See [list_file_versions] and the [pagination](crate::pagination) module.
+pub fn list_file_versions_stream<'a, C, E>(
+    auth: &'a mut Authorization<C>,
+    request: ListFileVersions<'a>,
+) -> ListStream<'a, C, E, ListFileVersions<'a>>
+    where C: HttpClient<Error=Error<E>> + Send + 'a,
+          E: fmt::Debug + fmt::Display + 'a,
+{
+    ListStream::new(auth, request)
+}
+
+impl<'a> Sealed for ListFileVersions<'a> {}
+
+impl<'a> ListRequest<'a> for ListFileVersions<'a> {
+    type Item = File;
+    type Cursor = (String, Option<String>);
+
+    fn start(&self) -> Option<Self::Cursor> {
+        self.start_file_name.as_ref()
+            .map(|name| (name.clone(), self.start_file_id.clone()))
+    }
+
+    fn cursor_of(file: &File) -> Self::Cursor {
+        (file.file_name.clone(), Some(file.file_id.clone()))
+    }
+
+    fn fetch<C, E>(self, auth: &'a mut Authorization<C>)
+    -> Fetch<'a, C, E, Self>
+        where C: HttpClient<Error=Error<E>> + Send + 'a,
+              E: fmt::Debug + fmt::Display + 'a,
+    {
+        Box::pin(async move {
+            let res = list_file_versions(auth, self).await;
+            (auth, res)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ListFileParts<'a> {
     file_id: &'a str,
@@ -2655,7 +2919,7 @@ pub async fn list_file_parts<'a, C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::WriteFiles);
+    require_capabilities!(auth, Operation::ListFileParts);
 
     let res = auth.client.post(auth.api_url("b2_list_parts"))
         .expect("Invalid URL")
@@ -2663,7 +2927,7 @@ pub async fn list_file_parts<'a, C, E>(
         .with_body_json(serde_json::to_value(&request)?)
         .send().await?;
 
-    let parts: B2Result<FilePartList> = serde_json::from_slice(&res)?;
+    let parts: B2Result<FilePartList> = B2Result::from_response(&res)?;
     match parts {
         B2Result::Ok(parts) => {
             if let Some(next_part) = parts.next_part_number {
@@ -2679,7 +2943,47 @@ pub async fn list_file_parts<'a, C, E>(
     }
 }
 
-#[derive(Debug, Serialize)]
+/// Stream the parts of an unfinished large file, requesting each page of the
+/// listing as it is needed.
+///
+/// The request's `max_part_count` is the size of each page. The stream's
+/// [cursor](ListStream::cursor) is a part number that can be passed to
+/// [ListFilePartsBuilder::start_part_number] to continue the listing.
+///
+/// See [list_file_parts] and the [pagination](crate::pagination) module.
+pub fn list_file_parts_stream<'a, C, E>(
+    auth: &'a mut Authorization<C>,
+    request: ListFileParts<'a>,
+) -> ListStream<'a, C, E, ListFileParts<'a>>
+    where C: HttpClient<Error=Error<E>> + Send + 'a,
+          E: fmt::Debug + fmt::Display + 'a,
+{
+    ListStream::new(auth, request)
+}
+
+impl<'a> Sealed for ListFileParts<'a> {}
+
+impl<'a> ListRequest<'a> for ListFileParts<'a> {
+    type Item = FilePart;
+    type Cursor = u16;
+
+    fn start(&self) -> Option<u16> { self.start_part_number }
+
+    fn cursor_of(part: &FilePart) -> u16 { part.part_number }
+
+    fn fetch<C, E>(self, auth: &'a mut Authorization<C>)
+    -> Fetch<'a, C, E, Self>
+        where C: HttpClient<Error=Error<E>> + Send + 'a,
+              E: fmt::Debug + fmt::Display + 'a,
+    {
+        Box::pin(async move {
+            let res = list_file_parts(auth, self).await;
+            (auth, res)
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 #[allow(dead_code)]
 pub struct ListUnfinishedLargeFiles<'a> {
@@ -2775,7 +3079,9 @@ pub async fn list_unfinished_large_files<'a, C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::ListFiles);
+    require_capabilities!(auth, Operation::ListUnfinishedLargeFiles);
+    auth.capabilities().check_bucket(request.bucket_id)?;
+    auth.capabilities().check_prefix(request.name_prefix)?;
 
     let res = auth.client.post(auth.api_url("b2_list_unfinished_large_files"))
         .expect("Invalid URL")
@@ -2783,7 +3089,7 @@ pub async fn list_unfinished_large_files<'a, C, E>(
         .with_body_json(serde_json::to_value(&request)?)
         .send().await?;
 
-    let files: B2Result<FileIdList> = serde_json::from_slice(&res)?;
+    let files: B2Result<FileIdList> = B2Result::from_response(&res)?;
     match files {
         B2Result::Ok(files) => {
             if let Some(next_file_id) = files.next_file_id {
@@ -2799,8 +3105,49 @@ pub async fn list_unfinished_large_files<'a, C, E>(
     }
 }
 
+/// Stream the unfinished large files in a bucket, requesting each page of the
+/// listing as it is needed.
+///
+/// The request's `max_file_count` is the size of each page. The stream's
+/// [cursor](ListStream::cursor) is a file ID that can be passed to
+/// [ListUnfinishedLargeFilesBuilder::start_file_id] to continue the listing.
+///
+/// See [list_unfinished_large_files] and the [pagination](crate::pagination)
+/// module.
+pub fn list_unfinished_large_files_stream<'a, C, E>(
+    auth: &'a mut Authorization<C>,
+    request: ListUnfinishedLargeFiles<'a>,
+) -> ListStream<'a, C, E, ListUnfinishedLargeFiles<'a>>
+    where C: HttpClient<Error=Error<E>> + Send + 'a,
+          E: fmt::Debug + fmt::Display + 'a,
+{
+    ListStream::new(auth, request)
+}
+
+impl<'a> Sealed for ListUnfinishedLargeFiles<'a> {}
+
+impl<'a> ListRequest<'a> for ListUnfinishedLargeFiles<'a> {
+    type Item = File;
+    type Cursor = String;
+
+    fn start(&self) -> Option<String> { self.start_file_id.clone() }
+
+    fn cursor_of(file: &File) -> String { file.file_id.clone() }
+
+    fn fetch<C, E>(self, auth: &'a mut Authorization<C>)
+    -> Fetch<'a, C, E, Self>
+        where C: HttpClient<Error=Error<E>> + Send + 'a,
+              E: fmt::Debug + fmt::Display + 'a,
+    {
+        Box::pin(async move {
+            let res = list_unfinished_large_files(auth, self).await;
+            (auth, res)
+        })
+    }
+}
+
 /// A request to prepare to upload a large file.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StartLargeFile<'a> {
     bucket_id: &'a str,
@@ -3068,23 +3415,23 @@ impl<'a> StartLargeFileBuilder<'a> {
 // TODO: Return a LargeFile or FileInProgress type? It would only matter for
 // something like `copy_file_part` where it provides type-safety when passing
 // both a source file and the large (destination) file IDs together.
-pub async fn start_large_file<'a, C, E>(
+pub async fn start_large_file<C, E>(
     auth: &mut Authorization<C>,
     file: StartLargeFile<'_>
 ) -> Result<File, Error<E>>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::WriteFiles);
-    if file.file_retention.is_some() {
-        require_capability!(auth, Capability::WriteFileRetentions);
-    }
-    if file.legal_hold.is_some() {
-        require_capability!(auth, Capability::WriteFileLegalHolds);
-    }
-    if file.server_side_encryption.is_some() {
-        require_capability!(auth, Capability::WriteBucketEncryption);
-    }
+    require_capabilities!(auth, Operation::StartLargeFile {
+        retention: file.file_retention.is_some(),
+        legal_hold: file.legal_hold.is_some(),
+        encryption: file.server_side_encryption.is_some(),
+    });
+
+    let file_name = percent_encoding::percent_decode_str(&file.file_name)
+        .decode_utf8_lossy();
+
+    auth.capabilities().check_file(file.bucket_id, &file_name)?;
 
     let res = auth.client.post(auth.api_url("b2_start_large_file"))
         .expect("Invalid URL")
@@ -3092,12 +3439,12 @@ pub async fn start_large_file<'a, C, E>(
         .with_body_json(serde_json::to_value(file)?)
         .send().await?;
 
-    let file: B2Result<File> = serde_json::from_slice(&res)?;
+    let file: B2Result<File> = B2Result::from_response(&res)?;
     file.into()
 }
 
 /// A request to enable or disable a legal hold on a specific file.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateFileLegalHold<'a> {
     file_name: &'a str,
@@ -3208,7 +3555,8 @@ pub async fn update_file_legal_hold<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::WriteFileLegalHolds);
+    require_capabilities!(auth, Operation::UpdateFileLegalHold);
+    auth.capabilities().check_file_name(file_update.file_name)?;
 
     let res = auth.client.post(auth.api_url("b2_update_file_legal_hold"))
         .expect("Invalid URL")
@@ -3216,12 +3564,12 @@ pub async fn update_file_legal_hold<C, E>(
         .with_body_json(serde_json::to_value(file_update)?)
         .send().await?;
 
-    let res: B2Result<UpdateFileLegalHold> = serde_json::from_slice(&res)?;
+    let res: B2Result<UpdateFileLegalHold> = B2Result::from_response(&res)?;
     res.map(|_| ()).into()
 }
 
 /// A request to update file retention settings on a file.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UpdateFileRetention<'a> {
     file_name: &'a str,
@@ -3333,11 +3681,14 @@ pub async fn update_file_retention<C, E>(
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
 {
-    require_capability!(auth, Capability::WriteFileRetentions);
-    if matches!(retention_update.bypass_governance, Some(BypassGovernance::Yes))
-    {
-        require_capability!(auth, Capability::BypassGovernance);
-    }
+    require_capabilities!(auth, Operation::UpdateFileRetention {
+        bypass_governance: matches!(
+            retention_update.bypass_governance,
+            Some(BypassGovernance::Yes)
+        ),
+    });
+
+    auth.capabilities().check_file_name(retention_update.file_name)?;
 
     let res = auth.client.post(auth.api_url("b2_update_file_retention"))
         .expect("Invalid URL")
@@ -3345,13 +3696,14 @@ pub async fn update_file_retention<C, E>(
         .with_body_json(serde_json::to_value(retention_update)?)
         .send().await?;
 
-    let res: B2Result<UpdateFileRetention> = serde_json::from_slice(&res)?;
+    let res: B2Result<UpdateFileRetention> = B2Result::from_response(&res)?;
     res.map(|_| ()).into()
 }
 
 /// A request to upload a file to B2.
 ///
 /// Use [UploadFileBuilder] to create an `UploadFile`.
+#[derive(Clone)]
 pub struct UploadFile<'a> {
     file_name: String,
     content_type: String,
@@ -3643,36 +3995,106 @@ impl<'a> UploadFileBuilder<'a> {
 ///
 /// You must first call [get_upload_authorization] to obtain an authorization to
 /// upload files to the bucket; then pass that authorization to `upload_file`.
+///
+/// To upload data without first reading it all into memory, see
+/// [upload_file_stream].
+///
+/// If the upload authorization was obtained via a
+/// [Session](crate::session::Session) and has expired, it is
+/// [refreshed](UploadAuthorization::refresh) and the upload is retried once.
 pub async fn upload_file<C, E>(
-    auth: &mut UploadAuthorization<'_, C, E>,
+    auth: &mut UploadAuthorization<C, E>,
     upload: UploadFile<'_>,
     data: &[u8],
 ) -> Result<File, Error<E>>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
+{
+    let mut res = prepare_upload_file(auth, &upload, data.len() as u64)?
+        .with_body(data)
+        .send().await?;
+
+    if auth.should_refresh(&res) {
+        auth.refresh().await?;
+
+        res = prepare_upload_file(auth, &upload, data.len() as u64)?
+            .with_body(data)
+            .send().await?;
+    }
+
+    let file: B2Result<File> = B2Result::from_response(&res)?;
+    file.into()
+}
+
+/// Upload a file to a B2 bucket, streaming its contents from `data`.
+///
+/// This behaves like [upload_file], but the file's contents are sent as they
+/// are read from `data` rather than being held in memory.
+///
+/// `content_length` must be the exact number of bytes that `data` will
+/// produce.
+///
+/// Because `data` cannot be read twice, an upload rejected for an expired
+/// authorization is not retried; the error is returned after the upload
+/// authorization is refreshed so that the next upload can succeed.
+pub async fn upload_file_stream<C, E, R>(
+    auth: &mut UploadAuthorization<C, E>,
+    upload: UploadFile<'_>,
+    data: R,
+    content_length: u64,
+) -> Result<File, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+          R: futures_io::AsyncRead + Send + Sync + Unpin + 'static,
+{
+    let res = prepare_upload_file(auth, &upload, content_length)?
+        .with_body_reader(data, content_length)
+        .send().await?;
+
+    if auth.should_refresh(&res) {
+        auth.refresh().await?;
+    }
+
+    let file: B2Result<File> = B2Result::from_response(&res)?;
+    file.into()
+}
+
+/// Create the request for [upload_file] and [upload_file_stream] with all
+/// headers set; the caller provides the body and sends it.
+fn prepare_upload_file<'c, C, E>(
+    auth: &'c mut UploadAuthorization<C, E>,
+    upload: &UploadFile<'_>,
+    content_length: u64,
+) -> Result<&'c mut C, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
 {
     // Unwrap safety: an `UploadAuthorization` can only be created from
     // `get_upload_authorization`, which will always embed an `Authorization`
-    // reference before returning.
+    // before returning.
     let inner_auth = auth.auth.as_mut().unwrap();
 
-    require_capability!(inner_auth, Capability::WriteFiles);
+    // We check the capabilities for all options here rather than when we need
+    // them below to satisfy the borrow checker.
+    require_capabilities!(inner_auth, Operation::UploadFile {
+        retention: upload.file_retention.is_some(),
+        legal_hold: upload.legal_hold.is_some(),
+    });
 
-    if upload.file_retention.is_some() {
-        // We check this here rather than when we need it below to satisfy the
-        // borrow checker.
-        require_capability!(inner_auth, Capability::WriteFileRetentions);
-    }
+    let file_name = percent_encoding::percent_decode_str(&upload.file_name)
+        .decode_utf8_lossy();
+
+    inner_auth.capabilities().check_file_name(&file_name)?;
 
     let mut req = inner_auth.client.post(&auth.upload_url)
         .expect("Invalid URL")
         .with_header("Authorization", &auth.authorization_token)?
         .with_header("X-Bz-File-Name", &upload.file_name)?
         .with_header("Content-Type", &upload.content_type)?
-        .with_header("Content-Length", &data.len().to_string())?
+        .with_header("Content-Length", &content_length.to_string())?
         .with_header("X-Bz-Content-Sha1", upload.sha1_checksum)?;
 
-    if let Some(mut file_info) = upload.file_info {
+    if let Some(mut file_info) = upload.file_info.clone() {
         let info_map = file_info.as_object_mut()
             .expect("file_info is not a JSON object");
 
@@ -3696,7 +4118,7 @@ pub async fn upload_file<C, E>(
         }
     }
 
-    if let Some(legal_hold) = upload.legal_hold {
+    if let Some(legal_hold) = &upload.legal_hold {
         req = req.with_header("X-Bz-File-Legal-Hold", &legal_hold.to_string())?;
     }
 
@@ -3707,7 +4129,7 @@ pub async fn upload_file<C, E>(
                 &timestamp.to_string())?;
     }
 
-    if let Some(enc) = upload.encryption {
+    if let Some(enc) = &upload.encryption {
         if let Some(headers) = enc.to_headers() {
             for (header, value) in headers.into_iter() {
                 req = req.with_header(header, &value)?;
@@ -3715,10 +4137,7 @@ pub async fn upload_file<C, E>(
         }
     }
 
-    let res = req.with_body(data).send().await?;
-
-    let file: B2Result<File> = serde_json::from_slice(&res)?;
-    file.into()
+    Ok(req)
 }
 
 /// A request to upload part of a large file.
@@ -3844,27 +4263,93 @@ impl<'a> UploadFilePartBuilder<'a> {
 /// Uploading a file part without a checksum is not recommended as it prevents
 /// B2 from determining if the file part is corrupt, allowing you to immediately
 /// retry.
-// TODO: Stream-based data upload to avoid requiring all data be in RAM at once.
+///
+/// To upload a part without first reading it all into memory, see
+/// [upload_file_part_stream].
+///
+/// As with [upload_file], an expired authorization obtained via a
+/// [Session](crate::session::Session) is refreshed and the part is uploaded
+/// again.
 pub async fn upload_file_part<C, E>(
-    auth: &mut UploadPartAuthorization<'_, '_, C, E>,
+    auth: &mut UploadPartAuthorization<C, E>,
     upload: &UploadFilePart<'_>,
     data: &[u8],
 ) -> Result<FilePart, Error<E>>
     where C: HttpClient<Error=Error<E>>,
           E: fmt::Debug + fmt::Display,
+{
+    let mut res = prepare_upload_file_part(auth, upload, data.len() as u64)?
+        .with_body(data)
+        .send().await?;
+
+    if auth.should_refresh(&res) {
+        auth.refresh().await?;
+
+        res = prepare_upload_file_part(auth, upload, data.len() as u64)?
+            .with_body(data)
+            .send().await?;
+    }
+
+    let part: B2Result<FilePart> = B2Result::from_response(&res)?;
+    part.into()
+}
+
+/// Upload a part of a large file to B2, streaming its contents from `data`.
+///
+/// This behaves like [upload_file_part], but the part is sent as it is read
+/// from `data` rather than being held in memory, so parts up to B2's 5 GB
+/// limit can be uploaded without a matching amount of RAM.
+///
+/// `content_length` must be the exact number of bytes that `data` will
+/// produce.
+///
+/// Like [upload_file_stream], a part rejected for an expired authorization is
+/// not uploaded again, but the upload authorization is refreshed before the
+/// error is returned.
+pub async fn upload_file_part_stream<C, E, R>(
+    auth: &mut UploadPartAuthorization<C, E>,
+    upload: &UploadFilePart<'_>,
+    data: R,
+    content_length: u64,
+) -> Result<FilePart, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+          R: futures_io::AsyncRead + Send + Sync + Unpin + 'static,
+{
+    let res = prepare_upload_file_part(auth, upload, content_length)?
+        .with_body_reader(data, content_length)
+        .send().await?;
+
+    if auth.should_refresh(&res) {
+        auth.refresh().await?;
+    }
+
+    let part: B2Result<FilePart> = B2Result::from_response(&res)?;
+    part.into()
+}
+
+/// Create the request for [upload_file_part] and [upload_file_part_stream]
+/// with all headers set; the caller provides the body and sends it.
+fn prepare_upload_file_part<'c, C, E>(
+    auth: &'c mut UploadPartAuthorization<C, E>,
+    upload: &UploadFilePart<'_>,
+    content_length: u64,
+) -> Result<&'c mut C, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
 {
     // Unwrap safety: an `UploadPartAuthorization` can only be created from
     // `get_upload_part_authorization`, which will always embed an
-    // `Authorization` reference before returning.
+    // `Authorization` before returning.
     let inner_auth = auth.auth.as_mut().unwrap();
 
-    require_capability!(inner_auth, Capability::WriteFiles);
+    require_capabilities!(inner_auth, Operation::UploadFilePart);
 
     let mut req = inner_auth.client.post(&auth.upload_url)
         .expect("Invalid URL")
         .with_header("Authorization", &auth.authorization_token).unwrap()
         .with_header("X-Bz-Part-Number", &upload.part_number.to_string())?
-        .with_header("Content-Length", &data.len().to_string())?
+        .with_header("Content-Length", &content_length.to_string())?
         .with_header("X-Bz-Content-Sha1", upload.content_sha1)?;
 
     if let Some(enc) = &upload.encryption {
@@ -3875,10 +4360,7 @@ pub async fn upload_file_part<C, E>(
         }
     }
 
-    let res = req.with_body(data).send().await?;
-
-    let part: B2Result<FilePart> = serde_json::from_slice(&res)?;
-    part.into()
+    Ok(req)
 }
 
 #[cfg(all(test, feature = "with_surf"))]
@@ -4121,6 +4603,35 @@ mod tests_mocked {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn upload_file_stream_success() -> anyhow::Result<()> {
+        let client = create_test_client(
+            VcrMode::Replay,
+            "test_sessions/file.yaml",
+            None, None
+        ).await?;
+
+        let mut auth = create_test_auth(client, vec![Capability::WriteFiles])
+            .await;
+
+        let mut upload_auth = get_upload_authorization_by_id(
+            &mut auth,
+            "8d625eb63be2775577c70e1a"
+        ).await?;
+
+        let file = UploadFile::builder()
+            .file_name("test-file-upload.txt")?
+            .sha1_checksum("81fe8bfe87576c3ecb22426f8e57847382917acf")
+            .build()?;
+
+        let data = async_std::io::Cursor::new(b"abcd");
+        let file = upload_file_stream(&mut upload_auth, file, data, 4).await?;
+
+        assert_eq!(file.action, FileAction::Upload);
+
+        Ok(())
+    }
+
     #[async_std::test]
     async fn copy_file_success() -> anyhow::Result<()> {
         let client = create_test_client(
@@ -4242,6 +4753,58 @@ mod tests_mocked {
         Ok(())
     }
 
+    #[async_std::test]
+    async fn download_file_stream_by_id_success() -> anyhow::Result<()> {
+        use async_std::io::ReadExt as _;
+
+        let client = create_test_client(
+            VcrMode::Replay,
+            "test_sessions/file.yaml",
+            None, None
+        ).await?;
+
+        let mut auth = create_test_auth(client, vec![Capability::ReadFiles])
+            .await;
+
+        let req = DownloadFile::with_id(concat!("4_z8d625eb63be2775577c70e1a_f",
+            "111954e3108ff3f6_d20211118_m151810_c002_v0001168_t0010"));
+
+        let (mut body, headers) = download_file_stream(&mut auth, req).await?;
+
+        let mut file = vec![];
+        body.read_to_end(&mut file).await?;
+
+        assert_eq!(file, b"Some text\n");
+        assert!(headers.contains_key("x-bz-file-name"));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn download_file_stream_by_name_success() -> anyhow::Result<()> {
+        use async_std::io::ReadExt as _;
+
+        let client = create_test_client(
+            VcrMode::Replay,
+            "test_sessions/file.yaml",
+            None, None
+        ).await?;
+
+        let mut auth = create_test_auth(client, vec![Capability::ReadFiles])
+            .await;
+
+        let req = DownloadFile::with_name("test-file.txt", "testing-b2-client");
+
+        let (mut body, _headers) = download_file_stream(&mut auth, req).await?;
+
+        let mut file = vec![];
+        body.read_to_end(&mut file).await?;
+
+        assert_eq!(file, b"Some text\n");
+
+        Ok(())
+    }
+
     #[async_std::test]
     async fn download_file_by_name_via_download_authorization_success()
     -> anyhow::Result<()> {
@@ -4732,7 +5295,7 @@ mod tests_mocked {
             vec![Capability::WriteFileRetentions]
         ).await;
 
-        let retain_until = Utc.ymd(3000, 1, 1).and_hms(0, 0, 0);
+        let retain_until = Utc.with_ymd_and_hms(3000, 1, 1, 0, 0, 0).unwrap();
 
         let update = UpdateFileRetention::builder()
             .file_name("test-file.txt")?
@@ -4765,7 +5328,7 @@ mod tests_mocked {
             vec![Capability::WriteFileRetentions]
         ).await;
 
-        let retain_until = Utc.ymd(3000, 1, 1).and_hms(0, 0, 0);
+        let retain_until = Utc.with_ymd_and_hms(3000, 1, 1, 0, 0, 0).unwrap();
 
         let update = UpdateFileRetention::builder()
             .file_name("test-file.txt")?
@@ -4806,4 +5369,30 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn file_replication_status_from_json() {
+        let mut file = serde_json::json!({
+            "accountId": "abcdefg",
+            "action": "upload",
+            "bucketId": "hijklmno",
+            "contentLength": 4,
+            "contentSha1": "none",
+            "contentType": "text/plain",
+            "fileId": "4_zhijklmno_f1",
+            "fileInfo": {},
+            "fileName": "a.txt",
+            "replicationStatus": "pending",
+            "uploadTimestamp": 0,
+        });
+
+        let replicated: File = serde_json::from_value(file.clone()).unwrap();
+        assert_eq!(replicated.replication_status(),
+            Some(ReplicationStatus::Pending));
+
+        file.as_object_mut().unwrap().remove("replicationStatus");
+
+        let unreplicated: File = serde_json::from_value(file).unwrap();
+        assert_eq!(unreplicated.replication_status(), None);
+    }
 }
@@ -55,11 +55,24 @@
 
 
 pub mod account;
+pub mod audit;
 pub mod bucket;
 pub mod file;
 
+pub mod blocking;
+pub mod cache;
 pub mod client;
+pub mod credentials;
 pub mod error;
+pub mod pagination;
+pub mod retry;
+pub mod s3;
+pub mod session;
+pub mod spec;
+pub mod webhook;
+
+#[cfg(feature = "testing")]
+pub mod testing;
 
 mod types;
 mod validate;
@@ -68,8 +81,9 @@ pub mod prelude {
     #![allow(unused_imports)]
 
     pub(crate) use super::{
-        account::{Authorization, Capability},
+        account::{Authorization, Capability, Operation},
         types::{B2Result, Duration},
+        require_capabilities,
         require_capability,
     };
 }
@@ -83,6 +97,17 @@ macro_rules! require_capability {
 }
 pub(crate) use require_capability;
 
+/// Check that the authorization has every capability required by an
+/// [Operation](crate::account::Operation).
+macro_rules! require_capabilities {
+    ($auth:expr, $op:expr) => {
+        for cap in $op.required_capabilities() {
+            $crate::require_capability!($auth, cap);
+        }
+    }
+}
+pub(crate) use require_capabilities;
+
 
 pub use account::*;
 pub use bucket::*;
@@ -130,6 +155,7 @@ pub(crate) mod test_utils {
         res_mod: Option<Box<dyn Fn(&mut VcrResponse) + Send + Sync + 'static>>,
     ) -> std::result::Result<SurfClient, VcrError> {
         #![allow(clippy::option_map_unit_fn)]
+        #![allow(clippy::type_complexity)]
 
         let vcr = VcrMiddleware::new(mode, cassette).await.unwrap()
             .with_modify_request(move |req| {
@@ -298,3 +324,257 @@ pub(crate) mod test_utils {
         }
     }
 }
+
+/// Replay the recorded B2 sessions from a local HTTP server.
+///
+/// surf-vcr can only intercept requests made with surf; this serves the same
+/// cassettes over plain HTTP so that other backends can run against them. Hosts
+/// in response bodies (such as upload URLs) are rewritten to point to the
+/// server.
+#[cfg(all(test, any(feature = "with_reqwest", feature = "with_ureq")))]
+pub(crate) mod replay_server {
+    use std::{
+        collections::HashMap,
+        io::{BufRead, BufReader, Read, Write},
+        net::{TcpListener, TcpStream},
+        sync::Arc,
+    };
+    use crate::{
+        account::{Authorization, Capability, Capabilities},
+        client::HttpClient,
+    };
+    use serde::Deserialize;
+
+
+    #[derive(Debug, Deserialize)]
+    enum Entry {
+        Request(Request),
+        Response(Response),
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Request {
+        method: String,
+        url: url::Url,
+        headers: HashMap<String, Vec<String>>,
+        body: Body,
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Response {
+        status: u16,
+        headers: HashMap<String, Vec<String>>,
+        body: Body,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(untagged)]
+    enum Body {
+        Bytes(Vec<u8>),
+        Str(String),
+    }
+
+    impl Body {
+        fn as_bytes(&self) -> &[u8] {
+            match self {
+                Self::Bytes(b) => b,
+                Self::Str(s) => s.as_bytes(),
+            }
+        }
+    }
+
+    type Session = Vec<(Request, Response)>;
+
+    /// A server that replays a surf-vcr cassette.
+    pub struct ReplayServer {
+        url: String,
+    }
+
+    impl ReplayServer {
+        /// Start serving the given cassette on a random local port.
+        pub fn start(cassette: &str) -> Self {
+            let session = std::fs::read_to_string(cassette).unwrap()
+                .split("\n---\n")
+                .map(|doc| match serde_yaml::from_str(doc).unwrap() {
+                    (Entry::Request(req), Entry::Response(res)) => (req, res),
+                    _ => panic!("Invalid cassette: {}", cassette),
+                })
+                .collect::<Session>();
+
+            let session = Arc::new(session);
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+
+            let base = url.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let session = session.clone();
+                    let base = base.clone();
+
+                    std::thread::spawn(move || {
+                        serve(stream.unwrap(), &session, &base)
+                    });
+                }
+            });
+
+            Self { url }
+        }
+
+        /// Create an [Authorization] whose API and download URLs point to
+        /// this server.
+        pub fn create_test_auth<C: HttpClient>(
+            &self,
+            client: C,
+            capabilities: Vec<Capability>
+        ) -> Authorization<C> {
+            Authorization::new(
+                client,
+                "some-account-id".into(),
+                "some-key-id".into(),
+                Capabilities::new(capabilities, None, None, None),
+                self.url.clone(),
+                self.url.clone(),
+                100000000,
+                5000000,
+                self.url.clone(),
+            )
+        }
+    }
+
+    /// Handle requests on a connection until the client closes it.
+    fn serve(stream: TcpStream, session: &Session, base: &str) {
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 { return; }
+
+            let mut parts = line.split_whitespace();
+            let method = parts.next().unwrap_or_default().to_owned();
+            let target = parts.next().unwrap_or_default().to_owned();
+
+            let mut headers = HashMap::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+
+                let line = line.trim_end();
+                if line.is_empty() { break; }
+
+                let (name, value) = line.split_once(':').unwrap();
+                let prev = headers.insert(
+                    name.to_lowercase(),
+                    value.trim().to_owned()
+                );
+
+                assert!(prev.is_none() || name != "content-length",
+                    "Duplicate Content-Length header: {} {}", method, target);
+            }
+
+            let len = headers.get("content-length")
+                .map(|l| l.parse().unwrap())
+                .unwrap_or(0);
+
+            let mut body = vec![0; len];
+            reader.read_exact(&mut body).unwrap();
+
+            let (_, res) = session.iter()
+                .find(|(req, _)|
+                    matches(req, &method, &target, &headers, &body)
+                )
+                .unwrap_or_else(|| panic!("No recording for {} {}: {}",
+                    method, target, String::from_utf8_lossy(&body)));
+
+            let mut out = format!("HTTP/1.1 {} Replayed\r\n", res.status);
+
+            for (name, values) in res.headers.iter() {
+                let skip = match name.as_str() {
+                    "content-length" => method != "HEAD",
+                    "connection" | "transfer-encoding" => true,
+                    _ => false,
+                };
+                if skip { continue; }
+
+                for value in values {
+                    out.push_str(&format!("{}: {}\r\n", name, value));
+                }
+            }
+
+            let body = match &res.body {
+                Body::Str(s) => rewrite_hosts(s, base).into_bytes(),
+                Body::Bytes(b) => b.clone(),
+            };
+
+            if method != "HEAD" {
+                out.push_str(&format!("content-length: {}\r\n", body.len()));
+            }
+            out.push_str("\r\n");
+
+            writer.write_all(out.as_bytes()).unwrap();
+
+            if method != "HEAD" {
+                writer.write_all(&body).unwrap();
+            }
+        }
+    }
+
+    fn matches(
+        req: &Request,
+        method: &str,
+        target: &str,
+        headers: &HashMap<String, String>,
+        body: &[u8]
+    ) -> bool {
+        // Some sessions were recorded with an empty query string.
+        let path = match req.url.query() {
+            Some(query) if ! query.is_empty() =>
+                format!("{}?{}", req.url.path(), query),
+            _ => req.url.path().to_owned(),
+        };
+        let target = target.trim_end_matches('?');
+
+        let range_matches = req.headers.get("range").and_then(|r| r.first())
+            == headers.get("range");
+
+        req.method == method
+            && path == target
+            && range_matches
+            && bodies_match(req.body.as_bytes(), body)
+    }
+
+    /// Compare request bodies, ignoring the account ID that the surf-vcr
+    /// sessions hide.
+    fn bodies_match(recorded: &[u8], body: &[u8]) -> bool {
+        let parse = |b| serde_json::from_slice::<serde_json::Value>(b).ok()
+            .map(|mut v| {
+                if let Some(obj) = v.as_object_mut() {
+                    obj.remove("accountId");
+                }
+                v
+            });
+
+        match (parse(recorded), parse(body)) {
+            (Some(recorded), Some(body)) => recorded == body,
+            _ => recorded == body,
+        }
+    }
+
+    /// Replace the scheme and host of every HTTPS URL in `body` with `base`.
+    fn rewrite_hosts(body: &str, base: &str) -> String {
+        let mut out = String::new();
+        let mut rest = body;
+
+        while let Some(i) = rest.find("https://") {
+            out.push_str(&rest[..i]);
+            out.push_str(base);
+
+            rest = &rest[i + "https://".len()..];
+            let end = rest.find(['/', '"']).unwrap_or(rest.len());
+            rest = &rest[end..];
+        }
+
+        out.push_str(rest);
+        out
+    }
+}
@@ -0,0 +1,243 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+   License, v. 2.0. If a copy of the MPL was not distributed with this
+   file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Streams over paginated listings.
+//!
+//! Each B2 listing endpoint returns a single page of results along with a
+//! request for the next page. The `*_stream` variants of the listing functions
+//! (such as [list_file_names_stream](crate::file::list_file_names_stream))
+//! return a [ListStream] that requests each page as the previous one is
+//! consumed:
+//!
+//! ```no_run
+//! # #[cfg(feature = "with_surf")]
+//! # async fn f() -> anyhow::Result<()> {
+//! use b2_client::{
+//!     client::SurfClient,
+//!     account::authorize_account,
+//!     file::{list_file_names_stream, ListFileNames},
+//! };
+//! use futures_util::TryStreamExt as _;
+//!
+//! let mut auth = authorize_account(SurfClient::default(), "KEY ID", "KEY")
+//!     .await?;
+//!
+//! let req = ListFileNames::builder()
+//!     .bucket_id("my-bucket-id")
+//!     .build()?;
+//!
+//! let mut files = list_file_names_stream(&mut auth, req).max_items(5_000);
+//!
+//! while let Some(file) = files.try_next().await? {
+//!     // ...
+//! }
+//!
+//! // Save the cursor to continue the listing later with
+//! // `ListFileNames::builder().start_file_name(...)`.
+//! let cursor: Option<String> = files.cursor();
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Every page is a separate (billable) transaction. A listing that ends early,
+//! whether because its item cap was reached or because it failed, can be
+//! continued later from its [cursor](ListStream::cursor).
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{
+    account::Authorization,
+    client::HttpClient,
+    error::Error,
+};
+
+
+/// The future returned by [ListRequest::fetch].
+#[doc(hidden)]
+pub type Fetch<'a, C, E, R> = Pin<Box<dyn Future<Output = (
+    &'a mut Authorization<C>,
+    Result<(Vec<<R as ListRequest<'a>>::Item>, Option<R>), Error<E>>,
+)> + Send + 'a>>;
+
+pub(crate) mod sealed {
+    pub trait Sealed {}
+}
+
+/// A listing request that can be used to create a [ListStream].
+///
+/// This trait is implemented for each of the crate's listing requests and
+/// cannot be implemented outside of it.
+pub trait ListRequest<'a>: sealed::Sealed + Sized + Send + 'a {
+    /// The type of the listed items.
+    type Item: Send + 'a;
+    /// A position in the listing from which it can be continued.
+    ///
+    /// This is the value (or values) passed to the request builder's `start_*`
+    /// method(s).
+    type Cursor: Clone;
+
+    /// The position at which this request starts, if it does not start at
+    /// the beginning of the listing.
+    #[doc(hidden)]
+    fn start(&self) -> Option<Self::Cursor>;
+
+    /// The position of `item` in the listing.
+    #[doc(hidden)]
+    fn cursor_of(item: &Self::Item) -> Self::Cursor;
+
+    /// Request a single page of the listing.
+    #[doc(hidden)]
+    fn fetch<C, E>(self, auth: &'a mut Authorization<C>)
+    -> Fetch<'a, C, E, Self>
+        where C: HttpClient<Error=Error<E>> + Send + 'a,
+              E: fmt::Debug + fmt::Display + 'a;
+}
+
+/// A [Stream](futures_core::Stream) of the items in a paginated listing.
+///
+/// Pages are requested one at a time as the stream is polled. The stream ends
+/// after the last item, after the [item cap](Self::max_items) is reached, or
+/// after returning an error.
+pub struct ListStream<'a, C, E, R>
+    where C: HttpClient,
+          E: fmt::Debug + fmt::Display,
+          R: ListRequest<'a>,
+{
+    auth: Option<&'a mut Authorization<C>>,
+    items: VecDeque<R::Item>,
+    next: Option<R>,
+    fetch: Option<Fetch<'a, C, E, R>>,
+    // The start of the page being requested, kept in case the request fails.
+    pending: Option<R::Cursor>,
+    failed: bool,
+    remaining: Option<usize>,
+}
+
+impl<'a, C, E, R> ListStream<'a, C, E, R>
+    where C: HttpClient<Error=Error<E>> + Send + 'a,
+          E: fmt::Debug + fmt::Display + 'a,
+          R: ListRequest<'a>,
+{
+    pub(crate) fn new(auth: &'a mut Authorization<C>, request: R) -> Self {
+        Self {
+            auth: Some(auth),
+            items: VecDeque::new(),
+            next: Some(request),
+            fetch: None,
+            pending: None,
+            failed: false,
+            remaining: None,
+        }
+    }
+
+    /// Stop the stream after it has returned `count` items.
+    ///
+    /// No further pages are requested once the cap is reached. Note that the
+    /// request's page size is not changed, so the last page requested may
+    /// contain items that are not returned; use the [cursor](Self::cursor) to
+    /// continue from the first of them.
+    pub fn max_items(mut self, count: usize) -> Self {
+        self.remaining = Some(count);
+        self
+    }
+
+    /// The position of the next item that has not been returned.
+    ///
+    /// Pass this to the request builder's `start_*` method(s) to continue the
+    /// listing later, such as after the item cap was reached or the stream
+    /// returned an error.
+    ///
+    /// Returns `None` if the listing is [finished](Self::is_finished), or if
+    /// the next item is the first of a listing that was started from the
+    /// beginning.
+    pub fn cursor(&self) -> Option<R::Cursor> {
+        if let Some(item) = self.items.front() {
+            Some(R::cursor_of(item))
+        } else if self.fetch.is_some() || self.failed {
+            self.pending.clone()
+        } else {
+            self.next.as_ref().and_then(R::start)
+        }
+    }
+
+    /// Returns `true` if every item in the listing has been returned.
+    pub fn is_finished(&self) -> bool {
+        self.items.is_empty() && self.next.is_none() && self.fetch.is_none()
+            && ! self.failed
+    }
+}
+
+// No field is structurally pinned; the in-flight request is already boxed.
+impl<'a, C, E, R> Unpin for ListStream<'a, C, E, R>
+    where C: HttpClient,
+          E: fmt::Debug + fmt::Display,
+          R: ListRequest<'a>,
+{}
+
+impl<'a, C, E, R> futures_core::Stream for ListStream<'a, C, E, R>
+    where C: HttpClient<Error=Error<E>> + Send + 'a,
+          E: fmt::Debug + fmt::Display + 'a,
+          R: ListRequest<'a>,
+{
+    type Item = Result<R::Item, Error<E>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>)
+    -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            if this.remaining == Some(0) {
+                return Poll::Ready(None);
+            }
+
+            if let Some(item) = this.items.pop_front() {
+                if let Some(remaining) = &mut this.remaining {
+                    *remaining -= 1;
+                }
+
+                return Poll::Ready(Some(Ok(item)));
+            }
+
+            if let Some(fetch) = &mut this.fetch {
+                let (auth, res) = match fetch.as_mut().poll(cx) {
+                    Poll::Ready(page) => page,
+                    Poll::Pending => return Poll::Pending,
+                };
+
+                this.fetch = None;
+                this.auth = Some(auth);
+
+                match res {
+                    Ok((items, next)) => {
+                        this.items = items.into();
+                        this.next = next;
+                        this.pending = None;
+                    },
+                    Err(e) => {
+                        this.failed = true;
+                        return Poll::Ready(Some(Err(e)));
+                    },
+                }
+            } else {
+                match (this.next.take(), this.auth.take()) {
+                    (Some(req), Some(auth)) => {
+                        this.pending = req.start();
+                        this.fetch = Some(req.fetch(auth));
+                    },
+                    (_, auth) => {
+                        this.auth = auth;
+                        return Poll::Ready(None);
+                    },
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,1077 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+   License, v. 2.0. If a copy of the MPL was not distributed with this
+   file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Automatic retries of failed B2 API calls.
+//!
+//! The B2 service asks clients to retry requests that fail with a temporary
+//! error, waiting exponentially longer between each attempt and honoring any
+//! `Retry-After` header in the response. See
+//! <https://www.backblaze.com/b2/docs/integration_checklist.html> for the
+//! relevant guidance.
+//!
+//! A [RetryClient] wraps an [HttpClient] and retries every request sent through
+//! it according to a [RetryPolicy]. Because every function in the
+//! [account](crate::account), [bucket](crate::bucket), and
+//! [file](crate::file) modules sends its requests via the
+//! [Authorization](crate::account::Authorization)'s client, using a
+//! `RetryClient` when authorizing the account is enough to retry all of them.
+//!
+//! ```no_run
+//! # #[cfg(feature = "with_surf")]
+//! # async fn f() -> anyhow::Result<()> {
+//! use b2_client::{self as b2, client::SurfClient, retry::RetryClient};
+//!
+//! let client = RetryClient::new(SurfClient::default());
+//! let mut auth = b2::authorize_account(client, "key-id", "key").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! # Notes
+//!
+//! A request that fails to connect or times out, as reported by
+//! [HttpClient::is_connection_error], is only retried if sending it again is
+//! safe: requests other than `POST`, and B2 API calls that only read data
+//! (e.g., `b2_list_file_names` or `b2_get_upload_url`). Other errors from the
+//! HTTP client, such as TLS failures, are never retried.
+//!
+//! Requests whose bodies are streamed via
+//! [with_body_reader](HttpClient::with_body_reader) cannot be replayed and are
+//! sent only once.
+//!
+//! When an upload fails with anything other than
+//! [TooManyRequests](ErrorCode::TooManyRequests), B2 requires the client to
+//! obtain a new upload URL before trying again. A `RetryClient` cannot do that
+//! on its own, so other failures of `b2_upload_file` and `b2_upload_part` are
+//! returned to the caller.
+
+use std::{
+    fmt,
+    future::Future,
+    path::PathBuf,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use crate::{
+    client::{
+        is_connection_io_error, HttpClient, Response, StreamingResponse,
+    },
+    error::{B2Error, Error, ErrorCode, ValidationError},
+};
+
+use futures_io::AsyncRead;
+
+
+/// Determines which failed requests are retried and how long to wait between
+/// attempts.
+///
+/// The default policy follows the B2 documentation: a request is attempted up
+/// to five times, waiting one second before the first retry and doubling the
+/// delay for each subsequent retry up to a maximum of 64 seconds, with random
+/// jitter added to each delay. Requests are retried when they fail with
+/// [ServiceUnavailable](ErrorCode::ServiceUnavailable),
+/// [TooManyRequests](ErrorCode::TooManyRequests),
+/// [RequestTimeout](ErrorCode::RequestTimeout), or
+/// [InternalError](ErrorCode::InternalError), and when an idempotent request
+/// fails with a connection error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+    jitter: bool,
+    retry_on: Vec<ErrorCode>,
+    retry_connection_errors: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(64),
+            jitter: true,
+            retry_on: vec![
+                ErrorCode::ServiceUnavailable,
+                ErrorCode::TooManyRequests,
+                ErrorCode::RequestTimeout,
+                ErrorCode::InternalError,
+            ],
+            retry_connection_errors: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Get a builder for a `RetryPolicy`.
+    ///
+    /// Any value not set on the builder is taken from the default policy.
+    pub fn builder() -> RetryPolicyBuilder {
+        RetryPolicyBuilder::default()
+    }
+
+    /// A policy that never retries a request.
+    pub fn never() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// The maximum number of times to send a request, including the first.
+    pub fn max_attempts(&self) -> u32 { self.max_attempts }
+
+    /// The delay before the first retry.
+    pub fn base_delay(&self) -> Duration { self.base_delay }
+
+    /// The longest time to wait between attempts.
+    ///
+    /// A `Retry-After` header from the B2 service takes precedence over this
+    /// limit.
+    pub fn max_delay(&self) -> Duration { self.max_delay }
+
+    /// Whether a random jitter is applied to each delay.
+    pub fn jitter(&self) -> bool { self.jitter }
+
+    /// The error codes that cause a request to be retried.
+    pub fn retry_on(&self) -> &[ErrorCode] { &self.retry_on }
+
+    /// Whether idempotent requests that fail with an error from the HTTP client
+    /// are retried.
+    pub fn retry_connection_errors(&self) -> bool {
+        self.retry_connection_errors
+    }
+
+    /// Returns `true` if a request that failed with the given error code
+    /// should be retried.
+    pub fn is_retryable(&self, code: &ErrorCode) -> bool {
+        self.retry_on.contains(code)
+    }
+
+    /// The time to wait before sending the request again after the given
+    /// number of failed attempts.
+    ///
+    /// The delay doubles with each failure, beginning with the base delay and
+    /// never exceeding the maximum delay. With jitter, the delay is a random
+    /// value between half of and the full calculated delay.
+    pub fn delay(&self, failed_attempts: u32) -> Duration {
+        let exp = failed_attempts.saturating_sub(1).min(31);
+
+        let delay = self.base_delay.saturating_mul(1 << exp)
+            .min(self.max_delay);
+
+        if self.jitter {
+            let half = delay / 2;
+            half + half.mul_f64(random_fraction())
+        } else {
+            delay
+        }
+    }
+}
+
+/// A builder for a [RetryPolicy].
+#[derive(Default)]
+pub struct RetryPolicyBuilder {
+    max_attempts: Option<u32>,
+    base_delay: Option<Duration>,
+    max_delay: Option<Duration>,
+    jitter: Option<bool>,
+    retry_on: Option<Vec<ErrorCode>>,
+    retry_connection_errors: Option<bool>,
+}
+
+impl RetryPolicyBuilder {
+    /// The maximum number of times to send a request, including the first
+    /// attempt.
+    ///
+    /// The number of attempts must be at least one.
+    pub fn max_attempts(mut self, attempts: u32)
+    -> Result<Self, ValidationError> {
+        if attempts < 1 {
+            Err(ValidationError::OutOfBounds(
+                "The number of attempts must be greater than zero".into()
+            ))
+        } else {
+            self.max_attempts = Some(attempts);
+            Ok(self)
+        }
+    }
+
+    /// The time to wait before the first retry.
+    pub fn base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = Some(delay);
+        self
+    }
+
+    /// The longest time to wait between attempts.
+    pub fn max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = Some(delay);
+        self
+    }
+
+    /// Apply a random jitter to each delay.
+    ///
+    /// Jitter prevents many clients that failed at the same time from retrying
+    /// in lockstep.
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = Some(jitter);
+        self
+    }
+
+    /// The error codes that cause a request to be retried.
+    ///
+    /// This replaces the default list of error codes.
+    pub fn retry_on(mut self, codes: impl Into<Vec<ErrorCode>>) -> Self {
+        self.retry_on = Some(codes.into());
+        self
+    }
+
+    /// Retry idempotent requests that fail with an error from the HTTP
+    /// client, such as a refused or reset connection.
+    ///
+    /// This is enabled by default.
+    pub fn retry_connection_errors(mut self, retry: bool) -> Self {
+        self.retry_connection_errors = Some(retry);
+        self
+    }
+
+    /// Create a [RetryPolicy].
+    ///
+    /// # Errors
+    ///
+    /// Returns [ValidationError::Incompatible] if the base delay is greater
+    /// than the maximum delay.
+    pub fn build(self) -> Result<RetryPolicy, ValidationError> {
+        let default = RetryPolicy::default();
+
+        let policy = RetryPolicy {
+            max_attempts: self.max_attempts.unwrap_or(default.max_attempts),
+            base_delay: self.base_delay.unwrap_or(default.base_delay),
+            max_delay: self.max_delay.unwrap_or(default.max_delay),
+            jitter: self.jitter.unwrap_or(default.jitter),
+            retry_on: self.retry_on.unwrap_or(default.retry_on),
+            retry_connection_errors: self.retry_connection_errors
+                .unwrap_or(default.retry_connection_errors),
+        };
+
+        if policy.base_delay > policy.max_delay {
+            Err(ValidationError::Incompatible(
+                "The base delay cannot be greater than the maximum delay"
+                    .into()
+            ))
+        } else {
+            Ok(policy)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+}
+
+enum Body {
+    Bytes(Vec<u8>),
+    Json(serde_json::Value),
+    File(PathBuf),
+    Reader(Box<dyn AsyncRead + Send + Sync + Unpin>, u64),
+    // A reader that was consumed by a previous attempt.
+    Spent,
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bytes(b) => f.debug_tuple("Bytes").field(b).finish(),
+            Self::Json(v) => f.debug_tuple("Json").field(v).finish(),
+            Self::File(p) => f.debug_tuple("File").field(p).finish(),
+            Self::Reader(_, len) => f.debug_struct("Reader")
+                .field("len", len)
+                .finish_non_exhaustive(),
+            Self::Spent => write!(f, "Spent"),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Request {
+    method: Method,
+    url: String,
+    headers: Vec<(String, String)>,
+    body: Option<Body>,
+}
+
+impl Request {
+    fn is_replayable(&self) -> bool {
+        ! matches!(self.body, Some(Body::Reader(..)) | Some(Body::Spent))
+    }
+
+    fn is_upload(&self) -> bool {
+        self.url.contains("/b2_upload_file")
+            || self.url.contains("/b2_upload_part")
+    }
+
+    /// Returns `true` if sending the request more than once has the same
+    /// effect as sending it once.
+    fn is_idempotent(&self) -> bool {
+        match self.method {
+            Method::Get | Method::Head | Method::Put | Method::Delete => true,
+            Method::Post => {
+                let call = self.url.split('?').next().unwrap_or_default()
+                    .rsplit('/').next().unwrap_or_default();
+
+                READ_ONLY_CALLS.iter().any(|prefix| call.starts_with(prefix))
+            },
+        }
+    }
+}
+
+/// Prefixes of the B2 API calls that are sent via `POST` but do not modify
+/// anything.
+const READ_ONLY_CALLS: [&str; 3] = ["b2_list_", "b2_get_", "b2_download_"];
+
+/// An [HttpClient] that retries failed requests according to a [RetryPolicy].
+///
+/// Requests that receive a retryable error response from the B2 service are
+/// retried, as are idempotent requests that fail with a connection error.
+/// Other errors from the underlying HTTP client are returned immediately.
+#[derive(Debug, Default)]
+pub struct RetryClient<C> {
+    client: C,
+    policy: RetryPolicy,
+    req: Option<Request>,
+}
+
+impl<C> Clone for RetryClient<C>
+    where C: Clone,
+{
+    /// Clone the client and its policy.
+    ///
+    /// Any request that has been created but not yet sent is not cloned.
+    fn clone(&self) -> Self {
+        Self {
+            client: self.client.clone(),
+            policy: self.policy.clone(),
+            req: None,
+        }
+    }
+}
+
+impl<C> RetryClient<C> {
+    /// Wrap the provided client, retrying requests according to the default
+    /// [RetryPolicy].
+    pub fn new(client: C) -> Self {
+        Self {
+            client,
+            policy: RetryPolicy::default(),
+            req: None,
+        }
+    }
+
+    /// Use the provided [RetryPolicy].
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// The [RetryPolicy] used by this client.
+    pub fn policy(&self) -> &RetryPolicy { &self.policy }
+
+    /// Get a reference to the wrapped client.
+    pub fn inner(&self) -> &C { &self.client }
+
+    /// Get a mutable reference to the wrapped client.
+    pub fn inner_mut(&mut self) -> &mut C { &mut self.client }
+
+    /// Unwrap the client.
+    pub fn into_inner(self) -> C { self.client }
+
+    fn new_request(&mut self, method: Method, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        let _url = url::Url::parse(url.as_ref())?;
+
+        self.req = Some(Request {
+            method,
+            url: url.as_ref().to_owned(),
+            headers: vec![],
+            body: None,
+        });
+
+        Ok(self)
+    }
+
+    fn set_body(&mut self, body: Body) -> &mut Self {
+        if let Some(req) = &mut self.req {
+            req.body = Some(body);
+        }
+
+        self
+    }
+
+    /// Determine how long to wait before retrying a request that received
+    /// the given response, or `None` if it should not be retried.
+    fn retry_delay(&self, req: &Request, res: &Response, attempt: u32)
+    -> Option<Duration> {
+        if res.is_success()
+            || attempt >= self.policy.max_attempts
+            || ! req.is_replayable()
+        {
+            return None;
+        }
+
+        let code = B2Error::from_response(res).code();
+
+        if ! self.policy.is_retryable(&code)
+            || (req.is_upload() && code != ErrorCode::TooManyRequests)
+        {
+            return None;
+        }
+
+        Some(retry_after(res).unwrap_or_else(|| self.policy.delay(attempt)))
+    }
+}
+
+impl<C, E> RetryClient<C>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    /// Build the request on the wrapped client.
+    fn prepare(&mut self, req: &mut Request) -> Result<&mut C, Error<E>> {
+        let client = match req.method {
+            Method::Get => self.client.get(&req.url),
+            Method::Head => self.client.head(&req.url),
+            Method::Post => self.client.post(&req.url),
+            Method::Put => self.client.put(&req.url),
+            Method::Delete => self.client.delete(&req.url),
+        }?;
+
+        for (name, value) in req.headers.iter() {
+            client.with_header(name, value)?;
+        }
+
+        match &req.body {
+            Some(Body::Bytes(data)) => { client.with_body(data.clone()); },
+            Some(Body::Json(data)) => { client.with_body_json(data.clone()); },
+            Some(Body::File(path)) => {
+                client.read_body_from_file(path.clone());
+            },
+            Some(Body::Reader(..)) => {
+                if let Some(Body::Reader(reader, len))
+                    = req.body.replace(Body::Spent)
+                {
+                    client.with_body_reader(reader, len);
+                }
+            },
+            Some(Body::Spent) => return Err(Error::NoRequest),
+            None => {},
+        }
+
+        Ok(client)
+    }
+
+    /// Determine how long to wait before retrying a request that failed with
+    /// the given error, or `None` if it should not be retried.
+    fn error_delay(&self, req: &Request, e: &Error<E>, attempt: u32)
+    -> Option<Duration> {
+        let retry = self.policy.retry_connection_errors
+            && attempt < self.policy.max_attempts
+            && req.is_replayable()
+            && req.is_idempotent()
+            && (C::is_connection_error(e)
+                || matches!(e, Error::IO(e) if is_connection_io_error(e)));
+
+        retry.then(|| self.policy.delay(attempt))
+    }
+}
+
+#[async_trait::async_trait]
+impl<C, E> HttpClient for RetryClient<C>
+    where C: HttpClient<Error=Error<E>> + Send,
+          E: fmt::Debug + fmt::Display + Send,
+{
+    type Error = Error<E>;
+
+    fn get(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        self.new_request(Method::Get, url)
+    }
+
+    fn head(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        self.new_request(Method::Head, url)
+    }
+
+    fn post(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        self.new_request(Method::Post, url)
+    }
+
+    fn put(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        self.new_request(Method::Put, url)
+    }
+
+    fn delete(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        self.new_request(Method::Delete, url)
+    }
+
+    fn with_header<S: AsRef<str>>(&mut self, name: S, value: S)
+    -> Result<&mut Self, ValidationError> {
+        if let Some(req) = &mut self.req {
+            req.headers.push(
+                (name.as_ref().to_owned(), value.as_ref().to_owned())
+            );
+        }
+
+        Ok(self)
+    }
+
+    fn with_body(&mut self, data: impl Into<Vec<u8>>) -> &mut Self {
+        self.set_body(Body::Bytes(data.into()))
+    }
+
+    fn with_body_json(&mut self, body: serde_json::Value) -> &mut Self {
+        self.set_body(Body::Json(body))
+    }
+
+    fn read_body_from_file(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.set_body(Body::File(path.into()))
+    }
+
+    fn with_body_reader<R>(&mut self, reader: R, content_length: u64)
+    -> &mut Self
+        where R: AsyncRead + Send + Sync + Unpin + 'static
+    {
+        self.set_body(Body::Reader(Box::new(reader), content_length))
+    }
+
+    fn user_agent(&mut self, user_agent_string: impl Into<String>)
+    -> Result<&mut Self, ValidationError> {
+        self.client.user_agent(user_agent_string)?;
+        Ok(self)
+    }
+
+    async fn send(&mut self) -> Result<Response, Self::Error> {
+        let mut req = self.req.take().ok_or(Error::NoRequest)?;
+        let mut attempt = 1;
+
+        loop {
+            let delay = match self.prepare(&mut req)?.send().await {
+                Ok(res) => match self.retry_delay(&req, &res, attempt) {
+                    Some(delay) => delay,
+                    None => return Ok(res),
+                },
+                Err(e) => match self.error_delay(&req, &e, attempt) {
+                    Some(delay) => delay,
+                    None => return Err(e),
+                },
+            };
+
+            sleep(delay).await;
+            attempt += 1;
+        }
+    }
+
+    async fn send_streaming(&mut self)
+    -> Result<StreamingResponse, Self::Error> {
+        let mut req = self.req.take().ok_or(Error::NoRequest)?;
+        let mut attempt = 1;
+
+        loop {
+            let res = match self.prepare(&mut req)?.send_streaming().await {
+                Ok(res) => res,
+                Err(e) => match self.error_delay(&req, &e, attempt) {
+                    Some(delay) => {
+                        sleep(delay).await;
+                        attempt += 1;
+                        continue;
+                    },
+                    None => return Err(e),
+                },
+            };
+
+            if res.is_success() { return Ok(res); }
+
+            // Error responses are small; we read them to see whether to retry.
+            let res = res.into_response().await?;
+
+            match self.retry_delay(&req, &res, attempt) {
+                Some(delay) => sleep(delay).await,
+                None => {
+                    let (status, headers, body) = res.into_parts();
+
+                    return Ok(StreamingResponse::new(
+                        status,
+                        headers,
+                        BufferedBody::new(body)
+                    ));
+                },
+            }
+
+            attempt += 1;
+        }
+    }
+
+    fn is_connection_error(e: &Self::Error) -> bool {
+        C::is_connection_error(e)
+    }
+}
+
+/// Read the number of seconds to wait from a `Retry-After` header.
+fn retry_after(res: &Response) -> Option<Duration> {
+    res.headers().iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case("retry-after"))
+        .and_then(|(_, v)| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Generate a random number in the range `[0, 1)`.
+fn random_fraction() -> f64 {
+    use std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+    };
+
+    // Each RandomState is seeded differently, which is random enough to keep
+    // clients from retrying in lockstep.
+    let bits = RandomState::new().build_hasher().finish() >> 11;
+    bits as f64 / (1u64 << 53) as f64
+}
+
+/// A response body that has already been read into memory.
+pub(crate) struct BufferedBody {
+    body: Vec<u8>,
+    pos: usize,
+}
+
+impl BufferedBody {
+    pub(crate) fn new(body: Vec<u8>) -> Self {
+        Self { body, pos: 0 }
+    }
+}
+
+impl AsyncRead for BufferedBody {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8]
+    ) -> Poll<std::io::Result<usize>> {
+        let remaining = &self.body[self.pos..];
+        let len = remaining.len().min(buf.len());
+
+        buf[..len].copy_from_slice(&remaining[..len]);
+        self.pos += len;
+
+        Poll::Ready(Ok(len))
+    }
+}
+
+#[derive(Default)]
+struct DelayState {
+    done: bool,
+    waker: Option<Waker>,
+}
+
+/// A future that completes after a given duration.
+///
+/// The timer runs on its own thread so that retries do not depend on any
+/// particular async runtime.
+struct Delay {
+    state: Arc<Mutex<DelayState>>,
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+
+        if state.done {
+            Poll::Ready(())
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+fn sleep(duration: Duration) -> Delay {
+    let state = Arc::new(Mutex::new(DelayState {
+        done: duration.is_zero(),
+        waker: None,
+    }));
+
+    if ! duration.is_zero() {
+        let state = state.clone();
+
+        std::thread::spawn(move || {
+            std::thread::sleep(duration);
+
+            let mut state = state.lock().unwrap();
+            state.done = true;
+
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+    }
+
+    Delay { state }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::HeaderMap;
+
+    /// A client that returns a scripted sequence of responses and records the
+    /// requests it sends.
+    #[derive(Debug, Default, Clone)]
+    struct ScriptedClient {
+        responses: Arc<Mutex<Vec<Response>>>,
+        sent: Arc<Mutex<Vec<String>>>,
+        // The number of requests to fail before returning any responses, and
+        // the error to fail with.
+        failures: Arc<Mutex<u32>>,
+        failure: &'static str,
+        url: String,
+        body: Option<Vec<u8>>,
+    }
+
+    impl ScriptedClient {
+        fn new(mut responses: Vec<Response>) -> Self {
+            responses.reverse();
+
+            Self {
+                responses: Arc::new(Mutex::new(responses)),
+                ..Default::default()
+            }
+        }
+
+        fn sent(&self) -> Vec<String> { self.sent.lock().unwrap().clone() }
+
+        fn failing(mut self, failures: u32, error: &'static str) -> Self {
+            *self.failures.lock().unwrap() = failures;
+            self.failure = error;
+            self
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpClient for ScriptedClient {
+        type Error = Error<&'static str>;
+
+        fn get(&mut self, url: impl AsRef<str>)
+        -> Result<&mut Self, ValidationError> {
+            self.url = url.as_ref().to_owned();
+            Ok(self)
+        }
+        fn head(&mut self, url: impl AsRef<str>)
+        -> Result<&mut Self, ValidationError> {
+            self.url = url.as_ref().to_owned();
+            Ok(self)
+        }
+        fn post(&mut self, url: impl AsRef<str>)
+        -> Result<&mut Self, ValidationError> {
+            self.url = url.as_ref().to_owned();
+            Ok(self)
+        }
+        fn put(&mut self, url: impl AsRef<str>)
+        -> Result<&mut Self, ValidationError> {
+            self.url = url.as_ref().to_owned();
+            Ok(self)
+        }
+        fn delete(&mut self, url: impl AsRef<str>)
+        -> Result<&mut Self, ValidationError> {
+            self.url = url.as_ref().to_owned();
+            Ok(self)
+        }
+
+        fn with_header<S: AsRef<str>>(&mut self, _name: S, _value: S)
+        -> Result<&mut Self, ValidationError> { Ok(self) }
+        fn with_body(&mut self, data: impl Into<Vec<u8>>) -> &mut Self {
+            self.body = Some(data.into());
+            self
+        }
+        fn with_body_json(&mut self, _body: serde_json::Value) -> &mut Self {
+            self
+        }
+        fn read_body_from_file(&mut self, _path: impl Into<PathBuf>)
+        -> &mut Self { self }
+        fn with_body_reader<R>(&mut self, _reader: R, _content_length: u64)
+        -> &mut Self
+            where R: AsyncRead + Send + Sync + Unpin + 'static
+        { self }
+
+        fn user_agent(&mut self, _user_agent_string: impl Into<String>)
+        -> Result<&mut Self, ValidationError> { Ok(self) }
+
+        async fn send(&mut self) -> Result<Response, Self::Error> {
+            let body = self.body.take().unwrap_or_default();
+
+            self.sent.lock().unwrap().push(format!("{} {}",
+                self.url, String::from_utf8_lossy(&body)));
+
+            let mut failures = self.failures.lock().unwrap();
+
+            if *failures > 0 {
+                *failures -= 1;
+                return Err(Error::Client(self.failure));
+            }
+
+            self.responses.lock().unwrap().pop().ok_or(Error::NoRequest)
+        }
+
+        async fn send_streaming(&mut self)
+        -> Result<StreamingResponse, Self::Error> {
+            let (status, headers, body) = self.send().await?.into_parts();
+            Ok(StreamingResponse::new(status, headers,
+                BufferedBody::new(body)))
+        }
+
+        fn is_connection_error(e: &Self::Error) -> bool {
+            matches!(e, Error::Client("connection refused"))
+        }
+    }
+
+    fn error_response(status: u16, code: &str) -> Response {
+        let body = format!(
+            r#"{{"status":{},"code":"{}","message":"msg"}}"#, status, code
+        );
+
+        Response::new(status, HeaderMap::new(), body.into_bytes())
+    }
+
+    fn ok_response() -> Response {
+        Response::new(200, HeaderMap::new(), b"{}".to_vec())
+    }
+
+    fn no_delay() -> RetryPolicy {
+        RetryPolicy::builder()
+            .base_delay(Duration::ZERO)
+            .max_delay(Duration::ZERO)
+            .build()
+            .unwrap()
+    }
+
+    #[async_std::test]
+    async fn retries_service_unavailable() -> anyhow::Result<()> {
+        let inner = ScriptedClient::new(vec![
+            error_response(503, "service_unavailable"),
+            error_response(429, "too_many_requests"),
+            ok_response(),
+        ]);
+        let mut client = RetryClient::new(inner.clone())
+            .with_policy(no_delay());
+
+        let res = client.post("https://example.com/b2api/v2/b2_list_buckets")?
+            .with_body("abc")
+            .send().await?;
+
+        assert_eq!(res.status(), 200);
+        assert_eq!(inner.sent().len(), 3);
+        assert!(inner.sent().iter()
+            .all(|r| r == "https://example.com/b2api/v2/b2_list_buckets abc"));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn does_not_retry_other_errors() -> anyhow::Result<()> {
+        let inner = ScriptedClient::new(vec![
+            error_response(400, "bad_request"),
+            ok_response(),
+        ]);
+        let mut client = RetryClient::new(inner.clone())
+            .with_policy(no_delay());
+
+        let res = client.get("https://example.com/b2api/v2/b2_list_buckets")?
+            .send().await?;
+
+        assert_eq!(res.status(), 400);
+        assert_eq!(inner.sent().len(), 1);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn stops_after_max_attempts() -> anyhow::Result<()> {
+        let inner = ScriptedClient::new(vec![
+            error_response(503, "service_unavailable"),
+            error_response(503, "service_unavailable"),
+            ok_response(),
+        ]);
+        let policy = RetryPolicy::builder()
+            .max_attempts(2)?
+            .base_delay(Duration::ZERO)
+            .build()?;
+        let mut client = RetryClient::new(inner.clone()).with_policy(policy);
+
+        let res = client.get("https://example.com/b2api/v2/b2_list_buckets")?
+            .send_streaming().await?
+            .into_response().await?;
+
+        assert_eq!(res.status(), 503);
+        assert_eq!(B2Error::from_response(&res).code(),
+            ErrorCode::ServiceUnavailable);
+        assert_eq!(inner.sent().len(), 2);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn uploads_only_retry_too_many_requests() -> anyhow::Result<()> {
+        let url = "https://pod-000.backblaze.com/b2api/v2/b2_upload_file/a/b";
+
+        let inner = ScriptedClient::new(vec![
+            error_response(429, "too_many_requests"),
+            error_response(503, "service_unavailable"),
+            ok_response(),
+        ]);
+        let mut client = RetryClient::new(inner.clone())
+            .with_policy(no_delay());
+
+        let res = client.post(url)?.with_body("abcd").send().await?;
+
+        assert_eq!(res.status(), 503);
+        assert_eq!(inner.sent().len(), 2);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn retries_connection_errors() -> anyhow::Result<()> {
+        let inner = ScriptedClient::new(vec![ok_response(), ok_response()])
+            .failing(2, "connection refused");
+        let mut client = RetryClient::new(inner.clone())
+            .with_policy(no_delay());
+
+        let res = client.post("https://example.com/b2api/v2/b2_list_buckets")?
+            .send_streaming().await?;
+
+        assert_eq!(res.status(), 200);
+        assert_eq!(inner.sent().len(), 3);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn does_not_retry_other_client_errors() -> anyhow::Result<()> {
+        let inner = ScriptedClient::new(vec![ok_response()])
+            .failing(1, "invalid certificate");
+        let mut client = RetryClient::new(inner.clone())
+            .with_policy(no_delay());
+
+        let res = client.get("https://example.com/file/bucket/a.txt")?
+            .send().await;
+
+        assert!(matches!(res, Err(Error::Client("invalid certificate"))));
+        assert_eq!(inner.sent().len(), 1);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn does_not_retry_connection_errors_if_not_idempotent()
+    -> anyhow::Result<()> {
+        let inner = ScriptedClient::new(vec![ok_response()])
+            .failing(1, "connection refused");
+        let mut client = RetryClient::new(inner.clone())
+            .with_policy(no_delay());
+
+        let res = client.post("https://example.com/b2api/v2/b2_hide_file")?
+            .send().await;
+
+        assert!(matches!(res, Err(Error::Client(_))));
+        assert_eq!(inner.sent().len(), 1);
+
+        let inner = ScriptedClient::new(vec![ok_response()])
+            .failing(1, "connection refused");
+        let policy = RetryPolicy::builder()
+            .base_delay(Duration::ZERO)
+            .retry_connection_errors(false)
+            .build()?;
+        let mut client = RetryClient::new(inner.clone()).with_policy(policy);
+
+        let res = client.get("https://example.com/file/bucket/a.txt")?
+            .send().await;
+
+        assert!(matches!(res, Err(Error::Client(_))));
+        assert_eq!(inner.sent().len(), 1);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn honors_retry_after() -> anyhow::Result<()> {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after".into(), "1".into());
+
+        let inner = ScriptedClient::new(vec![
+            Response::new(503, headers, vec![]),
+            ok_response(),
+        ]);
+        let mut client = RetryClient::new(inner.clone())
+            .with_policy(no_delay());
+
+        let start = std::time::Instant::now();
+
+        let res = client.get("https://example.com/b2api/v2/b2_list_buckets")?
+            .send().await?;
+
+        assert_eq!(res.status(), 200);
+        assert!(start.elapsed() >= Duration::from_secs(1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn delay_grows_exponentially() {
+        let policy = RetryPolicy::builder()
+            .jitter(false)
+            .max_delay(Duration::from_secs(10))
+            .build()
+            .unwrap();
+
+        assert_eq!(policy.delay(1), Duration::from_secs(1));
+        assert_eq!(policy.delay(2), Duration::from_secs(2));
+        assert_eq!(policy.delay(3), Duration::from_secs(4));
+        assert_eq!(policy.delay(5), Duration::from_secs(10));
+        assert_eq!(policy.delay(100), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        let policy = RetryPolicy::default();
+
+        for attempt in 1..10 {
+            let max = RetryPolicy { jitter: false, ..policy.clone() }
+                .delay(attempt);
+            let delay = policy.delay(attempt);
+
+            assert!(delay >= max / 2 && delay <= max);
+        }
+    }
+
+    #[test]
+    fn builder_rejects_bad_values() {
+        assert!(RetryPolicy::builder().max_attempts(0).is_err());
+        assert!(
+            RetryPolicy::builder()
+                .base_delay(Duration::from_secs(2))
+                .max_delay(Duration::from_secs(1))
+                .build()
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,930 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+   License, v. 2.0. If a copy of the MPL was not distributed with this
+   file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Sessions that renew their authorization when it expires.
+//!
+//! An [Authorization] is valid for no more than 24 hours. A [Session] keeps the
+//! application key it was created with so that it can call
+//! [authorize_account](crate::account::authorize_account) again when the B2
+//! service rejects its authorization token as expired.
+//!
+//! A `Session` has a method for each API function in the [account],
+//! [bucket], and [file](mod@file) modules that takes an [Authorization]. Each
+//! takes `&self` and calls the function again with a new token if necessary:
+//!
+//! ```no_run
+//! # #[cfg(feature = "with_surf")]
+//! # async fn f() -> anyhow::Result<()> {
+//! use b2_client::{self as b2, client::SurfClient, session::Session};
+//!
+//! let session = Session::new(SurfClient::default(), "key-id", "key").await?;
+//!
+//! let buckets = session.list_buckets(b2::ListBuckets::builder().build())
+//!     .await?;
+//!
+//! let upload = b2::UploadFile::builder()
+//!     .file_name("my-file.txt")?
+//!     .sha1_checksum("61b8d6600ac94d912874f569a9341120f680c9f8")
+//!     .build()?;
+//!
+//! let file = session.upload_file(buckets[0].bucket_id(), upload,
+//!     b"very important information").await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A `Session` is a cheaply cloned handle that can be shared between threads
+//! and tasks; every clone uses the same authorization, and renewing it through
+//! one clone renews it for all of them. When several calls find the token
+//! expired at once, the account is authorized only once and every call uses
+//! the new token.
+//!
+//! Other functions that take an [Authorization], such as
+//! [audit_account](crate::audit::audit_account), can be run through
+//! [Session::call]. [Session::authorization] returns a copy of the current
+//! [Authorization] for functions that need one of their own, such as those
+//! that return a [stream](crate::pagination::ListStream).
+//!
+//! [UploadAuthorization]s and [UploadPartAuthorization]s obtained from the
+//! session share its renewed token. When an upload is rejected because its own
+//! authorization has expired, [file::upload_file] and [file::upload_file_part]
+//! obtain a new upload URL and send the data again.
+
+use std::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+};
+
+use crate::{
+    account::{
+        self, Authorization, CreateKey, Key, ListKeys, SharedAuthorization,
+    },
+    bucket::{
+        self, Bucket, CreateBucket, ListBuckets, NotificationRule,
+        ServerSideEncryption, UpdateBucket,
+    },
+    client::{HeaderMap, HttpClient, ResponseReader},
+    credentials::Credentials,
+    error::{Error, RotateKeyError},
+    file::{
+        self, BypassGovernance, CancelledFileUpload, CopyFile, CopyFilePart,
+        DeletedFile, DownloadAuthorization, DownloadAuthorizationRequest,
+        DownloadFile, File, FilePart, ListFileNames, ListFileParts,
+        ListFileVersions, ListUnfinishedLargeFiles, StartLargeFile,
+        UpdateFileLegalHold, UpdateFileRetention, UploadAuthorization,
+        UploadFile, UploadPartAuthorization,
+    },
+};
+
+
+/// Evaluate `$call`, which uses the session's [Authorization] as `$auth`, and
+/// evaluate it once more with a new token if the token has expired.
+///
+/// Any request in `$call` must be cloned, since it may be sent twice.
+macro_rules! renewing {
+    ($session:expr, |$auth:ident| $call:expr) => {{
+        let mut $auth = $session.authorization();
+        let res = $call.await;
+
+        match res {
+            Err(e) if e.is_expired_authorization() => {
+                $auth.reauthorize().await?;
+                $call.await
+            },
+            res => res,
+        }
+    }};
+}
+
+/// A future returned by the function passed to [Session::call].
+pub type CallFuture<'a, T, E> =
+    Pin<Box<dyn Future<Output = Result<T, Error<E>>> + Send + 'a>>;
+
+/// An [Authorization] that is renewed automatically when it expires.
+///
+/// Clones of a `Session` share its authorization. Its methods may be called by
+/// any number of tasks at once.
+///
+/// The application key is kept in memory until the last clone, and every
+/// [Authorization] obtained from them, is dropped.
+#[derive(Debug, Clone)]
+pub struct Session<C>
+    where C: HttpClient,
+{
+    shared: Arc<SharedAuthorization<C>>,
+}
+
+impl<C, E> Session<C>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    /// Authorize the account and begin a session.
+    ///
+    /// See [authorize_account](crate::account::authorize_account) for
+    /// information on authorizing an account.
+    pub async fn new(client: C, key_id: &str, key: &str)
+    -> Result<Self, Error<E>> {
+        Self::with_credentials(client, Credentials::new(key_id, key)).await
+    }
+
+    /// Authorize the account at the given base URL and begin a session.
+    ///
+    /// The session renews its authorization at the same URL. See
+    /// [authorize_account_with_url](crate::account::authorize_account_with_url)
+    /// for more information.
+    pub async fn new_with_url(
+        client: C,
+        auth_url: &str,
+        key_id: &str,
+        key: &str,
+    ) -> Result<Self, Error<E>> {
+        let credentials = Credentials::new(key_id, key).with_auth_url(auth_url);
+        Self::with_credentials(client, credentials).await
+    }
+
+    /// Authorize the account with the given [Credentials] and begin a session.
+    ///
+    /// Credentials can be found with a
+    /// [ProviderChain](crate::credentials::ProviderChain).
+    pub async fn with_credentials(client: C, credentials: Credentials)
+    -> Result<Self, Error<E>> {
+        let auth = credentials.authorize(client).await?;
+
+        Ok(Self {
+            shared: Arc::new(SharedAuthorization::new(auth, credentials)),
+        })
+    }
+
+    /// Get a copy of the session's current [Authorization].
+    ///
+    /// Most functions called with the returned `Authorization` do not retry
+    /// requests rejected for an expired token; use [call](Self::call) instead.
+    /// Upload authorizations obtained with it are renewed as described in the
+    /// [module documentation](self).
+    pub fn authorization(&self) -> Authorization<C> {
+        self.shared.current()
+    }
+
+    /// Obtain a new authorization token immediately.
+    pub async fn reauthorize(&self) -> Result<(), Error<E>> {
+        self.authorization().reauthorize().await
+    }
+
+    /// Call a function that takes an [Authorization], such as
+    /// [audit_account](crate::audit::audit_account), with the session's
+    /// authorization.
+    ///
+    /// If `f` fails because the authorization token has expired or is
+    /// otherwise invalid, the session obtains a new token and calls `f` once
+    /// more.
+    ///
+    /// `f` may be called twice, so it should build any request it needs
+    /// rather than moving it into the API function.
+    pub async fn call<T, F>(&self, mut f: F) -> Result<T, Error<E>>
+        where F: for<'a> FnMut(&'a mut Authorization<C>)
+            -> CallFuture<'a, T, E>,
+    {
+        renewing!(self, |auth| f(&mut auth))
+    }
+
+    /// See [account::create_key].
+    pub async fn create_key(&self, new_key_info: CreateKey<'_>)
+    -> Result<(String, Key), Error<E>> {
+        renewing!(self, |auth|
+            account::create_key(&mut auth, new_key_info.clone()))
+    }
+
+    /// See [account::delete_key].
+    pub async fn delete_key(&self, key: Key) -> Result<Key, Error<E>> {
+        renewing!(self, |auth| account::delete_key(&mut auth, key.clone()))
+    }
+
+    /// See [account::delete_key_by_id].
+    pub async fn delete_key_by_id(&self, key_id: impl AsRef<str>)
+    -> Result<Key, Error<E>> {
+        renewing!(self, |auth|
+            account::delete_key_by_id(&mut auth, key_id.as_ref()))
+    }
+
+    /// See [account::rotate_key].
+    ///
+    /// Because `store` can only be called once, the rotation is not retried
+    /// if the session's token has expired; call
+    /// [reauthorize](Self::reauthorize) and rotate the key again.
+    pub async fn rotate_key<F, Fut, S>(
+        &self,
+        old_key: Key,
+        expires_after: Option<chrono::Duration>,
+        store: F,
+    ) -> Result<(String, Key), RotateKeyError<E, S>>
+        where F: FnOnce(String, Key) -> Fut,
+              Fut: Future<Output=Result<(), S>>,
+    {
+        let mut auth = self.authorization();
+        account::rotate_key(&mut auth, old_key, expires_after, store).await
+    }
+
+    /// See [account::list_keys].
+    pub async fn list_keys(&self, list_req: ListKeys<'_>)
+    -> Result<(Vec<Key>, Option<ListKeys<'static>>), Error<E>> {
+        renewing!(self, |auth| async {
+            account::list_keys(&mut auth, list_req.clone()).await
+                .map(|(keys, next)| (keys, next.map(ListKeys::into_owned)))
+        })
+    }
+
+    /// See [bucket::create_bucket].
+    pub async fn create_bucket(&self, new_bucket_info: CreateBucket<'_>)
+    -> Result<Bucket, Error<E>> {
+        renewing!(self, |auth|
+            bucket::create_bucket(&mut auth, new_bucket_info.clone()))
+    }
+
+    /// See [bucket::delete_bucket].
+    pub async fn delete_bucket(&self, bucket_id: impl AsRef<str>)
+    -> Result<Bucket, Error<E>> {
+        renewing!(self, |auth|
+            bucket::delete_bucket(&mut auth, bucket_id.as_ref()))
+    }
+
+    /// See [bucket::get_bucket_notification_rules].
+    pub async fn get_bucket_notification_rules(
+        &self,
+        bucket_id: impl AsRef<str>
+    ) -> Result<Vec<NotificationRule>, Error<E>> {
+        renewing!(self, |auth| bucket::get_bucket_notification_rules(
+            &mut auth, bucket_id.as_ref()
+        ))
+    }
+
+    /// See [bucket::list_buckets].
+    pub async fn list_buckets(&self, list_info: ListBuckets<'_>)
+    -> Result<Vec<Bucket>, Error<E>> {
+        renewing!(self, |auth|
+            bucket::list_buckets(&mut auth, list_info.clone()))
+    }
+
+    /// See [bucket::set_bucket_notification_rules].
+    pub async fn set_bucket_notification_rules(
+        &self,
+        bucket_id: impl AsRef<str>,
+        rules: impl Into<Vec<NotificationRule>>
+    ) -> Result<Vec<NotificationRule>, Error<E>> {
+        let rules = rules.into();
+
+        renewing!(self, |auth| bucket::set_bucket_notification_rules(
+            &mut auth, bucket_id.as_ref(), rules.clone()
+        ))
+    }
+
+    /// See [bucket::update_bucket].
+    pub async fn update_bucket(&self, bucket_info: UpdateBucket<'_>)
+    -> Result<Bucket, Error<E>> {
+        renewing!(self, |auth|
+            bucket::update_bucket(&mut auth, bucket_info.clone()))
+    }
+
+    /// See [file::cancel_large_file].
+    pub async fn cancel_large_file(&self, file: File)
+    -> Result<CancelledFileUpload, Error<E>> {
+        renewing!(self, |auth| file::cancel_large_file(&mut auth, file.clone()))
+    }
+
+    /// See [file::cancel_large_file_by_id].
+    pub async fn cancel_large_file_by_id(&self, id: impl AsRef<str>)
+    -> Result<CancelledFileUpload, Error<E>> {
+        renewing!(self, |auth|
+            file::cancel_large_file_by_id(&mut auth, id.as_ref()))
+    }
+
+    /// See [file::copy_file].
+    pub async fn copy_file(&self, file: CopyFile<'_>)
+    -> Result<File, Error<E>> {
+        renewing!(self, |auth| file::copy_file(&mut auth, file.clone()))
+    }
+
+    /// See [file::copy_file_part].
+    pub async fn copy_file_part(&self, file_part: CopyFilePart<'_>)
+    -> Result<FilePart, Error<E>> {
+        renewing!(self, |auth|
+            file::copy_file_part(&mut auth, file_part.clone()))
+    }
+
+    /// See [file::delete_file_version].
+    pub async fn delete_file_version(
+        &self,
+        file: File,
+        bypass_governance: BypassGovernance,
+    ) -> Result<DeletedFile, Error<E>> {
+        renewing!(self, |auth| file::delete_file_version(
+            &mut auth, file.clone(), bypass_governance
+        ))
+    }
+
+    /// See [file::delete_file_version_by_name_id].
+    pub async fn delete_file_version_by_name_id(
+        &self,
+        file_name: impl AsRef<str>,
+        file_id: impl AsRef<str>,
+        bypass_governance: BypassGovernance,
+    ) -> Result<DeletedFile, Error<E>> {
+        renewing!(self, |auth| file::delete_file_version_by_name_id(
+            &mut auth, file_name.as_ref(), file_id.as_ref(), bypass_governance
+        ))
+    }
+
+    /// See [file::download_file_headers].
+    pub async fn download_file_headers(&self, file: &File)
+    -> Result<HeaderMap, Error<E>> {
+        renewing!(self, |auth| file::download_file_headers(&mut auth, file))
+    }
+
+    /// See [file::download_file_headers_by_id].
+    pub async fn download_file_headers_by_id(&self, file_id: impl AsRef<str>)
+    -> Result<HeaderMap, Error<E>> {
+        renewing!(self, |auth|
+            file::download_file_headers_by_id(&mut auth, file_id.as_ref()))
+    }
+
+    /// See [file::download_file].
+    pub async fn download_file(&self, file: DownloadFile<'_>)
+    -> Result<(Vec<u8>, HeaderMap), Error<E>> {
+        renewing!(self, |auth| file::download_file(&mut auth, file.clone()))
+    }
+
+    /// See [file::download_file_stream].
+    pub async fn download_file_stream(&self, file: DownloadFile<'_>)
+    -> Result<(ResponseReader, HeaderMap), Error<E>> {
+        renewing!(self, |auth|
+            file::download_file_stream(&mut auth, file.clone()))
+    }
+
+    /// See [file::finish_large_file_upload].
+    pub async fn finish_large_file_upload(
+        &self,
+        file: &File,
+        sha1_checksums: &[String],
+    ) -> Result<File, Error<E>> {
+        renewing!(self, |auth|
+            file::finish_large_file_upload(&mut auth, file, sha1_checksums))
+    }
+
+    /// See [file::finish_large_file_upload_by_id].
+    pub async fn finish_large_file_upload_by_id(
+        &self,
+        file_id: impl AsRef<str>,
+        sha1_checksums: &[String],
+    ) -> Result<File, Error<E>> {
+        renewing!(self, |auth| file::finish_large_file_upload_by_id(
+            &mut auth, file_id.as_ref(), sha1_checksums
+        ))
+    }
+
+    /// See [file::get_file_info].
+    pub async fn get_file_info(&self, file_id: impl AsRef<str>)
+    -> Result<File, Error<E>> {
+        renewing!(self, |auth| file::get_file_info(&mut auth, file_id.as_ref()))
+    }
+
+    /// See [file::get_download_authorization].
+    pub async fn get_download_authorization(
+        &self,
+        download_req: DownloadAuthorizationRequest<'_>
+    ) -> Result<DownloadAuthorization<C>, Error<E>> {
+        renewing!(self, |auth|
+            file::get_download_authorization(&mut auth, download_req.clone()))
+    }
+
+    /// See [file::get_upload_part_authorization].
+    ///
+    /// The returned authorization owns a copy of the session's authorization
+    /// and may be moved to another task.
+    pub async fn get_upload_part_authorization(&self, file: &File)
+    -> Result<UploadPartAuthorization<C, E>, Error<E>> {
+        renewing!(self, |auth|
+            file::get_upload_part_authorization(&mut auth, file))
+    }
+
+    /// See [file::get_upload_part_authorization_by_id].
+    pub async fn get_upload_part_authorization_by_id(
+        &self,
+        file_id: impl AsRef<str>,
+        encryption: Option<&ServerSideEncryption>,
+    ) -> Result<UploadPartAuthorization<C, E>, Error<E>> {
+        renewing!(self, |auth| file::get_upload_part_authorization_by_id(
+            &mut auth, file_id.as_ref(), encryption
+        ))
+    }
+
+    /// See [file::get_upload_authorization].
+    ///
+    /// The returned authorization owns a copy of the session's authorization
+    /// and may be moved to another task.
+    pub async fn get_upload_authorization(&self, bucket: &Bucket)
+    -> Result<UploadAuthorization<C, E>, Error<E>> {
+        renewing!(self, |auth|
+            file::get_upload_authorization(&mut auth, bucket))
+    }
+
+    /// See [file::get_upload_authorization_by_id].
+    pub async fn get_upload_authorization_by_id(
+        &self,
+        bucket_id: impl AsRef<str>
+    ) -> Result<UploadAuthorization<C, E>, Error<E>> {
+        renewing!(self, |auth| file::get_upload_authorization_by_id(
+            &mut auth, bucket_id.as_ref()
+        ))
+    }
+
+    /// See [file::hide_file].
+    pub async fn hide_file(&self, file: &File) -> Result<File, Error<E>> {
+        renewing!(self, |auth| file::hide_file(&mut auth, file))
+    }
+
+    /// See [file::hide_file_by_name].
+    pub async fn hide_file_by_name(
+        &self,
+        bucket_id: impl AsRef<str>,
+        file_name: impl AsRef<str>,
+    ) -> Result<File, Error<E>> {
+        renewing!(self, |auth| file::hide_file_by_name(
+            &mut auth, bucket_id.as_ref(), file_name.as_ref()
+        ))
+    }
+
+    /// See [file::list_file_names].
+    pub async fn list_file_names<'a>(&self, request: ListFileNames<'a>)
+    -> Result<(Vec<File>, Option<ListFileNames<'a>>), Error<E>> {
+        renewing!(self, |auth|
+            file::list_file_names(&mut auth, request.clone()))
+    }
+
+    /// See [file::list_file_versions].
+    pub async fn list_file_versions<'a>(&self, request: ListFileVersions<'a>)
+    -> Result<(Vec<File>, Option<ListFileVersions<'a>>), Error<E>> {
+        renewing!(self, |auth|
+            file::list_file_versions(&mut auth, request.clone()))
+    }
+
+    /// See [file::list_file_parts].
+    pub async fn list_file_parts<'a>(&self, request: ListFileParts<'a>)
+    -> Result<(Vec<FilePart>, Option<ListFileParts<'a>>), Error<E>> {
+        renewing!(self, |auth|
+            file::list_file_parts(&mut auth, request.clone()))
+    }
+
+    /// See [file::list_unfinished_large_files].
+    pub async fn list_unfinished_large_files<'a>(
+        &self,
+        request: ListUnfinishedLargeFiles<'a>
+    ) -> Result<(Vec<File>, Option<ListUnfinishedLargeFiles<'a>>), Error<E>> {
+        renewing!(self, |auth|
+            file::list_unfinished_large_files(&mut auth, request.clone()))
+    }
+
+    /// See [file::start_large_file].
+    pub async fn start_large_file(&self, file: StartLargeFile<'_>)
+    -> Result<File, Error<E>> {
+        renewing!(self, |auth| file::start_large_file(&mut auth, file.clone()))
+    }
+
+    /// See [file::update_file_legal_hold].
+    pub async fn update_file_legal_hold(
+        &self,
+        file_update: UpdateFileLegalHold<'_>
+    ) -> Result<(), Error<E>> {
+        renewing!(self, |auth|
+            file::update_file_legal_hold(&mut auth, file_update.clone()))
+    }
+
+    /// See [file::update_file_retention].
+    pub async fn update_file_retention(
+        &self,
+        retention_update: UpdateFileRetention<'_>
+    ) -> Result<(), Error<E>> {
+        renewing!(self, |auth| file::update_file_retention(
+            &mut auth, retention_update.clone()
+        ))
+    }
+
+    /// Upload a file to the bucket with the given ID.
+    ///
+    /// This obtains a new upload URL for each file; to upload many files, get
+    /// an [UploadAuthorization] for each task with
+    /// [get_upload_authorization](Self::get_upload_authorization) and pass it
+    /// to [upload_file](file::upload_file). See [file::upload_file].
+    pub async fn upload_file(
+        &self,
+        bucket_id: impl AsRef<str>,
+        upload: UploadFile<'_>,
+        data: &[u8],
+    ) -> Result<File, Error<E>> {
+        let mut upload_auth =
+            self.get_upload_authorization_by_id(bucket_id).await?;
+
+        file::upload_file(&mut upload_auth, upload, data).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        account::authorize_account,
+        bucket::{list_buckets, ListBuckets},
+        client::{HeaderMap, Response, StreamingResponse},
+        error::ValidationError,
+        file::{
+            get_upload_authorization_by_id, upload_file, UploadAuthorization,
+            UploadFile,
+        },
+    };
+    use std::{
+        collections::HashSet,
+        path::PathBuf,
+        sync::{Arc, Mutex},
+    };
+
+    #[derive(Debug, Default)]
+    struct FakeB2 {
+        authorizations: u32,
+        auth_urls: Vec<String>,
+        upload_urls: u32,
+        expired: HashSet<String>,
+        uploads: u32,
+    }
+
+    /// A client that emulates the few B2 endpoints we need, rejecting any
+    /// token in the `expired` set.
+    #[derive(Debug, Default, Clone)]
+    struct FakeClient {
+        b2: Arc<Mutex<FakeB2>>,
+        url: String,
+        token: String,
+    }
+
+    impl FakeClient {
+        fn expire(&self, token: &str) {
+            self.b2.lock().unwrap().expired.insert(token.into());
+        }
+
+        fn respond(&self) -> Response {
+            let mut b2 = self.b2.lock().unwrap();
+
+            if b2.expired.contains(&self.token) {
+                return Response::new(401, HeaderMap::new(), serde_json::json!({
+                    "status": 401,
+                    "code": "expired_auth_token",
+                    "message": "Authorization token has expired",
+                }).to_string().into_bytes());
+            }
+
+            let endpoint = self.url.split('/').nth(5).unwrap_or_default();
+
+            let body = match endpoint {
+                "b2_authorize_account" => {
+                    b2.authorizations += 1;
+                    b2.auth_urls.push(self.url.clone());
+
+                    serde_json::json!({
+                        "absoluteMinimumPartSize": 5000000,
+                        "accountId": "account",
+                        "allowed": {
+                            "bucketId": null,
+                            "bucketName": null,
+                            "capabilities": ["listBuckets", "writeFiles"],
+                            "namePrefix": null,
+                        },
+                        "apiUrl": "https://api.example.com",
+                        "authorizationToken":
+                            format!("token-{}", b2.authorizations),
+                        "downloadUrl": "https://f.example.com",
+                        "recommendedPartSize": 100000000,
+                        "s3ApiUrl": "https://s3.example.com",
+                    })
+                },
+                "b2_list_buckets" => serde_json::json!({ "buckets": [] }),
+                "b2_get_upload_url" => {
+                    b2.upload_urls += 1;
+
+                    serde_json::json!({
+                        "bucketId": "bucket",
+                        "uploadUrl": format!(
+                            "https://pod.example.com/b2api/v2/{}/{}",
+                            "b2_upload_file", b2.upload_urls
+                        ),
+                        "authorizationToken":
+                            format!("upload-{}", b2.upload_urls),
+                    })
+                },
+                "b2_upload_file" => {
+                    b2.uploads += 1;
+
+                    serde_json::json!({
+                        "accountId": "account",
+                        "action": "upload",
+                        "bucketId": "bucket",
+                        "contentLength": 4,
+                        "contentMd5": null,
+                        "contentSha1": "abcd",
+                        "contentType": "text/plain",
+                        "fileId": "file",
+                        "fileInfo": {},
+                        "fileName": "file.txt",
+                        "fileRetention": null,
+                        "legalHold": null,
+                        "serverSideEncryption": null,
+                        "uploadTimestamp": 0,
+                    })
+                },
+                _ => panic!("Unexpected request to {}", self.url),
+            };
+
+            Response::new(200, HeaderMap::new(), body.to_string().into_bytes())
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl HttpClient for FakeClient {
+        type Error = Error<&'static str>;
+
+        fn get(&mut self, url: impl AsRef<str>)
+        -> Result<&mut Self, ValidationError> {
+            self.url = url.as_ref().to_owned();
+            Ok(self)
+        }
+        fn head(&mut self, url: impl AsRef<str>)
+        -> Result<&mut Self, ValidationError> {
+            self.url = url.as_ref().to_owned();
+            Ok(self)
+        }
+        fn post(&mut self, url: impl AsRef<str>)
+        -> Result<&mut Self, ValidationError> {
+            self.url = url.as_ref().to_owned();
+            Ok(self)
+        }
+        fn put(&mut self, url: impl AsRef<str>)
+        -> Result<&mut Self, ValidationError> {
+            self.url = url.as_ref().to_owned();
+            Ok(self)
+        }
+        fn delete(&mut self, url: impl AsRef<str>)
+        -> Result<&mut Self, ValidationError> {
+            self.url = url.as_ref().to_owned();
+            Ok(self)
+        }
+
+        fn with_header<S: AsRef<str>>(&mut self, name: S, value: S)
+        -> Result<&mut Self, ValidationError> {
+            if name.as_ref() == "Authorization" {
+                self.token = value.as_ref().to_owned();
+            }
+
+            Ok(self)
+        }
+        fn with_body(&mut self, _data: impl Into<Vec<u8>>) -> &mut Self {
+            self
+        }
+        fn with_body_json(&mut self, _body: serde_json::Value) -> &mut Self {
+            self
+        }
+        fn read_body_from_file(&mut self, _path: impl Into<PathBuf>)
+        -> &mut Self { self }
+        fn with_body_reader<R>(&mut self, _reader: R, _content_length: u64)
+        -> &mut Self
+            where R: futures_io::AsyncRead + Send + Sync + Unpin + 'static
+        { self }
+
+        fn user_agent(&mut self, _user_agent_string: impl Into<String>)
+        -> Result<&mut Self, ValidationError> { Ok(self) }
+
+        async fn send(&mut self) -> Result<Response, Self::Error> {
+            if self.url.ends_with("/b2_authorize_account") {
+                // Let concurrent calls run while we authorize.
+                async_std::task::yield_now().await;
+            }
+
+            Ok(self.respond())
+        }
+
+        async fn send_streaming(&mut self)
+        -> Result<StreamingResponse, Self::Error> {
+            Err(Error::NoRequest)
+        }
+    }
+
+    #[async_std::test]
+    async fn methods_reauthorize_expired_token() -> anyhow::Result<()> {
+        let client = FakeClient::default();
+        let session = Session::new(client.clone(), "id", "key").await?;
+
+        client.expire("token-1");
+
+        let buckets = session.list_buckets(ListBuckets::builder().build())
+            .await?;
+
+        assert!(buckets.is_empty());
+        assert_eq!(session.authorization().authorization_token(), "token-2");
+        assert_eq!(client.b2.lock().unwrap().authorizations, 2);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn call_reauthorizes_expired_token() -> anyhow::Result<()> {
+        let client = FakeClient::default();
+        let session = Session::new(client.clone(), "id", "key").await?;
+
+        client.expire("token-1");
+
+        let buckets = session.call(|auth| Box::pin(
+            list_buckets(auth, ListBuckets::builder().build())
+        )).await?;
+
+        assert!(buckets.is_empty());
+        assert_eq!(client.b2.lock().unwrap().authorizations, 2);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn call_does_not_hide_other_errors() -> anyhow::Result<()> {
+        let client = FakeClient::default();
+        let session = Session::new(client.clone(), "id", "key").await?;
+
+        let res: Result<(), _> = session.call(|_| Box::pin(async {
+            Err(Error::NoRequest)
+        })).await;
+
+        assert!(matches!(res, Err(Error::NoRequest)));
+        assert_eq!(client.b2.lock().unwrap().authorizations, 1);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn upload_refreshes_expired_upload_authorization()
+    -> anyhow::Result<()> {
+        let client = FakeClient::default();
+        let session = Session::new(client.clone(), "id", "key").await?;
+
+        let mut upload_auth = get_upload_authorization_by_id(
+            &mut session.authorization(),
+            "bucket"
+        ).await?;
+
+        // Both the upload URL and the account's token have expired; we need to
+        // renew both before the upload can succeed.
+        client.expire("upload-1");
+        client.expire("token-1");
+
+        let upload = UploadFile::builder()
+            .file_name("file.txt")?
+            .sha1_checksum("81fe8bfe87576c3ecb22426f8e57847382917acf")
+            .build()?;
+
+        let file = upload_file(&mut upload_auth, upload, b"abcd").await?;
+        assert_eq!(file.file_name(), "file.txt");
+
+        let b2 = client.b2.lock().unwrap();
+        assert_eq!(b2.authorizations, 2);
+        assert_eq!(b2.upload_urls, 2);
+        assert_eq!(b2.uploads, 1);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn authorization_without_session_is_not_renewed()
+    -> anyhow::Result<()> {
+        let client = FakeClient::default();
+        let mut auth = authorize_account(client.clone(), "id", "key").await?;
+
+        client.expire("token-1");
+
+        let res = get_upload_authorization_by_id(&mut auth, "bucket").await;
+
+        assert!(matches!(res, Err(Error::B2(e))
+            if e.code() == crate::error::ErrorCode::ExpiredAuthToken));
+        assert_eq!(client.b2.lock().unwrap().authorizations, 1);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn session_reauthorizes_at_its_own_url() -> anyhow::Result<()> {
+        let client = FakeClient::default();
+        let session = Session::new_with_url(
+            client.clone(),
+            "https://auth.example.com/",
+            "id",
+            "key"
+        ).await?;
+
+        client.expire("token-1");
+
+        session.list_buckets(ListBuckets::builder().build()).await?;
+
+        let b2 = client.b2.lock().unwrap();
+        assert_eq!(b2.auth_urls, vec![
+            "https://auth.example.com/b2api/v2/b2_authorize_account";
+            2
+        ]);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn clones_share_renewed_authorization() -> anyhow::Result<()> {
+        let client = FakeClient::default();
+        let session = Session::new(client.clone(), "id", "key").await?;
+        let other = session.clone();
+        let mut stale = session.authorization();
+
+        client.expire("token-1");
+
+        for s in [&session, &other] {
+            s.list_buckets(ListBuckets::builder().build()).await?;
+        }
+
+        // A copy obtained before the renewal reuses the new token rather than
+        // authorizing again.
+        stale.reauthorize().await?;
+        assert_eq!(stale.authorization_token(), "token-2");
+        assert_eq!(other.authorization().authorization_token(), "token-2");
+        assert_eq!(client.b2.lock().unwrap().authorizations, 2);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn concurrent_calls_renew_once() -> anyhow::Result<()> {
+        let client = FakeClient::default();
+        let session = Session::new(client.clone(), "id", "key").await?;
+
+        client.expire("token-1");
+
+        let list = || session.list_buckets(ListBuckets::builder().build());
+
+        let (first, second) = futures_util::future::join(list(), list()).await;
+        first?;
+        second?;
+
+        assert_eq!(session.authorization().authorization_token(), "token-2");
+        assert_eq!(client.b2.lock().unwrap().authorizations, 2);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn upload_authorizations_can_be_used_concurrently()
+    -> anyhow::Result<()> {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<Session<FakeClient>>();
+        assert_send_sync::<UploadAuthorization<FakeClient, &'static str>>();
+
+        let client = FakeClient::default();
+        let session = Session::new(client.clone(), "id", "key").await?;
+
+        let mut tasks = Vec::new();
+
+        for _ in 0..2 {
+            let mut upload_auth =
+                session.get_upload_authorization_by_id("bucket").await?;
+
+            tasks.push(async_std::task::spawn(async move {
+                let upload = UploadFile::builder()
+                    .file_name("file.txt")?
+                    .sha1_checksum("81fe8bfe87576c3ecb22426f8e57847382917acf")
+                    .build()?;
+
+                upload_file(&mut upload_auth, upload, b"abcd").await
+                    .map_err(anyhow::Error::from)
+            }));
+        }
+
+        // Sessions can be moved to other tasks and used there too.
+        let shared = session.clone();
+        tasks.push(async_std::task::spawn(async move {
+            let upload = UploadFile::builder()
+                .file_name("file.txt")?
+                .sha1_checksum("81fe8bfe87576c3ecb22426f8e57847382917acf")
+                .build()?;
+
+            shared.upload_file("bucket", upload, b"abcd").await
+                .map_err(anyhow::Error::from)
+        }));
+
+        for task in tasks {
+            task.await?;
+        }
+
+        assert_eq!(client.b2.lock().unwrap().uploads, 3);
+
+        Ok(())
+    }
+}
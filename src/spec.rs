@@ -0,0 +1,757 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+   License, v. 2.0. If a copy of the MPL was not distributed with this
+   file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Declarative bucket configuration.
+//!
+//! A [BucketSpec] describes the desired settings of a bucket, and can be
+//! stored in any format supported by serde. [plan] compares a list of specs
+//! with the account's buckets and returns a [Plan] of the buckets to create or
+//! update, which displays as a human-readable diff; [apply] makes the changes:
+//!
+//! ```no_run
+//! # #[cfg(feature = "with_surf")]
+//! # async fn f() -> anyhow::Result<()> {
+//! use b2_client::{
+//!     client::SurfClient,
+//!     account::authorize_account,
+//!     bucket::{list_buckets, ListBuckets},
+//!     spec::{apply, plan, BucketSpec},
+//! };
+//!
+//! let specs: Vec<BucketSpec> =
+//!     serde_json::from_str(&std::fs::read_to_string("buckets.json")?)?;
+//!
+//! let mut auth = authorize_account(SurfClient::default(), "KEY ID", "KEY")
+//!     .await?;
+//!
+//! let buckets = list_buckets(&mut auth, ListBuckets::builder().build())
+//!     .await?;
+//!
+//! let plan = plan(&specs, &buckets)?;
+//! print!("{}", plan);
+//!
+//! apply(&mut auth, &plan).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Buckets without a spec are left alone. A bucket's default retention and
+//! default encryption are only managed if its spec sets them; a setting that
+//! the authorization cannot read (such as a bucket's encryption without
+//! [ReadBucketEncryption](crate::account::Capability::ReadBucketEncryption))
+//! is planned as a change whenever the spec sets it.
+//!
+//! Each update is only made if the bucket has not been modified since it was
+//! listed. If it has, [apply] stops with an
+//! [ErrorCode::Conflict](crate::error::ErrorCode::Conflict) error; the changes
+//! before it in the plan have already been made, so list the buckets and plan
+//! again.
+
+use std::fmt;
+
+use crate::{
+    account::Authorization,
+    bucket::{
+        create_bucket, update_bucket, Bucket, BucketInfo, BucketType,
+        CorsRule, CreateBucket, FileRetentionPolicy, LifecycleRule,
+        ServerSideEncryption, UpdateBucket,
+    },
+    client::HttpClient,
+    error::{
+        BucketValidationError, Error, LifecycleRuleValidationError,
+        ValidationError,
+    },
+    validate::{validated_bucket_name, validated_lifecycle_rules},
+};
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+
+/// The desired configuration of a bucket.
+///
+/// The bucket is matched with an existing bucket by name. Any setting not
+/// given is left empty; e.g., a spec without CORS rules removes the rules
+/// from an existing bucket. The default retention and default encryption are
+/// the exception: if not given, the bucket's current settings are kept.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BucketSpec {
+    name: String,
+    bucket_type: BucketType,
+    #[serde(default)]
+    cors_rules: Vec<CorsRule>,
+    #[serde(default)]
+    lifecycle_rules: Vec<LifecycleRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_retention: Option<FileRetentionPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_encryption: Option<ServerSideEncryption>,
+    #[serde(default)]
+    info: BucketInfo,
+}
+
+impl BucketSpec {
+    /// Get a builder for a `BucketSpec`.
+    pub fn builder() -> BucketSpecBuilder {
+        BucketSpecBuilder::default()
+    }
+
+    pub fn name(&self) -> &str { &self.name }
+    pub fn bucket_type(&self) -> BucketType { self.bucket_type }
+    pub fn cors_rules(&self) -> &[CorsRule] { &self.cors_rules }
+    pub fn lifecycle_rules(&self) -> &[LifecycleRule] { &self.lifecycle_rules }
+
+    pub fn default_retention(&self) -> Option<FileRetentionPolicy> {
+        self.default_retention
+    }
+
+    pub fn default_encryption(&self) -> Option<&ServerSideEncryption> {
+        self.default_encryption.as_ref()
+    }
+
+    pub fn info(&self) -> &BucketInfo { &self.info }
+
+    /// The desired value of `setting`, or `None` if the spec leaves it
+    /// unmanaged.
+    fn setting(&self, setting: Setting) -> Option<Value> {
+        match setting {
+            Setting::BucketType => Some(json(&self.bucket_type)),
+            Setting::CorsRules => Some(json(&self.cors_rules)),
+            Setting::LifecycleRules => Some(json(&self.lifecycle_rules)),
+            Setting::DefaultRetention =>
+                self.default_retention.map(|policy| json(&policy)),
+            Setting::DefaultEncryption =>
+                self.default_encryption.as_ref().map(json),
+            Setting::Info => Some(json(&self.info)),
+        }
+    }
+
+    /// The retention policy to set, if the spec has one.
+    fn retention(&self) -> Option<FileRetentionPolicy> {
+        self.default_retention.filter(|r| r.mode().is_some())
+    }
+}
+
+/// A builder for a [BucketSpec].
+#[derive(Default)]
+pub struct BucketSpecBuilder {
+    name: Option<String>,
+    bucket_type: Option<BucketType>,
+    cors_rules: Vec<CorsRule>,
+    lifecycle_rules: Vec<LifecycleRule>,
+    default_retention: Option<FileRetentionPolicy>,
+    default_encryption: Option<ServerSideEncryption>,
+    info: BucketInfo,
+}
+
+impl BucketSpecBuilder {
+    /// The name of the bucket.
+    ///
+    /// Bucket names must be globally unique; see
+    /// [CreateBucketBuilder](crate::bucket::CreateBucketBuilder::name) for the
+    /// other requirements.
+    pub fn name(mut self, name: impl Into<String>)
+    -> Result<Self, BucketValidationError> {
+        self.name = Some(validated_bucket_name(name)?);
+        Ok(self)
+    }
+
+    /// The [BucketType] of the bucket, which must be public or private.
+    pub fn bucket_type(mut self, typ: BucketType)
+    -> Result<Self, ValidationError> {
+        if matches!(typ, BucketType::Snapshot) {
+            return Err(ValidationError::OutOfBounds(
+                "Bucket type must be either Public or Private".into()
+            ));
+        }
+
+        self.bucket_type = Some(typ);
+        Ok(self)
+    }
+
+    /// The bucket's CORS rules.
+    pub fn cors_rules(mut self, rules: impl Into<Vec<CorsRule>>)
+    -> Result<Self, ValidationError> {
+        let rules = rules.into();
+
+        if rules.len() > 100 {
+            return Err(ValidationError::OutOfBounds(
+                "A bucket can have no more than 100 CORS rules".into()
+            ));
+        }
+
+        self.cors_rules = rules;
+        Ok(self)
+    }
+
+    /// The bucket's lifecycle rules.
+    pub fn lifecycle_rules(mut self, rules: impl Into<Vec<LifecycleRule>>)
+    -> Result<Self, LifecycleRuleValidationError> {
+        self.lifecycle_rules = validated_lifecycle_rules(rules)?;
+        Ok(self)
+    }
+
+    /// The bucket's default retention policy.
+    ///
+    /// A bucket created with a retention policy has file lock enabled. File
+    /// lock cannot be enabled on an existing bucket.
+    ///
+    /// If not set, an existing bucket's retention policy is left unchanged.
+    pub fn default_retention(mut self, policy: FileRetentionPolicy) -> Self {
+        self.default_retention = Some(policy);
+        self
+    }
+
+    /// The bucket's default server-side encryption.
+    ///
+    /// Only [ServerSideEncryption::B2Managed] and
+    /// [ServerSideEncryption::NoEncryption] can be used as a bucket's default.
+    ///
+    /// If not set, an existing bucket's default encryption is left unchanged.
+    pub fn default_encryption(mut self, settings: ServerSideEncryption)
+    -> Result<Self, ValidationError> {
+        if matches!(settings, ServerSideEncryption::SelfManaged(_)) {
+            return Err(ValidationError::BadFormat(
+                "A bucket cannot default to self-managed encryption".into()
+            ));
+        }
+
+        self.default_encryption = Some(settings);
+        Ok(self)
+    }
+
+    /// The bucket's [BucketInfo].
+    pub fn info(mut self, info: BucketInfo) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Create a [BucketSpec].
+    pub fn build(self) -> Result<BucketSpec, ValidationError> {
+        let name = self.name.ok_or_else(||
+            ValidationError::MissingData("The bucket must have a name".into())
+        )?;
+
+        let bucket_type = self.bucket_type.ok_or_else(||
+            ValidationError::MissingData(
+                "The bucket must have a type set".into()
+            )
+        )?;
+
+        Ok(BucketSpec {
+            name,
+            bucket_type,
+            cors_rules: self.cors_rules,
+            lifecycle_rules: self.lifecycle_rules,
+            default_retention: self.default_retention,
+            default_encryption: self.default_encryption,
+            info: self.info,
+        })
+    }
+}
+
+/// A bucket setting managed by a [BucketSpec].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Setting {
+    BucketType,
+    CorsRules,
+    LifecycleRules,
+    DefaultRetention,
+    DefaultEncryption,
+    Info,
+}
+
+const SETTINGS: [Setting; 6] = [
+    Setting::BucketType,
+    Setting::CorsRules,
+    Setting::LifecycleRules,
+    Setting::DefaultRetention,
+    Setting::DefaultEncryption,
+    Setting::Info,
+];
+
+impl Setting {
+    /// The setting's value on `bucket`, or `None` if it could not be read.
+    fn of(&self, bucket: &Bucket) -> Option<Value> {
+        match self {
+            Self::BucketType => Some(json(&bucket.bucket_type())),
+            Self::CorsRules => Some(json(bucket.cors_rules())),
+            Self::LifecycleRules => Some(json(bucket.lifecycle_rules())),
+            Self::DefaultRetention => bucket.file_lock_configuration()
+                .retention_policy()
+                .map(|policy| json(&policy)),
+            Self::DefaultEncryption => {
+                let encryption = bucket.encryption_info();
+
+                encryption.can_read().then(|| json(
+                    encryption.settings()
+                        .unwrap_or(&ServerSideEncryption::NoEncryption)
+                ))
+            },
+            Self::Info => Some(json(bucket.info())),
+        }
+    }
+
+    /// The setting's value on a newly-created bucket.
+    fn initial(&self) -> Option<Value> {
+        match self {
+            Self::BucketType => None,
+            Self::CorsRules | Self::LifecycleRules =>
+                Some(Value::Array(vec![])),
+            Self::DefaultRetention =>
+                Some(json(&FileRetentionPolicy::default())),
+            Self::DefaultEncryption =>
+                Some(json(&ServerSideEncryption::NoEncryption)),
+            Self::Info => Some(json(&BucketInfo::default())),
+        }
+    }
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BucketType => write!(f, "type"),
+            Self::CorsRules => write!(f, "CORS rules"),
+            Self::LifecycleRules => write!(f, "lifecycle rules"),
+            Self::DefaultRetention => write!(f, "default retention"),
+            Self::DefaultEncryption => write!(f, "default encryption"),
+            Self::Info => write!(f, "bucket info"),
+        }
+    }
+}
+
+/// A setting of an existing bucket that differs from its [BucketSpec].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Difference {
+    setting: Setting,
+    current: Option<Value>,
+    desired: Value,
+}
+
+impl Difference {
+    pub fn setting(&self) -> Setting { self.setting }
+
+    /// The bucket's current value, as JSON.
+    ///
+    /// This is `None` if the authorization could not read the setting.
+    pub fn current(&self) -> Option<&Value> { self.current.as_ref() }
+
+    /// The value in the [BucketSpec], as JSON.
+    pub fn desired(&self) -> &Value { &self.desired }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.current {
+            Some(current) => write!(f, "{}: {} -> {}",
+                self.setting, current, self.desired),
+            None => write!(f, "{}: (unreadable) -> {}",
+                self.setting, self.desired),
+        }
+    }
+}
+
+/// A change to a single bucket in a [Plan].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum BucketChange {
+    /// The bucket does not exist and will be created.
+    Create { spec: BucketSpec },
+    /// The bucket will be updated if it is still at `revision`.
+    #[serde(rename_all = "camelCase")]
+    Update {
+        bucket_id: String,
+        revision: u16,
+        spec: BucketSpec,
+        differences: Vec<Difference>,
+    },
+}
+
+impl BucketChange {
+    pub fn spec(&self) -> &BucketSpec {
+        match self {
+            Self::Create { spec } | Self::Update { spec, .. } => spec,
+        }
+    }
+}
+
+impl fmt::Display for BucketChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Create { spec } => {
+                writeln!(f, "+ create bucket {}", spec.name)?;
+
+                for setting in SETTINGS {
+                    let desired = match spec.setting(setting) {
+                        Some(desired) => desired,
+                        None => continue,
+                    };
+
+                    if setting.initial().as_ref() != Some(&desired) {
+                        writeln!(f, "    {}: {}", setting, desired)?;
+                    }
+                }
+            },
+            Self::Update { revision, spec, differences, .. } => {
+                writeln!(f, "~ update bucket {} (revision {})",
+                    spec.name, revision)?;
+
+                for difference in differences {
+                    writeln!(f, "    {}", difference)?;
+                }
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// The changes needed to make an account's buckets match their specs.
+///
+/// Create a `Plan` with [plan] and make the changes with [apply].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Plan {
+    changes: Vec<BucketChange>,
+}
+
+impl Plan {
+    pub fn changes(&self) -> &[BucketChange] { &self.changes }
+
+    /// Returns `true` if every bucket matches its spec.
+    pub fn is_empty(&self) -> bool { self.changes.is_empty() }
+}
+
+impl fmt::Display for Plan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            return writeln!(f, "No changes");
+        }
+
+        for change in &self.changes {
+            write!(f, "{}", change)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Compare bucket specs with the account's current buckets.
+///
+/// `buckets` is typically the result of
+/// [list_buckets](crate::bucket::list_buckets). This does not contact the B2
+/// service.
+///
+/// A default retention or default encryption that the spec does not set is
+/// not compared. One that it sets but the authorization cannot read is always
+/// planned as a change.
+///
+/// # Errors
+///
+/// Returns [ValidationError::Incompatible] if more than one spec has the same
+/// name.
+pub fn plan(specs: &[BucketSpec], buckets: &[Bucket])
+-> Result<Plan, ValidationError> {
+    let mut changes = vec![];
+
+    for (i, spec) in specs.iter().enumerate() {
+        if specs[..i].iter().any(|s| s.name == spec.name) {
+            return Err(ValidationError::Incompatible(format!(
+                "Bucket {} has more than one spec", spec.name
+            )));
+        }
+
+        let bucket = match buckets.iter().find(|b| b.name() == spec.name) {
+            Some(bucket) => bucket,
+            None => {
+                changes.push(BucketChange::Create { spec: spec.clone() });
+                continue;
+            },
+        };
+
+        let differences: Vec<_> = SETTINGS.iter()
+            .filter_map(|setting| {
+                let desired = spec.setting(*setting)?;
+                let current = setting.of(bucket);
+
+                (current.as_ref() != Some(&desired)).then_some(Difference {
+                    setting: *setting,
+                    current,
+                    desired,
+                })
+            })
+            .collect();
+
+        if ! differences.is_empty() {
+            changes.push(BucketChange::Update {
+                bucket_id: bucket.bucket_id().to_owned(),
+                revision: bucket.revision(),
+                spec: spec.clone(),
+                differences,
+            });
+        }
+    }
+
+    Ok(Plan { changes })
+}
+
+/// Make the changes in a [Plan].
+///
+/// Returns the created and updated buckets in the order of the plan. Changes
+/// are made one bucket at a time; if a change fails, the changes before it
+/// have been made and the rest are not attempted.
+///
+/// Creating a bucket with a default retention policy takes two requests: the
+/// bucket is created with file lock enabled, then its retention policy is set.
+pub async fn apply<C, E>(auth: &mut Authorization<C>, plan: &Plan)
+-> Result<Vec<Bucket>, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    let mut buckets = Vec::with_capacity(plan.changes.len());
+
+    for change in &plan.changes {
+        let bucket = match change {
+            BucketChange::Create { spec } => create(auth, spec).await?,
+            BucketChange::Update { bucket_id, revision, spec, differences } =>
+                update(auth, bucket_id, *revision, spec, differences).await?,
+        };
+
+        buckets.push(bucket);
+    }
+
+    Ok(buckets)
+}
+
+async fn create<C, E>(auth: &mut Authorization<C>, spec: &BucketSpec)
+-> Result<Bucket, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    let mut req = CreateBucket::builder()
+        .name(spec.name.as_str()).map_err(invalid)?
+        .bucket_type(spec.bucket_type)?
+        .cors_rules(spec.cors_rules.clone())?
+        .lifecycle_rules(spec.lifecycle_rules.clone()).map_err(invalid)?;
+
+    if let Some(settings) = &spec.default_encryption {
+        req = req.encryption_settings(settings.clone());
+    }
+    if ! spec.info.is_empty() {
+        req = req.bucket_info(spec.info.clone());
+    }
+    if spec.retention().is_some() {
+        req = req.with_file_lock();
+    }
+
+    let bucket = create_bucket(auth, req.build()?).await?;
+
+    match spec.retention() {
+        Some(policy) => update_bucket(auth, UpdateBucket::builder()
+            .bucket_id(bucket.bucket_id())
+            .retention_policy(policy)
+            .if_revision_is(bucket.revision())
+            .build()?
+        ).await,
+        None => Ok(bucket),
+    }
+}
+
+async fn update<C, E>(
+    auth: &mut Authorization<C>,
+    bucket_id: &str,
+    revision: u16,
+    spec: &BucketSpec,
+    differences: &[Difference],
+) -> Result<Bucket, Error<E>>
+    where C: HttpClient<Error=Error<E>>,
+          E: fmt::Debug + fmt::Display,
+{
+    let mut req = UpdateBucket::builder()
+        .bucket_id(bucket_id)
+        .if_revision_is(revision);
+
+    for difference in differences {
+        req = match difference.setting {
+            Setting::BucketType => req.bucket_type(spec.bucket_type)?,
+            Setting::CorsRules => req.cors_rules(spec.cors_rules.clone())?,
+            Setting::LifecycleRules => req
+                .lifecycle_rules(spec.lifecycle_rules.clone())
+                .map_err(invalid)?,
+            // Only planned when the spec sets them, but a deserialized plan
+            // may have been edited.
+            Setting::DefaultRetention => match spec.default_retention {
+                Some(policy) => req.retention_policy(policy),
+                None => req,
+            },
+            Setting::DefaultEncryption => match &spec.default_encryption {
+                Some(settings) => req.encryption_settings(settings.clone()),
+                None => req,
+            },
+            Setting::Info => req.bucket_info(spec.info.clone()),
+        };
+    }
+
+    update_bucket(auth, req.build()?).await
+}
+
+fn json(value: &(impl Serialize + ?Sized)) -> Value {
+    serde_json::to_value(value).expect("Bucket settings are valid JSON")
+}
+
+/// Specs that were deserialized rather than built may fail the request
+/// builders' validation.
+fn invalid(e: impl fmt::Display) -> ValidationError {
+    ValidationError::BadFormat(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bucket::EncryptionAlgorithm;
+    use serde_json::{from_value, json};
+
+
+    fn bucket_json(name: &str, bucket_type: &str, revision: u16) -> Value {
+        json!({
+            "accountId": "account",
+            "bucketId": format!("{}-id", name),
+            "bucketName": name,
+            "bucketType": bucket_type,
+            "bucketInfo": {},
+            "corsRules": [],
+            "fileLockConfiguration": {
+                "isClientAuthorizedToRead": true,
+                "value": {
+                    "defaultRetention": { "mode": null },
+                    "isFileLockEnabled": false,
+                },
+            },
+            "defaultServerSideEncryption": {
+                "isClientAuthorizedToRead": true,
+                "value": { "mode": null },
+            },
+            "lifecycleRules": [],
+            "revision": revision,
+            "options": [],
+        })
+    }
+
+    #[test]
+    fn plan_creates_and_updates_buckets() -> anyhow::Result<()> {
+        let specs = [
+            BucketSpec::builder()
+                .name("new-bucket")?
+                .bucket_type(BucketType::Private)?
+                .default_encryption(ServerSideEncryption::B2Managed(
+                    EncryptionAlgorithm::Aes256
+                ))?
+                .build()?,
+            BucketSpec::builder()
+                .name("public-bucket")?
+                .bucket_type(BucketType::Public)?
+                .default_encryption(ServerSideEncryption::NoEncryption)?
+                .build()?,
+            BucketSpec::builder()
+                .name("unchanged-bucket")?
+                .bucket_type(BucketType::Private)?
+                .build()?,
+        ];
+
+        // The encryption of this bucket cannot be read.
+        let mut public = bucket_json("public-bucket", "allPrivate", 3);
+        public["defaultServerSideEncryption"] = json!({
+            "isClientAuthorizedToRead": false,
+            "value": null,
+        });
+
+        // Unreadable settings that the spec does not set are not compared.
+        let mut unchanged = bucket_json("unchanged-bucket", "allPrivate", 1);
+        unchanged["defaultServerSideEncryption"] = json!({
+            "isClientAuthorizedToRead": false,
+            "value": null,
+        });
+        unchanged["fileLockConfiguration"] = json!({
+            "isClientAuthorizedToRead": false,
+            "value": null,
+        });
+
+        let buckets: Vec<Bucket> = from_value(json!([
+            public,
+            unchanged,
+            bucket_json("unmanaged-bucket", "allPublic", 1),
+        ]))?;
+
+        let plan = plan(&specs, &buckets)?;
+        assert_eq!(plan.changes().len(), 2);
+
+        assert!(matches!(&plan.changes()[0],
+            BucketChange::Create { spec } if spec.name() == "new-bucket"
+        ));
+
+        match &plan.changes()[1] {
+            BucketChange::Update { bucket_id, revision, differences, .. } => {
+                assert_eq!(bucket_id, "public-bucket-id");
+                assert_eq!(*revision, 3);
+
+                let settings: Vec<_> = differences.iter()
+                    .map(Difference::setting)
+                    .collect();
+                assert_eq!(settings,
+                    [Setting::BucketType, Setting::DefaultEncryption]);
+            },
+            change => panic!("Expected an update, got {:?}", change),
+        }
+
+        assert_eq!(plan.to_string(), concat!(
+            "+ create bucket new-bucket\n",
+            "    type: \"allPrivate\"\n",
+            "    default encryption: {\"algorithm\":\"AES256\",",
+            "\"mode\":\"SSE-B2\"}\n",
+            "~ update bucket public-bucket (revision 3)\n",
+            "    type: \"allPrivate\" -> \"allPublic\"\n",
+            "    default encryption: (unreadable) -> {\"mode\":null}\n",
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn plan_rejects_duplicate_specs() -> anyhow::Result<()> {
+        let spec = BucketSpec::builder()
+            .name("my-bucket")?
+            .bucket_type(BucketType::Private)?
+            .build()?;
+
+        assert!(plan(&[spec.clone(), spec], &[]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bucket_spec_from_json() -> anyhow::Result<()> {
+        let spec: BucketSpec = from_value(json!({
+            "name": "my-bucket",
+            "bucketType": "allPublic",
+            "info": { "owner": "storage-team" },
+        }))?;
+
+        assert_eq!(spec.name(), "my-bucket");
+        assert!(spec.cors_rules().is_empty());
+        assert_eq!(spec.default_encryption(), None);
+        assert!(spec.default_retention().is_none());
+        assert_eq!(spec.info().get("owner"), Some("storage-team"));
+
+        let bucket = from_value(bucket_json("my-bucket", "allPublic", 1))?;
+        let plan = plan(&[spec], &[bucket])?;
+        let text = plan.to_string();
+        assert!(! text.contains("type"), "{}", text);
+        assert!(
+            text.contains(r#"bucket info: {} -> {"owner":"storage-team"}"#)
+        );
+
+        Ok(())
+    }
+}
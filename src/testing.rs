@@ -0,0 +1,3877 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+   License, v. 2.0. If a copy of the MPL was not distributed with this
+   file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! An emulation of the B2 service for tests.
+//!
+//! [FakeB2] answers API requests the way the B2 service does, without network
+//! access or a B2 account. It keeps buckets, file versions and hide markers,
+//! unfinished large files and their parts, and application keys in memory or,
+//! if created with [FakeB2::open], in a directory on disk. The
+//! capabilities and bucket and file name restrictions of the key behind each
+//! request are enforced, and invalid requests are rejected with the status and
+//! error code B2 would return.
+//!
+//! A [FakeClient] is the [HttpClient] that sends requests to a `FakeB2`. Every
+//! client obtained from [FakeB2::client] (and every clone of those clients)
+//! shares the same data, so a test can inspect the service's state through the
+//! API after the code under test has run.
+//!
+//! Each `FakeB2` starts with a master key that has every capability:
+//!
+//! ```
+//! # fn main() -> anyhow::Result<()> {
+//! use b2_client::{self as b2, blocking::block_on, testing::FakeB2};
+//!
+//! let b2 = FakeB2::new();
+//! let (key_id, key) = b2.master_key();
+//!
+//! block_on(async {
+//!     let mut auth = b2::authorize_account(b2.client(), &key_id, &key).await?;
+//!
+//!     let bucket = b2::create_bucket(&mut auth, b2::CreateBucket::builder()
+//!         .name("my-bucket")?
+//!         .bucket_type(b2::BucketType::Private)?
+//!         .build()?
+//!     ).await?;
+//!
+//!     let mut upload_auth = b2::get_upload_authorization(&mut auth, &bucket)
+//!         .await?;
+//!
+//!     let upload = b2::UploadFile::builder()
+//!         .file_name("my-file.txt")?
+//!         .sha1_checksum("81fe8bfe87576c3ecb22426f8e57847382917acf")
+//!         .build()?;
+//!
+//!     let file = b2::upload_file(&mut upload_auth, upload, b"abcd").await?;
+//!     assert_eq!(file.content_length(), Some(4));
+//!
+//!     Ok(())
+//! })
+//! # }
+//! ```
+//!
+//! # Serving over HTTP
+//!
+//! [FakeB2::serve] answers HTTP requests on a local socket so that programs
+//! using any [HttpClient] can talk to the emulated service. The `b2-emulator`
+//! binary, built with the `testing` feature, serves a directory-backed
+//! `FakeB2`. Pass the URL it prints to
+//! [authorize_account_with_url](crate::account::authorize_account_with_url).
+//! A program built with the `testing` feature can instead set the
+//! `B2_CLIENT_AUTH_URL` environment variable to it so that
+//! [authorize_account](crate::account::authorize_account) signs in to the
+//! emulator instead of the B2 service.
+//!
+//! # Limitations
+//!
+//! * Server-side encryption settings are stored and reported but the data is
+//!   not encrypted, and customer-managed keys are not checked on download.
+//! * Lifecycle rules, CORS rules, and bucket default retention settings are
+//!   stored but never applied.
+//! * Downloads ignore the `b2Content*` overrides of their response headers.
+//! * Authorization tokens do not expire on their own; use
+//!   [FakeB2::expire_authorizations] to test renewing them.
+//! * [FakeB2::serve] speaks plain HTTP/1.1 only; there is no TLS.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    convert::Infallible,
+    fmt, fs,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::{
+    account::{ApiVersion, Capability},
+    client::{HeaderMap, HttpClient, Response, StreamingResponse},
+    error::{Error, ValidationError},
+    retry::BufferedBody,
+};
+
+use futures_io::AsyncRead;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+
+const API_URL: &str = "https://api.b2.test";
+const DOWNLOAD_URL: &str = "https://f000.b2.test";
+const UPLOAD_URL: &str = "https://pod-000.b2.test";
+const S3_API_URL: &str = "https://s3.b2.test";
+
+// The name of the file in a FakeB2's directory that holds everything except
+// file contents.
+const STATE_FILE: &str = "state.json";
+
+const ACCOUNT_ID: &str = "0f0000000000";
+const MASTER_KEY: &str = "K000mastermastermastermastermas";
+
+const RECOMMENDED_PART_SIZE: u64 = 100_000_000;
+const MINIMUM_PART_SIZE: u64 = 5_000_000;
+
+const ALL_CAPABILITIES: [Capability; 26] = [
+    Capability::ListKeys,
+    Capability::WriteKeys,
+    Capability::DeleteKeys,
+    Capability::ListAllBucketNames,
+    Capability::ListBuckets,
+    Capability::ReadBuckets,
+    Capability::WriteBuckets,
+    Capability::DeleteBuckets,
+    Capability::ReadBucketRetentions,
+    Capability::WriteBucketRetentions,
+    Capability::ReadBucketEncryption,
+    Capability::WriteBucketEncryption,
+    Capability::ListFiles,
+    Capability::ReadFiles,
+    Capability::ShareFiles,
+    Capability::WriteFiles,
+    Capability::DeleteFiles,
+    Capability::ReadFileLegalHolds,
+    Capability::WriteFileLegalHolds,
+    Capability::ReadFileRetentions,
+    Capability::WriteFileRetentions,
+    Capability::BypassGovernance,
+    Capability::ReadBucketReplications,
+    Capability::WriteBucketReplications,
+    Capability::ReadBucketNotifications,
+    Capability::WriteBucketNotifications,
+];
+
+/// An emulated B2 service.
+///
+/// Cloning a `FakeB2` returns another handle to the same service.
+#[derive(Debug, Clone)]
+pub struct FakeB2 {
+    state: Arc<Mutex<State>>,
+}
+
+impl Default for FakeB2 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeB2 {
+    /// Create an empty in-memory service with a master application key.
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(State::new(Storage::default()))),
+        }
+    }
+
+    /// Open a service that stores its data in the given directory.
+    ///
+    /// The directory is created if necessary. If it holds the data of a
+    /// previous service, its keys, buckets, files, and authorization tokens
+    /// are restored; otherwise the service starts empty, as with
+    /// [new](Self::new).
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(dir.join("data"))?;
+
+        let state = match fs::read(dir.join(STATE_FILE)) {
+            Ok(json) => {
+                let mut state: State = serde_json::from_slice(&json)?;
+                state.storage = Storage::Directory(dir);
+                state
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound =>
+                State::new(Storage::Directory(dir)),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    /// Set the smallest size in bytes allowed for each part of a large file
+    /// except the last.
+    ///
+    /// B2 requires at least 5 MB; tests can use a smaller size to keep large
+    /// file uploads small.
+    pub fn with_minimum_part_size(self, size: u64) -> Self {
+        self.state.lock().unwrap().minimum_part_size = size;
+        self
+    }
+
+    /// Get the key ID and key of the account's master application key.
+    pub fn master_key(&self) -> (String, String) {
+        (ACCOUNT_ID.to_owned(), MASTER_KEY.to_owned())
+    }
+
+    /// Create a new [FakeClient] that sends its requests to this service.
+    pub fn client(&self) -> FakeClient {
+        FakeClient {
+            b2: self.clone(),
+            req: None,
+        }
+    }
+
+    /// Expire every authorization token issued so far.
+    ///
+    /// Subsequent requests that use one of those tokens are rejected with
+    /// [ExpiredAuthToken](crate::error::ErrorCode::ExpiredAuthToken).
+    pub fn expire_authorizations(&self) {
+        let mut state = self.state.lock().unwrap();
+        let tokens: Vec<_> = state.tokens.drain().map(|(t, _)| t).collect();
+
+        state.expired.extend(tokens);
+    }
+
+    /// Reject the next request sent to the service with the given HTTP status
+    /// and B2 error code, regardless of its content.
+    ///
+    /// Failures are queued; calling this twice fails the next two requests.
+    pub fn fail_next_request(&self, status: u16, code: impl Into<String>) {
+        self.state.lock().unwrap().failures.push_back((status, code.into()));
+    }
+
+    /// Serve the B2 API over HTTP to connections accepted by `listener`.
+    ///
+    /// The URLs given to clients (such as the API and download URLs returned
+    /// by `b2_authorize_account`) point to the listener's local address. Each
+    /// connection is handled on its own thread; this function only returns if
+    /// accepting a connection fails.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        let url = format!("http://{}", listener.local_addr()?);
+        self.state.lock().unwrap().urls = Urls::from_base(&url);
+
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let b2 = self.clone();
+
+            // Errors only affect that connection, which is dropped.
+            thread::spawn(move || b2.serve_connection(stream));
+        }
+
+        Ok(())
+    }
+
+    /// Handle HTTP requests on a connection until the client closes it.
+    fn serve_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        let mut reader = BufReader::new(stream);
+
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line)? == 0 { return Ok(()); }
+
+            let mut parts = line.split_whitespace();
+            let method = parts.next().unwrap_or_default().to_owned();
+            let target = parts.next().unwrap_or_default().to_owned();
+
+            let mut headers = Vec::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line)?;
+
+                let line = line.trim_end();
+                if line.is_empty() { break; }
+
+                if let Some((name, value)) = line.split_once(':') {
+                    headers.push((name.to_owned(), value.trim().to_owned()));
+                }
+            }
+
+            let req = Request {
+                method: match method.as_str() {
+                    "GET" => Method::Get,
+                    "HEAD" => Method::Head,
+                    "PUT" => Method::Put,
+                    "DELETE" => Method::Delete,
+                    _ => Method::Post,
+                },
+                url: url::Url::parse("http://localhost")
+                    .and_then(|base| base.join(&target))
+                    .map_err(|e|
+                        io::Error::new(io::ErrorKind::InvalidData, e)
+                    )?,
+                headers,
+                body: None,
+            };
+
+            if req.header_is("Expect", "100-continue") {
+                writer.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+            }
+
+            let body = read_body(&mut reader, &req)?;
+
+            let res = if matches!(method.as_str(), "GET" | "HEAD" | "POST") {
+                self.respond(&req, &body)
+            } else {
+                json_response(405, json!({
+                    "status": 405,
+                    "code": "method_not_allowed",
+                    "message": format!("Unsupported method: {}", method),
+                }))
+            };
+
+            let (status, headers, body) = res.into_parts();
+            let mut out = format!("HTTP/1.1 {} {}\r\n", status,
+                if status < 400 { "OK" } else { "Error" });
+
+            for (name, value) in headers.iter() {
+                if name != "content-length" {
+                    out.push_str(&format!("{}: {}\r\n", name, value));
+                }
+            }
+
+            // Responses to HEAD requests give the length of the body that a
+            // GET would have returned.
+            let len = match req.method {
+                Method::Head => headers.get("content-length").cloned()
+                    .unwrap_or_else(|| "0".into()),
+                _ => body.len().to_string(),
+            };
+
+            out.push_str(&format!("content-length: {}\r\n\r\n", len));
+            writer.write_all(out.as_bytes())?;
+
+            if req.method != Method::Head {
+                writer.write_all(&body)?;
+            }
+
+            if req.header_is("Connection", "close") { return Ok(()); }
+        }
+    }
+
+    fn respond(&self, req: &Request, body: &[u8]) -> Response {
+        self.state.lock().unwrap().respond(req, body)
+    }
+}
+
+/// Read the body of an HTTP request, which may be sent with a length or in
+/// chunks.
+fn read_body(reader: &mut impl BufRead, req: &Request) -> io::Result<Vec<u8>> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    if ! req.header_is("Transfer-Encoding", "chunked") {
+        let len = match req.header("Content-Length") {
+            Some(len) => len.parse()
+                .map_err(|_| invalid("Invalid Content-Length"))?,
+            None => 0,
+        };
+
+        let mut body = vec![0; len];
+        reader.read_exact(&mut body)?;
+        return Ok(body);
+    }
+
+    let mut body = Vec::new();
+
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+
+        let size = line.trim_end().split(';').next().unwrap_or_default();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| invalid("Invalid chunk size"))?;
+
+        if size == 0 {
+            // Skip any trailers.
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line)?;
+                if line.trim_end().is_empty() { break; }
+            }
+
+            return Ok(body);
+        }
+
+        let start = body.len();
+        body.resize(start + size, 0);
+        reader.read_exact(&mut body[start..])?;
+
+        let mut crlf = [0; 2];
+        reader.read_exact(&mut crlf)?;
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+}
+
+enum Body {
+    Bytes(Vec<u8>),
+    File(PathBuf),
+    Reader(Box<dyn AsyncRead + Send + Sync + Unpin>),
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bytes(b) => f.debug_tuple("Bytes").field(b).finish(),
+            Self::File(p) => f.debug_tuple("File").field(p).finish(),
+            Self::Reader(_) => f.debug_struct("Reader").finish_non_exhaustive(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Request {
+    method: Method,
+    url: url::Url,
+    headers: Vec<(String, String)>,
+    body: Option<Body>,
+}
+
+impl Request {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Check whether a header has the given value, ignoring case.
+    fn header_is(&self, name: &str, value: &str) -> bool {
+        matches!(self.header(name), Some(v) if v.eq_ignore_ascii_case(value))
+    }
+
+    fn query(&self, name: &str) -> Option<String> {
+        self.url.query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    }
+}
+
+/// An [HttpClient] that sends its requests to a [FakeB2].
+///
+/// A `FakeClient` created with `default()` uses a new, empty service; use
+/// [FakeB2::client] to share one.
+#[derive(Debug, Default)]
+pub struct FakeClient {
+    b2: FakeB2,
+    req: Option<Request>,
+}
+
+impl Clone for FakeClient {
+    /// Clone the client.
+    ///
+    /// The clone sends requests to the same service. Any request that has been
+    /// created but not yet sent is not cloned.
+    fn clone(&self) -> Self {
+        self.b2.client()
+    }
+}
+
+impl FakeClient {
+    /// Get the service that this client sends its requests to.
+    pub fn service(&self) -> &FakeB2 { &self.b2 }
+
+    fn new_request(&mut self, method: Method, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        self.req = Some(Request {
+            method,
+            url: url::Url::parse(url.as_ref())?,
+            headers: Vec::new(),
+            body: None,
+        });
+
+        Ok(self)
+    }
+
+    fn set_body(&mut self, body: Body) -> &mut Self {
+        if let Some(req) = &mut self.req {
+            req.body = Some(body);
+        }
+
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl HttpClient for FakeClient {
+    type Error = Error<Infallible>;
+
+    fn get(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        self.new_request(Method::Get, url)
+    }
+
+    fn head(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        self.new_request(Method::Head, url)
+    }
+
+    fn post(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        self.new_request(Method::Post, url)
+    }
+
+    fn put(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        self.new_request(Method::Put, url)
+    }
+
+    fn delete(&mut self, url: impl AsRef<str>)
+    -> Result<&mut Self, ValidationError> {
+        self.new_request(Method::Delete, url)
+    }
+
+    fn with_header<S: AsRef<str>>(&mut self, name: S, value: S)
+    -> Result<&mut Self, ValidationError> {
+        if let Some(req) = &mut self.req {
+            req.headers.push(
+                (name.as_ref().to_owned(), value.as_ref().to_owned())
+            );
+        }
+
+        Ok(self)
+    }
+
+    fn with_body(&mut self, data: impl Into<Vec<u8>>) -> &mut Self {
+        self.set_body(Body::Bytes(data.into()))
+    }
+
+    fn with_body_json(&mut self, body: serde_json::Value) -> &mut Self {
+        self.set_body(Body::Bytes(body.to_string().into_bytes()))
+    }
+
+    fn read_body_from_file(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.set_body(Body::File(path.into()))
+    }
+
+    fn with_body_reader<R>(&mut self, reader: R, _content_length: u64)
+    -> &mut Self
+        where R: AsyncRead + Send + Sync + Unpin + 'static
+    {
+        self.set_body(Body::Reader(Box::new(reader)))
+    }
+
+    fn user_agent(&mut self, _user_agent_string: impl Into<String>)
+    -> Result<&mut Self, ValidationError> {
+        Ok(self)
+    }
+
+    async fn send(&mut self) -> Result<Response, Self::Error> {
+        let mut req = self.req.take().ok_or(Error::NoRequest)?;
+
+        let body = match req.body.take() {
+            Some(Body::Bytes(bytes)) => bytes,
+            Some(Body::File(path)) => std::fs::read(path)?,
+            Some(Body::Reader(mut reader)) => {
+                let mut body = Vec::new();
+                let mut buf = [0; 8 * 1024];
+
+                loop {
+                    let len = std::future::poll_fn(|cx|
+                        Pin::new(&mut reader).poll_read(cx, &mut buf)
+                    ).await?;
+
+                    if len == 0 { break; }
+                    body.extend_from_slice(&buf[..len]);
+                }
+
+                body
+            },
+            None => Vec::new(),
+        };
+
+        Ok(self.b2.respond(&req, &body))
+    }
+
+    async fn send_streaming(&mut self)
+    -> Result<StreamingResponse, Self::Error> {
+        let (status, headers, body) = self.send().await?.into_parts();
+
+        Ok(StreamingResponse::new(status, headers, BufferedBody::new(body)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Key {
+    name: String,
+    secret: String,
+    capabilities: Vec<Capability>,
+    #[serde(default)]
+    bucket_ids: Vec<String>,
+    name_prefix: Option<String>,
+    // Milliseconds since the epoch.
+    expires: Option<i64>,
+}
+
+impl Key {
+    fn has_capability(&self, cap: Capability) -> bool {
+        self.capabilities.contains(&cap)
+    }
+
+    fn allows_bucket(&self, bucket_id: &str) -> bool {
+        self.bucket_ids.is_empty()
+            || self.bucket_ids.iter().any(|id| id == bucket_id)
+    }
+
+    fn to_json(&self, id: &str, version: ApiVersion) -> Value {
+        // Key deserializes its expiration as a date string.
+        let expires = self.expires.and_then(|ms|
+            chrono::TimeZone::timestamp_millis_opt(&chrono::Utc, ms).single()
+        ).map(|t| t.to_rfc3339());
+
+        let mut key = json!({
+            "keyName": self.name,
+            "applicationKeyId": id,
+            "capabilities": self.capabilities,
+            "accountId": ACCOUNT_ID,
+            "expirationTimestamp": expires,
+            "namePrefix": self.name_prefix,
+            "options": ["s3"],
+        });
+
+        match version {
+            ApiVersion::V2 => key["bucketId"] = json!(self.bucket_ids.first()),
+            ApiVersion::V3 => key["bucketIds"] = match self.bucket_ids.len() {
+                0 => Value::Null,
+                _ => json!(self.bucket_ids),
+            },
+        }
+
+        key
+    }
+}
+
+/// What an authorization token grants access to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Grant {
+    Account { key_id: String },
+    Upload { key_id: String, bucket_id: String },
+    UploadPart { key_id: String, file_id: String },
+    Download { bucket_id: String, prefix: String, expires: i64 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Bucket {
+    id: String,
+    name: String,
+    bucket_type: String,
+    info: Value,
+    cors_rules: Value,
+    lifecycle_rules: Value,
+    encryption: Value,
+    default_retention: Value,
+    file_lock_enabled: bool,
+    #[serde(default)]
+    replication: Value,
+    #[serde(default)]
+    notification_rules: Vec<Value>,
+    revision: u16,
+}
+
+impl Bucket {
+    fn to_json(&self, key: &Key) -> Value {
+        let retention = if key.has_capability(Capability::ReadBucketRetentions)
+        {
+            json!({
+                "isClientAuthorizedToRead": true,
+                "value": {
+                    "defaultRetention": self.default_retention,
+                    "isFileLockEnabled": self.file_lock_enabled,
+                },
+            })
+        } else {
+            json!({ "isClientAuthorizedToRead": false, "value": null })
+        };
+
+        let encryption = if key.has_capability(Capability::ReadBucketEncryption)
+        {
+            json!({
+                "isClientAuthorizedToRead": true,
+                "value": self.encryption,
+            })
+        } else {
+            json!({ "isClientAuthorizedToRead": false, "value": null })
+        };
+
+        let can_read = key.has_capability(Capability::ReadBucketReplications);
+
+        let replication = if can_read {
+            let value = match &self.replication {
+                Value::Null => json!({}),
+                value => value.clone(),
+            };
+
+            json!({ "isClientAuthorizedToRead": true, "value": value })
+        } else {
+            json!({ "isClientAuthorizedToRead": false, "value": null })
+        };
+
+        json!({
+            "accountId": ACCOUNT_ID,
+            "bucketId": self.id,
+            "bucketName": self.name,
+            "bucketType": self.bucket_type,
+            "bucketInfo": self.info,
+            "corsRules": self.cors_rules,
+            "fileLockConfiguration": retention,
+            "defaultServerSideEncryption": encryption,
+            "lifecycleRules": self.lifecycle_rules,
+            "replicationConfiguration": replication,
+            "revision": self.revision,
+            "options": ["s3"],
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Action {
+    Start,
+    Upload,
+    Copy,
+    Hide,
+}
+
+impl Action {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Start => "start",
+            Self::Upload => "upload",
+            Self::Copy => "copy",
+            Self::Hide => "hide",
+        }
+    }
+
+    fn has_content(self) -> bool {
+        matches!(self, Self::Upload | Self::Copy)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Part {
+    content_length: u64,
+    sha1: String,
+    md5: String,
+    timestamp: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct FileVersion {
+    id: String,
+    bucket_id: String,
+    name: String,
+    action: Action,
+    content_type: String,
+    content_length: u64,
+    // "none" for large files and hide markers.
+    sha1: String,
+    // Only set for files uploaded in a single request.
+    md5: Option<String>,
+    info: Value,
+    timestamp: i64,
+    legal_hold: Option<String>,
+    retention: Value,
+    encryption: Value,
+    // Only used while a large file is unfinished.
+    parts: BTreeMap<u16, Part>,
+}
+
+impl FileVersion {
+    fn to_json(&self, key: &Key) -> Value {
+        let legal_hold = if key.has_capability(Capability::ReadFileLegalHolds) {
+            json!({
+                "isClientAuthorizedToRead": true,
+                "value": self.legal_hold,
+            })
+        } else {
+            json!({ "isClientAuthorizedToRead": false, "value": null })
+        };
+
+        let retention = if key.has_capability(Capability::ReadFileRetentions) {
+            json!({ "isClientAuthorizedToRead": true, "value": self.retention })
+        } else {
+            json!({
+                "isClientAuthorizedToRead": false,
+                "value": { "mode": null, "retainUntilTimestamp": null },
+            })
+        };
+
+        json!({
+            "accountId": ACCOUNT_ID,
+            "action": self.action.as_str(),
+            "bucketId": self.bucket_id,
+            "contentLength": self.content_length,
+            "contentMd5": self.md5,
+            "contentSha1": self.sha1,
+            "contentType": self.content_type,
+            "fileId": self.id,
+            "fileInfo": self.info,
+            "fileName": self.name,
+            "fileRetention": retention,
+            "legalHold": legal_hold,
+            "serverSideEncryption": self.encryption,
+            "uploadTimestamp": self.timestamp,
+        })
+    }
+
+    /// Returns `true` if the file's retention settings forbid deleting it or
+    /// shortening its retention period.
+    ///
+    /// Governance mode can be bypassed by a key with
+    /// [Capability::BypassGovernance] if the request asks to.
+    fn is_retained(&self, now: i64, bypass: bool) -> bool {
+        let until = self.retention["retainUntilTimestamp"].as_i64();
+
+        match (self.retention["mode"].as_str(), until) {
+            (Some("compliance"), Some(t)) => t > now,
+            (Some("governance"), Some(t)) => t > now && ! bypass,
+            _ => false,
+        }
+    }
+}
+
+/// An error response from the service.
+#[derive(Debug)]
+struct Failure {
+    status: u16,
+    code: String,
+    message: String,
+}
+
+impl From<io::Error> for Failure {
+    fn from(e: io::Error) -> Self {
+        Self {
+            status: 500,
+            code: "internal_error".into(),
+            message: e.to_string(),
+        }
+    }
+}
+
+fn fail<T>(status: u16, code: &str, message: impl Into<String>)
+-> Result<T, Failure> {
+    Err(Failure {
+        status,
+        code: code.to_owned(),
+        message: message.into(),
+    })
+}
+
+fn unauthorized<T>() -> Result<T, Failure> {
+    fail(401, "unauthorized", "The key does not allow that operation")
+}
+
+/// Get a required string field from a JSON request.
+fn required<'a>(body: &'a Value, field: &str) -> Result<&'a str, Failure> {
+    match body[field].as_str() {
+        Some(s) => Ok(s),
+        None => fail(400, "bad_request", format!("Missing {}", field)),
+    }
+}
+
+fn json_response(status: u16, body: Value) -> Response {
+    let mut headers = HeaderMap::new();
+    headers.insert("content-type".into(), "application/json".into());
+
+    Response::new(status, headers, body.to_string().into_bytes())
+}
+
+fn decode(s: &str) -> String {
+    percent_encoding::percent_decode_str(s).decode_utf8_lossy().into_owned()
+}
+
+fn sha1_hex(data: &[u8]) -> String {
+    sha1_smol::Sha1::from(data).digest().to_string()
+}
+
+fn md5_hex(data: &[u8]) -> String {
+    format!("{:x}", md5::compute(data))
+}
+
+/// Parse an inclusive `bytes=start-end` range, clamping it to `len`.
+fn parse_range(range: &str, len: usize) -> Result<(usize, usize), Failure> {
+    let bad_range = || Failure {
+        status: 416,
+        code: "range_not_satisfiable".into(),
+        message: format!("Invalid range: {}", range),
+    };
+
+    let (start, end) = range.strip_prefix("bytes=")
+        .and_then(|r| r.split_once('-'))
+        .ok_or_else(bad_range)?;
+
+    let start: usize = start.parse().map_err(|_| bad_range())?;
+    let end = match end {
+        "" => len.saturating_sub(1),
+        end => end.parse::<usize>().map_err(|_| bad_range())?
+            .min(len.saturating_sub(1)),
+    };
+
+    if start >= len || start > end {
+        Err(bad_range())
+    } else {
+        Ok((start, end))
+    }
+}
+
+/// A page of files, and the name and ID of the file that begins the next page.
+type FilePage = (Vec<Value>, Option<(String, String)>);
+
+/// The base URLs given to clients.
+#[derive(Debug, Clone)]
+struct Urls {
+    api: String,
+    download: String,
+    upload: String,
+    s3: String,
+}
+
+impl Default for Urls {
+    fn default() -> Self {
+        Self {
+            api: API_URL.into(),
+            download: DOWNLOAD_URL.into(),
+            upload: UPLOAD_URL.into(),
+            s3: S3_API_URL.into(),
+        }
+    }
+}
+
+impl Urls {
+    fn from_base(url: &str) -> Self {
+        Self {
+            api: url.into(),
+            download: url.into(),
+            upload: url.into(),
+            s3: url.into(),
+        }
+    }
+}
+
+/// Where file contents and large file parts are kept.
+#[derive(Debug)]
+enum Storage {
+    Memory(HashMap<String, Vec<u8>>),
+    // Everything but file contents is saved to the directory's STATE_FILE;
+    // contents are in its `data` subdirectory.
+    Directory(PathBuf),
+}
+
+impl Default for Storage {
+    fn default() -> Self {
+        Self::Memory(HashMap::new())
+    }
+}
+
+impl Storage {
+    fn path(dir: &Path, name: &str) -> PathBuf {
+        dir.join("data").join(name)
+    }
+
+    fn read(&self, name: &str) -> io::Result<Vec<u8>> {
+        match self {
+            Self::Memory(blobs) => blobs.get(name).cloned()
+                .ok_or_else(|| io::ErrorKind::NotFound.into()),
+            Self::Directory(dir) => fs::read(Self::path(dir, name)),
+        }
+    }
+
+    fn write(&mut self, name: &str, data: Vec<u8>) -> io::Result<()> {
+        match self {
+            Self::Memory(blobs) => {
+                blobs.insert(name.to_owned(), data);
+                Ok(())
+            },
+            Self::Directory(dir) => fs::write(Self::path(dir, name), data),
+        }
+    }
+
+    fn remove(&mut self, name: &str) -> io::Result<()> {
+        match self {
+            Self::Memory(blobs) => {
+                blobs.remove(name);
+                Ok(())
+            },
+            Self::Directory(dir) => match fs::remove_file(Self::path(dir, name))
+            {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+                _ => Ok(()),
+            },
+        }
+    }
+}
+
+/// The name under which a part of a large file is stored.
+fn part_blob(file_id: &str, part_number: u16) -> String {
+    format!("{}.{}", file_id, part_number)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct State {
+    #[serde(skip)]
+    storage: Storage,
+    #[serde(skip)]
+    urls: Urls,
+    minimum_part_size: u64,
+    next_id: u64,
+    clock: i64,
+    // Keyed by application key ID.
+    keys: BTreeMap<String, Key>,
+    tokens: HashMap<String, Grant>,
+    expired: HashSet<String>,
+    // Keyed by bucket ID.
+    buckets: BTreeMap<String, Bucket>,
+    // Every file version, in the order they were created.
+    files: Vec<FileVersion>,
+    #[serde(skip)]
+    failures: VecDeque<(u16, String)>,
+}
+
+impl State {
+    fn new(storage: Storage) -> Self {
+        let mut keys = BTreeMap::new();
+
+        keys.insert(ACCOUNT_ID.to_owned(), Key {
+            name: "Master Application Key".into(),
+            secret: MASTER_KEY.into(),
+            capabilities: ALL_CAPABILITIES.to_vec(),
+            bucket_ids: vec![],
+            name_prefix: None,
+            expires: None,
+        });
+
+        Self {
+            storage,
+            urls: Urls::default(),
+            minimum_part_size: MINIMUM_PART_SIZE,
+            next_id: 1,
+            clock: 0,
+            keys,
+            tokens: HashMap::new(),
+            expired: HashSet::new(),
+            buckets: BTreeMap::new(),
+            files: Vec::new(),
+            failures: VecDeque::new(),
+        }
+    }
+
+    /// Save everything except file contents if the service is backed by a
+    /// directory.
+    fn save(&self) -> io::Result<()> {
+        if let Storage::Directory(dir) = &self.storage {
+            let tmp = dir.join(format!("{}.tmp", STATE_FILE));
+
+            fs::write(&tmp, serde_json::to_vec(self)?)?;
+            fs::rename(tmp, dir.join(STATE_FILE))?;
+        }
+
+        Ok(())
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    /// Get the current time in milliseconds; every call returns a later time
+    /// than the previous call so that file versions are strictly ordered.
+    fn now(&mut self) -> i64 {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.clock = now.max(self.clock + 1);
+        self.clock
+    }
+
+    fn issue_token(&mut self, grant: Grant) -> String {
+        let token = format!("4_{:030x}_token", self.next_id());
+        self.tokens.insert(token.clone(), grant);
+        token
+    }
+
+    fn respond(&mut self, req: &Request, body: &[u8]) -> Response {
+        let res = match self.failures.pop_front() {
+            Some((status, code)) => fail(status, &code, "Injected failure"),
+            None => self.dispatch(req, body)
+                .and_then(|res| Ok(self.save().map(|_| res)?)),
+        };
+
+        match res {
+            Ok(res) => res,
+            // Responses to HEAD requests have no body.
+            Err(e) if req.method == Method::Head =>
+                Response::new(e.status, HeaderMap::new(), Vec::new()),
+            Err(e) => json_response(e.status, json!({
+                "status": e.status,
+                "code": e.code,
+                "message": e.message,
+            })),
+        }
+    }
+
+    fn dispatch(&mut self, req: &Request, body: &[u8])
+    -> Result<Response, Failure> {
+        let path = req.url.path().to_owned();
+
+        if let Some(path) = path.strip_prefix("/file/") {
+            return self.download_file_by_name(req, path);
+        }
+
+        let (version, endpoint) = match path.strip_prefix("/b2api/")
+            .and_then(|p| p.split_once('/'))
+        {
+            Some(("v2", rest)) => (ApiVersion::V2, rest),
+            Some(("v3", rest)) => (ApiVersion::V3, rest),
+            _ => (ApiVersion::V2, ""),
+        };
+        let endpoint = endpoint.split('/').next().unwrap_or_default();
+
+        match endpoint {
+            "b2_authorize_account" =>
+                return self.authorize_account(req, version),
+            "b2_upload_file" => return self.upload_file(req, body),
+            "b2_upload_part" => return self.upload_part(req, body),
+            "b2_download_file_by_id" =>
+                return self.download_file_by_id(req, body),
+            _ => {},
+        }
+
+        let body: Value = serde_json::from_slice(body)
+            .map_err(|e| Failure {
+                status: 400,
+                code: "bad_request".into(),
+                message: e.to_string(),
+            })?;
+
+        let res = match endpoint {
+            "b2_create_key" => self.create_key(req, &body, version),
+            "b2_delete_key" => self.delete_key(req, &body, version),
+            "b2_list_keys" => self.list_keys(req, &body, version),
+            "b2_create_bucket" => self.create_bucket(req, &body),
+            "b2_delete_bucket" => self.delete_bucket(req, &body),
+            "b2_list_buckets" => self.list_buckets(req, &body),
+            "b2_update_bucket" => self.update_bucket(req, &body),
+            "b2_get_bucket_notification_rules" =>
+                self.get_bucket_notification_rules(req, &body),
+            "b2_set_bucket_notification_rules" =>
+                self.set_bucket_notification_rules(req, &body),
+            "b2_get_upload_url" => self.get_upload_url(req, &body),
+            "b2_get_upload_part_url" => self.get_upload_part_url(req, &body),
+            "b2_start_large_file" => self.start_large_file(req, &body),
+            "b2_finish_large_file" => self.finish_large_file(req, &body),
+            "b2_cancel_large_file" => self.cancel_large_file(req, &body),
+            "b2_list_parts" => self.list_parts(req, &body),
+            "b2_list_unfinished_large_files" =>
+                self.list_unfinished_large_files(req, &body),
+            "b2_list_file_names" => self.list_file_names(req, &body),
+            "b2_list_file_versions" => self.list_file_versions(req, &body),
+            "b2_get_file_info" => self.get_file_info(req, &body),
+            "b2_hide_file" => self.hide_file(req, &body),
+            "b2_delete_file_version" => self.delete_file_version(req, &body),
+            "b2_copy_file" => self.copy_file(req, &body),
+            "b2_copy_part" => self.copy_part(req, &body),
+            "b2_get_download_authorization" =>
+                self.get_download_authorization(req, &body),
+            "b2_update_file_legal_hold" =>
+                self.update_file_legal_hold(req, &body),
+            "b2_update_file_retention" =>
+                self.update_file_retention(req, &body),
+            _ => fail(404, "not_found", format!("Unknown endpoint: {}", path)),
+        }?;
+
+        Ok(json_response(200, res))
+    }
+
+    /// Find the key that issued the request's authorization token.
+    fn authenticate(&self, req: &Request) -> Result<(String, Key), Failure> {
+        let token = req.header("Authorization").unwrap_or_default();
+
+        if self.expired.contains(token) {
+            return fail(401, "expired_auth_token",
+                "Authorization token has expired");
+        }
+
+        let key_id = match self.tokens.get(token) {
+            Some(Grant::Account { key_id }) => key_id,
+            _ => return fail(401, "bad_auth_token",
+                "Invalid authorization token"),
+        };
+
+        // Tokens are revoked when their key is deleted.
+        match self.keys.get(key_id) {
+            Some(key) => Ok((key_id.to_owned(), key.clone())),
+            None => fail(401, "bad_auth_token", "Invalid authorization token"),
+        }
+    }
+
+    /// Authenticate a request that requires the given capability.
+    fn authorize(&self, req: &Request, cap: Capability)
+    -> Result<Key, Failure> {
+        let (_, key) = self.authenticate(req)?;
+
+        if key.has_capability(cap) {
+            Ok(key)
+        } else {
+            unauthorized()
+        }
+    }
+
+    fn authorize_upload(&self, req: &Request)
+    -> Result<(Grant, Key), Failure> {
+        let token = req.header("Authorization").unwrap_or_default();
+
+        if self.expired.contains(token) {
+            return fail(401, "expired_auth_token",
+                "Authorization token has expired");
+        }
+
+        let grant = self.tokens.get(token).cloned();
+
+        let key_id = match &grant {
+            Some(Grant::Upload { key_id, .. }) => key_id,
+            Some(Grant::UploadPart { key_id, .. }) => key_id,
+            _ => return fail(401, "bad_auth_token",
+                "Invalid authorization token"),
+        };
+
+        match self.keys.get(key_id) {
+            Some(key) => Ok((grant.unwrap(), key.clone())),
+            None => fail(401, "bad_auth_token", "Invalid authorization token"),
+        }
+    }
+
+    fn bucket(&self, key: &Key, bucket_id: &str) -> Result<&Bucket, Failure> {
+        if ! key.allows_bucket(bucket_id) {
+            return unauthorized();
+        }
+
+        match self.buckets.get(bucket_id) {
+            Some(bucket) => Ok(bucket),
+            None => fail(400, "bad_bucket_id",
+                format!("Invalid bucketId: {}", bucket_id)),
+        }
+    }
+
+    fn check_name(key: &Key, name: &str) -> Result<(), Failure> {
+        match &key.name_prefix {
+            Some(prefix) if ! name.starts_with(prefix.as_str()) =>
+                unauthorized(),
+            _ => Ok(()),
+        }
+    }
+
+    fn file_index(&self, file_id: &str) -> Option<usize> {
+        self.files.iter().position(|f| f.id == file_id)
+    }
+
+    /// Find a file version that the key can access.
+    fn file(&self, key: &Key, file_id: &str) -> Result<usize, Failure> {
+        let idx = match self.file_index(file_id) {
+            Some(idx) => idx,
+            None => return fail(404, "not_found",
+                format!("File not present: {}", file_id)),
+        };
+
+        let file = &self.files[idx];
+        self.bucket(key, &file.bucket_id)?;
+        Self::check_name(key, &file.name)?;
+
+        Ok(idx)
+    }
+
+    /// Find an unfinished large file that the key can access.
+    fn large_file(&self, key: &Key, file_id: &str) -> Result<usize, Failure> {
+        match self.file(key, file_id) {
+            Ok(idx) if self.files[idx].action == Action::Start => Ok(idx),
+            Ok(_) => fail(400, "bad_request",
+                format!("Not an unfinished large file: {}", file_id)),
+            Err(_) => fail(400, "bad_request",
+                format!("No active upload for: {}", file_id)),
+        }
+    }
+
+    fn authorize_account(&mut self, req: &Request, version: ApiVersion)
+    -> Result<Response, Failure> {
+        let credentials = req.header("Authorization")
+            .and_then(|v| v.strip_prefix("Basic "))
+            .and_then(|v| base64::decode(v).ok())
+            .and_then(|v| String::from_utf8(v).ok())
+            .unwrap_or_default();
+
+        let (key_id, secret) = credentials.split_once(':')
+            .unwrap_or_default();
+
+        let key = match self.keys.get(key_id) {
+            Some(key) if key.secret == secret => key.clone(),
+            _ => return fail(401, "unauthorized", "Invalid application key"),
+        };
+
+        if matches!(key.expires, Some(t) if t <= self.now()) {
+            return fail(401, "unauthorized", "Application key has expired");
+        }
+
+        let bucket_name = |id: &String| self.buckets.get(id)
+            .map(|b| b.name.clone());
+
+        let allowed = match version {
+            ApiVersion::V2 => json!({
+                "capabilities": key.capabilities,
+                "bucketId": key.bucket_ids.first(),
+                "bucketName": key.bucket_ids.first().and_then(bucket_name),
+                "namePrefix": key.name_prefix,
+            }),
+            ApiVersion::V3 => {
+                let buckets: Vec<_> = key.bucket_ids.iter()
+                    .map(|id| json!({ "id": id, "name": bucket_name(id) }))
+                    .collect();
+
+                json!({
+                    "capabilities": key.capabilities,
+                    "buckets": if buckets.is_empty() {
+                        Value::Null
+                    } else {
+                        json!(buckets)
+                    },
+                    "namePrefix": key.name_prefix,
+                })
+            },
+        };
+
+        let token = self.issue_token(Grant::Account {
+            key_id: key_id.to_owned()
+        });
+
+        let storage_api = json!({
+            "allowed": allowed,
+            "apiUrl": self.urls.api,
+            "downloadUrl": self.urls.download,
+            "recommendedPartSize": RECOMMENDED_PART_SIZE,
+            "absoluteMinimumPartSize": self.minimum_part_size,
+            "s3ApiUrl": self.urls.s3,
+        });
+
+        let mut res = json!({
+            "accountId": ACCOUNT_ID,
+            "authorizationToken": token,
+        });
+
+        match version {
+            ApiVersion::V2 => {
+                for (field, value) in storage_api.as_object().unwrap() {
+                    res[field] = value.clone();
+                }
+            },
+            ApiVersion::V3 =>
+                res["apiInfo"] = json!({ "storageApi": storage_api }),
+        }
+
+        Ok(json_response(200, res))
+    }
+
+    fn create_key(&mut self, req: &Request, body: &Value, version: ApiVersion)
+    -> Result<Value, Failure> {
+        let creator = self.authorize(req, Capability::WriteKeys)?;
+
+        if ! creator.bucket_ids.is_empty() {
+            return unauthorized();
+        }
+
+        let name = required(body, "keyName")?.to_owned();
+        let capabilities: Vec<Capability> =
+            serde_json::from_value(body["capabilities"].clone())
+                .map_err(|e| Failure {
+                    status: 400,
+                    code: "bad_request".into(),
+                    message: e.to_string(),
+                })?;
+
+        let bucket_ids: Vec<String> = match version {
+            ApiVersion::V2 => body["bucketId"].as_str()
+                .map(String::from)
+                .into_iter()
+                .collect(),
+            ApiVersion::V3 => body["bucketIds"].as_array()
+                .map(|ids| ids.iter()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect())
+                .unwrap_or_default(),
+        };
+        let name_prefix = body["namePrefix"].as_str().map(String::from);
+
+        if let Some(id) = bucket_ids.iter()
+            .find(|id| ! self.buckets.contains_key(*id))
+        {
+            return fail(400, "bad_bucket_id",
+                format!("Invalid bucketId: {}", id));
+        } else if bucket_ids.is_empty() && name_prefix.is_some() {
+            return fail(400, "bad_request",
+                "A name prefix requires a bucketId");
+        }
+
+        let expires = body["validDurationInSeconds"].as_i64()
+            .map(|secs| self.now() + secs * 1000);
+
+        let n = self.next_id();
+        let id = format!("{}{:013x}", &ACCOUNT_ID[..12], n);
+        let key = Key {
+            name,
+            secret: format!("K{:030x}", n),
+            capabilities,
+            bucket_ids,
+            name_prefix,
+            expires,
+        };
+
+        let mut res = key.to_json(&id, version);
+        res["applicationKey"] = json!(key.secret);
+
+        self.keys.insert(id, key);
+        Ok(res)
+    }
+
+    fn delete_key(&mut self, req: &Request, body: &Value, version: ApiVersion)
+    -> Result<Value, Failure> {
+        self.authorize(req, Capability::DeleteKeys)?;
+
+        let id = required(body, "applicationKeyId")?;
+
+        match self.keys.remove(id) {
+            Some(key) => Ok(key.to_json(id, version)),
+            None => fail(400, "bad_request",
+                format!("Key does not exist: {}", id)),
+        }
+    }
+
+    fn list_keys(&mut self, req: &Request, body: &Value, version: ApiVersion)
+    -> Result<Value, Failure> {
+        self.authorize(req, Capability::ListKeys)?;
+
+        let max = body["maxKeyCount"].as_u64().unwrap_or(100).min(10_000);
+        let start = body["startApplicationKeyId"].as_str().unwrap_or_default();
+
+        let mut keys = self.keys.range(start.to_owned()..)
+            .map(|(id, key)| (id, key.to_json(id, version)));
+
+        let page: Vec<_> = keys.by_ref()
+            .take(max as usize)
+            .map(|(_, key)| key)
+            .collect();
+
+        Ok(json!({
+            "keys": page,
+            "nextApplicationKeyId": keys.next().map(|(id, _)| id),
+        }))
+    }
+
+    fn create_bucket(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteBuckets)?;
+
+        if ! key.bucket_ids.is_empty() {
+            return unauthorized();
+        }
+
+        let name = required(body, "bucketName")?;
+
+        if self.buckets.values().any(|b| b.name == name) {
+            return fail(400, "duplicate_bucket_name",
+                format!("Bucket name is already in use: {}", name));
+        }
+
+        let replication = body.get("replicationConfiguration").cloned()
+            .unwrap_or_default();
+
+        if ! replication.is_null()
+            && ! key.has_capability(Capability::WriteBucketReplications)
+        {
+            return unauthorized();
+        }
+
+        let bucket = Bucket {
+            id: format!("{:024x}", self.next_id()),
+            name: name.to_owned(),
+            bucket_type: required(body, "bucketType")?.to_owned(),
+            info: body.get("bucketInfo").cloned().unwrap_or(json!({})),
+            cors_rules: body.get("corsRules").cloned().unwrap_or(json!([])),
+            lifecycle_rules: body.get("lifecycleRules").cloned()
+                .unwrap_or(json!([])),
+            encryption: body.get("defaultServerSideEncryption").cloned()
+                .unwrap_or(json!({ "mode": null })),
+            default_retention: json!({ "mode": null }),
+            file_lock_enabled: body["fileLockEnabled"].as_bool()
+                .unwrap_or(false),
+            replication,
+            notification_rules: vec![],
+            revision: 1,
+        };
+
+        let res = bucket.to_json(&key);
+        self.buckets.insert(bucket.id.clone(), bucket);
+
+        Ok(res)
+    }
+
+    fn delete_bucket(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::DeleteBuckets)?;
+        let id = required(body, "bucketId")?;
+        let res = self.bucket(&key, id)?.to_json(&key);
+
+        if self.files.iter().any(|f| f.bucket_id == id) {
+            return fail(400, "cannot_delete_non_empty_bucket",
+                "Cannot delete non-empty bucket");
+        }
+
+        self.buckets.remove(id);
+        Ok(res)
+    }
+
+    fn list_buckets(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::ListBuckets)?;
+
+        let bucket_id = body["bucketId"].as_str();
+        let bucket_name = body["bucketName"].as_str();
+
+        // A key restricted to buckets may only list those buckets, and must
+        // name one of them in the request.
+        if ! key.bucket_ids.is_empty() {
+            let named = key.bucket_ids.iter().any(|allowed| {
+                let allowed_name = self.buckets.get(allowed).map(|b| &b.name);
+
+                bucket_id == Some(allowed.as_str())
+                    || bucket_name == allowed_name.map(String::as_str)
+            });
+
+            if ! named {
+                return unauthorized();
+            }
+        }
+
+        let types: Option<Vec<&str>> = body["bucketTypes"].as_array()
+            .map(|t| t.iter().filter_map(Value::as_str).collect());
+
+        let buckets: Vec<_> = self.buckets.values()
+            .filter(|b| bucket_id.is_none() || bucket_id == Some(&b.id))
+            .filter(|b| bucket_name.is_none() || bucket_name == Some(&b.name))
+            .filter(|b| match &types {
+                Some(t) => t.contains(&"all")
+                    || t.contains(&b.bucket_type.as_str()),
+                None => true,
+            })
+            .map(|b| b.to_json(&key))
+            .collect();
+
+        Ok(json!({ "buckets": buckets }))
+    }
+
+    fn update_bucket(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteBuckets)?;
+        let id = required(body, "bucketId")?;
+        let mut bucket = self.bucket(&key, id)?.clone();
+
+        if let Some(rev) = body["ifRevisionIs"].as_u64() {
+            if rev != bucket.revision as u64 {
+                return fail(409, "conflict",
+                    "The bucket has been modified since the given revision");
+            }
+        }
+
+        if let Some(typ) = body["bucketType"].as_str() {
+            bucket.bucket_type = typ.to_owned();
+        }
+        if let Some(info) = body.get("bucketInfo") {
+            bucket.info = info.clone();
+        }
+        if let Some(rules) = body.get("corsRules") {
+            bucket.cors_rules = rules.clone();
+        }
+        if let Some(rules) = body.get("lifecycleRules") {
+            bucket.lifecycle_rules = rules.clone();
+        }
+        if let Some(enc) = body.get("defaultServerSideEncryption") {
+            if ! key.has_capability(Capability::WriteBucketEncryption) {
+                return unauthorized();
+            }
+
+            bucket.encryption = enc.clone();
+        }
+        if let Some(retention) = body.get("defaultRetention") {
+            if ! key.has_capability(Capability::WriteBucketRetentions) {
+                return unauthorized();
+            }
+            if ! bucket.file_lock_enabled {
+                return fail(400, "bucket_missing_file_lock",
+                    "File lock is not enabled on the bucket");
+            }
+
+            bucket.default_retention = retention.clone();
+        }
+        if let Some(replication) = body.get("replicationConfiguration") {
+            if ! key.has_capability(Capability::WriteBucketReplications) {
+                return unauthorized();
+            }
+
+            bucket.replication = replication.clone();
+        }
+
+        bucket.revision += 1;
+
+        let res = bucket.to_json(&key);
+        self.buckets.insert(bucket.id.clone(), bucket);
+
+        Ok(res)
+    }
+
+    fn get_bucket_notification_rules(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::ReadBucketNotifications)?;
+        let id = required(body, "bucketId")?;
+        let bucket = self.bucket(&key, id)?;
+
+        Ok(json!({
+            "bucketId": id,
+            "eventNotificationRules": bucket.notification_rules,
+        }))
+    }
+
+    fn set_bucket_notification_rules(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteBucketNotifications)?;
+        let id = required(body, "bucketId")?;
+        self.bucket(&key, id)?;
+
+        let rules = match body["eventNotificationRules"].as_array() {
+            Some(rules) => rules,
+            None => return fail(400, "bad_request",
+                "Missing eventNotificationRules"),
+        };
+
+        // We store the rules as B2 would return them.
+        let rules: Vec<_> = rules.iter()
+            .cloned()
+            .map(|mut rule| {
+                rule["isSuspended"] = json!(false);
+                rule["suspensionReason"] = json!("");
+                rule
+            })
+            .collect();
+
+        if let Some(bucket) = self.buckets.get_mut(id) {
+            bucket.notification_rules = rules.clone();
+        }
+
+        Ok(json!({
+            "bucketId": id,
+            "eventNotificationRules": rules,
+        }))
+    }
+
+    fn get_upload_url(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let (key_id, key) = self.authenticate(req)?;
+
+        if ! key.has_capability(Capability::WriteFiles) {
+            return unauthorized();
+        }
+
+        let bucket_id = required(body, "bucketId")?;
+        self.bucket(&key, bucket_id)?;
+
+        let n = self.next_id();
+        let token = self.issue_token(Grant::Upload {
+            key_id,
+            bucket_id: bucket_id.to_owned(),
+        });
+
+        Ok(json!({
+            "bucketId": bucket_id,
+            "uploadUrl": format!("{}/b2api/v2/b2_upload_file/{}/{}",
+                self.urls.upload, bucket_id, n),
+            "authorizationToken": token,
+        }))
+    }
+
+    fn get_upload_part_url(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let (key_id, key) = self.authenticate(req)?;
+
+        if ! key.has_capability(Capability::WriteFiles) {
+            return unauthorized();
+        }
+
+        let file_id = required(body, "fileId")?;
+        self.large_file(&key, file_id)?;
+
+        let n = self.next_id();
+        let token = self.issue_token(Grant::UploadPart {
+            key_id,
+            file_id: file_id.to_owned(),
+        });
+
+        Ok(json!({
+            "fileId": file_id,
+            "uploadUrl": format!("{}/b2api/v2/b2_upload_part/{}/{}",
+                self.urls.upload, file_id, n),
+            "authorizationToken": token,
+        }))
+    }
+
+    /// Check the length and checksum of uploaded data against the request's
+    /// headers, returning the data's SHA1 checksum.
+    fn verify_upload(req: &Request, data: &[u8]) -> Result<String, Failure> {
+        let len = req.header("Content-Length")
+            .and_then(|l| l.parse::<usize>().ok());
+
+        if len != Some(data.len()) {
+            return fail(400, "bad_request",
+                "Content-Length does not match the data received");
+        }
+
+        let sha1 = sha1_hex(data);
+
+        match req.header("X-Bz-Content-Sha1") {
+            Some("do_not_verify") => Ok(sha1),
+            Some(expected) if expected.eq_ignore_ascii_case(&sha1) => Ok(sha1),
+            Some(_) => fail(400, "bad_request",
+                "Checksum did not match data received"),
+            None => fail(400, "bad_request", "Missing X-Bz-Content-Sha1"),
+        }
+    }
+
+    fn upload_file(&mut self, req: &Request, data: &[u8])
+    -> Result<Response, Failure> {
+        let (grant, key) = self.authorize_upload(req)?;
+
+        let bucket_id = match grant {
+            Grant::Upload { bucket_id, .. } => bucket_id,
+            _ => return fail(401, "bad_auth_token",
+                "Invalid authorization token"),
+        };
+
+        let name = match req.header("X-Bz-File-Name") {
+            Some(name) => decode(name),
+            None => return fail(400, "bad_request", "Missing X-Bz-File-Name"),
+        };
+
+        Self::check_name(&key, &name)?;
+        let sha1 = Self::verify_upload(req, data)?;
+
+        let file_lock_enabled = self.bucket(&key, &bucket_id)?
+            .file_lock_enabled;
+
+        let mut info = serde_json::Map::new();
+
+        for (header, value) in &req.headers {
+            if header.len() > 10
+                && header[..10].eq_ignore_ascii_case("x-bz-info-")
+            {
+                info.insert(header[10..].to_owned(), json!(decode(value)));
+            }
+        }
+
+        let legal_hold = req.header("X-Bz-File-Legal-Hold").map(String::from);
+        let mode = req.header("X-Bz-File-Retention-Mode");
+        let until = req.header("X-Bz-File-Retention-Retain-Until-Timestamp")
+            .and_then(|t| t.parse::<i64>().ok());
+
+        if (legal_hold.is_some() || mode.is_some()) && ! file_lock_enabled {
+            return fail(400, "bucket_missing_file_lock",
+                "File lock is not enabled on the bucket");
+        }
+        if legal_hold.is_some()
+            && ! key.has_capability(Capability::WriteFileLegalHolds)
+        {
+            return unauthorized();
+        }
+        if mode.is_some()
+            && ! key.has_capability(Capability::WriteFileRetentions)
+        {
+            return unauthorized();
+        }
+
+        let mut encryption = json!({ "mode": null });
+
+        if let Some(algorithm) =
+            req.header("X-Bz-Server-Side-Encryption")
+        {
+            encryption = json!({ "mode": "SSE-B2", "algorithm": algorithm });
+        } else if let Some(algorithm) =
+            req.header("X-Bz-Server-Side-Encryption-Customer-Algorithm")
+        {
+            encryption = json!({ "mode": "SSE-C", "algorithm": algorithm });
+        }
+
+        let content_type = match req.header("Content-Type") {
+            None | Some("b2/x-auto") => "application/octet-stream",
+            Some(typ) => typ,
+        };
+
+        let file = FileVersion {
+            id: format!("4_z{}_f{:015x}", bucket_id, self.next_id()),
+            bucket_id,
+            name,
+            action: Action::Upload,
+            content_type: content_type.to_owned(),
+            content_length: data.len() as u64,
+            sha1,
+            md5: Some(md5_hex(data)),
+            info: Value::Object(info),
+            timestamp: self.now(),
+            legal_hold,
+            retention: json!({ "mode": mode, "retainUntilTimestamp": until }),
+            encryption,
+            parts: BTreeMap::new(),
+        };
+
+        self.storage.write(&file.id, data.to_vec())?;
+
+        let res = file.to_json(&key);
+        self.files.push(file);
+
+        Ok(json_response(200, res))
+    }
+
+    fn upload_part(&mut self, req: &Request, data: &[u8])
+    -> Result<Response, Failure> {
+        let (grant, key) = self.authorize_upload(req)?;
+
+        let file_id = match grant {
+            Grant::UploadPart { file_id, .. } => file_id,
+            _ => return fail(401, "bad_auth_token",
+                "Invalid authorization token"),
+        };
+
+        let part_number = req.header("X-Bz-Part-Number")
+            .and_then(|n| n.parse::<u16>().ok())
+            .filter(|n| (1..=10_000).contains(n));
+
+        let part_number = match part_number {
+            Some(n) => n,
+            None => return fail(400, "bad_request", "Invalid part number"),
+        };
+
+        let sha1 = Self::verify_upload(req, data)?;
+        let idx = self.large_file(&key, &file_id)?;
+        let timestamp = self.now();
+
+        self.storage.write(&part_blob(&file_id, part_number), data.to_vec())?;
+        self.files[idx].parts.insert(part_number, Part {
+            content_length: data.len() as u64,
+            sha1: sha1.clone(),
+            md5: md5_hex(data),
+            timestamp,
+        });
+
+        Ok(json_response(200, json!({
+            "fileId": file_id,
+            "partNumber": part_number,
+            "contentLength": data.len(),
+            "contentSha1": sha1,
+            "contentMd5": md5_hex(data),
+            "serverSideEncryption": self.files[idx].encryption,
+            "uploadTimestamp": timestamp,
+        })))
+    }
+
+    fn start_large_file(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteFiles)?;
+
+        let bucket_id = required(body, "bucketId")?.to_owned();
+        let name = required(body, "fileName")?.to_owned();
+
+        let file_lock_enabled = self.bucket(&key, &bucket_id)?
+            .file_lock_enabled;
+        Self::check_name(&key, &name)?;
+
+        let legal_hold = body["legalHold"].as_str().map(String::from);
+        let retention = body.get("fileRetention").cloned()
+            .unwrap_or(json!({ "mode": null, "retainUntilTimestamp": null }));
+
+        if (legal_hold.is_some() || ! retention["mode"].is_null())
+            && ! file_lock_enabled
+        {
+            return fail(400, "bucket_missing_file_lock",
+                "File lock is not enabled on the bucket");
+        }
+
+        let content_type = match required(body, "contentType")? {
+            "b2/x-auto" => "application/octet-stream",
+            typ => typ,
+        };
+
+        let file = FileVersion {
+            id: format!("4_z{}_f{:015x}", bucket_id, self.next_id()),
+            bucket_id,
+            name,
+            action: Action::Start,
+            content_type: content_type.to_owned(),
+            content_length: 0,
+            sha1: "none".into(),
+            md5: None,
+            info: body.get("fileInfo").cloned().unwrap_or(json!({})),
+            timestamp: self.now(),
+            legal_hold,
+            retention,
+            encryption: body.get("serverSideEncryption").cloned()
+                .unwrap_or(json!({ "mode": null })),
+            parts: BTreeMap::new(),
+        };
+
+        let res = file.to_json(&key);
+        self.files.push(file);
+
+        Ok(res)
+    }
+
+    fn finish_large_file(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteFiles)?;
+        let idx = self.large_file(&key, required(body, "fileId")?)?;
+
+        let checksums: Vec<&str> = body["partSha1Array"].as_array()
+            .map(|a| a.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default();
+
+        let file = &self.files[idx];
+
+        if checksums.len() < 2 {
+            return fail(400, "bad_request",
+                "A large file must have at least two parts");
+        }
+        if checksums.len() != file.parts.len() {
+            return fail(400, "bad_request",
+                "The number of checksums does not match the parts uploaded");
+        }
+
+        for (i, (number, part)) in file.parts.iter().enumerate() {
+            if *number as usize != i + 1 {
+                return fail(400, "bad_request",
+                    format!("Part {} is missing", i + 1));
+            }
+            if ! checksums[i].eq_ignore_ascii_case(&part.sha1) {
+                return fail(400, "bad_request",
+                    format!("Checksum of part {} does not match", number));
+            }
+            if i + 1 < checksums.len()
+                && part.content_length < self.minimum_part_size
+            {
+                return fail(400, "bad_request",
+                    format!("Part {} is smaller than the minimum part size",
+                        number));
+            }
+        }
+
+        let file_id = file.id.clone();
+        let parts: Vec<u16> = file.parts.keys().copied().collect();
+        let mut data = Vec::new();
+
+        for &number in &parts {
+            data.extend(self.storage.read(&part_blob(&file_id, number))?);
+        }
+
+        self.storage.write(&file_id, data)?;
+
+        for &number in &parts {
+            self.storage.remove(&part_blob(&file_id, number))?;
+        }
+
+        let timestamp = self.now();
+        let file = &mut self.files[idx];
+
+        file.content_length = std::mem::take(&mut file.parts).into_values()
+            .map(|p| p.content_length)
+            .sum();
+        file.action = Action::Upload;
+        file.timestamp = timestamp;
+
+        Ok(file.to_json(&key))
+    }
+
+    fn cancel_large_file(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteFiles)?;
+        let idx = self.large_file(&key, required(body, "fileId")?)?;
+        let file = self.files.remove(idx);
+
+        for number in file.parts.keys() {
+            self.storage.remove(&part_blob(&file.id, *number))?;
+        }
+
+        Ok(json!({
+            "fileId": file.id,
+            "accountId": ACCOUNT_ID,
+            "bucketId": file.bucket_id,
+            "fileName": file.name,
+        }))
+    }
+
+    fn list_parts(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteFiles)?;
+        let idx = self.large_file(&key, required(body, "fileId")?)?;
+        let file = &self.files[idx];
+
+        let start = body["startPartNumber"].as_u64().unwrap_or(1) as u16;
+        let max = body["maxPartCount"].as_u64().unwrap_or(100).min(1000);
+
+        let mut parts = file.parts.range(start..);
+
+        let page: Vec<_> = parts.by_ref()
+            .take(max as usize)
+            .map(|(number, part)| json!({
+                "fileId": file.id,
+                "partNumber": number,
+                "contentLength": part.content_length,
+                "contentSha1": part.sha1,
+                "contentMd5": part.md5,
+                "serverSideEncryption": file.encryption,
+                "uploadTimestamp": part.timestamp,
+            }))
+            .collect();
+
+        Ok(json!({
+            "parts": page,
+            "nextPartNumber": parts.next().map(|(number, _)| number),
+        }))
+    }
+
+    fn list_unfinished_large_files(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::ListFiles)?;
+        let bucket_id = required(body, "bucketId")?;
+        self.bucket(&key, bucket_id)?;
+
+        let prefix = body["namePrefix"].as_str().unwrap_or_default();
+        let max = body["maxFileCount"].as_u64().unwrap_or(100).min(100);
+
+        let mut files = self.files.iter()
+            .filter(|f| f.bucket_id == bucket_id && f.action == Action::Start)
+            .filter(|f| f.name.starts_with(prefix))
+            .skip_while(|f| match body["startFileId"].as_str() {
+                Some(start) => f.id != start,
+                None => false,
+            });
+
+        let page: Vec<_> = files.by_ref()
+            .take(max as usize)
+            .map(|f| f.to_json(&key))
+            .collect();
+
+        Ok(json!({
+            "files": page,
+            "nextFileId": files.next().map(|f| &f.id),
+        }))
+    }
+
+    /// List the files in a bucket for `b2_list_file_names` and
+    /// `b2_list_file_versions`.
+    ///
+    /// Files are sorted by name, then newest first. If `all_versions` is
+    /// `false`, only the current version of each visible file is included.
+    /// Returns the requested page of files and the name and ID of the first
+    /// file of the next page.
+    fn list_files(&self, key: &Key, body: &Value, all_versions: bool)
+    -> Result<FilePage, Failure> {
+        if ! key.has_capability(Capability::ListFiles) {
+            return unauthorized();
+        }
+
+        let bucket_id = required(body, "bucketId")?;
+        self.bucket(key, bucket_id)?;
+
+        let prefix = body["prefix"].as_str().unwrap_or_default();
+
+        // Keys restricted to a prefix can only list within it.
+        if let Some(allowed) = &key.name_prefix {
+            if ! prefix.starts_with(allowed.as_str()) {
+                return unauthorized();
+            }
+        }
+
+        let delimiter = body["delimiter"].as_str();
+        let start_name = body["startFileName"].as_str().unwrap_or_default();
+        let start_id = body["startFileId"].as_str();
+        let max = body["maxFileCount"].as_u64().unwrap_or(100).min(10_000);
+
+        let mut versions: Vec<&FileVersion> = self.files.iter()
+            .filter(|f| f.bucket_id == bucket_id && f.name.starts_with(prefix))
+            .collect();
+
+        versions.sort_by(|a, b| a.name.cmp(&b.name)
+            .then(b.timestamp.cmp(&a.timestamp)));
+
+        if ! all_versions {
+            let mut latest: Vec<&FileVersion> = Vec::new();
+
+            for file in versions {
+                if file.action == Action::Start { continue; }
+
+                match latest.last() {
+                    Some(prev) if prev.name == file.name => {},
+                    _ => latest.push(file),
+                }
+            }
+
+            latest.retain(|f| f.action != Action::Hide);
+            versions = latest;
+        }
+
+        // Each entry is a file or a folder, with the name and file ID by which
+        // pagination resumes.
+        let mut entries: Vec<(String, String, Value)> = Vec::new();
+
+        for file in versions {
+            let folder = delimiter.and_then(|d|
+                file.name[prefix.len()..].find(d)
+                    .map(|i| &file.name[..prefix.len() + i + d.len()])
+            );
+
+            match folder {
+                Some(folder) => {
+                    if matches!(entries.last(), Some((name, _, _))
+                        if name == folder)
+                    {
+                        continue;
+                    }
+
+                    entries.push((folder.to_owned(), String::new(), json!({
+                        "accountId": ACCOUNT_ID,
+                        "action": "folder",
+                        "bucketId": bucket_id,
+                        "contentLength": 0,
+                        "contentMd5": null,
+                        "contentSha1": null,
+                        "contentType": null,
+                        "fileId": "",
+                        "fileInfo": {},
+                        "fileName": folder,
+                        "fileRetention": null,
+                        "legalHold": null,
+                        "serverSideEncryption": null,
+                        "uploadTimestamp": 0,
+                    })));
+                },
+                None => entries.push(
+                    (file.name.clone(), file.id.clone(), file.to_json(key))
+                ),
+            }
+        }
+
+        let start = match start_id {
+            Some(id) => entries.iter()
+                .position(|(name, fid, _)| name == start_name && fid == id),
+            None => None,
+        }.unwrap_or_else(||
+            entries.iter()
+                .position(|(name, _, _)| name.as_str() >= start_name)
+                .unwrap_or(entries.len())
+        );
+
+        let mut entries = entries.into_iter().skip(start);
+
+        let page = entries.by_ref()
+            .take(max as usize)
+            .map(|(_, _, file)| file)
+            .collect();
+
+        Ok((page, entries.next().map(|(name, id, _)| (name, id))))
+    }
+
+    fn list_file_names(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let (_, key) = self.authenticate(req)?;
+        let (files, next) = self.list_files(&key, body, false)?;
+
+        Ok(json!({
+            "files": files,
+            "nextFileName": next.map(|(name, _)| name),
+        }))
+    }
+
+    fn list_file_versions(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let (_, key) = self.authenticate(req)?;
+        let (files, next) = self.list_files(&key, body, true)?;
+
+        let (next_name, next_id) = match next {
+            Some((name, id)) => (Some(name), Some(id)),
+            None => (None, None),
+        };
+
+        Ok(json!({
+            "files": files,
+            "nextFileName": next_name,
+            "nextFileId": next_id,
+        }))
+    }
+
+    fn get_file_info(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::ReadFiles)?;
+        let idx = self.file(&key, required(body, "fileId")?)?;
+
+        Ok(self.files[idx].to_json(&key))
+    }
+
+    fn hide_file(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteFiles)?;
+        let bucket_id = required(body, "bucketId")?.to_owned();
+        let name = required(body, "fileName")?.to_owned();
+
+        self.bucket(&key, &bucket_id)?;
+        Self::check_name(&key, &name)?;
+
+        let latest = self.files.iter()
+            .filter(|f| f.bucket_id == bucket_id && f.name == name)
+            .filter(|f| f.action != Action::Start)
+            .max_by_key(|f| f.timestamp);
+
+        match latest {
+            None => return fail(400, "no_such_file",
+                format!("File not present: {}", name)),
+            Some(f) if f.action == Action::Hide =>
+                return fail(400, "already_hidden",
+                    format!("File already hidden: {}", name)),
+            _ => {},
+        }
+
+        let file = FileVersion {
+            id: format!("4_z{}_f{:015x}", bucket_id, self.next_id()),
+            bucket_id,
+            name,
+            action: Action::Hide,
+            content_type: "application/x-bz-hide-marker".into(),
+            content_length: 0,
+            sha1: "none".into(),
+            md5: None,
+            info: json!({}),
+            timestamp: self.now(),
+            legal_hold: None,
+            retention: json!({ "mode": null, "retainUntilTimestamp": null }),
+            encryption: json!({ "mode": null }),
+            parts: BTreeMap::new(),
+        };
+
+        let res = file.to_json(&key);
+        self.files.push(file);
+
+        Ok(res)
+    }
+
+    fn delete_file_version(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::DeleteFiles)?;
+        let name = required(body, "fileName")?;
+        let id = required(body, "fileId")?;
+
+        let bypass = body["bypassGovernance"].as_bool().unwrap_or(false);
+
+        if bypass && ! key.has_capability(Capability::BypassGovernance) {
+            return unauthorized();
+        }
+
+        let idx = match self.file(&key, id) {
+            Ok(idx) if self.files[idx].name == name => idx,
+            Err(e) if e.status != 404 => return Err(e),
+            _ => return fail(400, "file_not_present",
+                format!("File not present: {} {}", name, id)),
+        };
+
+        let now = self.now();
+        let file = &self.files[idx];
+
+        if file.legal_hold.as_deref() == Some("on")
+            || file.is_retained(now, bypass)
+        {
+            return fail(401, "access_denied",
+                "The file is protected by a legal hold or retention setting");
+        }
+
+        let file = self.files.remove(idx);
+
+        self.storage.remove(&file.id)?;
+        for number in file.parts.keys() {
+            self.storage.remove(&part_blob(&file.id, *number))?;
+        }
+
+        Ok(json!({ "fileId": file.id, "fileName": file.name }))
+    }
+
+    /// Get the requested range of a file's data for copying.
+    fn copy_source(&self, key: &Key, body: &Value)
+    -> Result<(usize, Vec<u8>), Failure> {
+        let idx = match self.file(key, required(body, "sourceFileId")?) {
+            Ok(idx) if self.files[idx].action.has_content() => idx,
+            _ => return fail(400, "bad_request", "Invalid sourceFileId"),
+        };
+
+        let mut data = self.storage.read(&self.files[idx].id)?;
+
+        if let Some(range) = body["range"].as_str() {
+            let (start, end) = parse_range(range, data.len())?;
+
+            data.truncate(end + 1);
+            data.drain(..start);
+        }
+
+        Ok((idx, data))
+    }
+
+    fn copy_file(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteFiles)?;
+
+        if ! key.has_capability(Capability::ReadFiles) {
+            return unauthorized();
+        }
+
+        let (src, data) = self.copy_source(&key, body)?;
+        let src = self.files[src].clone();
+
+        let bucket_id = body["destinationBucketId"].as_str()
+            .unwrap_or(&src.bucket_id)
+            .to_owned();
+        let name = required(body, "fileName")?.to_owned();
+
+        self.bucket(&key, &bucket_id)?;
+        Self::check_name(&key, &name)?;
+
+        let (content_type, info) = match body["metadataDirective"].as_str() {
+            Some("REPLACE") => (
+                required(body, "contentType")?.to_owned(),
+                body.get("fileInfo").cloned().unwrap_or(json!({})),
+            ),
+            _ => (src.content_type, src.info),
+        };
+
+        let file = FileVersion {
+            id: format!("4_z{}_f{:015x}", bucket_id, self.next_id()),
+            bucket_id,
+            name,
+            action: Action::Copy,
+            content_type,
+            content_length: data.len() as u64,
+            sha1: sha1_hex(&data),
+            md5: Some(md5_hex(&data)),
+            info,
+            timestamp: self.now(),
+            legal_hold: body["legalHold"].as_str().map(String::from),
+            retention: body.get("fileRetention").cloned().unwrap_or(
+                json!({ "mode": null, "retainUntilTimestamp": null })
+            ),
+            encryption: body.get("destinationServerSideEncryption").cloned()
+                .unwrap_or(json!({ "mode": null })),
+            parts: BTreeMap::new(),
+        };
+
+        self.storage.write(&file.id, data)?;
+
+        let res = file.to_json(&key);
+        self.files.push(file);
+
+        Ok(res)
+    }
+
+    fn copy_part(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteFiles)?;
+
+        if ! key.has_capability(Capability::ReadFiles) {
+            return unauthorized();
+        }
+
+        let (_, data) = self.copy_source(&key, body)?;
+        let idx = self.large_file(&key, required(body, "largeFileId")?)?;
+
+        let part_number = body["partNumber"].as_u64()
+            .filter(|n| (1..=10_000).contains(n));
+
+        let part_number = match part_number {
+            Some(n) => n as u16,
+            None => return fail(400, "bad_request", "Invalid part number"),
+        };
+
+        let timestamp = self.now();
+        let part = Part {
+            content_length: data.len() as u64,
+            sha1: sha1_hex(&data),
+            md5: md5_hex(&data),
+            timestamp,
+        };
+
+        let file_id = self.files[idx].id.clone();
+        self.storage.write(&part_blob(&file_id, part_number), data)?;
+
+        let res = json!({
+            "fileId": file_id,
+            "partNumber": part_number,
+            "contentLength": part.content_length,
+            "contentSha1": part.sha1,
+            "contentMd5": part.md5,
+            "serverSideEncryption": self.files[idx].encryption,
+            "uploadTimestamp": timestamp,
+        });
+
+        self.files[idx].parts.insert(part_number, part);
+        Ok(res)
+    }
+
+    fn get_download_authorization(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::ShareFiles)?;
+        let bucket_id = required(body, "bucketId")?.to_owned();
+        let prefix = required(body, "fileNamePrefix")?.to_owned();
+
+        self.bucket(&key, &bucket_id)?;
+        Self::check_name(&key, &prefix)?;
+
+        let duration = body["validDurationInSeconds"].as_i64().unwrap_or(0);
+        let expires = self.now() + duration * 1000;
+
+        let token = self.issue_token(Grant::Download {
+            bucket_id: bucket_id.clone(),
+            prefix: prefix.clone(),
+            expires,
+        });
+
+        Ok(json!({
+            "bucketId": bucket_id,
+            "fileNamePrefix": prefix,
+            "authorizationToken": token,
+        }))
+    }
+
+    fn update_file_legal_hold(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteFileLegalHolds)?;
+        let name = required(body, "fileName")?;
+        let id = required(body, "fileId")?;
+        let hold = required(body, "legalHold")?;
+
+        let idx = match self.file(&key, id) {
+            Ok(idx) if self.files[idx].name == name => idx,
+            Err(e) if e.status != 404 => return Err(e),
+            _ => return fail(400, "file_not_present",
+                format!("File not present: {} {}", name, id)),
+        };
+
+        if ! self.buckets[&self.files[idx].bucket_id].file_lock_enabled {
+            return fail(400, "bucket_missing_file_lock",
+                "File lock is not enabled on the bucket");
+        }
+
+        self.files[idx].legal_hold = Some(hold.to_owned());
+
+        Ok(json!({ "fileName": name, "fileId": id, "legalHold": hold }))
+    }
+
+    fn update_file_retention(&mut self, req: &Request, body: &Value)
+    -> Result<Value, Failure> {
+        let key = self.authorize(req, Capability::WriteFileRetentions)?;
+        let name = required(body, "fileName")?;
+        let id = required(body, "fileId")?;
+        let retention = body["fileRetention"].clone();
+
+        let bypass = body["bypassGovernance"].as_bool().unwrap_or(false);
+
+        if bypass && ! key.has_capability(Capability::BypassGovernance) {
+            return unauthorized();
+        }
+
+        let idx = match self.file(&key, id) {
+            Ok(idx) if self.files[idx].name == name => idx,
+            Err(e) if e.status != 404 => return Err(e),
+            _ => return fail(400, "file_not_present",
+                format!("File not present: {} {}", name, id)),
+        };
+
+        if ! self.buckets[&self.files[idx].bucket_id].file_lock_enabled {
+            return fail(400, "bucket_missing_file_lock",
+                "File lock is not enabled on the bucket");
+        }
+
+        let now = self.now();
+        let file = &mut self.files[idx];
+
+        // Retention can always be extended, but only shortened or removed if
+        // the file is not currently retained.
+        let old_until = file.retention["retainUntilTimestamp"].as_i64();
+        let new_until = retention["retainUntilTimestamp"].as_i64();
+        let is_extension = matches!((old_until, new_until),
+            (Some(old), Some(new)) if new >= old)
+            && file.retention["mode"] == retention["mode"];
+
+        if ! is_extension && file.is_retained(now, bypass) {
+            return fail(401, "access_denied",
+                "The file's retention settings cannot be shortened");
+        }
+
+        file.retention = retention.clone();
+
+        Ok(json!({
+            "fileName": name,
+            "fileId": id,
+            "fileRetention": retention,
+        }))
+    }
+
+    /// Check that the request may download the given file version.
+    ///
+    /// Files in public buckets may be downloaded by anyone; otherwise the
+    /// request must have an account authorization token with
+    /// [Capability::ReadFiles] or a download authorization covering the file.
+    fn authorize_download(&mut self, req: &Request, idx: usize)
+    -> Result<(), Failure> {
+        let file = &self.files[idx];
+
+        let is_public = self.buckets.get(&file.bucket_id)
+            .map(|b| b.bucket_type == "allPublic")
+            .unwrap_or(false);
+
+        if is_public { return Ok(()); }
+
+        let token = req.header("Authorization").unwrap_or_default();
+
+        if let Some(Grant::Download { bucket_id, prefix, expires }) =
+            self.tokens.get(token).cloned()
+        {
+            if expires <= self.now() {
+                return fail(401, "expired_auth_token",
+                    "Authorization token has expired");
+            }
+
+            let file = &self.files[idx];
+
+            return if bucket_id == file.bucket_id
+                && file.name.starts_with(&prefix)
+            {
+                Ok(())
+            } else {
+                unauthorized()
+            };
+        }
+
+        let key = self.authorize(req, Capability::ReadFiles)?;
+        let file = &self.files[idx];
+
+        self.bucket(&key, &file.bucket_id)?;
+        Self::check_name(&key, &file.name)
+    }
+
+    fn download_file_by_id(&mut self, req: &Request, body: &[u8])
+    -> Result<Response, Failure> {
+        let file_id = match req.query("fileId") {
+            Some(id) => id,
+            None => {
+                let body: Value = serde_json::from_slice(body)
+                    .unwrap_or_default();
+                required(&body, "fileId")?.to_owned()
+            },
+        };
+
+        let idx = match self.file_index(&file_id) {
+            Some(idx) if self.files[idx].action.has_content() => idx,
+            _ => return fail(404, "not_found",
+                format!("File not present: {}", file_id)),
+        };
+
+        self.authorize_download(req, idx)?;
+        self.download(req, idx)
+    }
+
+    fn download_file_by_name(&mut self, req: &Request, path: &str)
+    -> Result<Response, Failure> {
+        let (bucket_name, name) = path.split_once('/').unwrap_or((path, ""));
+        let name = decode(name);
+
+        let bucket_id = self.buckets.values()
+            .find(|b| b.name == bucket_name)
+            .map(|b| b.id.clone())
+            .unwrap_or_default();
+
+        let latest = self.files.iter()
+            .enumerate()
+            .filter(|(_, f)| f.bucket_id == bucket_id && f.name == name)
+            .filter(|(_, f)| f.action != Action::Start)
+            .max_by_key(|(_, f)| f.timestamp);
+
+        let idx = match latest {
+            Some((idx, f)) if f.action.has_content() => idx,
+            _ => return fail(404, "not_found",
+                format!("File not present: {}", name)),
+        };
+
+        self.authorize_download(req, idx)?;
+        self.download(req, idx)
+    }
+
+    fn download(&self, req: &Request, idx: usize) -> Result<Response, Failure> {
+        let file = &self.files[idx];
+
+        let mut headers = HeaderMap::new();
+        let mut status = 200;
+        let data = self.storage.read(&file.id)?;
+        let mut data = &data[..];
+
+        if let Some(range) = req.header("Range") {
+            let (start, end) = parse_range(range, data.len())?;
+
+            headers.insert("content-range".into(),
+                format!("bytes {}-{}/{}", start, end, data.len()));
+
+            status = 206;
+            data = &data[start..=end];
+        }
+
+        headers.insert("content-length".into(), data.len().to_string());
+        headers.insert("content-type".into(), file.content_type.clone());
+        headers.insert("accept-ranges".into(), "bytes".into());
+        headers.insert("x-bz-file-id".into(), file.id.clone());
+        headers.insert("x-bz-file-name".into(),
+            percent_encoding::utf8_percent_encode(
+                &file.name,
+                percent_encoding::NON_ALPHANUMERIC
+            ).to_string()
+        );
+        headers.insert("x-bz-content-sha1".into(), file.sha1.clone());
+        headers.insert("x-bz-upload-timestamp".into(),
+            file.timestamp.to_string());
+
+        if let Some(info) = file.info.as_object() {
+            for (k, v) in info {
+                let v = v.as_str().map(String::from)
+                    .unwrap_or_else(|| v.to_string());
+
+                headers.insert(format!("x-bz-info-{}", k.to_lowercase()), v);
+            }
+        }
+
+        let body = if req.method == Method::Head {
+            Vec::new()
+        } else {
+            data.to_vec()
+        };
+
+        Ok(Response::new(status, headers, body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        account::{
+            authorize_account, authorize_account_with_version, create_key,
+            delete_key_by_id, list_keys, list_keys_stream,
+            required_capabilities, rotate_key, Authorization, CreateKey,
+            Key as AccountKey, ListKeys, Operation, B2_AUTH_URL,
+        },
+        bucket::{
+            create_bucket, delete_bucket, get_bucket_notification_rules,
+            list_buckets, set_bucket_notification_rules, update_bucket,
+            Bucket, BucketInfo, BucketType, CreateBucket, EventType,
+            FileRetentionMode, FileRetentionPolicy, ListBuckets,
+            NotificationRule, ReplicationConfiguration, ReplicationRule,
+            UpdateBucket,
+        },
+        credentials::Credentials,
+        error::{ErrorCode, Restriction, RotateKeyError, ValidationError},
+        file::*,
+        session::Session,
+        spec::{apply, plan, BucketSpec},
+    };
+    use futures_util::TryStreamExt as _;
+    use std::io::Read as _;
+
+
+    async fn setup(b2: &FakeB2)
+    -> anyhow::Result<(Authorization<FakeClient>, Bucket)> {
+        let (key_id, key) = b2.master_key();
+        let mut auth = authorize_account(b2.client(), &key_id, &key).await?;
+
+        let bucket = create_bucket(&mut auth, CreateBucket::builder()
+            .name("test-bucket")?
+            .bucket_type(BucketType::Private)?
+            .build()?
+        ).await?;
+
+        Ok((auth, bucket))
+    }
+
+    async fn upload(
+        auth: &mut Authorization<FakeClient>,
+        bucket: &Bucket,
+        name: &str,
+        data: &[u8],
+    ) -> anyhow::Result<File> {
+        let mut upload_auth = get_upload_authorization(auth, bucket).await?;
+        let sha1 = sha1_hex(data);
+
+        let upload = UploadFile::builder()
+            .file_name(name)?
+            .sha1_checksum(&sha1)
+            .build()?;
+
+        Ok(upload_file(&mut upload_auth, upload, data).await?)
+    }
+
+    fn assert_code<T>(res: Result<T, Error<Infallible>>, code: ErrorCode) {
+        match res {
+            Err(Error::B2(e)) => assert_eq!(e.code(), code),
+            Err(e) => panic!("Expected {:?}, got {:?}", code, e),
+            Ok(_) => panic!("Expected {:?}, got a success", code),
+        }
+    }
+
+    #[async_std::test]
+    async fn authorize_with_bad_key_fails() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (key_id, _) = b2.master_key();
+
+        let res = authorize_account(b2.client(), &key_id, "wrong").await;
+        assert_code(res, ErrorCode::Unauthorized);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn bucket_names_are_unique() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let res = create_bucket(&mut auth, CreateBucket::builder()
+            .name("test-bucket")?
+            .bucket_type(BucketType::Public)?
+            .build()?
+        ).await;
+        assert_code(res, ErrorCode::DuplicateBucketName);
+
+        let buckets = list_buckets(&mut auth, ListBuckets::builder().build())
+            .await?;
+        assert_eq!(buckets.len(), 1);
+        assert_eq!(buckets[0].bucket_id(), bucket.bucket_id());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn update_bucket_checks_revision() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let updated = update_bucket(&mut auth, UpdateBucket::builder()
+            .bucket_id(bucket.bucket_id())
+            .bucket_type(BucketType::Public)?
+            .if_revision_is(bucket.revision())
+            .build()?
+        ).await?;
+        assert!(matches!(updated.bucket_type(), BucketType::Public));
+
+        let res = update_bucket(&mut auth, UpdateBucket::builder()
+            .bucket_id(bucket.bucket_id())
+            .bucket_type(BucketType::Private)?
+            .if_revision_is(bucket.revision())
+            .build()?
+        ).await;
+        assert_code(res, ErrorCode::Conflict);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn bucket_replication_can_be_set_and_removed() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, destination) = setup(&b2).await?;
+
+        let config = ReplicationConfiguration::builder()
+            .source_key_id(auth.account_id())
+            .rules([
+                ReplicationRule::builder()
+                    .name("to-test-bucket")?
+                    .destination_bucket_id(destination.bucket_id())
+                    .file_name_prefix("docs/")?
+                    .build()?,
+            ])?
+            .build()?;
+
+        let source = create_bucket(&mut auth, CreateBucket::builder()
+            .name("source-bucket")?
+            .bucket_type(BucketType::Private)?
+            .replication_configuration(config.clone())
+            .build()?
+        ).await?;
+
+        let info = source.replication_info();
+        assert!(info.can_read());
+        assert_eq!(info.configuration(), Some(&config));
+        assert_eq!(
+            destination.replication_info().configuration(),
+            Some(&ReplicationConfiguration::default())
+        );
+
+        let updated = update_bucket(&mut auth, UpdateBucket::builder()
+            .bucket_id(source.bucket_id())
+            .replication_configuration(ReplicationConfiguration::default())
+            .build()?
+        ).await?;
+        assert!(updated.replication_info().configuration().unwrap().rules()
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn bucket_info_is_stored() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+        assert!(bucket.info().is_empty());
+
+        let info = BucketInfo::builder()
+            .entry("owner", "storage-team")?
+            .build()?;
+
+        let updated = update_bucket(&mut auth, UpdateBucket::builder()
+            .bucket_id(bucket.bucket_id())
+            .bucket_info(info.clone())
+            .build()?
+        ).await?;
+
+        assert_eq!(updated.info(), &info);
+        assert_eq!(updated.info().get("owner"), Some("storage-team"));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn bucket_specs_are_applied() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let public = BucketSpec::builder()
+            .name(bucket.name())?
+            .bucket_type(BucketType::Public)?
+            .build()?;
+
+        let locked = BucketSpec::builder()
+            .name("locked-bucket")?
+            .bucket_type(BucketType::Private)?
+            .default_retention(FileRetentionPolicy::new(
+                FileRetentionMode::Governance,
+                chrono::Duration::days(7)
+            ))
+            .info(BucketInfo::builder()
+                .entry("owner", "storage-team")?
+                .build()?
+            )
+            .build()?;
+
+        let specs = [public, locked];
+
+        let buckets = list_buckets(&mut auth, ListBuckets::builder().build())
+            .await?;
+        let changes = plan(&specs, &buckets)?;
+        assert_eq!(changes.changes().len(), 2);
+
+        let applied = apply(&mut auth, &changes).await?;
+        assert!(matches!(applied[0].bucket_type(), BucketType::Public));
+        assert_eq!(applied[1].info().get("owner"), Some("storage-team"));
+        assert!(matches!(
+            applied[1].retention_policy().mode(),
+            Some(FileRetentionMode::Governance)
+        ));
+
+        let buckets = list_buckets(&mut auth, ListBuckets::builder().build())
+            .await?;
+        assert!(plan(&specs, &buckets)?.is_empty());
+
+        // A bucket modified after it was planned is not overwritten.
+        let private = BucketSpec::builder()
+            .name(bucket.name())?
+            .bucket_type(BucketType::Private)?
+            .build()?;
+        let stale = plan(&[private], &buckets)?;
+
+        update_bucket(&mut auth, UpdateBucket::builder()
+            .bucket_id(bucket.bucket_id())
+            .bucket_info(BucketInfo::builder().entry("edited", "yes")?.build()?)
+            .build()?
+        ).await?;
+
+        assert_code(apply(&mut auth, &stale).await, ErrorCode::Conflict);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn bucket_notification_rules_can_be_set_and_read()
+    -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        assert!(get_bucket_notification_rules(&mut auth, bucket.bucket_id())
+            .await?.is_empty());
+
+        let rule = NotificationRule::builder()
+            .name("uploads")?
+            .event_types([EventType::ObjectCreatedUpload])?
+            .object_name_prefix("incoming/")?
+            .url("https://example.com/hooks/b2")?
+            .signing_secret("0123456789abcdefghijABCDEFGHIJ01")?
+            .custom_header("X-Source", "b2")?
+            .build()?;
+
+        let set = set_bucket_notification_rules(
+            &mut auth, bucket.bucket_id(), [rule.clone()]
+        ).await?;
+        assert_eq!(set.len(), 1);
+
+        let rules =
+            get_bucket_notification_rules(&mut auth, bucket.bucket_id()).await?;
+        assert_eq!(rules.len(), 1);
+        assert_eq!(rules[0].name(), "uploads");
+        assert_eq!(rules[0].event_types(), [EventType::ObjectCreatedUpload]);
+        assert_eq!(rules[0].object_name_prefix(), "incoming/");
+        assert_eq!(rules[0].url(), "https://example.com/hooks/b2");
+        assert_eq!(rules[0].signing_secret(), rule.signing_secret());
+        assert_eq!(
+            rules[0].custom_headers().collect::<Vec<_>>(),
+            [("X-Source", "b2")]
+        );
+        assert!(rules[0].is_enabled());
+        assert!(! rules[0].is_suspended());
+        assert_eq!(rules[0].suspension_reason(), None);
+
+        let res = set_bucket_notification_rules(
+            &mut auth, bucket.bucket_id(), [rule.clone(), rule]
+        ).await;
+        assert!(matches!(res, Err(Error::Validation(_))));
+
+        assert!(set_bucket_notification_rules(
+            &mut auth, bucket.bucket_id(), Vec::new()
+        ).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn upload_and_download_file() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let file = upload(&mut auth, &bucket, "dir/a file.txt", b"abcd")
+            .await?;
+        assert_eq!(file.content_length(), Some(4));
+
+        let (data, headers) = download_file(&mut auth,
+            DownloadFile::with_name("dir/a file.txt", "test-bucket")
+        ).await?;
+        assert_eq!(data, b"abcd");
+        assert_eq!(headers["x-bz-file-id"], file.file_id());
+
+        let (data, _) = download_file(&mut auth,
+            DownloadFile::builder()
+                .file_id(file.file_id())
+                .range(ByteRange::new(1, 2)?)
+                .build()?
+        ).await?;
+        assert_eq!(data, b"bc");
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn upload_with_wrong_checksum_fails() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+        let mut upload_auth = get_upload_authorization(&mut auth, &bucket)
+            .await?;
+
+        let upload = UploadFile::builder()
+            .file_name("file.txt")?
+            .sha1_checksum("0000000000000000000000000000000000000000")
+            .build()?;
+
+        let res = upload_file(&mut upload_auth, upload, b"abcd").await;
+        assert_code(res, ErrorCode::BadRequest);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn hidden_files_keep_their_versions() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let old = upload(&mut auth, &bucket, "a.txt", b"old").await?;
+        let new = upload(&mut auth, &bucket, "a.txt", b"new").await?;
+        upload(&mut auth, &bucket, "b.txt", b"b").await?;
+
+        let hidden = hide_file_by_name(&mut auth, bucket.bucket_id(), "a.txt")
+            .await?;
+        assert_eq!(hidden.action(), FileAction::Hide);
+
+        let res = hide_file_by_name(&mut auth, bucket.bucket_id(), "a.txt")
+            .await;
+        assert_code(res, ErrorCode::Unknown("already_hidden".into()));
+
+        let (names, _) = list_file_names(&mut auth, ListFileNames::builder()
+            .bucket_id(bucket.bucket_id())
+            .build()?
+        ).await?;
+        let names: Vec<_> = names.iter().map(|f| f.file_name()).collect();
+        assert_eq!(names, ["b.txt"]);
+
+        let (versions, _) = list_file_versions(&mut auth,
+            ListFileVersions::builder()
+                .bucket_id(bucket.bucket_id())
+                .build()?
+        ).await?;
+        let ids: Vec<_> = versions.iter().map(|f| f.file_id()).collect();
+        assert_eq!(ids[..3], [hidden.file_id(), new.file_id(), old.file_id()]);
+        assert_eq!(versions.len(), 4);
+
+        let res = download_file(&mut auth,
+            DownloadFile::with_name("a.txt", "test-bucket")
+        ).await;
+        assert_code(res, ErrorCode::NotFound);
+
+        // Deleting the hide marker makes the newest version visible again.
+        delete_file_version(&mut auth, hidden, BypassGovernance::No).await?;
+
+        let (data, _) = download_file(&mut auth,
+            DownloadFile::with_name("a.txt", "test-bucket")
+        ).await?;
+        assert_eq!(data, b"new");
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn list_file_versions_paginates() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        for data in [&b"1"[..], b"2", b"3"] {
+            upload(&mut auth, &bucket, "file.txt", data).await?;
+        }
+
+        let (first, next) = list_file_versions(&mut auth,
+            ListFileVersions::builder()
+                .bucket_id(bucket.bucket_id())
+                .max_file_count(2)
+                .build()?
+        ).await?;
+        assert_eq!(first.len(), 2);
+
+        let (rest, next) = list_file_versions(&mut auth, next.unwrap())
+            .await?;
+        assert_eq!(rest.len(), 1);
+        assert!(next.is_none());
+        assert_eq!(rest[0].sha1_checksum().unwrap(), &sha1_hex(b"1"));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn list_stream_requests_each_page() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        for name in ["a", "b", "c", "d", "e"] {
+            upload(&mut auth, &bucket, name, b"x").await?;
+        }
+
+        let req = ListFileNames::builder()
+            .bucket_id(bucket.bucket_id())
+            .max_file_count(2)
+            .build()?;
+
+        let mut files = list_file_names_stream(&mut auth, req);
+        let mut names = vec![];
+
+        while let Some(file) = files.try_next().await? {
+            names.push(file.file_name().to_owned());
+        }
+
+        assert_eq!(names, ["a", "b", "c", "d", "e"]);
+        assert!(files.is_finished());
+        assert!(files.cursor().is_none());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn list_stream_resumes_from_cursor() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        for name in ["a", "b", "c"] {
+            upload(&mut auth, &bucket, name, b"1").await?;
+            upload(&mut auth, &bucket, name, b"2").await?;
+        }
+
+        let req = ListFileVersions::builder()
+            .bucket_id(bucket.bucket_id())
+            .max_file_count(4)
+            .build()?;
+
+        let mut stream = list_file_versions_stream(&mut auth, req)
+            .max_items(3);
+
+        let first: Vec<File> = (&mut stream).try_collect().await?;
+        assert_eq!(first.len(), 3);
+        assert!(! stream.is_finished());
+
+        let (name, id) = stream.cursor().unwrap();
+        assert_eq!(name, "b");
+        drop(stream);
+
+        let mut req = ListFileVersions::builder()
+            .bucket_id(bucket.bucket_id())
+            .start_file_name(name);
+
+        if let Some(id) = id {
+            req = req.start_file_id(id);
+        }
+
+        let rest: Vec<File> = list_file_versions_stream(&mut auth, req.build()?)
+            .try_collect().await?;
+
+        let ids: Vec<_> = first.iter().chain(rest.iter())
+            .map(|f| f.file_id())
+            .collect();
+
+        let (all, _) = list_file_versions(&mut auth,
+            ListFileVersions::builder()
+                .bucket_id(bucket.bucket_id())
+                .build()?
+        ).await?;
+
+        assert_eq!(ids, all.iter().map(|f| f.file_id()).collect::<Vec<_>>());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn list_stream_keeps_cursor_after_error() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, _) = setup(&b2).await?;
+
+        for name in ["one", "two"] {
+            create_key(&mut auth, CreateKey::builder()
+                .name(name)?
+                .capabilities([Capability::ListBuckets])?
+                .build()?
+            ).await?;
+        }
+
+        let req = ListKeys::builder().max_keys(1)?.build();
+        let mut keys = list_keys_stream(&mut auth, req);
+
+        let first = keys.try_next().await?.unwrap();
+
+        b2.fail_next_request(503, "service_unavailable");
+        assert!(keys.try_next().await.is_err());
+        assert!(keys.try_next().await?.is_none());
+        assert!(! keys.is_finished());
+
+        let cursor = keys.cursor().unwrap();
+        assert_ne!(cursor, first.key_id());
+        drop(keys);
+
+        let req = ListKeys::builder()
+            .max_keys(1)?
+            .start_at_key(cursor)?
+            .build();
+
+        let rest: Vec<_> = list_keys_stream(&mut auth, req)
+            .try_collect().await?;
+        assert_eq!(rest.len(), 2);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn list_file_names_with_delimiter() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        for name in ["a/1.txt", "a/2.txt", "b.txt", "c/d/3.txt"] {
+            upload(&mut auth, &bucket, name, b"x").await?;
+        }
+
+        let (files, _) = list_file_names(&mut auth, ListFileNames::builder()
+            .bucket_id(bucket.bucket_id())
+            .delimiter('/')?
+            .build()?
+        ).await?;
+
+        let names: Vec<_> = files.iter()
+            .map(|f| (f.file_name(), f.action()))
+            .collect();
+
+        assert_eq!(names, [
+            ("a/", FileAction::Folder),
+            ("b.txt", FileAction::Upload),
+            ("c/", FileAction::Folder),
+        ]);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn large_file_upload() -> anyhow::Result<()> {
+        let b2 = FakeB2::new().with_minimum_part_size(4);
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let file = start_large_file(&mut auth, StartLargeFile::builder()
+            .bucket_id(bucket.bucket_id())
+            .file_name("large.bin")?
+            .content_type("application/octet-stream")
+            .build()?
+        ).await?;
+
+        let mut checksums = Vec::new();
+        let mut upload_auth = get_upload_part_authorization(&mut auth, &file)
+            .await?;
+
+        for (number, data) in [(1, &b"abcd"[..]), (2, b"ef")] {
+            let sha1 = sha1_hex(data);
+            let part = UploadFilePart::builder()
+                .part_number(number)
+                .part_sha1_checksum(&sha1)
+                .build();
+
+            upload_file_part(&mut upload_auth, &part, data).await?;
+            checksums.push(sha1);
+        }
+
+        let (parts, _) = list_file_parts(&mut auth, ListFileParts::builder()
+            .file(&file)
+            .build()?
+        ).await?;
+        assert_eq!(parts.len(), 2);
+
+        let res = finish_large_file_upload(&mut auth, &file, &checksums[..1])
+            .await;
+        assert_code(res, ErrorCode::BadRequest);
+
+        let done = finish_large_file_upload(&mut auth, &file, &checksums)
+            .await?;
+        assert_eq!(done.action(), FileAction::Upload);
+        assert_eq!(done.content_length(), Some(6));
+
+        let (data, _) = download_file(&mut auth,
+            DownloadFile::with_id(done.file_id())
+        ).await?;
+        assert_eq!(data, b"abcdef");
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn large_file_parts_must_meet_minimum_size() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let file = start_large_file(&mut auth, StartLargeFile::builder()
+            .bucket_id(bucket.bucket_id())
+            .file_name("large.bin")?
+            .content_type("application/octet-stream")
+            .build()?
+        ).await?;
+
+        let mut checksums = Vec::new();
+        let mut upload_auth = get_upload_part_authorization(&mut auth, &file)
+            .await?;
+
+        for (number, data) in [(1, &b"abcd"[..]), (2, b"ef")] {
+            let sha1 = sha1_hex(data);
+            let part = UploadFilePart::builder()
+                .part_number(number)
+                .part_sha1_checksum(&sha1)
+                .build();
+
+            upload_file_part(&mut upload_auth, &part, data).await?;
+            checksums.push(sha1);
+        }
+
+        let res = finish_large_file_upload(&mut auth, &file, &checksums).await;
+        assert_code(res, ErrorCode::BadRequest);
+
+        let (unfinished, _) = list_unfinished_large_files(&mut auth,
+            ListUnfinishedLargeFiles::builder()
+                .bucket_id(bucket.bucket_id())
+                .build()?
+        ).await?;
+        assert_eq!(unfinished.len(), 1);
+
+        cancel_large_file(&mut auth, file).await?;
+
+        let res = delete_bucket(&mut auth, bucket.bucket_id()).await;
+        assert!(res.is_ok());
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn restricted_keys_are_enforced() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let other = create_bucket(&mut auth, CreateBucket::builder()
+            .name("other-bucket")?
+            .bucket_type(BucketType::Private)?
+            .build()?
+        ).await?;
+
+        let (secret, key) = create_key(&mut auth, CreateKey::builder()
+            .name("restricted")?
+            .capabilities([Capability::ListBuckets, Capability::WriteFiles])?
+            .limit_to_bucket(bucket.bucket_id())?
+            .name_prefix("public/")?
+            .build()?
+        ).await?;
+
+        let mut restricted = authorize_account(
+            b2.client(), key.key_id(), &secret
+        ).await?;
+
+        let res = get_upload_authorization(&mut restricted, &other).await;
+        assert!(matches!(res, Err(Error::Restricted(Restriction::Bucket {
+            requested: Some(id), ..
+        })) if id == other.bucket_id()));
+
+        let res = upload(&mut restricted, &bucket, "private.txt", b"x").await;
+        assert!(res.is_err());
+        upload(&mut restricted, &bucket, "public/a.txt", b"x").await?;
+
+        let (keys, _) = list_keys(&mut auth, ListKeys::builder().build())
+            .await?;
+        assert_eq!(keys.len(), 2);
+
+        // Deleting a key revokes its authorization tokens.
+        delete_key_by_id(&mut auth, key.key_id()).await?;
+
+        let res = list_buckets(&mut restricted, ListBuckets::builder()
+            .bucket_id(bucket.bucket_id())
+            .build()
+        ).await;
+        assert_code(res, ErrorCode::BadAuthToken);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn v3_keys_can_be_restricted_to_several_buckets()
+    -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let mut buckets = vec![];
+        for name in ["other-bucket", "third-bucket"] {
+            buckets.push(create_bucket(&mut auth, CreateBucket::builder()
+                .name(name)?
+                .bucket_type(BucketType::Private)?
+                .build()?
+            ).await?);
+        }
+        let (other, third) = (&buckets[0], &buckets[1]);
+
+        let two_buckets = || CreateKey::builder()
+            .name("two-buckets")?
+            .capabilities([Capability::ListBuckets, Capability::WriteFiles])?
+            .limit_to_buckets([bucket.bucket_id(), other.bucket_id()])?
+            .build();
+
+        // Version 2 can only restrict a key to one bucket.
+        let res = create_key(&mut auth, two_buckets()?).await;
+        assert!(matches!(res,
+            Err(Error::Validation(ValidationError::Incompatible(_)))));
+
+        let (key_id, key) = b2.master_key();
+        let session = Session::with_credentials(b2.client(),
+            Credentials::new(key_id, key).with_api_version(ApiVersion::V3)
+        ).await?;
+
+        let mut auth = session.authorization();
+        assert_eq!(auth.api_version(), ApiVersion::V3);
+        assert!(auth.capabilities().buckets().is_empty());
+
+        let (secret, key) = create_key(&mut auth, two_buckets()?).await?;
+        assert_eq!(key.bucket_ids(), [bucket.bucket_id(), other.bucket_id()]);
+
+        let mut restricted = authorize_account_with_version(
+            b2.client(), B2_AUTH_URL, ApiVersion::V3, key.key_id(), &secret
+        ).await?;
+
+        let names: Vec<_> = restricted.capabilities().buckets().iter()
+            .map(|b| b.name())
+            .collect();
+        assert_eq!(names, [Some("test-bucket"), Some("other-bucket")]);
+
+        upload(&mut restricted, &bucket, "a.txt", b"a").await?;
+        upload(&mut restricted, other, "b.txt", b"b").await?;
+
+        let res = get_upload_authorization(&mut restricted, third).await;
+        assert!(matches!(res, Err(Error::Restricted(Restriction::Bucket {
+            allowed, ..
+        })) if allowed.len() == 2));
+
+        let res = list_buckets(&mut restricted, ListBuckets::builder()
+            .bucket_name(other.name())?
+            .build()
+        ).await?;
+        assert_eq!(res.len(), 1);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn restrictions_are_checked_before_sending() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let (secret, key) = create_key(&mut auth, CreateKey::builder()
+            .name("restricted")?
+            .capabilities([Capability::ListBuckets, Capability::ListFiles])?
+            .limit_to_bucket(bucket.bucket_id())?
+            .name_prefix("logs/")?
+            .build()?
+        ).await?;
+
+        let mut restricted = authorize_account(
+            b2.client(), key.key_id(), &secret
+        ).await?;
+
+        // If any of the requests below were sent, it would receive this
+        // failure instead of the final request.
+        b2.fail_next_request(500, "internal_error");
+
+        let res = list_buckets(&mut restricted, ListBuckets::builder().build())
+            .await;
+        assert!(matches!(res, Err(Error::Restricted(Restriction::Bucket {
+            requested: None, ..
+        }))));
+
+        let res = list_file_names(&mut restricted, ListFileNames::builder()
+            .bucket_id(bucket.bucket_id())
+            .build()?
+        ).await;
+        assert!(matches!(res, Err(Error::Restricted(Restriction::NamePrefix {
+            requested: None, ..
+        }))));
+
+        let res = list_file_names(&mut restricted, ListFileNames::builder()
+            .bucket_id(bucket.bucket_id())
+            .prefix("other/")?
+            .build()?
+        ).await;
+        assert!(matches!(res, Err(Error::Restricted(_))));
+
+        let res = list_file_names(&mut restricted, ListFileNames::builder()
+            .bucket_id(bucket.bucket_id())
+            .prefix("logs/2024/")?
+            .build()?
+        ).await;
+        assert_code(res, ErrorCode::InternalError);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn restrictions_compare_decoded_file_names() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let (secret, key) = create_key(&mut auth, CreateKey::builder()
+            .name("restricted")?
+            .capabilities([Capability::WriteFiles])?
+            .limit_to_bucket(bucket.bucket_id())?
+            .name_prefix("my docs/")?
+            .build()?
+        ).await?;
+
+        let mut restricted = authorize_account(
+            b2.client(), key.key_id(), &secret
+        ).await?;
+
+        let file = upload(&mut restricted, &bucket, "my docs/a.txt", b"x")
+            .await?;
+        assert_eq!(file.file_name(), "my docs/a.txt");
+
+        let res = upload(&mut restricted, &bucket, "my-docs/a.txt", b"x")
+            .await;
+        assert!(matches!(
+            res.unwrap_err().downcast::<Error<Infallible>>()?,
+            Error::Restricted(Restriction::NamePrefix { .. })
+        ));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn required_capabilities_are_sufficient() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+
+        let caps = required_capabilities(&[
+            Operation::UploadFile { retention: false, legal_hold: false },
+            Operation::ListFileNames,
+            Operation::DownloadFile,
+        ]);
+
+        let (secret, key) = create_key(&mut auth, CreateKey::builder()
+            .name("planned")?
+            .capabilities(caps)?
+            .build()?
+        ).await?;
+
+        let mut planned = authorize_account(
+            b2.client(), key.key_id(), &secret
+        ).await?;
+
+        upload(&mut planned, &bucket, "a.txt", b"x").await?;
+
+        let (files, _) = list_file_names(&mut planned, ListFileNames::builder()
+            .bucket_id(bucket.bucket_id())
+            .build()?
+        ).await?;
+        assert_eq!(files.len(), 1);
+
+        download_file(&mut planned, DownloadFile::with_id(files[0].file_id()))
+            .await?;
+
+        let missing = planned.capabilities()
+            .missing_capabilities(&[Operation::DeleteBucket]);
+        assert_eq!(missing, [
+            (Operation::DeleteBucket, vec![Capability::DeleteBuckets]),
+        ]);
+
+        let res = delete_bucket(&mut planned, bucket.bucket_id()).await;
+        assert!(matches!(res,
+            Err(Error::Unauthorized(Capability::DeleteBuckets))
+        ));
+
+        Ok(())
+    }
+
+    async fn create_restricted_key(
+        auth: &mut Authorization<FakeClient>,
+        bucket: &Bucket,
+    ) -> anyhow::Result<AccountKey> {
+        let (_, key) = create_key(auth, CreateKey::builder()
+            .name("restricted")?
+            .capabilities([Capability::ListBuckets, Capability::WriteFiles])?
+            .limit_to_bucket(bucket.bucket_id())?
+            .name_prefix("public/")?
+            .build()?
+        ).await?;
+
+        Ok(key)
+    }
+
+    async fn key_ids(auth: &mut Authorization<FakeClient>)
+    -> anyhow::Result<Vec<String>> {
+        let (keys, _) = list_keys(auth, ListKeys::builder().build()).await?;
+        Ok(keys.iter().map(|k| k.key_id().to_owned()).collect())
+    }
+
+    #[async_std::test]
+    async fn rotate_key_replaces_key() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+        let old_key = create_restricted_key(&mut auth, &bucket).await?;
+        let old_id = old_key.key_id().to_owned();
+
+        let stored = std::sync::Mutex::new(None);
+
+        let (secret, new_key) = rotate_key(&mut auth, old_key.clone(), None,
+            |secret, key| {
+                let id = key.key_id().to_owned();
+                *stored.lock().unwrap() = Some((secret, id));
+                async { Ok::<_, Infallible>(()) }
+            }
+        ).await?;
+
+        assert_eq!(
+            stored.into_inner().unwrap(),
+            Some((secret.clone(), new_key.key_id().to_owned()))
+        );
+
+        assert_eq!(new_key.key_name(), old_key.key_name());
+        assert_eq!(new_key.capabilities(), old_key.capabilities());
+        assert_eq!(new_key.bucket_id(), old_key.bucket_id());
+        assert_eq!(new_key.name_prefix(), old_key.name_prefix());
+
+        let ids = key_ids(&mut auth).await?;
+        assert!(! ids.contains(&old_id));
+        assert!(ids.iter().any(|id| id == new_key.key_id()));
+
+        authorize_account(b2.client(), new_key.key_id(), &secret).await?;
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn rotate_key_keeps_old_key_if_not_stored() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+        let old_key = create_restricted_key(&mut auth, &bucket).await?;
+
+        let res = rotate_key(&mut auth, old_key.clone(), None,
+            |_, _| async { Err("disk full") }
+        ).await;
+
+        let (secret, new_key) = match res {
+            Err(RotateKeyError::Store { secret, new_key, error }) => {
+                assert_eq!(error, "disk full");
+                (secret, new_key)
+            },
+            res => panic!("Expected a storage failure, got {:?}", res),
+        };
+
+        let ids = key_ids(&mut auth).await?;
+        assert!(ids.iter().any(|id| id == old_key.key_id()));
+        assert!(ids.iter().any(|id| id == new_key.key_id()));
+
+        authorize_account(b2.client(), new_key.key_id(), &secret).await?;
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn rotate_key_reports_failure_to_delete_old_key()
+    -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, bucket) = setup(&b2).await?;
+        let old_key = create_restricted_key(&mut auth, &bucket).await?;
+
+        let res = rotate_key(&mut auth, old_key.clone(), None, |_, _| {
+            b2.fail_next_request(500, "internal_error");
+            async { Ok::<_, Infallible>(()) }
+        }).await;
+
+        let new_key = match res {
+            Err(RotateKeyError::DeleteOld { new_key, error }) => {
+                assert_code::<()>(Err(error), ErrorCode::InternalError);
+                new_key
+            },
+            res => panic!("Expected a deletion failure, got {:?}", res),
+        };
+
+        let ids = key_ids(&mut auth).await?;
+        assert!(ids.iter().any(|id| id == old_key.key_id()));
+        assert!(ids.iter().any(|id| id == new_key.key_id()));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn audit_reports_risky_configuration() -> anyhow::Result<()> {
+        use crate::{
+            audit::{audit_account, Issue, Severity, Subject},
+            bucket::{
+                CorsOperation, CorsRule, EncryptionAlgorithm,
+                ServerSideEncryption,
+            },
+        };
+
+        let b2 = FakeB2::new();
+        let (mut auth, private) = setup(&b2).await?;
+
+        let public = create_bucket(&mut auth, CreateBucket::builder()
+            .name("public-bucket")?
+            .bucket_type(BucketType::Public)?
+            .with_file_lock()
+            .encryption_settings(
+                ServerSideEncryption::B2Managed(EncryptionAlgorithm::Aes256)
+            )
+            .cors_rules(vec![
+                CorsRule::builder()
+                    .name("uploads")?
+                    .allowed_origins(vec!["*".into()])?
+                    .allowed_operations(vec![
+                        CorsOperation::DownloadFileByName,
+                        CorsOperation::UploadFile,
+                    ])?
+                    .max_age(chrono::Duration::hours(1))?
+                    .build()?,
+            ])?
+            .build()?
+        ).await?;
+
+        let restricted = create_restricted_key(&mut auth, &private).await?;
+
+        let (_, admin) = create_key(&mut auth, CreateKey::builder()
+            .name("admin")?
+            .capabilities([Capability::ListKeys, Capability::WriteKeys])?
+            .expires_after(chrono::Duration::days(1))?
+            .build()?
+        ).await?;
+
+        let report = audit_account(&mut auth).await?;
+        assert_eq!(report.buckets_checked(), 2);
+        assert!(report.keys_checked() >= 2);
+
+        let issues = |subject: Subject| -> Vec<Issue> {
+            report.findings().iter()
+                .filter(|f| *f.subject() == subject)
+                .map(|f| f.issue().clone())
+                .collect()
+        };
+
+        assert_eq!(
+            issues(Subject::from(&restricted)),
+            vec![Issue::KeyNeverExpires]
+        );
+        assert_eq!(
+            issues(Subject::from(&admin)),
+            vec![
+                Issue::KeyHasDangerousCapabilities {
+                    capabilities: vec![Capability::WriteKeys],
+                },
+                Issue::KeyNotRestrictedToBucket,
+            ]
+        );
+        assert_eq!(
+            issues(Subject::from(&private)),
+            vec![Issue::NoDefaultEncryption, Issue::NoFileLock]
+        );
+        assert_eq!(
+            issues(Subject::from(&public)),
+            vec![
+                Issue::PublicBucket,
+                Issue::CorsAllowsAnyOriginWrite {
+                    rule_name: "uploads".into(),
+                    operations: vec![CorsOperation::UploadFile],
+                },
+            ]
+        );
+
+        assert!(report.at_least(Severity::High).all(|f|
+            f.issue().severity() == Severity::High
+        ));
+
+        let json = serde_json::to_value(&report)?;
+        assert_eq!(json["bucketsChecked"], 2);
+        assert!(json["findings"].as_array().unwrap().contains(&json!({
+            "subject": {
+                "type": "bucket",
+                "bucketId": public.bucket_id(),
+                "bucketName": "public-bucket",
+            },
+            "severity": "high",
+            "issue": "publicBucket",
+        })));
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn expired_authorizations_are_rejected() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, _) = setup(&b2).await?;
+
+        b2.expire_authorizations();
+
+        let res = list_buckets(&mut auth, ListBuckets::builder().build())
+            .await;
+        assert_code(res, ErrorCode::ExpiredAuthToken);
+
+        Ok(())
+    }
+
+    #[async_std::test]
+    async fn injected_failures_are_returned_once() -> anyhow::Result<()> {
+        let b2 = FakeB2::new();
+        let (mut auth, _) = setup(&b2).await?;
+
+        b2.fail_next_request(503, "service_unavailable");
+
+        let res = list_buckets(&mut auth, ListBuckets::builder().build())
+            .await;
+        assert_code(res, ErrorCode::ServiceUnavailable);
+
+        let buckets = list_buckets(&mut auth, ListBuckets::builder().build())
+            .await?;
+        assert_eq!(buckets.len(), 1);
+
+        Ok(())
+    }
+
+    /// Create an empty directory for a test's data.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("b2-client-{}-{}", name, std::process::id()));
+
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[async_std::test]
+    async fn directory_storage_persists() -> anyhow::Result<()> {
+        let dir = temp_dir("persist");
+
+        let file = {
+            let b2 = FakeB2::open(&dir)?;
+            let (mut auth, bucket) = setup(&b2).await?;
+
+            upload(&mut auth, &bucket, "a.txt", b"abcd").await?
+        };
+
+        let b2 = FakeB2::open(&dir)?;
+        let (key_id, key) = b2.master_key();
+        let mut auth = authorize_account(b2.client(), &key_id, &key).await?;
+
+        let buckets = list_buckets(&mut auth, ListBuckets::builder().build())
+            .await?;
+        assert_eq!(buckets.len(), 1);
+
+        let (data, _) = download_file(&mut auth,
+            DownloadFile::with_id(file.file_id())
+        ).await?;
+        assert_eq!(data, b"abcd");
+
+        delete_file_version(&mut auth, file, BypassGovernance::No).await?;
+        assert!(fs::read_dir(dir.join("data"))?.next().is_none());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn serve_answers_http_requests() -> anyhow::Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let b2 = FakeB2::new();
+
+        thread::spawn(move || b2.serve(listener));
+
+        let (key_id, key) = FakeB2::new().master_key();
+        let credentials = base64::encode(format!("{}:{}", key_id, key));
+
+        let mut stream = TcpStream::connect(addr)?;
+        write!(stream,
+            "GET /b2api/v2/b2_authorize_account HTTP/1.1\r\n\
+            Host: {}\r\n\
+            Authorization: Basic {}\r\n\
+            Connection: close\r\n\r\n",
+            addr, credentials
+        )?;
+
+        let mut res = String::new();
+        stream.read_to_string(&mut res)?;
+
+        let (head, body) = res.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 "));
+
+        let body: Value = serde_json::from_str(body)?;
+        assert_eq!(body["apiUrl"], format!("http://{}", addr));
+
+        Ok(())
+    }
+}
@@ -6,22 +6,17 @@
 //! Collection of internal, general-purpose types used throughout the crate.
 
 use std::fmt;
-use super::error::{B2Error, Error};
+use super::{
+    client::Response,
+    error::{B2Error, Error},
+};
 
 use percent_encoding::{AsciiSet, CONTROLS};
 use serde::{Serialize, Deserialize};
 
-pub use http_types::{
-    cache::{CacheDirective, Expires},
-    content::ContentEncoding,
-    mime::Mime,
-};
-
 
 // This gives us nicer error handling when deserializing JSON responses.
 // TODO: If/when Try trait is stable, impl it here.
-#[derive(serde::Deserialize)]
-#[serde(untagged)]
 #[must_use]
 pub(crate) enum B2Result<T> {
     Ok(T),
@@ -39,6 +34,22 @@ impl<T, E> From<B2Result<T>> for std::result::Result<T, Error<E>>
     }
 }
 
+impl<'a, T> B2Result<T>
+    where T: Deserialize<'a>,
+{
+    /// Deserialize a response from the B2 API.
+    ///
+    /// The HTTP status code determines whether the response is an error; the
+    /// body of a successful response is never interpreted as a [B2Error].
+    pub fn from_response(res: &'a Response) -> serde_json::Result<Self> {
+        if res.is_success() {
+            serde_json::from_slice(res.body()).map(Self::Ok)
+        } else {
+            Ok(Self::Err(B2Error::from_response(res)))
+        }
+    }
+}
+
 impl<T> B2Result<T> {
     pub fn map<U, F>(self, op: F) -> B2Result<U>
         where F: FnOnce(T) -> U,
@@ -135,6 +146,50 @@ pub(crate) const QUERY_ENCODE_SET: AsciiSet = CONTROLS
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::{client::HeaderMap, error::ErrorCode};
+
+    #[test]
+    fn success_response_is_never_an_error() {
+        let body = br#"{"code":"not_found","message":"","status":404}"#;
+        let res = Response::new(200, HeaderMap::new(), body.to_vec());
+
+        let res: B2Result<serde_json::Value> = B2Result::from_response(&res)
+            .unwrap();
+
+        assert!(matches!(res, B2Result::Ok(_)));
+    }
+
+    #[test]
+    fn error_response_is_b2_error() {
+        let body = br#"{"code":"bad_auth_token","message":"","status":401}"#;
+        let res = Response::new(401, HeaderMap::new(), body.to_vec());
+
+        let res: B2Result<serde_json::Value> = B2Result::from_response(&res)
+            .unwrap();
+
+        match res {
+            B2Result::Err(e) => assert_eq!(e.code(), ErrorCode::BadAuthToken),
+            B2Result::Ok(_) => panic!("Expected an error"),
+        }
+    }
+
+    #[test]
+    fn error_response_without_body_uses_status() {
+        let res = Response::new(404, HeaderMap::new(), vec![]);
+
+        let res: B2Result<serde_json::Value> = B2Result::from_response(&res)
+            .unwrap();
+
+        match res {
+            B2Result::Err(e) => {
+                assert_eq!(e.http_status(), 404);
+                assert_eq!(e.code(), ErrorCode::NotFound);
+            },
+            B2Result::Ok(_) => panic!("Expected an error"),
+        }
+    }
+
     #[test]
     fn b2_filename_encoding_tests() {
         use crate::types::QUERY_ENCODE_SET;
@@ -268,7 +323,7 @@ mod tests {
             assert!(
                 encoded == test["fullyEncoded"]
                     || encoded == test["minimallyEncoded"],
-                "Failed test: {}. Actual: `{}`", test.to_string(), encoded
+                "Failed test: {}. Actual: `{}`", test, encoded
             );
         }
     }
@@ -91,6 +91,46 @@ pub(crate) fn validated_cors_rule_name(name: impl Into<String>)
     validated_bucket_name(name)
 }
 
+pub(crate) fn validated_replication_rule_name(name: impl Into<String>)
+-> Result<String, ValidationError> {
+    let name = name.into();
+
+    if name.len() < 2 || name.len() > 64 {
+        return Err(ValidationError::OutOfBounds(
+            "A replication rule name must be 2 to 64 characters long".into()
+        ));
+    }
+
+    let invalid_char = |c: &char| !(c.is_ascii_alphanumeric() || *c == '-');
+
+    match name.chars().find(invalid_char) {
+        None => Ok(name),
+        Some(ch) => Err(ValidationError::BadFormat(
+            format!("Invalid character in replication rule name: {}", ch)
+        )),
+    }
+}
+
+pub(crate) fn validated_notification_rule_name(name: impl Into<String>)
+-> Result<String, ValidationError> {
+    let name = name.into();
+
+    if name.is_empty() || name.len() > 63 {
+        return Err(ValidationError::OutOfBounds(
+            "A notification rule name must be 1 to 63 characters long".into()
+        ));
+    }
+
+    let invalid_char = |c: &char| !(c.is_ascii_alphanumeric() || *c == '-');
+
+    match name.chars().find(invalid_char) {
+        None => Ok(name),
+        Some(ch) => Err(ValidationError::BadFormat(
+            format!("Invalid character in notification rule name: {}", ch)
+        )),
+    }
+}
+
 /// Ensure that file metadata fits within the B2 length requirements.
 pub(crate) fn validate_file_metadata_size(
     file_name: &str,
@@ -106,8 +146,7 @@ pub(crate) fn validate_file_metadata_size(
     // add them up rather than convert the entire Value to a string and
     // check its length.
     let info_len = file_info
-        .map(|v| v.as_object())
-        .flatten()
+        .and_then(|v| v.as_object())
         .map(|obj| obj.iter()
             .fold(0, |acc, (k, v)| acc + k.len() + v.to_string().len())
         )
@@ -162,12 +201,8 @@ fn validate_info_key_val(key: &str, val: &serde_json::Value)
         validate_info_val(key, val)?
     }
 
-    let is_valid = |c: char| c.is_alphanumeric()
-        || ['-', '_', '.', '`', '~', '!', '#', '$', '%', '^', '&', '*', '\'',
-            '|', '+'].contains(&c);
-
     for ch in key.chars() {
-        if ! is_valid(ch) {
+        if ! is_info_key_char(ch) {
             return Err(ValidationError::BadFormat(format!(
                 "Invalid character in key: '{}'", ch
             )));
@@ -177,6 +212,34 @@ fn validate_info_key_val(key: &str, val: &serde_json::Value)
     Ok(())
 }
 
+fn is_info_key_char(c: char) -> bool {
+    c.is_alphanumeric()
+        || ['-', '_', '.', '`', '~', '!', '#', '$', '%', '^', '&', '*', '\'',
+            '|', '+'].contains(&c)
+}
+
+/// Ensure that a bucket info key is valid.
+///
+/// Bucket info keys follow the same rules as file info keys.
+pub(crate) fn validated_bucket_info_key(key: impl Into<String>)
+-> Result<String, ValidationError> {
+    let key = key.into();
+
+    if key.is_empty() || key.len() > 50 {
+        return Err(ValidationError::OutOfBounds(format!(
+            "Bucket info keys must be 1 to 50 bytes, but {} is {}",
+            key, key.len()
+        )));
+    }
+
+    match key.chars().find(|c| ! is_info_key_char(*c)) {
+        None => Ok(key),
+        Some(ch) => Err(ValidationError::BadFormat(
+            format!("Invalid character in bucket info key: '{}'", ch)
+        )),
+    }
+}
+
 /// Validate the file_info for B2-specific metadata.
 pub fn validate_info_val(key: &str, val: &serde_json::Value)
 -> Result<(), ValidationError> {
@@ -0,0 +1,177 @@
+/* This Source Code Form is subject to the terms of the Mozilla Public
+   License, v. 2.0. If a copy of the MPL was not distributed with this
+   file, You can obtain one at http://mozilla.org/MPL/2.0/.
+*/
+
+//! Verify and parse the event notifications B2 sends to webhooks.
+//!
+//! When a [NotificationRule](crate::bucket::NotificationRule) has a
+//! [signing secret](crate::bucket::NotificationRuleBuilder::signing_secret),
+//! B2 signs each request body with HMAC-SHA256 and sends the signature in the
+//! [SIGNATURE_HEADER] header. [parse_notification] checks the signature before
+//! parsing the body, so events are only returned from a request that was sent
+//! by B2:
+//!
+//! ```no_run
+//! use b2_client::{
+//!     file::FileAction,
+//!     webhook::parse_notification,
+//! };
+//!
+//! # fn f(body: &[u8], signature: &str) -> anyhow::Result<()> {
+//! // `body` is the raw request body and `signature` is the value of the
+//! // `x-bz-event-notification-signature` header.
+//! let notification = parse_notification(
+//!     body,
+//!     signature,
+//!     "0123456789abcdefghijABCDEFGHIJ01"
+//! )?;
+//!
+//! for event in notification.events() {
+//!     if event.action() == Some(FileAction::Upload) {
+//!         println!("Uploaded {}", event.file_name().unwrap_or_default());
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Nothing in this module makes a network request; it can be used with any
+//! HTTP server.
+
+use crate::{
+    bucket::EventNotification,
+    error::WebhookError,
+};
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+
+/// The name of the header containing the request's signature.
+pub const SIGNATURE_HEADER: &str = "x-bz-event-notification-signature";
+
+/// The prefix of the only signature version B2 currently sends.
+const SIGNATURE_VERSION: &str = "v1=";
+
+/// Check that `signature` is B2's signature of `body` using the given secret.
+///
+/// `body` must be the request body exactly as received; re-serializing a
+/// parsed body will generally change the signature.
+///
+/// The comparison is performed in constant time.
+pub fn verify_signature(body: &[u8], signature: &str, secret: &str)
+-> Result<(), WebhookError> {
+    let digest = signature.trim()
+        .strip_prefix(SIGNATURE_VERSION)
+        .and_then(decode_hex)
+        .ok_or(WebhookError::MalformedSignature)?;
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+
+    mac.update(body);
+    mac.verify_slice(&digest).map_err(|_| WebhookError::SignatureMismatch)
+}
+
+/// Verify the signature of an event notification request, then parse its
+/// body.
+///
+/// See [verify_signature] for the requirements on `body`.
+pub fn parse_notification(body: &[u8], signature: &str, secret: &str)
+-> Result<EventNotification, WebhookError> {
+    verify_signature(body, signature, secret)?;
+    Ok(serde_json::from_slice(body)?)
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    let pairs = s.as_bytes().chunks_exact(2);
+
+    if s.is_empty() || ! pairs.remainder().is_empty() {
+        return None;
+    }
+
+    let digit = |b: u8| char::from(b).to_digit(16);
+
+    pairs.map(|pair| Some((digit(pair[0])? << 4 | digit(pair[1])?) as u8))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{bucket::EventType, file::FileAction};
+
+    const SECRET: &str = "0123456789abcdefghijABCDEFGHIJ01";
+
+    const BODY: &str = concat!(
+        r#"{"events":[{"accountId":"e85c6a500333","#,
+        r#""bucketId":"aea8c5bc362ef5e58183001c","bucketName":"my-bucket","#,
+        r#""eventId":"ce8f4b4b2b6a4f7d9b8f","eventTimestamp":1684793309123,"#,
+        r#""eventType":"b2:ObjectCreated:Upload","eventVersion":1,"#,
+        r#""matchedRuleName":"new-uploads","#,
+        r#""objectName":"incoming/report.pdf","objectSize":10495842,"#,
+        r#""objectVersionId":"4_zaea8c5bc362ef5e58183001c_f1"}]}"#,
+    );
+
+    const SIGNATURE: &str =
+        "v1=f411d23c5c4a41c3c0494639cf356afbb1db633665bfe15d41100700c1461a4d";
+
+
+    #[test]
+    fn valid_signature_is_accepted() -> anyhow::Result<()> {
+        verify_signature(BODY.as_bytes(), SIGNATURE, SECRET)?;
+
+        let events = parse_notification(BODY.as_bytes(), SIGNATURE, SECRET)?
+            .into_events();
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].event_type(), &EventType::ObjectCreatedUpload);
+        assert_eq!(events[0].action(), Some(FileAction::Upload));
+        assert_eq!(events[0].file_name(), Some("incoming/report.pdf"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_signature_is_rejected() {
+        let body = BODY.replace("report.pdf", "invoice.pdf");
+
+        assert!(matches!(
+            parse_notification(body.as_bytes(), SIGNATURE, SECRET),
+            Err(WebhookError::SignatureMismatch)
+        ));
+        assert!(matches!(
+            verify_signature(BODY.as_bytes(), SIGNATURE, "wrong-secret"),
+            Err(WebhookError::SignatureMismatch)
+        ));
+        assert!(matches!(
+            verify_signature(BODY.as_bytes(), &SIGNATURE[..21], SECRET),
+            Err(WebhookError::SignatureMismatch)
+        ));
+
+        for bad in ["", "v1=", "v1=xyz", "v2=f411d2", &SIGNATURE[3..]] {
+            assert!(matches!(
+                verify_signature(BODY.as_bytes(), bad, SECRET),
+                Err(WebhookError::MalformedSignature)
+            ), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn signed_invalid_body_is_rejected() {
+        let body = b"not json";
+        let mut mac = Hmac::<Sha256>::new_from_slice(SECRET.as_bytes())
+            .unwrap();
+        mac.update(body);
+
+        let signature: String = mac.finalize().into_bytes().iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+
+        assert!(matches!(
+            parse_notification(body, &format!("v1={}", signature), SECRET),
+            Err(WebhookError::BadPayload(_))
+        ));
+    }
+}
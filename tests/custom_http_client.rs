@@ -5,7 +5,7 @@ use std::fmt;
 
 use b2_client::{
     account::authorize_account,
-    client::{HeaderMap, HttpClient},
+    client::{HttpClient, Response, StreamingResponse},
     error::{ValidationError, Error},
 };
 
@@ -39,6 +39,8 @@ impl HttpClient for FakeClient {
     gen_send_func!(get);
     gen_send_func!(head);
     gen_send_func!(post);
+    gen_send_func!(put);
+    gen_send_func!(delete);
 
     fn with_header<S: AsRef<str>>(&mut self, _name: S, _value: S)
     -> Result<&mut Self, ValidationError> {
@@ -53,18 +55,25 @@ impl HttpClient for FakeClient {
         self
     }
 
+    fn with_body_reader<R>(&mut self, _reader: R, _content_length: u64)
+    -> &mut Self
+        where R: futures_io::AsyncRead + Send + Sync + Unpin + 'static,
+    {
+        self
+    }
+
     fn user_agent(&mut self, _user_agent_string: impl Into<String>)
     -> Result<&mut Self, ValidationError>
     {
         Ok(self)
     }
 
-    async fn send(&mut self) -> Result<Vec<u8>, Self::Error> {
+    async fn send(&mut self) -> Result<Response, Self::Error> {
         Err(Error::Client(FakeError))
     }
 
-    async fn send_keep_headers(&mut self)
-    -> Result<(Vec<u8>, HeaderMap), Self::Error> {
+    async fn send_streaming(&mut self)
+    -> Result<StreamingResponse, Self::Error> {
         Err(Error::Client(FakeError))
     }
 }